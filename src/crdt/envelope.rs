@@ -0,0 +1,317 @@
+//! Signed and encrypted envelopes for CRDT operations and snapshots.
+//!
+//! Everything leaving a replica (an `Operation` or a full state) is sealed
+//! with a `Sealer`: serialized, encrypted with a key derived from the shared
+//! secret (ChaCha20-Poly1305) and signed with the replica's own ed25519 key.
+//! An `Opener` holds the shared key and the verifying keys of the replicas it
+//! trusts, it rejects envelopes with an unknown signer, a bad signature, a
+//! tampered ciphertext or an operation authored by another actor than the
+//! signer before anything is applied.
+//!
+//! ``` rust
+//! use libtheia::crdt::{CmRDT, List};
+//! use libtheia::crdt::envelope::{Opener, Sealer, SecretKey, SigningKey};
+//!
+//! let key = SecretKey::from_secret("0f1e2d3c4b5a69788796a5b4c3d2e1f0");
+//! let sealer = Sealer::new(key.clone(), 1u64, SigningKey::generate());
+//! let mut opener = Opener::new(key);
+//! opener.trust(1, sealer.verifying_key());
+//!
+//! let source: List<char, u64> = List::new();
+//! let envelope = sealer.seal_operation(&source.append('a', 1)).unwrap();
+//!
+//! let mut replica: List<char, u64> = List::new();
+//! opener.apply(&mut replica, &envelope).unwrap();
+//! assert_eq!(replica.read::<String>(), "a");
+//! ```
+
+use core::convert::Infallible;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Display};
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use ed25519_dalek::{Signature, Signer, Verifier};
+use hkdf::Hkdf;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::crdt::{list, map, CmRDT};
+
+pub use ed25519_dalek::VerifyingKey;
+
+/// Domain separation for the key derived from the shared secret.
+static KEY_INFO: &[u8] = b"theia envelope v1";
+
+/// What an envelope carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Kind {
+    Operation,
+    Snapshot,
+}
+
+/// A sealed operation or snapshot, safe to store or send as is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Envelope<A> {
+    pub actor: A,
+    pub kind: Kind,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+/// Reasons an envelope cannot be sealed or opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvelopeError<V = Infallible> {
+    Serialization(String),
+    Encryption,
+    /// The ciphertext was tampered with or sealed under another secret.
+    Decryption,
+    UnknownSigner,
+    BadSignature,
+    WrongKind(Kind),
+    /// A signed operation claims to be authored by another actor, or is a
+    /// batch that names no actor at all.
+    AuthorMismatch,
+    /// The operation opened fine but does not pass `validate_apply`.
+    Rejected(V),
+}
+
+impl<V: Debug> Display for EnvelopeError<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl<V: Debug> std::error::Error for EnvelopeError<V> {}
+
+/// Operations that name the actor that produced them.
+///
+/// Operations that name no actor (e.g. `Map` removes) are authenticated by
+/// their signature only.
+pub trait Authored<A> {
+    /// Whether every actor the operation names is `signer`.
+    fn authored_by(&self, signer: &A) -> bool;
+}
+
+impl<T, A: Ord + Clone> Authored<A> for list::Operation<T, A> {
+    fn authored_by(&self, signer: &A) -> bool {
+        self.version().actor == *signer
+    }
+}
+
+impl<K: Ord, V: map::Val<A>, A: Ord + Clone> Authored<A> for map::Operation<K, V, A> {
+    fn authored_by(&self, signer: &A) -> bool {
+        match self {
+            map::Operation::Update { version, .. } => version.actor == *signer,
+            // an empty batch would pass as authored by anyone
+            map::Operation::Batch { updates } => {
+                !updates.is_empty() && updates.iter().all(|(version, _, _)| version.actor == *signer)
+            }
+            map::Operation::Remove { .. } => true,
+        }
+    }
+}
+
+/// Symmetric key shared by all instances, derived from the configured secret.
+#[derive(Clone)]
+pub struct SecretKey(Key);
+
+impl Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+impl SecretKey {
+    /// SecretKey instance
+    ///
+    /// * `secret` - the shared secret, as configured.
+    pub fn from_secret(secret: &str) -> SecretKey {
+        let mut key = Key::default();
+        Hkdf::<Sha256>::new(None, secret.as_bytes())
+            .expand(KEY_INFO, &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        SecretKey(key)
+    }
+}
+
+/// Per replica signing key.
+pub struct SigningKey(ed25519_dalek::SigningKey);
+
+impl Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SigningKey").field(&self.0.verifying_key()).finish()
+    }
+}
+
+impl SigningKey {
+    pub fn generate() -> SigningKey {
+        SigningKey(ed25519_dalek::SigningKey::generate(&mut OsRng))
+    }
+
+    pub fn from_bytes(bytes: &[u8; 32]) -> SigningKey {
+        SigningKey(ed25519_dalek::SigningKey::from_bytes(bytes))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.0.verifying_key()
+    }
+}
+
+/// Bytes authenticated by both the AEAD tag and the signature.
+fn header<A: Serialize>(actor: &A, kind: Kind) -> Result<Vec<u8>, EnvelopeError> {
+    serde_json::to_vec(&(actor, kind)).map_err(|e| EnvelopeError::Serialization(e.to_string()))
+}
+
+fn signed_bytes(header: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(header.len() + nonce.len() + ciphertext.len());
+    bytes.extend_from_slice(header);
+    bytes.extend_from_slice(nonce);
+    bytes.extend_from_slice(ciphertext);
+    bytes
+}
+
+/// Seals everything a single replica sends.
+#[derive(Debug)]
+pub struct Sealer<A> {
+    key: SecretKey,
+    actor: A,
+    signing_key: SigningKey,
+}
+
+impl<A: Clone + Serialize> Sealer<A> {
+    /// Sealer instance
+    ///
+    /// * `key` - shared key.
+    /// * `actor` - the actor this replica writes as.
+    /// * `signing_key` - key of this replica.
+    pub fn new(key: SecretKey, actor: A, signing_key: SigningKey) -> Sealer<A> {
+        Sealer { key, actor, signing_key }
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.signing_key.verifying_key()
+    }
+
+    pub fn seal_operation<O: Serialize>(&self, operation: &O) -> Result<Envelope<A>, EnvelopeError> {
+        self.seal(Kind::Operation, operation)
+    }
+
+    pub fn seal_snapshot<S: Serialize>(&self, state: &S) -> Result<Envelope<A>, EnvelopeError> {
+        self.seal(Kind::Snapshot, state)
+    }
+
+    fn seal<T: Serialize>(&self, kind: Kind, value: &T) -> Result<Envelope<A>, EnvelopeError> {
+        let header = header(&self.actor, kind)?;
+        let plaintext = serde_json::to_vec(value).map_err(|e| EnvelopeError::Serialization(e.to_string()))?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&self.key.0)
+            .encrypt(&nonce, Payload { msg: &plaintext, aad: &header })
+            .map_err(|_| EnvelopeError::Encryption)?;
+        let signature = self.signing_key.0.sign(&signed_bytes(&header, &nonce, &ciphertext));
+        Ok(Envelope {
+            actor: self.actor.clone(),
+            kind,
+            nonce: nonce.to_vec(),
+            ciphertext,
+            signature: signature.to_bytes().to_vec(),
+        })
+    }
+}
+
+/// Verifies and decrypts envelopes from trusted replicas.
+#[derive(Debug)]
+pub struct Opener<A> {
+    key: SecretKey,
+    trusted: BTreeMap<A, VerifyingKey>,
+}
+
+impl<A: Ord + Clone + Serialize> Opener<A> {
+    /// Opener instance
+    ///
+    /// * `key` - shared key.
+    pub fn new(key: SecretKey) -> Opener<A> {
+        Opener {
+            key,
+            trusted: BTreeMap::new(),
+        }
+    }
+
+    /// Accept envelopes signed by `key` for `actor`, replacing a previous key.
+    pub fn trust(&mut self, actor: A, key: VerifyingKey) {
+        self.trusted.insert(actor, key);
+    }
+
+    pub fn revoke(&mut self, actor: &A) -> bool {
+        self.trusted.remove(actor).is_some()
+    }
+
+    pub fn open_operation<O: DeserializeOwned + Authored<A>>(&self, envelope: &Envelope<A>) -> Result<O, EnvelopeError> {
+        let operation: O = self.open(Kind::Operation, envelope)?;
+        match operation.authored_by(&envelope.actor) {
+            true => Ok(operation),
+            false => Err(EnvelopeError::AuthorMismatch),
+        }
+    }
+
+    pub fn open_snapshot<S: DeserializeOwned>(&self, envelope: &Envelope<A>) -> Result<S, EnvelopeError> {
+        self.open(Kind::Snapshot, envelope)
+    }
+
+    /// Open an operation and apply it to `target` if it passes
+    /// `validate_apply`, `target` is left untouched on any error.
+    pub fn apply<T>(&self, target: &mut T, envelope: &Envelope<A>) -> Result<(), EnvelopeError<T::Validation>>
+    where
+        T: CmRDT,
+        T::Operation: DeserializeOwned + Authored<A>,
+    {
+        let operation: T::Operation = self.open_operation(envelope).map_err(EnvelopeError::widen)?;
+        target.validate_apply(&operation).map_err(EnvelopeError::Rejected)?;
+        target.apply(operation);
+        Ok(())
+    }
+
+    fn open<T: DeserializeOwned>(&self, kind: Kind, envelope: &Envelope<A>) -> Result<T, EnvelopeError> {
+        if envelope.kind != kind {
+            return Err(EnvelopeError::WrongKind(envelope.kind));
+        }
+        let verifying_key = self.trusted.get(&envelope.actor).ok_or(EnvelopeError::UnknownSigner)?;
+        let header = header(&envelope.actor, envelope.kind)?;
+        let signature = Signature::from_slice(&envelope.signature).map_err(|_| EnvelopeError::BadSignature)?;
+        verifying_key
+            .verify(&signed_bytes(&header, &envelope.nonce, &envelope.ciphertext), &signature)
+            .map_err(|_| EnvelopeError::BadSignature)?;
+
+        if envelope.nonce.len() != 12 {
+            return Err(EnvelopeError::Decryption);
+        }
+        let plaintext = ChaCha20Poly1305::new(&self.key.0)
+            .decrypt(
+                Nonce::from_slice(&envelope.nonce),
+                Payload { msg: &envelope.ciphertext, aad: &header },
+            )
+            .map_err(|_| EnvelopeError::Decryption)?;
+        serde_json::from_slice(&plaintext).map_err(|e| EnvelopeError::Serialization(e.to_string()))
+    }
+}
+
+impl EnvelopeError {
+    fn widen<V>(self) -> EnvelopeError<V> {
+        match self {
+            EnvelopeError::Serialization(e) => EnvelopeError::Serialization(e),
+            EnvelopeError::Encryption => EnvelopeError::Encryption,
+            EnvelopeError::Decryption => EnvelopeError::Decryption,
+            EnvelopeError::UnknownSigner => EnvelopeError::UnknownSigner,
+            EnvelopeError::BadSignature => EnvelopeError::BadSignature,
+            EnvelopeError::WrongKind(k) => EnvelopeError::WrongKind(k),
+            EnvelopeError::AuthorMismatch => EnvelopeError::AuthorMismatch,
+            EnvelopeError::Rejected(never) => match never {},
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Secondary indexes over the values of a `Map`.
+//!
+//! An index is declared with a name and an extractor closure that derives
+//! zero or more `Attribute`s from a value. Indexes are local to a replica:
+//! they are not serialized, not compared and have to be declared again after
+//! deserializing a `Map`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug};
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+/// An indexable attribute of a value.
+///
+/// Attributes of different variants never compare in range queries.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Attribute {
+    Bool(bool),
+    Int(i64),
+    Text(String),
+}
+
+impl From<bool> for Attribute {
+    fn from(b: bool) -> Self {
+        Attribute::Bool(b)
+    }
+}
+
+impl From<i32> for Attribute {
+    fn from(i: i32) -> Self {
+        Attribute::Int(i.into())
+    }
+}
+
+impl From<i64> for Attribute {
+    fn from(i: i64) -> Self {
+        Attribute::Int(i)
+    }
+}
+
+impl From<&str> for Attribute {
+    fn from(s: &str) -> Self {
+        Attribute::Text(s.to_string())
+    }
+}
+
+impl From<String> for Attribute {
+    fn from(s: String) -> Self {
+        Attribute::Text(s)
+    }
+}
+
+pub type Extractor<V> = Arc<dyn Fn(&V) -> Vec<Attribute> + Send + Sync>;
+
+struct Index<K, V> {
+    extractor: Extractor<V>,
+    values: BTreeMap<Attribute, BTreeSet<K>>,
+    keys: BTreeMap<K, Vec<Attribute>>,
+}
+
+impl<K: Ord + Clone, V> Index<K, V> {
+    fn remove(&mut self, key: &K) {
+        for attribute in self.keys.remove(key).unwrap_or_default() {
+            if let Some(keys) = self.values.get_mut(&attribute) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.values.remove(&attribute);
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, key: &K, value: &V) {
+        let mut attributes = (self.extractor)(value);
+        attributes.sort();
+        attributes.dedup();
+        if attributes.is_empty() {
+            return;
+        }
+        for attribute in attributes.iter() {
+            self.values
+                .entry(attribute.clone())
+                .or_default()
+                .insert(key.clone());
+        }
+        self.keys.insert(key.clone(), attributes);
+    }
+}
+
+/// All indexes declared on a `Map`, by name.
+pub(crate) struct Indexes<K, V> {
+    indexes: BTreeMap<String, Index<K, V>>,
+}
+
+impl<K, V> Default for Indexes<K, V> {
+    fn default() -> Self {
+        Self {
+            indexes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Clone, V> Clone for Indexes<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            indexes: self
+                .indexes
+                .iter()
+                .map(|(name, index)| {
+                    (
+                        name.clone(),
+                        Index {
+                            extractor: index.extractor.clone(),
+                            values: index.values.clone(),
+                            keys: index.keys.clone(),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Indexes are derived data, two maps with the same entries are equal
+/// regardless of the indexes declared on them.
+impl<K, V> PartialEq for Indexes<K, V> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<K, V> Eq for Indexes<K, V> {}
+
+impl<K, V> Debug for Indexes<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.indexes.keys()).finish()
+    }
+}
+
+impl<K: Ord + Clone, V> Indexes<K, V> {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.indexes.is_empty()
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.indexes.keys().map(|name| name.as_str())
+    }
+
+    pub(crate) fn declare<'a>(
+        &mut self,
+        name: &str,
+        extractor: Extractor<V>,
+        entries: impl Iterator<Item = (&'a K, &'a V)>,
+    ) where
+        K: 'a,
+        V: 'a,
+    {
+        let mut index = Index {
+            extractor,
+            values: BTreeMap::new(),
+            keys: BTreeMap::new(),
+        };
+        for (key, value) in entries {
+            index.insert(key, value);
+        }
+        self.indexes.insert(name.to_string(), index);
+    }
+
+    pub(crate) fn drop_index(&mut self, name: &str) -> bool {
+        self.indexes.remove(name).is_some()
+    }
+
+    /// Re-derive the attributes of a single key, `None` when it was removed.
+    pub(crate) fn update(&mut self, key: &K, value: Option<&V>) {
+        for index in self.indexes.values_mut() {
+            index.remove(key);
+            if let Some(v) = value {
+                index.insert(key, v);
+            }
+        }
+    }
+
+    pub(crate) fn rebuild<'a>(&mut self, entries: impl Iterator<Item = (&'a K, &'a V)> + Clone)
+    where
+        K: 'a,
+        V: 'a,
+    {
+        for index in self.indexes.values_mut() {
+            index.values.clear();
+            index.keys.clear();
+            for (key, value) in entries.clone() {
+                index.insert(key, value);
+            }
+        }
+    }
+
+    pub(crate) fn lookup(&self, name: &str, attribute: &Attribute) -> Vec<&K> {
+        self.indexes
+            .get(name)
+            .and_then(|index| index.values.get(attribute))
+            .map(|keys| keys.iter().collect())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn attributes(&self, name: &str) -> Vec<&Attribute> {
+        self.indexes
+            .get(name)
+            .map(|index| index.values.keys().collect())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn lookup_range<R: RangeBounds<Attribute>>(&self, name: &str, range: R) -> Vec<&K> {
+        let variant = match (range.start_bound(), range.end_bound()) {
+            (Bound::Included(a) | Bound::Excluded(a), _) | (_, Bound::Included(a) | Bound::Excluded(a)) => {
+                Some(mem::discriminant(a))
+            }
+            (Bound::Unbounded, Bound::Unbounded) => None,
+        };
+        if is_empty(&range) {
+            return Vec::new();
+        }
+        let mut keys: BTreeSet<&K> = BTreeSet::new();
+        if let Some(index) = self.indexes.get(name) {
+            for (attribute, attribute_keys) in index.values.range(range) {
+                if variant.is_none_or(|v| v == mem::discriminant(attribute)) {
+                    keys.extend(attribute_keys.iter());
+                }
+            }
+        }
+        keys.into_iter().collect()
+    }
+}
+
+/// Whether `range` holds no attribute at all, `BTreeMap::range` panics on
+/// those.
+fn is_empty<R: RangeBounds<Attribute>>(range: &R) -> bool {
+    match (range.start_bound(), range.end_bound()) {
+        (Bound::Included(a), Bound::Included(b)) => a > b,
+        (Bound::Included(a) | Bound::Excluded(a), Bound::Included(b) | Bound::Excluded(b)) => a >= b,
+        _ => false,
+    }
+}
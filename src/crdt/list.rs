@@ -1,10 +1,14 @@
+use core::convert::Infallible;
 use core::fmt;
 use core::iter::FromIterator;
 use std::collections::BTreeMap;
+use std::ops::{Bound, RangeBounds};
+
+use num::BigRational;
 
 use serde::{Deserialize, Serialize};
 use crate::crdt::serde_ext::SerDe;
-use crate::crdt::{Identifier, CmRDT, VectorClock, Version, VersionRange};
+use crate::crdt::{Identifier, CmRDT, CvRDT, VectorClock, Version, VersionRange};
 use crate::crdt::version::OrderedVersion;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -24,21 +28,61 @@ pub enum Operation<T, A: Ord> {
         id: Identifier<OrderedVersion<A>>,
         version: Version<A>,
     },
+    /// A run of consecutive elements sharing one version, the element at
+    /// offset `i` is identified by `id` extended with `(i, version)`.
+    InsertRun {
+        id: Identifier<OrderedVersion<A>>,
+        values: Vec<T>,
+    },
+    /// Delete every element between `from` and `to` (inclusive) that was
+    /// covered by `clock` when the range was selected, concurrent inserts
+    /// into the range survive.
+    DeleteRange {
+        from: Identifier<OrderedVersion<A>>,
+        to: Identifier<OrderedVersion<A>>,
+        clock: VectorClock<A>,
+        version: Version<A>,
+    },
 }
 
 impl<T, A: Ord + Clone + Eq> Operation<T, A> {
+    /// Identifier the operation is anchored at, the first of the range for
+    /// `DeleteRange`.
     pub fn id(&self) -> &Identifier<OrderedVersion<A>> {
         match self {
-            Operation::Insert { id, .. } | Operation::Delete { id, .. } => id,
+            Operation::Insert { id, .. }
+            | Operation::Delete { id, .. }
+            | Operation::InsertRun { id, .. }
+            | Operation::DeleteRange { from: id, .. } => id,
         }
     }
 
     pub fn version(&self) -> Version<A> {
         match self {
-            Operation::Insert { id, .. } => id.value().clone().into(),
-            Operation::Delete { version: dot, .. } => dot.clone(),
+            Operation::Insert { id, .. } | Operation::InsertRun { id, .. } => id.value().clone().into(),
+            Operation::Delete { version: dot, .. } | Operation::DeleteRange { version: dot, .. } => dot.clone(),
         }
     }
+
+    /// Identifiers of the elements inserted by this operation.
+    pub fn ids(&self) -> Vec<Identifier<OrderedVersion<A>>> {
+        match self {
+            Operation::Insert { id, .. } => vec![id.clone()],
+            Operation::InsertRun { id, values } => (0..values.len()).map(|i| run_id(id, i)).collect(),
+            Operation::Delete { .. } | Operation::DeleteRange { .. } => vec![],
+        }
+    }
+}
+
+/// Identifier of the element at `offset` in a run anchored at `id`.
+///
+/// Extensions of an identifier sort directly before it and after anything
+/// `Identifier::between` placed it behind, so the whole run lands between the
+/// neighbours the anchor was created for.
+fn run_id<A: Ord + Clone>(id: &Identifier<OrderedVersion<A>>, offset: usize) -> Identifier<OrderedVersion<A>> {
+    let mut path = id.0.clone();
+    path.push((BigRational::from_integer(offset.into()), id.value().clone()));
+    Identifier(path)
 }
 
 impl<T: SerDe, A: Ord> Default for List<T, A> {
@@ -55,7 +99,13 @@ impl<T: SerDe, A: Ord + Clone> List<T, A> {
         Self::default()
     }
 
-    pub fn insert_index(&self, mut index: usize, element: T, actor: A) -> Operation<T, A> {
+    pub fn insert_index(&self, index: usize, element: T, actor: A) -> Operation<T, A> {
+        let id = self.id_at(index, actor);
+        Operation::Insert { id, value: element }
+    }
+
+    /// Fresh identifier for an element inserted at `index`.
+    fn id_at(&self, mut index: usize, actor: A) -> Identifier<OrderedVersion<A>> {
         index = index.min(self.sequence.len());
         let (prev, next) = match index.checked_sub(1) {
             Some(indices_to_drop) => {
@@ -69,8 +119,7 @@ impl<T: SerDe, A: Ord + Clone> List<T, A> {
         };
 
         let version = self.clock.increment(actor);
-        let id = Identifier::between(prev, next, version.into());
-        Operation::Insert { id, value: element }
+        Identifier::between(prev, next, version.into())
     }
 
     pub fn append(&self, element: T, actor: A) -> Operation<T, A> {
@@ -78,6 +127,29 @@ impl<T: SerDe, A: Ord + Clone> List<T, A> {
         self.insert_index(index, element, actor)
     }
 
+    /// Insert a run of elements at `index` as a single operation.
+    ///
+    /// ```rust
+    /// use libtheia::crdt::{List, CmRDT};
+    ///
+    /// let mut list = List::new();
+    /// list.apply(list.append('a', 'A'));
+    /// list.apply(list.append('e', 'A'));
+    /// list.apply(list.insert_run(1, "bcd".chars(), 'A'));
+    /// assert_eq!(list.read::<String>(), "abcde");
+    /// ```
+    pub fn insert_run(&self, index: usize, elements: impl IntoIterator<Item = T>, actor: A) -> Operation<T, A> {
+        Operation::InsertRun {
+            id: self.id_at(index, actor),
+            values: elements.into_iter().collect(),
+        }
+    }
+
+    pub fn append_run(&self, elements: impl IntoIterator<Item = T>, actor: A) -> Operation<T, A> {
+        let index = self.sequence.len();
+        self.insert_run(index, elements, actor)
+    }
+
     pub fn delete_index(&self, index: usize, actor: A) -> Option<Operation<T, A>> {
         self.sequence.keys().nth(index).cloned().map(|id| {
             let version = self.clock.increment(actor);
@@ -85,6 +157,39 @@ impl<T: SerDe, A: Ord + Clone> List<T, A> {
         })
     }
 
+    /// Delete the elements at the positions in `range` as a single
+    /// operation, `None` when the range selects nothing.
+    ///
+    /// ```rust
+    /// use libtheia::crdt::{List, CmRDT};
+    ///
+    /// let mut list = List::new();
+    /// list.apply(list.append_run("abcde".chars(), 'A'));
+    /// list.apply(list.delete_range(1..4, 'A').unwrap());
+    /// assert_eq!(list.read::<String>(), "ae");
+    /// ```
+    pub fn delete_range<R: RangeBounds<usize>>(&self, range: R, actor: A) -> Option<Operation<T, A>> {
+        let start = match range.start_bound() {
+            Bound::Included(i) => *i,
+            Bound::Excluded(i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(i) => i + 1,
+            Bound::Excluded(i) => *i,
+            Bound::Unbounded => self.sequence.len(),
+        };
+        let mut ids = self.sequence.keys().skip(start).take(end.saturating_sub(start));
+        let from = ids.next()?.clone();
+        let to = ids.last().cloned().unwrap_or_else(|| from.clone());
+        Some(Operation::DeleteRange {
+            from,
+            to,
+            clock: self.clock.clone(),
+            version: self.clock.increment(actor),
+        })
+    }
+
     pub fn len(&self) -> usize {
         self.sequence.len()
     }
@@ -127,6 +232,12 @@ impl<T: SerDe, A: Ord + Clone> List<T, A> {
         self.sequence.values()
     }
 
+    /// Mutable access to the elements, changes made through it are local to
+    /// this replica and not list operations.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.sequence.values_mut()
+    }
+
     pub fn iter_entries(&self) -> impl Iterator<Item = (&Identifier<OrderedVersion<A>>, &T)> {
         self.sequence.iter()
     }
@@ -168,6 +279,33 @@ impl<T: SerDe, A: Ord + Clone> List<T, A> {
     fn delete(&mut self, id: &Identifier<OrderedVersion<A>>) {
         self.sequence.remove(id);
     }
+
+    fn delete_range_seen(
+        &mut self,
+        from: &Identifier<OrderedVersion<A>>,
+        to: &Identifier<OrderedVersion<A>>,
+        clock: &VectorClock<A>,
+    ) {
+        if from > to {
+            return;
+        }
+        let ids: Vec<_> = self
+            .sequence
+            .range(from..=to)
+            .map(|(id, _)| id)
+            .filter(|id| seen(clock, id))
+            .cloned()
+            .collect();
+        for id in ids {
+            self.sequence.remove(&id);
+        }
+    }
+}
+
+/// Whether the insert of `id` is covered by `clock`.
+fn seen<A: Ord + Clone>(clock: &VectorClock<A>, id: &Identifier<OrderedVersion<A>>) -> bool {
+    let version = id.value();
+    clock.get(&version.actor) >= version.counter
 }
 
 impl<T: SerDe, A: Ord + Clone + fmt::Debug> CmRDT for List<T, A> {
@@ -189,7 +327,36 @@ impl<T: SerDe, A: Ord + Clone + fmt::Debug> CmRDT for List<T, A> {
         match operation {
             Operation::Insert { id, value: val } => self.insert(id, val),
             Operation::Delete { id, .. } => self.delete(&id),
+            Operation::InsertRun { id, values } => {
+                for (offset, val) in values.into_iter().enumerate() {
+                    self.insert(run_id(&id, offset), val);
+                }
+            }
+            Operation::DeleteRange { from, to, clock, .. } => self.delete_range_seen(&from, &to, &clock),
+        }
+    }
+}
+
+impl<T: SerDe, A: Ord + Clone + fmt::Debug> CvRDT for List<T, A> {
+    type Validation = Infallible;
+
+    fn validate_merge(&self, _other: &Self) -> Result<(), Self::Validation> {
+        Ok(())
+    }
+
+    /// An element missing on one side was either deleted there (its insert
+    /// version is covered by that side's clock) or never seen (it is not).
+    fn merge(&mut self, other: Self) {
+        self.sequence
+            .retain(|id, _| other.sequence.contains_key(id) || !seen(&other.clock, id));
+
+        for (id, val) in other.sequence {
+            if !seen(&self.clock, &id) {
+                self.sequence.entry(id).or_insert(val);
+            }
         }
+
+        self.clock.merge(other.clock);
     }
 }
 
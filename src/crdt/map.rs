@@ -4,10 +4,13 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{self, Debug, Display};
 use std::hash::Hash;
 use std::mem;
+use std::ops::RangeBounds;
+use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 use crate::crdt::base::{Add, Read, Remove};
-use crate::crdt::{CmRDT, CvRDT, Reset, VectorClock, Version, VersionRange};
+use crate::crdt::index::{Attribute, Indexes};
+use crate::crdt::{CausalMerge, CmRDT, CvRDT, Reset, VectorClock, Version, VersionRange};
 
 pub trait Val<A: Ord>: Clone + Default + Reset<A> + CmRDT {}
 
@@ -17,7 +20,10 @@ impl<A, T> Val<A> for T where A: Ord, T: Clone + Default + Reset<A> + CmRDT {}
 pub struct Map<K: Ord, V: Val<A>, A: Ord + Hash> {
     clock: VectorClock<A>,
     entries: BTreeMap<K, Entry<V, A>>,
+    #[serde(with = "crate::crdt::serde_ext::hash_map_to_vec")]
     deferred: HashMap<VectorClock<A>, BTreeSet<K>>,
+    #[serde(skip)]
+    indexes: Indexes<K, V>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -37,6 +43,11 @@ pub enum Operation<K: Ord, V: Val<A>, A: Ord> {
         key: K,
         operation: V::Operation,
     },
+    /// Updates of several keys by a single actor under consecutive versions,
+    /// replicated as one operation.
+    Batch {
+        updates: Vec<(Version<A>, K, V::Operation)>,
+    },
 }
 
 impl<V: Val<A>, A: Ord> Default for Entry<V, A> {
@@ -54,11 +65,12 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash> Default for Map<K, V, A> {
             clock: Default::default(),
             entries: Default::default(),
             deferred: Default::default(),
+            indexes: Default::default(),
         }
     }
 }
 
-impl<K: Ord, V: Val<A>, A: Ord + Hash> Reset<A> for Map<K, V, A> {
+impl<K: Ord + Clone, V: Val<A>, A: Ord + Hash> Reset<A> for Map<K, V, A> {
     fn reset(&mut self, clock: &VectorClock<A>) {
         self.entries = mem::take(&mut self.entries)
             .into_iter()
@@ -86,6 +98,7 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash> Reset<A> for Map<K, V, A> {
             .collect();
 
         self.clock.reset(clock);
+        self.indexes.rebuild(self.entries.iter().map(|(k, e)| (k, &e.value)));
     }
 }
 
@@ -93,6 +106,18 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash> Reset<A> for Map<K, V, A> {
 pub enum CmRDTValidation<V: CmRDT, A> {
     SourceOrder(VersionRange<A>),
     Value(V::Validation),
+    /// A remove that names no keys.
+    EmptyKeySet,
+    /// A remove without causal context, it would not remove anything.
+    EmptyClock,
+    /// A remove names an actor at counter zero, a version no actor can have
+    /// written. Actors the map has not seen yet are `RemoveAhead`.
+    ZeroCounter(A),
+    /// A remove observed versions this map has not seen yet, they have to be
+    /// applied first.
+    RemoveAhead(VersionRange<A>),
+    /// A batch without updates, or with updates of more than one actor.
+    InvalidBatch,
 }
 
 impl<V: CmRDT + Debug, A: Debug> Display for CmRDTValidation<V, A> {
@@ -121,23 +146,65 @@ impl<K: Debug, V: CvRDT + Debug, A: Debug> Display for CvRDTValidation<K, V, A>
 
 impl<K: Debug, V: CvRDT + Debug, A: Debug> std::error::Error for CvRDTValidation<K, V, A> {}
 
-impl<K: Ord, V: Val<A> + Debug, A: Ord + Hash + Clone + Debug> CmRDT for Map<K, V, A> {
+impl<K: Ord + Clone, V: Val<A> + Debug, A: Ord + Hash + Clone + Debug> CmRDT for Map<K, V, A> {
     type Operation = Operation<K, V, A>;
     type Validation = CmRDTValidation<V, A>;
 
     fn validate_apply(&self, op: &Self::Operation) -> Result<(), Self::Validation> {
         match op {
-            Operation::Remove { .. } => Ok(()),
+            Operation::Remove { clock, key_set } => {
+                if key_set.is_empty() {
+                    return Err(CmRDTValidation::EmptyKeySet);
+                }
+                if clock.is_empty() {
+                    return Err(CmRDTValidation::EmptyClock);
+                }
+                for Version { actor, counter } in clock.iterator() {
+                    if counter == 0 {
+                        return Err(CmRDTValidation::ZeroCounter(actor.clone()));
+                    }
+                    // actors this map has not seen yet are behind as well,
+                    // their versions arrive before the remove applies
+                    let ours = self.clock.get(actor);
+                    if counter > ours {
+                        return Err(CmRDTValidation::RemoveAhead(VersionRange {
+                            actor: actor.clone(),
+                            counter_range: ours + 1..counter + 1,
+                        }));
+                    }
+                }
+                Ok(())
+            }
             Operation::Update { version: v, key, operation: o } => {
+                // the entry clock only holds the versions of this key, source
+                // order is a property of the whole map
                 self.clock
                     .validate_apply(v)
                     .map_err(CmRDTValidation::SourceOrder)?;
-                let entry = self.entries.get(key).cloned().unwrap_or_default();
-                entry
-                    .clock
-                    .validate_apply(v)
-                    .map_err(CmRDTValidation::SourceOrder)?;
-                entry.value.validate_apply(o).map_err(CmRDTValidation::Value)
+                match self.entries.get(key) {
+                    Some(entry) => entry.value.validate_apply(o),
+                    None => V::default().validate_apply(o),
+                }
+                .map_err(CmRDTValidation::Value)
+            }
+            Operation::Batch { updates } => {
+                let Some((first, _, _)) = updates.first() else {
+                    return Err(CmRDTValidation::InvalidBatch);
+                };
+                let mut clock = self.clock.clone();
+                for (v, key, o) in updates.iter() {
+                    if v.actor != first.actor {
+                        return Err(CmRDTValidation::InvalidBatch);
+                    }
+                    clock.validate_apply(v).map_err(CmRDTValidation::SourceOrder)?;
+                    clock.apply(v.clone());
+                    match self.entries.get(key) {
+                        Some(entry) => entry.value.validate_apply(o),
+                        None => V::default().validate_apply(o),
+                    }
+                    .map_err(CmRDTValidation::Value)?;
+                }
+                Ok(())
             }
         }
     }
@@ -146,20 +213,30 @@ impl<K: Ord, V: Val<A> + Debug, A: Ord + Hash + Clone + Debug> CmRDT for Map<K,
         match operation {
             Operation::Remove { clock, key_set } => self.apply_key_set_remove(key_set, clock),
             Operation::Update { version: v, key, operation: o } => {
-                if self.clock.get(&v.actor) >= v.counter {
-                    return;
+                if self.apply_update(v, key.clone(), o) {
+                    self.apply_deferred();
+                    self.reindex(&key);
+                }
+            }
+            Operation::Batch { updates } => {
+                let mut touched = Vec::new();
+                for (v, key, o) in updates {
+                    if self.apply_update(v, key.clone(), o) {
+                        touched.push(key);
+                    }
+                }
+                if !touched.is_empty() {
+                    self.apply_deferred();
+                }
+                for key in touched.iter() {
+                    self.reindex(key);
                 }
-                let entry = self.entries.entry(key).or_default();
-                entry.clock.apply(v.clone());
-                entry.value.apply(o);
-                self.clock.apply(v);
-                self.apply_deferred();
             }
         }
     }
 }
 
-impl<K: Ord + Clone + Debug, V: Val<A> + CvRDT + Debug, A: Ord + Hash + Clone + Debug> CvRDT for Map<K, V, A> {
+impl<K: Ord + Clone + Debug, V: Val<A> + CausalMerge<A> + Debug, A: Ord + Hash + Clone + Debug> CvRDT for Map<K, V, A> {
     type Validation = CvRDTValidation<K, V, A>;
 
     fn validate_merge(&self, other: &Self) -> Result<(), Self::Validation> {
@@ -188,19 +265,20 @@ impl<K: Ord + Clone + Debug, V: Val<A> + CvRDT + Debug, A: Ord + Hash + Clone +
     }
 
     fn merge(&mut self, other: Self) {
+        let touched: BTreeSet<K> = match self.indexes.is_empty() {
+            true => BTreeSet::new(),
+            false => self.entries.keys().chain(other.entries.keys()).cloned().collect(),
+        };
+
+        // the entry clocks only name the latest version of every actor, what
+        // one side holds is gone on the other side once its clock covers it
         self.entries = mem::take(&mut self.entries)
             .into_iter()
             .filter_map(|(key, mut entry)| {
                 if !other.entries.contains_key(&key) {
-                    if other.clock >= entry.clock {
-                        None
-                    } else {
-                        entry.clock.reset(&other.clock);
-                        let mut removed_information = other.clock.clone();
-                        removed_information.reset(&entry.clock);
-                        entry.value.reset(&removed_information);
-                        Some((key, entry))
-                    }
+                    entry.clock.reset(&other.clock);
+                    entry.value.reset(&other.clock);
+                    (!entry.clock.is_empty()).then_some((key, entry))
                 } else {
                     Some((key, entry))
                 }
@@ -215,22 +293,13 @@ impl<K: Ord + Clone + Debug, V: Val<A> + CvRDT + Debug, A: Ord + Hash + Clone +
                 if common.is_empty() {
                     self.entries.remove(&key).unwrap();
                 } else {
-                    our_entry.value.merge(entry.value);
-
-                    let mut information_that_was_deleted = entry.clock.clone();
-                    information_that_was_deleted.merge(our_entry.clock.clone());
-                    information_that_was_deleted.reset(&common);
-                    our_entry.value.reset(&information_that_was_deleted);
+                    our_entry.value.causal_merge(entry.value, &self.clock, &other.clock);
                     our_entry.clock = common;
                 }
             } else {
-                if self.clock >= entry.clock {
-                } else {
-                    entry.clock.reset(&self.clock);
-
-                    let mut information_we_deleted = self.clock.clone();
-                    information_we_deleted.reset(&entry.clock);
-                    entry.value.reset(&information_we_deleted);
+                entry.clock.reset(&self.clock);
+                entry.value.reset(&self.clock);
+                if !entry.clock.is_empty() {
                     self.entries.insert(key, entry);
                 }
             }
@@ -243,6 +312,17 @@ impl<K: Ord + Clone + Debug, V: Val<A> + CvRDT + Debug, A: Ord + Hash + Clone +
         self.clock.merge(other.clock);
 
         self.apply_deferred();
+
+        for key in touched.iter() {
+            self.reindex(key);
+        }
+    }
+}
+
+impl<K: Ord + Clone + Debug, V: Val<A> + CausalMerge<A> + Debug, A: Ord + Hash + Clone + Debug> CausalMerge<A> for Map<K, V, A> {
+    /// A nested map carries its own clock, the container clocks add nothing.
+    fn causal_merge(&mut self, other: Self, _ours: &VectorClock<A>, _theirs: &VectorClock<A>) {
+        self.merge(other);
     }
 }
 
@@ -267,18 +347,28 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
         }
     }
 
+    /// Read a single entry.
+    ///
+    /// The remove context is the full map clock rather than the entry clock:
+    /// values carry the map clock they were written under, so only the full
+    /// clock covers everything the reader has observed of them.
     pub fn get(&self, key: &K) -> Read<Option<V>, A> {
         let add_clock = self.clock.clone();
         let entry_opt = self.entries.get(key);
         Read {
             add_clock,
             remove_clock: entry_opt
-                .map(|map_entry| map_entry.clock.clone())
+                .map(|_| self.clock.clone())
                 .unwrap_or_default(),
             value: entry_opt.map(|map_entry| map_entry.value.clone()),
         }
     }
 
+    /// Borrow the value of a single entry, without its causal context.
+    pub fn get_value(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
     pub fn update<F>(&self, key: impl Into<K>, a: Add<A>, f: F) -> Operation<K, V, A> where F: FnOnce(&V, Add<A>) -> V::Operation
     {
         let key = key.into();
@@ -291,6 +381,30 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
         Operation::Update { version, key, operation }
     }
 
+    /// Update several keys in one operation, each under the next version of
+    /// `actor` as if they were updated one after the other.
+    pub fn update_all<F>(&self, actor: A, updates: impl IntoIterator<Item = (K, F)>) -> Operation<K, V, A>
+    where
+        A: Debug,
+        F: FnOnce(&V, Add<A>) -> V::Operation,
+    {
+        let mut clock = self.clock.clone();
+        let updates = updates
+            .into_iter()
+            .map(|(key, f)| {
+                let version = clock.increment(actor.clone());
+                clock.apply(version.clone());
+                let add = Add { clock: clock.clone(), version: version.clone() };
+                let operation = match self.entries.get(&key).map(|e| &e.value) {
+                    Some(data) => f(data, add),
+                    None => f(&V::default(), add),
+                };
+                (version, key, operation)
+            })
+            .collect();
+        Operation::Batch { updates }
+    }
+
     pub fn remove(&self, key: impl Into<K>, r: Remove<A>) -> Operation<K, V, A> {
         let mut keyset = BTreeSet::new();
         keyset.insert(key.into());
@@ -308,34 +422,6 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
         }
     }
 
-    fn apply_deferred(&mut self) {
-        let deferred = mem::take(&mut self.deferred);
-        for (clock, keys) in deferred {
-            self.apply_key_set_remove(keys, clock);
-        }
-    }
-
-    fn apply_key_set_remove(&mut self, mut keyset: BTreeSet<K>, clock: VectorClock<A>) {
-        for key in keyset.iter() {
-            if let Some(entry) = self.entries.get_mut(key) {
-                entry.clock.reset(&clock);
-                if entry.clock.is_empty() {
-                    self.entries.remove(key);
-                } else {
-                    entry.value.reset(&clock);
-                }
-            }
-        }
-
-        match self.clock.partial_cmp(&clock) {
-            None | Some(Ordering::Less) => {
-                let deferred_set = self.deferred.entry(clock).or_default();
-                deferred_set.append(&mut keyset);
-            }
-            _ => {}
-        }
-    }
-
     pub fn keys(&self) -> impl Iterator<Item = Read<&K, A>> {
         self.entries.iter().map(move |(k, v)| Read {
             add_clock: self.clock.clone(),
@@ -392,3 +478,110 @@ impl<K: Ord, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
         })
     }
 }
+
+impl<K: Ord + Clone, V: Val<A>, A: Ord + Hash + Clone> Map<K, V, A> {
+    /// Declare a secondary index over the values of this map.
+    ///
+    /// The index is built from the current entries and kept up to date on
+    /// `apply`, `merge` and `reset`. Declaring an index under an existing name
+    /// replaces it. Indexes are local to this replica, they are not serialized.
+    ///
+    /// ```rust
+    /// use libtheia::crdt::{ CmRDT, Map };
+    /// use libtheia::crdt::index::Attribute;
+    /// use libtheia::crdt::multi_value::MultiValue;
+    ///
+    /// let mut map: Map<&str, MultiValue<i32, u8>, u8> = Map::new();
+    /// map.declare_index("cores", |mv| mv.read().value.into_iter().map(Attribute::from).collect());
+    ///
+    /// map.apply(map.update("small", map.get(&"small").derive_add(1), |v, a| v.write(4, a)));
+    /// map.apply(map.update("large", map.get(&"large").derive_add(1), |v, a| v.write(64, a)));
+    ///
+    /// assert_eq!(map.lookup("cores", 64), vec![&"large"]);
+    /// assert_eq!(map.lookup_range("cores", Attribute::Int(8)..), vec![&"large"]);
+    /// ```
+    pub fn declare_index<F>(&mut self, name: &str, extractor: F)
+    where
+        F: Fn(&V) -> Vec<Attribute> + Send + Sync + 'static,
+    {
+        let entries = self.entries.iter().map(|(k, e)| (k, &e.value));
+        self.indexes.declare(name, Arc::new(extractor), entries);
+    }
+
+    /// Drop a secondary index, returns false if it was not declared.
+    pub fn drop_index(&mut self, name: &str) -> bool {
+        self.indexes.drop_index(name)
+    }
+
+    pub fn index_names(&self) -> impl Iterator<Item = &str> {
+        self.indexes.names()
+    }
+
+    /// Keys whose value has `attribute` in index `name`, in key order.
+    /// An undeclared index yields no keys.
+    pub fn lookup(&self, name: &str, attribute: impl Into<Attribute>) -> Vec<&K> {
+        self.indexes.lookup(name, &attribute.into())
+    }
+
+    /// Keys whose value has an attribute within `range` in index `name`, in
+    /// key order. Only attributes of the same kind as the bounds match, a
+    /// reversed or empty range yields no keys.
+    pub fn lookup_range<R: RangeBounds<Attribute>>(&self, name: &str, range: R) -> Vec<&K> {
+        self.indexes.lookup_range(name, range)
+    }
+
+    /// Attributes any value has in index `name`, in order.
+    pub fn index_attributes(&self, name: &str) -> Vec<&Attribute> {
+        self.indexes.attributes(name)
+    }
+
+    /// Apply a single update, false if it was seen before.
+    fn apply_update(&mut self, v: Version<A>, key: K, o: V::Operation) -> bool
+    where
+        A: Debug,
+    {
+        if self.clock.get(&v.actor) >= v.counter {
+            return false;
+        }
+        let entry = self.entries.entry(key).or_default();
+        entry.clock.apply(v.clone());
+        entry.value.apply(o);
+        self.clock.apply(v);
+        true
+    }
+
+    fn reindex(&mut self, key: &K) {
+        if !self.indexes.is_empty() {
+            self.indexes.update(key, self.entries.get(key).map(|e| &e.value));
+        }
+    }
+
+    fn apply_deferred(&mut self) {
+        let deferred = mem::take(&mut self.deferred);
+        for (clock, keys) in deferred {
+            self.apply_key_set_remove(keys, clock);
+        }
+    }
+
+    fn apply_key_set_remove(&mut self, mut keyset: BTreeSet<K>, clock: VectorClock<A>) {
+        for key in keyset.iter() {
+            if let Some(entry) = self.entries.get_mut(key) {
+                entry.clock.reset(&clock);
+                if entry.clock.is_empty() {
+                    self.entries.remove(key);
+                } else {
+                    entry.value.reset(&clock);
+                }
+            }
+            self.reindex(key);
+        }
+
+        match self.clock.partial_cmp(&clock) {
+            None | Some(Ordering::Less) => {
+                let deferred_set = self.deferred.entry(clock).or_default();
+                deferred_set.append(&mut keyset);
+            }
+            _ => {}
+        }
+    }
+}
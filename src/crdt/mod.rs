@@ -5,7 +5,7 @@ pub mod vector_clock;
 pub use vector_clock::VectorClock;
 
 pub mod traits;
-pub use traits::{Actor, CvRDT, CmRDT, Reset};
+pub use traits::{Actor, CausalMerge, CvRDT, CmRDT, Reset};
 
 pub mod version;
 pub use version::{Version, VersionRange};
@@ -15,10 +15,17 @@ mod serde_ext;
 pub mod map;
 pub use map::Map;
 
+pub mod index;
+
+pub mod envelope;
+
 pub mod multi_value;
-mod list;
+pub mod list;
 pub use list::List;
 
 mod identifier;
 pub use identifier::Identifier;
 
+#[cfg(feature = "testing")]
+pub mod testing;
+
@@ -5,7 +5,7 @@ use core::mem;
 
 use serde::{Deserialize, Serialize};
 use crate::crdt::base::{Add, Read};
-use crate::crdt::traits::{CmRDT, CvRDT, Reset};
+use crate::crdt::traits::{CausalMerge, CmRDT, CvRDT, Reset};
 use crate::crdt::vector_clock::VectorClock;
 
 /// Multi-Value storage
@@ -42,6 +42,21 @@ pub enum Operation<V, A: Ord> {
     },
 }
 
+/// Reasons a `Put` cannot be applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Validation {
+    /// The write carries no causal context, it would be dropped on apply.
+    EmptyClock,
+}
+
+impl Display for Validation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for Validation {}
+
 impl<V: Display, A: Ord + Display> Display for MultiValue<V, A> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "|")?;
@@ -78,18 +93,10 @@ impl<V: PartialEq, A: Ord> PartialEq for MultiValue<V, A> {
 impl<V: Eq, A: Ord> Eq for MultiValue<V, A> {}
 
 impl<V, A: Ord> Reset<A> for MultiValue<V, A> {
+    /// Drop the values `clock` has seen. A value keeps its whole clock until
+    /// then, so both sides of a state merge still recognize it.
     fn reset(&mut self, clock: &VectorClock<A>) {
-        self.values = mem::take(&mut self.values)
-            .into_iter()
-            .filter_map(|(mut val_clock, val)| {
-                val_clock.reset(clock);
-                if val_clock.is_empty() {
-                    None // remove this value from the register
-                } else {
-                    Some((val_clock, val))
-                }
-            })
-            .collect()
+        self.values.retain(|(val_clock, _)| !seen(val_clock, clock));
     }
 }
 
@@ -107,9 +114,14 @@ impl<V, A: Ord> CvRDT for MultiValue<V, A> {
     }
 
     fn merge(&mut self, other: Self) {
+        // a reset can leave concurrent values with comparable clocks, values
+        // both sides hold are kept so merging stays idempotent
         self.values = mem::take(&mut self.values)
             .into_iter()
-            .filter(|(clock, _)| other.values.iter().filter(|(c, _)| clock < c).count() == 0)
+            .filter(|(clock, _)| {
+                other.values.iter().any(|(c, _)| clock == c)
+                    || other.values.iter().filter(|(c, _)| clock < c).count() == 0
+            })
             .collect();
 
         self.values.extend(
@@ -123,12 +135,26 @@ impl<V, A: Ord> CvRDT for MultiValue<V, A> {
     }
 }
 
+impl<V, A: Ord> CausalMerge<A> for MultiValue<V, A> {
+    /// Values both sides hold are kept, a value only one side holds is kept
+    /// unless the other side has seen it, then it was overwritten or removed.
+    fn causal_merge(&mut self, other: Self, ours: &VectorClock<A>, theirs: &VectorClock<A>) {
+        let mut other = other.values;
+        self.values.retain(|(clock, _)| other.iter().any(|(c, _)| c == clock) || !seen(clock, theirs));
+        other.retain(|(clock, _)| self.values.iter().all(|(c, _)| c != clock) && !seen(clock, ours));
+        self.values.extend(other);
+    }
+}
+
 impl<V, A: Ord> CmRDT for MultiValue<V, A> {
     type Operation = Operation<V, A>;
-    type Validation = Infallible;
+    type Validation = Validation;
 
-    fn validate_apply(&self, _operation: &Self::Operation) -> Result<(), Self::Validation> {
-        Ok(())
+    fn validate_apply(&self, operation: &Self::Operation) -> Result<(), Self::Validation> {
+        match operation {
+            Operation::Put { clock, .. } if clock.is_empty() => Err(Validation::EmptyClock),
+            Operation::Put { .. } => Ok(()),
+        }
     }
 
     fn apply(&mut self, operation: Self::Operation) {
@@ -180,6 +206,11 @@ impl<V, A: Ord + Clone + Debug> MultiValue<V, A> {
         }
     }
 
+    /// Concurrent values together with the clock they were written under.
+    pub fn values_with_clock(&self) -> impl Iterator<Item = (&VectorClock<A>, &V)> {
+        self.values.iter().map(|(clock, val)| (clock, val))
+    }
+
     pub fn read_all(&self) -> Read<(), A> {
         let clock = self.clock();
         Read {
@@ -197,4 +228,9 @@ impl<V, A: Ord + Clone + Debug> MultiValue<V, A> {
                 accum_clock
             })
     }
-}
\ No newline at end of file
+}
+
+/// True if `by` has seen the write under `clock`.
+fn seen<A: Ord>(clock: &VectorClock<A>, by: &VectorClock<A>) -> bool {
+    matches!(by.partial_cmp(clock), Some(Ordering::Greater | Ordering::Equal))
+}
@@ -26,4 +26,32 @@ pub(crate) mod btree_map_to_vec {
         let vec: Vec<(K, V)> = Vec::deserialize(deserializer)?;
         Ok(vec.into_iter().collect())
     }
-}
\ No newline at end of file
+}
+
+/// Maps keyed by structured values (e.g. vector clocks) have no JSON object
+/// representation, store them as a list of pairs.
+pub(crate) mod hash_map_to_vec {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    pub(crate) fn serialize<S, K, V>(v: &HashMap<K, V>, s: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        let vec = Vec::from_iter(v.iter());
+        vec.serialize(s)
+    }
+
+    pub(crate) fn deserialize<'de, D, K, V>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        D: Deserializer<'de>,
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+    {
+        let vec: Vec<(K, V)> = Vec::deserialize(deserializer)?;
+        Ok(vec.into_iter().collect())
+    }
+}
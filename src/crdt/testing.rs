@@ -0,0 +1,369 @@
+//! Convergence harness for Conflict-free Replicated Data Types.
+//!
+//! Runs a set of simulated replicas that generate operations, exchange them
+//! in random (but causal) order, receive duplicates, get partitioned and
+//! synchronize their full state. Every operation that is causally ready must
+//! pass `validate_apply`. At the end every replica must hold the same
+//! state, and the state snapshots taken along the way must merge idempotently
+//! and commutatively.
+//!
+//! Only available with the `testing` feature.
+//!
+//! ``` rust
+//! use libtheia::crdt::{CmRDT, VectorClock};
+//! use libtheia::crdt::testing::{Config, Simulation};
+//!
+//! let report = Simulation::new(VectorClock::new(), vec!['a', 'b', 'c'], |clock: &VectorClock<char>, actor: &char, _rng: &mut _| {
+//!     Some(clock.increment(*actor))
+//! })
+//! .with_config(Config { seed: 7, ..Config::default() })
+//! .run()
+//! .unwrap();
+//!
+//! assert!(report.operations > 0);
+//! ```
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug, Display};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::crdt::{CmRDT, CvRDT};
+
+/// Knobs for a simulated run.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub seed: u64,
+    pub steps: usize,
+    pub duplicate_rate: f64,
+    pub partition_rate: f64,
+    pub merge_rate: f64,
+    pub snapshots: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            steps: 200,
+            duplicate_rate: 0.2,
+            partition_rate: 0.05,
+            merge_rate: 0.1,
+            snapshots: 6,
+        }
+    }
+}
+
+/// Produces the next operation for a replica, given its current state.
+///
+/// Returning `None` skips the turn, e.g. when deleting from an empty list.
+pub trait OpGenerator<T: CmRDT, A> {
+    fn generate(&mut self, state: &T, actor: &A, rng: &mut StdRng) -> Option<T::Operation>;
+}
+
+impl<T, A, F> OpGenerator<T, A> for F
+where
+    T: CmRDT,
+    F: FnMut(&T, &A, &mut StdRng) -> Option<T::Operation>,
+{
+    fn generate(&mut self, state: &T, actor: &A, rng: &mut StdRng) -> Option<T::Operation> {
+        self(state, actor, rng)
+    }
+}
+
+/// Counters describing what a run exercised.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub operations: usize,
+    pub deliveries: usize,
+    pub duplicates: usize,
+    pub merges: usize,
+    pub partitions: usize,
+}
+
+/// A CRDT law that did not hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    Divergence { left: usize, right: usize, detail: String },
+    Rejected { replica: usize, detail: String },
+    DuplicateApply { replica: usize, detail: String },
+    MergeRejected { detail: String },
+    MergeIdempotence { detail: String },
+    MergeCommutativity { detail: String },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for Violation {}
+
+struct Logged<O> {
+    origin: usize,
+    op: O,
+    deps: BTreeSet<usize>,
+}
+
+struct Replica<T, A> {
+    state: T,
+    actor: A,
+    seen: BTreeSet<usize>,
+    inbox: Vec<usize>,
+}
+
+/// A set of replicas of `T`, one per actor, driven by a generator `G`.
+pub struct Simulation<T: CmRDT, A, G> {
+    config: Config,
+    equivalent: fn(&T, &T) -> bool,
+    initial: T,
+    actors: Vec<A>,
+    generator: G,
+}
+
+impl<T, A, G> Simulation<T, A, G>
+where
+    T: CmRDT + CvRDT + Clone + PartialEq + Debug,
+    T::Operation: Clone,
+    A: Clone,
+    G: OpGenerator<T, A>,
+{
+    /// Simulation instance
+    ///
+    /// * `initial` - state every replica starts from.
+    /// * `actors` - one replica is created per actor.
+    /// * `generator` - operation generator.
+    pub fn new(initial: T, actors: Vec<A>, generator: G) -> Self {
+        Self {
+            config: Config::default(),
+            equivalent: T::eq,
+            initial,
+            actors,
+            generator,
+        }
+    }
+
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Compare replicas with `equivalent` instead of `PartialEq`, for types
+    /// whose causal metadata may legitimately differ between replicas that
+    /// hold the same observable value.
+    pub fn with_equivalence(mut self, equivalent: fn(&T, &T) -> bool) -> Self {
+        self.equivalent = equivalent;
+        self
+    }
+
+    /// Run the simulation, returning the first law that was broken.
+    pub fn run(mut self) -> Result<Report, Violation> {
+        let mut net = Network {
+            log: Vec::new(),
+            partition: None,
+            rng: StdRng::seed_from_u64(self.config.seed),
+            report: Report::default(),
+        };
+        let mut snapshots: Vec<T> = vec![self.initial.clone()];
+        let mut replicas: Vec<Replica<T, A>> = self
+            .actors
+            .iter()
+            .map(|actor| Replica {
+                state: self.initial.clone(),
+                actor: actor.clone(),
+                seen: BTreeSet::new(),
+                inbox: Vec::new(),
+            })
+            .collect();
+
+        if replicas.is_empty() {
+            return Ok(net.report);
+        }
+
+        for _ in 0..self.config.steps {
+            if net.rng.gen_bool(self.config.partition_rate) {
+                net.partition = match net.partition {
+                    Some(_) => None,
+                    None => {
+                        net.report.partitions += 1;
+                        Some(replicas.iter().map(|_| net.rng.gen_bool(0.5)).collect())
+                    }
+                };
+            }
+
+            if replicas.len() > 1 && net.rng.gen_bool(self.config.merge_rate) {
+                let to = net.rng.gen_range(0..replicas.len());
+                let from = net.rng.gen_range(0..replicas.len());
+                if to != from && net.reachable(to, from) {
+                    let state = replicas[from].state.clone();
+                    let seen = replicas[from].seen.clone();
+                    replicas[to]
+                        .state
+                        .validate_merge(&state)
+                        .map_err(|e| Violation::MergeRejected { detail: format!("{:?}", e) })?;
+                    replicas[to].state.merge(state);
+                    replicas[to].seen.extend(seen);
+                    net.report.merges += 1;
+                }
+            }
+
+            let index = net.rng.gen_range(0..replicas.len());
+            if net.rng.gen_bool(0.5) {
+                let replica = &mut replicas[index];
+                if let Some(op) = self.generator.generate(&replica.state, &replica.actor, &mut net.rng) {
+                    let id = net.log.len();
+                    net.log.push(Logged {
+                        origin: index,
+                        op: op.clone(),
+                        deps: replica.seen.clone(),
+                    });
+                    replica.state.apply(op);
+                    replica.seen.insert(id);
+                    for (other, r) in replicas.iter_mut().enumerate() {
+                        if other != index {
+                            r.inbox.push(id);
+                        }
+                    }
+                    net.report.operations += 1;
+                }
+            } else {
+                let rate = self.config.duplicate_rate;
+                net.deliver(index, &mut replicas[index], rate, self.equivalent)?;
+            }
+
+            if snapshots.len() < self.config.snapshots && net.rng.gen_bool(0.1) {
+                snapshots.push(replicas[index].state.clone());
+            }
+        }
+
+        // heal and drain every inbox in causal order
+        net.partition = None;
+        loop {
+            let mut progress = false;
+            for (index, replica) in replicas.iter_mut().enumerate() {
+                while net.deliver(index, replica, 0.0, self.equivalent)? {
+                    progress = true;
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+
+        for (right, replica) in replicas.iter().enumerate().skip(1) {
+            if !(self.equivalent)(&replicas[0].state, &replica.state) {
+                return Err(Violation::Divergence {
+                    left: 0,
+                    right,
+                    detail: format!("{:?} != {:?}", replicas[0].state, replica.state),
+                });
+            }
+        }
+
+        snapshots.push(replicas[0].state.clone());
+        check_merge_laws(&snapshots, self.equivalent)?;
+
+        Ok(net.report)
+    }
+}
+
+/// Everything the replicas share: the operation log, the current partition
+/// and the randomness driving delivery.
+struct Network<O> {
+    log: Vec<Logged<O>>,
+    partition: Option<Vec<bool>>,
+    rng: StdRng,
+    report: Report,
+}
+
+impl<O: Clone> Network<O> {
+    fn reachable(&self, left: usize, right: usize) -> bool {
+        match &self.partition {
+            Some(sides) => sides[left] == sides[right],
+            None => true,
+        }
+    }
+
+    /// Deliver one random causally ready message from the inbox, returns
+    /// false when nothing could be delivered.
+    fn deliver<T, A>(
+        &mut self,
+        index: usize,
+        replica: &mut Replica<T, A>,
+        duplicate_rate: f64,
+        equivalent: fn(&T, &T) -> bool,
+    ) -> Result<bool, Violation>
+    where
+        T: CmRDT<Operation = O> + Clone + Debug,
+    {
+        let ready: Vec<usize> = replica
+            .inbox
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| self.reachable(index, self.log[**id].origin))
+            .filter(|(_, id)| self.log[**id].deps.is_subset(&replica.seen))
+            .map(|(position, _)| position)
+            .collect();
+        if ready.is_empty() {
+            return Ok(false);
+        }
+
+        let position = ready[self.rng.gen_range(0..ready.len())];
+        let id = replica.inbox[position];
+        if !self.rng.gen_bool(duplicate_rate) {
+            replica.inbox.swap_remove(position);
+        }
+
+        if replica.seen.contains(&id) {
+            let before = replica.state.clone();
+            replica.state.apply(self.log[id].op.clone());
+            if !equivalent(&before, &replica.state) {
+                return Err(Violation::DuplicateApply {
+                    replica: index,
+                    detail: format!("{:?} became {:?}", before, replica.state),
+                });
+            }
+            self.report.duplicates += 1;
+        } else {
+            // causally ready operations must always pass validation
+            replica
+                .state
+                .validate_apply(&self.log[id].op)
+                .map_err(|e| Violation::Rejected { replica: index, detail: format!("{:?}", e) })?;
+            replica.state.apply(self.log[id].op.clone());
+            replica.seen.insert(id);
+            self.report.deliveries += 1;
+        }
+        Ok(true)
+    }
+}
+
+fn check_merge_laws<T>(snapshots: &[T], equivalent: fn(&T, &T) -> bool) -> Result<(), Violation>
+where
+    T: CvRDT + Clone + PartialEq + Debug,
+{
+    for left in snapshots.iter() {
+        let mut twice = left.clone();
+        twice.merge(left.clone());
+        if !equivalent(&twice, left) {
+            return Err(Violation::MergeIdempotence {
+                detail: format!("{:?} merged with itself became {:?}", left, twice),
+            });
+        }
+
+        for right in snapshots.iter() {
+            let mut lr = left.clone();
+            lr.merge(right.clone());
+            let mut rl = right.clone();
+            rl.merge(left.clone());
+            if !equivalent(&lr, &rl) {
+                return Err(Violation::MergeCommutativity {
+                    detail: format!("{:?} != {:?}", lr, rl),
+                });
+            }
+        }
+    }
+    Ok(())
+}
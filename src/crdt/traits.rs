@@ -27,4 +27,11 @@ pub trait CmRDT {
 
 pub trait Reset<A: Ord> {
     fn reset(&mut self, clock: &VectorClock<A>);
-}
\ No newline at end of file
+}
+
+/// State merge of a value held by a causal container, e.g. an entry of a
+/// `Map`. `ours` and `theirs` are the container clocks of both sides: what
+/// only one side holds was removed by the other side once its clock covers it.
+pub trait CausalMerge<A: Ord>: CvRDT {
+    fn causal_merge(&mut self, other: Self, ours: &VectorClock<A>, theirs: &VectorClock<A>);
+}
@@ -0,0 +1,224 @@
+//! Structural diff between snapshots of data centres.
+//!
+//! Compute, storage and interconnects are matched by name and reported as
+//! added, removed or modified. Modified resources list their changed fields,
+//! nested disks, GPUs and links are matched by name as well so a changed disk
+//! shows as `disks[d0].capacity` rather than as a new list of disks.
+//!
+//! A diff renders for people through `Display` and for tools through
+//! `to_json`.
+//!
+//! ```rust
+//! use libtheia::diff;
+//! use libtheia::models::data_centre::{Compute, DataCentre};
+//! use libtheia::models::units::{Bytes, Hertz};
+//!
+//! let mut old = DataCentre::new("dc1".to_string(), 1);
+//! old.add_compute(Compute::new("n1".to_string(), 64, Hertz::ghz(2), Bytes::gib(512))).unwrap();
+//! let mut new = old.clone();
+//! new.update_compute(Compute::new("n1".to_string(), 64, Hertz::ghz(2), Bytes::tib(1))).unwrap();
+//!
+//! let d = diff::data_centres(&old, &new);
+//! assert_eq!(d.to_string(), "~ dc1/compute/n1\n    ~ ram: 512GiB -> 1TiB\n");
+//! ```
+
+use std::fmt::{self, Debug, Display};
+
+use serde::Serialize;
+use serde_json::Value;
+use crate::models::collection::{Collection, Named};
+use crate::models::data_centre::DataCentre;
+use crate::models::infrastructure::LogicalInfrastructure;
+use crate::reconcile::Kind;
+
+/// A changed field of a resource, `old` is missing for added and `new` for
+/// removed fields.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Field {
+    pub path: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => write!(f, "~ {}: {} -> {}", self.path, Plain(old), Plain(new)),
+            (None, Some(new)) => write!(f, "+ {}: {}", self.path, Plain(new)),
+            (Some(old), None) => write!(f, "- {}: {}", self.path, Plain(old)),
+            (None, None) => write!(f, "  {}", self.path),
+        }
+    }
+}
+
+/// Values as written in inventory, strings without quotes.
+struct Plain<'a>(&'a Value);
+
+impl Display for Plain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::String(s) => write!(f, "{}", s),
+            v => write!(f, "{}", v),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    DataCentreAdded { data_centre: String },
+    DataCentreRemoved { data_centre: String },
+    Added { data_centre: String, kind: Kind, name: String },
+    Removed { data_centre: String, kind: Kind, name: String },
+    Modified { data_centre: String, kind: Kind, name: String, fields: Vec<Field> },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::DataCentreAdded { data_centre } => writeln!(f, "+ {}", data_centre),
+            Change::DataCentreRemoved { data_centre } => writeln!(f, "- {}", data_centre),
+            Change::Added { data_centre, kind, name } => writeln!(f, "+ {}/{}/{}", data_centre, kind, name),
+            Change::Removed { data_centre, kind, name } => writeln!(f, "- {}/{}/{}", data_centre, kind, name),
+            Change::Modified { data_centre, kind, name, fields } => {
+                writeln!(f, "~ {}/{}/{}", data_centre, kind, name)?;
+                for field in fields {
+                    writeln!(f, "    {}", field)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+///
+/// Changes between two snapshots
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The diff as a JSON document, one object per change tagged with `change`.
+    pub fn to_json(&self) -> String {
+        // strings and json values always serialize
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+impl Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.changes.iter() {
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Diff two snapshots of a data centre
+///
+/// * `old` - earlier snapshot.
+/// * `new` - later snapshot.
+pub fn data_centres(old: &DataCentre, new: &DataCentre) -> Diff {
+    let mut diff = Diff::default();
+    data_centre(&mut diff, old, new);
+    diff
+}
+
+/// Diff two snapshots of an infrastructure, data centres are matched by name
+///
+/// * `old` - earlier snapshot.
+/// * `new` - later snapshot.
+pub fn infrastructures(old: &LogicalInfrastructure, new: &LogicalInfrastructure) -> Diff {
+    let mut diff = Diff::default();
+    for dc in old.data_centres.iter() {
+        if new.get_data_centre(&dc.name).is_none() {
+            diff.changes.push(Change::DataCentreRemoved { data_centre: dc.name.clone() });
+        }
+    }
+    for dc in new.data_centres.iter() {
+        match old.get_data_centre(&dc.name) {
+            Some(o) => data_centre(&mut diff, o, dc),
+            None => diff.changes.push(Change::DataCentreAdded { data_centre: dc.name.clone() }),
+        }
+    }
+    diff
+}
+
+fn data_centre(diff: &mut Diff, old: &DataCentre, new: &DataCentre) {
+    collection(diff, &new.name, Kind::Compute, &old.compute, &new.compute);
+    collection(diff, &new.name, Kind::Storage, &old.storage, &new.storage);
+    collection(diff, &new.name, Kind::InterConnect, &old.interconnects, &new.interconnects);
+}
+
+fn collection<T: Named + Clone + Debug + Serialize>(diff: &mut Diff, dc: &str, kind: Kind, old: &Collection<T>, new: &Collection<T>) {
+    for o in old.iter().filter(|o| !new.contains(o.name())) {
+        diff.changes.push(Change::Removed { data_centre: dc.to_string(), kind, name: o.name().to_string() });
+    }
+    for n in new.iter() {
+        let Some(o) = old.get(n.name()) else {
+            diff.changes.push(Change::Added { data_centre: dc.to_string(), kind, name: n.name().to_string() });
+            continue;
+        };
+        let mut changed = Vec::new();
+        fields("", &to_value(o), &to_value(n), &mut changed);
+        if !changed.is_empty() {
+            diff.changes.push(Change::Modified {
+                data_centre: dc.to_string(),
+                kind,
+                name: n.name().to_string(),
+                fields: changed,
+            });
+        }
+    }
+}
+
+fn to_value<T: Serialize>(t: &T) -> Value {
+    serde_json::to_value(t).unwrap_or(Value::Null)
+}
+
+/// Collect the fields that differ between `old` and `new` below `path`.
+fn fields(path: &str, old: &Value, new: &Value, out: &mut Vec<Field>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (Value::Object(o), Value::Object(n)) => {
+            let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+            for (key, value) in o.iter() {
+                match n.get(key) {
+                    Some(new) => fields(&join(key), value, new, out),
+                    None => out.push(Field { path: join(key), old: Some(value.clone()), new: None }),
+                }
+            }
+            for (key, value) in n.iter().filter(|(key, _)| !o.contains_key(*key)) {
+                out.push(Field { path: join(key), old: None, new: Some(value.clone()) });
+            }
+        }
+        (Value::Array(o), Value::Array(n)) if named(o) && named(n) => {
+            let name = |v: &Value| v.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+            let find = |items: &[Value], key: &str| items.iter().find(|v| name(v) == key).cloned();
+            for value in o.iter() {
+                let key = format!("{}[{}]", path, name(value));
+                match find(n, &name(value)) {
+                    Some(new) => fields(&key, value, &new, out),
+                    None => out.push(Field { path: key, old: Some(value.clone()), new: None }),
+                }
+            }
+            for value in n.iter().filter(|v| find(o, &name(v)).is_none()) {
+                out.push(Field { path: format!("{}[{}]", path, name(value)), old: None, new: Some(value.clone()) });
+            }
+        }
+        _ => out.push(Field { path: path.to_string(), old: Some(old.clone()), new: Some(new.clone()) }),
+    }
+}
+
+/// Whether the items of a list are resources with a name to match them by.
+fn named(items: &[Value]) -> bool {
+    items.iter().all(|v| v.get("name").is_some_and(Value::is_string))
+}
@@ -0,0 +1,119 @@
+//! Crate wide error.
+//!
+//! Every module reports its own failures with its own error type, so callers
+//! that care can match on exactly what went wrong. `TheiaError` collects all of
+//! them together with failures of the store, of (de)serialization and of the
+//! configuration, every module error converts into it with `?`:
+//! - storage: `Storage`, `Envelope`;
+//! - serialization: `Serialization`, `Unit`;
+//! - validation: `Collection`, `Location`, `Selector`, `Requirement`, `Pool`,
+//!   `Score`, `Inventory`;
+//! - planning: `Claim`, `Lifecycle`, `Reconcile`;
+//! - configuration: `Config`.
+//!
+//! The write APIs of `DataCentre` and `LogicalInfrastructure` return
+//! `TheiaError` directly, with the module error as its variant.
+//!
+//! ```rust
+//! use libtheia::error::TheiaError;
+//! use libtheia::models::collection::CollectionError;
+//! use libtheia::models::infrastructure::LogicalInfrastructure;
+//!
+//! let mut infra = LogicalInfrastructure::new(1);
+//! assert_eq!(
+//!     infra.remove_data_centre("dc1"),
+//!     Err(TheiaError::Collection(CollectionError::NotFound("dc1".to_string())))
+//! );
+//! ```
+
+use std::fmt::{self, Debug, Display};
+
+use crate::crdt::envelope::EnvelopeError;
+use crate::inventory::InventoryError;
+use crate::models::capability::RequirementError;
+use crate::models::collection::CollectionError;
+use crate::models::cpu::ScoreError;
+use crate::models::hierarchy::LocationError;
+use crate::models::labels::SelectorError;
+use crate::models::lifecycle::LifecycleError;
+use crate::models::pool::PoolError;
+use crate::models::resource::ClaimError;
+use crate::models::units::UnitError;
+use crate::reconcile::ReconcileError;
+
+/// Reasons any operation of the crate fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TheiaError {
+    /// The store could not be reached or refused the request.
+    Storage(String),
+    /// A value could not be serialized or deserialized.
+    Serialization(String),
+    /// The configuration could not be loaded.
+    Config(String),
+    /// A stored snapshot could not be sealed or opened.
+    Envelope(EnvelopeError),
+    Unit(UnitError),
+    Collection(CollectionError),
+    Location(LocationError),
+    Selector(SelectorError),
+    Requirement(RequirementError),
+    Pool(PoolError),
+    Score(ScoreError),
+    Inventory(InventoryError),
+    Claim(ClaimError),
+    Lifecycle(LifecycleError),
+    Reconcile(ReconcileError),
+}
+
+impl Display for TheiaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for TheiaError {}
+
+impl From<redis::RedisError> for TheiaError {
+    fn from(e: redis::RedisError) -> Self {
+        TheiaError::Storage(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for TheiaError {
+    fn from(e: serde_json::Error) -> Self {
+        TheiaError::Serialization(e.to_string())
+    }
+}
+
+impl From<config::ConfigError> for TheiaError {
+    fn from(e: config::ConfigError) -> Self {
+        TheiaError::Config(e.to_string())
+    }
+}
+
+macro_rules! from {
+    ($($error:ident => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$error> for TheiaError {
+                fn from(e: $error) -> Self {
+                    TheiaError::$variant(e)
+                }
+            }
+        )*
+    };
+}
+
+from! {
+    EnvelopeError => Envelope,
+    UnitError => Unit,
+    CollectionError => Collection,
+    LocationError => Location,
+    SelectorError => Selector,
+    RequirementError => Requirement,
+    PoolError => Pool,
+    ScoreError => Score,
+    InventoryError => Inventory,
+    ClaimError => Claim,
+    LifecycleError => Lifecycle,
+    ReconcileError => Reconcile,
+}
@@ -0,0 +1,490 @@
+//! Declarative inventory files.
+//!
+//! An inventory describes data centres with their rooms, switches, compute,
+//! storage and interconnects in YAML, TOML or JSON. Quantities have to be
+//! written with their unit (`512GiB`, `100Gbps`, `2.4GHz`), bare numbers and
+//! unknown fields are refused, in nested entries as well. In YAML variants with data are tags (`redundancy: !Raid Raid6`).
+//! Loading validates:
+//! - names are unique per data centre, and data centre names are unique;
+//! - every link `peer` names a switch, resource or interconnect of the same
+//!   data centre, or another data centre;
+//! - every `location` lies in the rooms of its data centre;
+//! - GPU links connect existing devices and storage pools are consistent;
+//! - `site_links` join data centres of the inventory, at most once per pair.
+//!
+//! Parse errors carry the (1-based) line and column the parser reports,
+//! validation errors name the offending entry.
+//!
+//! ```rust
+//! use libtheia::inventory::{self, Format};
+//! use libtheia::models::units::Bytes;
+//!
+//! let source = r#"
+//! data_centres:
+//!   - name: dc1
+//!     compute:
+//!       - name: n1
+//!         cores: 64
+//!         core_clock: 2.4GHz
+//!         ram: 512GiB
+//!         links:
+//!           - name: eth0
+//!             speed: 100Gbps
+//!             peer: uplink
+//!     interconnects:
+//!       - name: uplink
+//!         speed: 400Gbps
+//! "#;
+//! let infra = inventory::parse(source, Format::Yaml, 1).unwrap();
+//! let dc = infra.get_data_centre("dc1").unwrap();
+//!
+//! assert_eq!(dc.get_compute("n1").unwrap().ram, Bytes::gib(512));
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug, Display};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use crate::error::TheiaError;
+use crate::models::capability::Capabilities;
+use crate::models::collection::CollectionError;
+use crate::models::cpu::{Cpu, EquivalenceTable};
+use crate::models::data_centre::{Compute, DataCentre, Disk, DiskType, InterConnect, Storage, Switch, GPU};
+use crate::models::gpu::GPULink;
+use crate::models::hierarchy::{Location, Room};
+use crate::models::infrastructure::LogicalInfrastructure;
+use crate::models::labels::Taint;
+use crate::models::lifecycle::{Lifecycle, State};
+use crate::models::pool::{PoolError, Redundancy};
+use crate::models::power::{Budget, Power};
+use crate::models::units::{self, BitsPerSecond, Bytes, Hertz};
+use crate::models::wan::{Coordinates, SiteLink};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl Format {
+    /// Format by file extension.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "yaml" | "yml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Reasons an inventory cannot be loaded, lines and columns are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InventoryError {
+    Io(String),
+    UnknownFormat(String),
+    /// Syntax, type or unit error, at the position the parser reports.
+    Parse { line: Option<usize>, column: Option<usize>, message: String },
+    Duplicate { name: String },
+    UnknownPeer { name: String, peer: String },
+    /// The location is not part of the rooms of the data centre.
+    Unplaced { name: String, location: Location },
+    UnknownDevice { name: String, device: usize },
+    Pool { name: String, error: PoolError },
+}
+
+impl InventoryError {
+    /// Line of a parse error.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            InventoryError::Parse { line, .. } => *line,
+            _ => None,
+        }
+    }
+
+    /// Column of a parse error.
+    pub fn column(&self) -> Option<usize> {
+        match self {
+            InventoryError::Parse { column, .. } => *column,
+            _ => None,
+        }
+    }
+
+    fn invalid(e: impl Display) -> InventoryError {
+        InventoryError::Parse { line: None, column: None, message: e.to_string() }
+    }
+}
+
+impl Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for InventoryError {}
+
+///
+/// Inventory document
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Inventory {
+    /// Scores per core of CPU generations, for compute without its own.
+    #[serde(default)]
+    pub generations: EquivalenceTable,
+    #[serde(default)]
+    pub data_centres: Vec<DataCentreSpec>,
+    #[serde(default)]
+    pub site_links: Vec<SiteLinkSpec>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DataCentreSpec {
+    pub name: String,
+    #[serde(default)]
+    pub coordinates: Option<Coordinates>,
+    #[serde(default)]
+    pub budget: Budget,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub taints: Vec<Taint>,
+    #[serde(default)]
+    pub rooms: Vec<Room>,
+    #[serde(default)]
+    pub switches: Vec<SwitchSpec>,
+    #[serde(default)]
+    pub compute: Vec<ComputeSpec>,
+    #[serde(default)]
+    pub storage: Vec<StorageSpec>,
+    #[serde(default)]
+    pub interconnects: Vec<InterConnectSpec>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct InterConnectSpec {
+    pub name: String,
+    pub speed: BitsPerSecond,
+    #[serde(default)]
+    pub low_latency: bool,
+    #[serde(default)]
+    pub peer: Option<String>,
+    #[serde(default)]
+    pub latency_us: Option<i32>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub taints: Vec<Taint>,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+/// Link between the data centres `a` and `b`, with a declared latency.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SiteLinkSpec {
+    pub a: String,
+    pub b: String,
+    pub bandwidth: BitsPerSecond,
+    #[serde(default)]
+    pub latency_us: Option<i32>,
+    /// Cost per GB sent over the link.
+    #[serde(default)]
+    pub egress_cost: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SwitchSpec {
+    pub name: String,
+    #[serde(default)]
+    pub links: Vec<InterConnectSpec>,
+    #[serde(default)]
+    pub location: Option<Location>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ComputeSpec {
+    pub name: String,
+    pub cores: i32,
+    pub core_clock: Hertz,
+    pub ram: Bytes,
+    #[serde(default)]
+    pub cpu: Cpu,
+    #[serde(default)]
+    pub disks: Vec<Disk>,
+    #[serde(default)]
+    pub links: Vec<InterConnectSpec>,
+    #[serde(default)]
+    pub gpus: Vec<GPU>,
+    #[serde(default)]
+    pub gpu_links: Vec<GPULink>,
+    #[serde(default)]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub state: Option<State>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub taints: Vec<Taint>,
+    #[serde(default)]
+    pub power: Power,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+/// Storage, without `capacity` it gets the effective capacity of its disks.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StorageSpec {
+    pub name: String,
+    #[serde(default)]
+    pub capacity: Option<Bytes>,
+    #[serde(default)]
+    pub disks: Vec<Disk>,
+    #[serde(default)]
+    pub redundancy: Redundancy,
+    #[serde(default)]
+    pub tier_redundancy: BTreeMap<DiskType, Redundancy>,
+    #[serde(default)]
+    pub links: Vec<InterConnectSpec>,
+    #[serde(default)]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub state: Option<State>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub taints: Vec<Taint>,
+    #[serde(default)]
+    pub power: Power,
+}
+
+impl InterConnectSpec {
+    fn build(&self) -> InterConnect {
+        let mut i = InterConnect::new(self.name.clone(), self.speed, self.low_latency);
+        i.peer = self.peer.clone();
+        i.latency_us = self.latency_us;
+        i.labels = self.labels.clone();
+        i.taints = self.taints.clone();
+        i.capabilities = self.capabilities.clone();
+        i
+    }
+}
+
+impl SiteLinkSpec {
+    fn build(&self) -> SiteLink {
+        let mut l = SiteLink::new(self.a.clone(), self.b.clone(), self.bandwidth);
+        l.declared_latency_us = self.latency_us;
+        l.egress_cost = self.egress_cost;
+        l
+    }
+}
+
+impl SwitchSpec {
+    fn build(&self) -> Switch {
+        let mut s = Switch::new(self.name.clone());
+        s.links = self.links.iter().map(|l| l.build()).collect();
+        s.location = self.location.clone();
+        s
+    }
+}
+
+impl ComputeSpec {
+    fn build(&self, generations: &EquivalenceTable) -> Compute {
+        let mut c = Compute::new(self.name.clone(), self.cores, self.core_clock, self.ram);
+        c.cpu = generations.resolve(self.cpu.clone());
+        c.disks = self.disks.clone();
+        c.links = self.links.iter().map(|l| l.build()).collect();
+        c.gpus = self.gpus.clone();
+        c.gpu_links = self.gpu_links.clone();
+        c.location = self.location.clone();
+        c.labels = self.labels.clone();
+        c.taints = self.taints.clone();
+        c.power = self.power;
+        c.capabilities = self.capabilities.clone();
+        c
+    }
+}
+
+impl StorageSpec {
+    fn build(&self) -> Result<Storage, PoolError> {
+        let disks = (!self.disks.is_empty()).then(|| self.disks.clone());
+        let mut s = Storage::new(self.name.clone(), disks, self.capacity.unwrap_or_default());
+        s.redundancy = self.redundancy;
+        s.tier_redundancy = self.tier_redundancy.clone();
+        if self.capacity.is_none() {
+            s.capacity = s.effective_capacity()?;
+        }
+        s.validate()?;
+        s.links = self.links.iter().map(|l| l.build()).collect();
+        s.location = self.location.clone();
+        s.labels = self.labels.clone();
+        s.taints = self.taints.clone();
+        s.power = self.power;
+        Ok(s)
+    }
+}
+
+impl Inventory {
+    /// Parse an inventory document without validating it.
+    pub fn from_str(source: &str, format: Format) -> Result<Inventory, InventoryError> {
+        units::explicit(|| match format {
+            Format::Yaml => serde_yaml::from_str(source).map_err(|e| InventoryError::Parse {
+                line: e.location().map(|l| l.line()),
+                column: e.location().map(|l| l.column()),
+                message: e.to_string(),
+            }),
+            Format::Toml => toml::from_str(source).map_err(|e| {
+                let (line, column) = e.span().map(|s| position(source, s.start)).unzip();
+                InventoryError::Parse { line, column, message: e.message().to_string() }
+            }),
+            Format::Json => serde_json::from_str(source).map_err(|e| InventoryError::Parse {
+                line: Some(e.line()).filter(|l| *l > 0),
+                column: Some(e.column()).filter(|c| *c > 0),
+                message: e.to_string(),
+            }),
+        })
+    }
+
+    /// Validate the inventory and build the infrastructure it describes.
+    ///
+    /// * `actor` - replica the infrastructure is built as.
+    pub fn build(&self, actor: u64) -> Result<LogicalInfrastructure, InventoryError> {
+        let names: BTreeSet<&str> = self.data_centres.iter().map(|dc| dc.name.as_str()).collect();
+        let mut infra = LogicalInfrastructure::new(actor);
+        for spec in self.data_centres.iter() {
+            if infra.get_data_centre(&spec.name).is_some() {
+                return Err(InventoryError::Duplicate { name: spec.name.clone() });
+            }
+            infra
+                .add_data_centre(spec.build(actor, &names, &self.generations)?)
+                .map_err(InventoryError::invalid)?;
+        }
+        for spec in self.site_links.iter() {
+            let l = spec.build();
+            if let Some(peer) = [&spec.a, &spec.b].into_iter().find(|dc| !names.contains(dc.as_str())) {
+                return Err(InventoryError::UnknownPeer { name: l.name.clone(), peer: peer.clone() });
+            }
+            infra.add_site_link(l).map_err(|e| match e {
+                TheiaError::Collection(CollectionError::Duplicate(name) | CollectionError::NotFound(name)) => InventoryError::Duplicate { name },
+                e => InventoryError::invalid(e),
+            })?;
+        }
+        Ok(infra)
+    }
+}
+
+impl DataCentreSpec {
+    /// * `actor` - replica the data centre is built as.
+    /// * `data_centres` - names of all data centres of the inventory.
+    /// * `generations` - scores of CPU generations.
+    fn build(&self, actor: u64, data_centres: &BTreeSet<&str>, generations: &EquivalenceTable) -> Result<DataCentre, InventoryError> {
+        let duplicate = |e: TheiaError| match e {
+            TheiaError::Collection(CollectionError::Duplicate(name) | CollectionError::NotFound(name)) => InventoryError::Duplicate { name },
+            e => InventoryError::invalid(e),
+        };
+        let mut dc = DataCentre::new(self.name.clone(), actor);
+        if let Some(c) = self.coordinates {
+            dc.set_coordinates(c).map_err(duplicate)?;
+        }
+        if self.budget != Budget::default() {
+            dc.set_budget(self.budget).map_err(duplicate)?;
+        }
+        for (k, v) in self.labels.iter() {
+            dc.set_label(k, v).map_err(duplicate)?;
+        }
+        for t in self.taints.iter() {
+            dc.add_taint(t.clone()).map_err(duplicate)?;
+        }
+        for r in self.rooms.iter() {
+            dc.add_room(r.clone()).map_err(duplicate)?;
+        }
+        for s in self.switches.iter() {
+            dc.add_switch(s.build()).map_err(duplicate)?;
+        }
+        for c in self.compute.iter() {
+            dc.add_compute(c.build(generations)).map_err(duplicate)?;
+            if let Some(state) = c.state {
+                dc.set_compute_lifecycle(&c.name, Lifecycle::new(state)).map_err(duplicate)?;
+            }
+        }
+        for s in self.storage.iter() {
+            let storage = s.build().map_err(|error| InventoryError::Pool { name: s.name.clone(), error })?;
+            dc.add_storage(storage).map_err(duplicate)?;
+            if let Some(state) = s.state {
+                dc.set_storage_lifecycle(&s.name, Lifecycle::new(state)).map_err(duplicate)?;
+            }
+        }
+        for i in self.interconnects.iter() {
+            dc.add_interconnect(i.build()).map_err(duplicate)?;
+        }
+
+        let mut peers: BTreeSet<&str> = data_centres.clone();
+        peers.extend(dc.switches.names());
+        peers.extend(dc.compute.names());
+        peers.extend(dc.storage.names());
+        peers.extend(dc.interconnects.names());
+        let links = self
+            .switches
+            .iter()
+            .flat_map(|s| s.links.iter())
+            .chain(self.compute.iter().flat_map(|c| c.links.iter()))
+            .chain(self.storage.iter().flat_map(|s| s.links.iter()))
+            .chain(self.interconnects.iter());
+        for l in links {
+            if let Some(peer) = l.peer.as_ref().filter(|p| !peers.contains(p.as_str())) {
+                return Err(InventoryError::UnknownPeer { name: l.name.clone(), peer: peer.clone() });
+            }
+        }
+
+        let located = self
+            .switches
+            .iter()
+            .map(|s| (&s.name, &s.location))
+            .chain(self.compute.iter().map(|c| (&c.name, &c.location)))
+            .chain(self.storage.iter().map(|s| (&s.name, &s.location)));
+        for (name, location) in located {
+            if let Some(location) = location.as_ref().filter(|l| dc.domains(l).is_none()) {
+                return Err(InventoryError::Unplaced { name: name.clone(), location: location.clone() });
+            }
+        }
+
+        for spec in self.compute.iter() {
+            let c = spec.build(generations);
+            if let Some(device) = c.gpu_links.iter().flat_map(|l| [l.a, l.b]).find(|d| *d >= c.gpu_count()) {
+                return Err(InventoryError::UnknownDevice { name: c.name, device });
+            }
+        }
+        Ok(dc)
+    }
+}
+
+/// Parse, validate and build an inventory
+///
+/// * `source` - inventory document.
+/// * `format` - format of the document.
+/// * `actor` - replica the infrastructure is built as.
+pub fn parse(source: &str, format: Format, actor: u64) -> Result<LogicalInfrastructure, InventoryError> {
+    Inventory::from_str(source, format)?.build(actor)
+}
+
+/// Load an inventory file as `actor`, the format follows from its extension.
+pub fn load(path: &Path, actor: u64) -> Result<LogicalInfrastructure, InventoryError> {
+    let format = Format::from_path(path).ok_or_else(|| InventoryError::UnknownFormat(path.display().to_string()))?;
+    let source = fs::read_to_string(path).map_err(|e| InventoryError::Io(e.to_string()))?;
+    parse(&source, format, actor)
+}
+
+/// Line and column (1-based) of byte offset `offset`.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
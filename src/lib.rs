@@ -10,17 +10,29 @@ use settings::Settings;
 
 pub mod crdt;
 
+pub mod diff;
+pub mod error;
+pub mod inventory;
 pub mod models;
+pub mod pricing;
+pub mod reconcile;
+pub mod validate;
+use crdt::envelope::{Envelope, Opener, Sealer, SecretKey, SigningKey};
+use error::TheiaError;
+use models::collection::CollectionError;
 use models::data_centre::DataCentre;
 
-use redis;
 use redis::Commands;
 
 lazy_static! {
-    pub static ref CONFIG: Settings = match Settings::new() {
-        Ok(cfg) => cfg,
-        Err(error) => panic!("failure in loading settings {:?}", error),
-    };
+    pub static ref CONFIG: Result<Settings, TheiaError> = Settings::new();
+}
+
+/// Settings loaded once on first use
+///
+/// Loading failures are reported on every call rather than panicking.
+pub fn config() -> Result<&'static Settings, TheiaError> {
+    CONFIG.as_ref().map_err(|e| e.clone())
 }
 
 /// Simple function to add two ints
@@ -42,16 +54,60 @@ pub fn add_two(a: i32, b: i32) -> i32 {
     a + b
 }
 
-/// Insert a DataCentre into the Redis instance
+/// Replica ID this instance writes replicated state as
+///
+/// Pass it to `DataCentre::new` and `LogicalInfrastructure::new`, or to
+/// `with_actor` for deserialized state.
+pub fn replica() -> Result<u64, TheiaError> {
+    config()?
+        .replica()
+        .ok_or_else(|| TheiaError::Config("no replica configured".to_string()))
+}
+
+/// Sealer for the snapshots this replica stores, with the configured secret
+/// and replica ID
+///
+/// * `signing_key` - key of this replica.
+pub fn sealer(signing_key: SigningKey) -> Result<Sealer<u64>, TheiaError> {
+    Ok(Sealer::new(secret_key()?, replica()?, signing_key))
+}
+
+/// Opener for stored snapshots, with the configured secret
+///
+/// Replicas whose snapshots are read have to be trusted on it first.
+pub fn opener() -> Result<Opener<u64>, TheiaError> {
+    Ok(Opener::new(secret_key()?))
+}
+
+fn secret_key() -> Result<SecretKey, TheiaError> {
+    config()?
+        .secret_key()
+        .ok_or_else(|| TheiaError::Config("no secret configured".to_string()))
+}
+
+/// Insert a DataCentre into the Redis instance, sealed
 ///
 /// * `con` - Redis connection
+/// * `sealer` - seals the snapshot before it is written
 /// * `dc` - DataCentre to insert
-pub fn insert_data_centre(con: &mut redis::Connection, dc: DataCentre) -> Result<(), String> {
-    if con.exists(dc.name.as_str()).unwrap() {
-        let m = format!("DataCentre {} already exists", dc.name);
-        Err(m)
+pub fn insert_data_centre(con: &mut redis::Connection, sealer: &Sealer<u64>, dc: DataCentre) -> Result<(), TheiaError> {
+    if con.exists(dc.name.as_str())? {
+        Err(CollectionError::Duplicate(dc.name).into())
     } else {
-        let r = con.set(dc.name.as_str(), serde_json::to_string(&dc).unwrap());
-        Ok(r.unwrap())
+        let envelope = sealer.seal_snapshot(&dc)?;
+        let r: () = con.set(dc.name.as_str(), serde_json::to_string(&envelope)?)?;
+        Ok(r)
     }
 }
+
+/// Read a DataCentre from the Redis instance
+///
+/// * `con` - Redis connection
+/// * `opener` - verifies and decrypts the stored snapshot
+/// * `name` - name of the DataCentre
+pub fn get_data_centre(con: &mut redis::Connection, opener: &Opener<u64>, name: &str) -> Result<DataCentre, TheiaError> {
+    let stored: Option<String> = con.get(name)?;
+    let stored = stored.ok_or_else(|| CollectionError::NotFound(name.to_string()))?;
+    let envelope: Envelope<u64> = serde_json::from_str(&stored)?;
+    Ok(opener.open_snapshot(&envelope)?)
+}
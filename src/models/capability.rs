@@ -0,0 +1,582 @@
+//! Hardware capabilities and requirement expressions.
+//!
+//! Compute resources and interconnects carry a set of capabilities, each a
+//! known feature key (`rdma`, `avx512`, `tpm`, ...) with an optional version.
+//! Vendor specific features are namespaced with a `/` (`nvidia/mig`). A
+//! compute resource also offers the capabilities of its links, a node with an
+//! RDMA capable NIC is an RDMA capable node.
+//!
+//! Requirements are written as expressions over capabilities:
+//! - `feature` tests whether the feature is present;
+//! - `feature = v`, `!=`, `>=`, `>`, `<=` and `<` compare its version,
+//!   versions are dotted numbers (`2.0`, `1.3.1`) with an optional `v`;
+//! - `!` negates, `&&` and `||` combine, `&&` binds tighter.
+//!
+//! ```rust
+//! use libtheia::models::capability::{Capabilities, Requirement};
+//!
+//! let capabilities: Capabilities = ["rdma", "avx512", "tpm=2.0"].iter().map(|c| c.parse().unwrap()).collect();
+//! let requirement: Requirement = "rdma && avx512 && tpm >= 2".parse().unwrap();
+//!
+//! assert!(requirement.matches(&capabilities));
+//! assert!(!"sev || tdx".parse::<Requirement>().unwrap().matches(&capabilities));
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Display};
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Reasons a capability or requirement cannot be parsed, positions are byte
+/// offsets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequirementError {
+    Unexpected { position: usize, found: String },
+    UnexpectedEnd,
+    /// Neither a known feature nor namespaced with a `/`.
+    UnknownFeature(String),
+    InvalidVersion(String),
+}
+
+impl Display for RequirementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for RequirementError {}
+
+///
+/// Feature keys
+///
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(unused)]
+pub enum Feature {
+    // network
+    Sriov,
+    Rdma,
+    Roce,
+    Infiniband,
+    GpuDirect,
+    Dpdk,
+    // NIC offloads
+    Tso,
+    Lro,
+    VxlanOffload,
+    IpsecOffload,
+    TlsOffload,
+    // instruction sets
+    Avx2,
+    Avx512,
+    Amx,
+    Sve,
+    // platform security
+    Tpm,
+    SecureBoot,
+    Sev,
+    Tdx,
+    Sgx,
+    /// Vendor specific feature, `vendor/feature`.
+    Vendor(String),
+}
+
+static FEATURES: &[(Feature, &str)] = &[
+    (Feature::Sriov, "sriov"),
+    (Feature::Rdma, "rdma"),
+    (Feature::Roce, "roce"),
+    (Feature::Infiniband, "infiniband"),
+    (Feature::GpuDirect, "gpudirect"),
+    (Feature::Dpdk, "dpdk"),
+    (Feature::Tso, "tso"),
+    (Feature::Lro, "lro"),
+    (Feature::VxlanOffload, "vxlan-offload"),
+    (Feature::IpsecOffload, "ipsec-offload"),
+    (Feature::TlsOffload, "tls-offload"),
+    (Feature::Avx2, "avx2"),
+    (Feature::Avx512, "avx512"),
+    (Feature::Amx, "amx"),
+    (Feature::Sve, "sve"),
+    (Feature::Tpm, "tpm"),
+    (Feature::SecureBoot, "secure-boot"),
+    (Feature::Sev, "sev"),
+    (Feature::Tdx, "tdx"),
+    (Feature::Sgx, "sgx"),
+];
+
+impl Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Feature::Vendor(key) => write!(f, "{}", key),
+            known => {
+                let key = FEATURES.iter().find(|(k, _)| k == known).map_or("", |(_, key)| key);
+                write!(f, "{}", key)
+            }
+        }
+    }
+}
+
+impl FromStr for Feature {
+    type Err = RequirementError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = s.to_ascii_lowercase();
+        if let Some((feature, _)) = FEATURES.iter().find(|(_, k)| *k == key) {
+            return Ok(feature.clone());
+        }
+        match key.split_once('/') {
+            Some((vendor, name)) if !vendor.is_empty() && !name.is_empty() => Ok(Feature::Vendor(key)),
+            _ => Err(RequirementError::UnknownFeature(s.to_string())),
+        }
+    }
+}
+
+///
+/// Dotted version number, missing parts count as zero
+///
+#[derive(Debug, Clone)]
+pub struct Version(pub Vec<u32>);
+
+impl Version {
+    fn parts(&self) -> impl Iterator<Item = u32> + '_ {
+        let end = self.0.iter().rposition(|p| *p != 0).map_or(0, |i| i + 1);
+        self.0[..end].iter().copied()
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.parts().cmp(other.parts())
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.iter().map(|p| p.to_string()).collect::<Vec<_>>().join("."))
+    }
+}
+
+impl FromStr for Version {
+    type Err = RequirementError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix(['v', 'V']).unwrap_or(s);
+        digits
+            .split('.')
+            .map(|p| p.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map(Version)
+            .map_err(|_| RequirementError::InvalidVersion(s.to_string()))
+    }
+}
+
+///
+/// A feature with an optional version, written `feature` or `feature=version`
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capability {
+    pub feature: Feature,
+    pub version: Option<Version>,
+}
+
+impl Capability {
+    /// Capability instance
+    ///
+    /// * `f` - feature.
+    /// * `v` - version of the feature, if it has one.
+    pub fn new(f: Feature, v: Option<Version>) -> Capability {
+        Capability { feature: f, version: v }
+    }
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(v) => write!(f, "{}={}", self.feature, v),
+            None => write!(f, "{}", self.feature),
+        }
+    }
+}
+
+impl FromStr for Capability {
+    type Err = RequirementError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((f, v)) => Ok(Capability::new(f.trim().parse()?, Some(v.trim().parse()?))),
+            None => Ok(Capability::new(s.trim().parse()?, None)),
+        }
+    }
+}
+
+///
+/// Capabilities of a resource, at most one version per feature
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Capabilities(BTreeMap<Feature, Option<Version>>);
+
+impl Capabilities {
+    pub fn new() -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Add a capability, replacing the version of the same feature.
+    pub fn insert(&mut self, c: Capability) {
+        self.0.insert(c.feature, c.version);
+    }
+
+    pub fn remove(&mut self, f: &Feature) {
+        self.0.remove(f);
+    }
+
+    pub fn has(&self, f: &Feature) -> bool {
+        self.0.contains_key(f)
+    }
+
+    pub fn version(&self, f: &Feature) -> Option<&Version> {
+        self.0.get(f).and_then(|v| v.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Capabilities in feature order.
+    pub fn iter(&self) -> impl Iterator<Item = Capability> + '_ {
+        self.0.iter().map(|(f, v)| Capability::new(f.clone(), v.clone()))
+    }
+
+    /// Add all capabilities of `other`, keeping the highest version of
+    /// features both have.
+    pub fn merge(&mut self, other: &Capabilities) {
+        for (f, v) in other.0.iter() {
+            let version = self.0.entry(f.clone()).or_default();
+            if v.as_ref() > version.as_ref() {
+                *version = v.clone();
+            }
+        }
+    }
+}
+
+impl FromIterator<Capability> for Capabilities {
+    fn from_iter<I: IntoIterator<Item = Capability>>(iter: I) -> Self {
+        let mut capabilities = Capabilities::new();
+        for c in iter {
+            capabilities.insert(c);
+        }
+        capabilities
+    }
+}
+
+impl Serialize for Capabilities {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self.iter().map(|c| c.to_string()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Capabilities {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        Vec::<String>::deserialize(d)?
+            .iter()
+            .map(|c| c.parse::<Capability>())
+            .collect::<Result<Capabilities, _>>()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Resources that carry capabilities.
+pub trait Capable {
+    fn capabilities(&self) -> Capabilities;
+}
+
+/// Comparison of a feature version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+    Le,
+    Lt,
+}
+
+impl Comparison {
+    fn holds(&self, o: Ordering) -> bool {
+        match self {
+            Comparison::Eq => o == Ordering::Equal,
+            Comparison::Ne => o != Ordering::Equal,
+            Comparison::Ge => o != Ordering::Less,
+            Comparison::Gt => o == Ordering::Greater,
+            Comparison::Le => o != Ordering::Greater,
+            Comparison::Lt => o == Ordering::Less,
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Comparison::Eq => "=",
+            Comparison::Ne => "!=",
+            Comparison::Ge => ">=",
+            Comparison::Gt => ">",
+            Comparison::Le => "<=",
+            Comparison::Lt => "<",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+///
+/// Requirement over capabilities
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Requirement {
+    Has(Feature),
+    /// The feature is present with a version that compares as asked.
+    Version(Feature, Comparison, Version),
+    Not(Box<Requirement>),
+    And(Vec<Requirement>),
+    Or(Vec<Requirement>),
+}
+
+impl Requirement {
+    pub fn matches(&self, capabilities: &Capabilities) -> bool {
+        match self {
+            Requirement::Has(f) => capabilities.has(f),
+            Requirement::Version(f, c, v) => capabilities.version(f).is_some_and(|have| c.holds(have.cmp(v))),
+            Requirement::Not(r) => !r.matches(capabilities),
+            Requirement::And(all) => all.iter().all(|r| r.matches(capabilities)),
+            Requirement::Or(any) => any.iter().any(|r| r.matches(capabilities)),
+        }
+    }
+}
+
+impl Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Requirement::Has(feature) => write!(f, "{}", feature),
+            Requirement::Version(feature, c, v) => write!(f, "{} {} {}", feature, c, v),
+            Requirement::Not(r) => match r.as_ref() {
+                Requirement::Has(_) => write!(f, "!{}", r),
+                _ => write!(f, "!({})", r),
+            },
+            Requirement::And(all) => {
+                let parts: Vec<String> = all
+                    .iter()
+                    .map(|r| match r {
+                        Requirement::Or(_) => format!("({})", r),
+                        _ => r.to_string(),
+                    })
+                    .collect();
+                write!(f, "{}", parts.join(" && "))
+            }
+            Requirement::Or(any) => write!(f, "{}", any.iter().map(|r| r.to_string()).collect::<Vec<_>>().join(" || ")),
+        }
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_./".contains(c)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Compare(Comparison),
+    Not,
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+/// Deepest nesting of `!` and parentheses, deeper expressions are refused
+/// rather than parsed with unbounded recursion.
+const MAX_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    peeked: Option<(usize, Token)>,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Result<Option<(usize, Token)>, RequirementError> {
+        if let Some(t) = self.peeked.take() {
+            return Ok(Some(t));
+        }
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let Some((i, c)) = self.chars.next() else {
+            return Ok(None);
+        };
+        let then_eq = |chars: &mut Peekable<CharIndices<'a>>| chars.next_if(|(_, c)| *c == '=').is_some();
+        let token = match c {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '=' => {
+                then_eq(&mut self.chars);
+                Token::Compare(Comparison::Eq)
+            }
+            '!' if then_eq(&mut self.chars) => Token::Compare(Comparison::Ne),
+            '!' => Token::Not,
+            '>' if then_eq(&mut self.chars) => Token::Compare(Comparison::Ge),
+            '>' => Token::Compare(Comparison::Gt),
+            '<' if then_eq(&mut self.chars) => Token::Compare(Comparison::Le),
+            '<' => Token::Compare(Comparison::Lt),
+            '&' if self.chars.next_if(|(_, c)| *c == '&').is_some() => Token::And,
+            '|' if self.chars.next_if(|(_, c)| *c == '|').is_some() => Token::Or,
+            c if is_word(c) => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = self.chars.next_if(|(_, c)| is_word(*c)) {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            c => {
+                return Err(RequirementError::Unexpected {
+                    position: i,
+                    found: c.to_string(),
+                })
+            }
+        };
+        Ok(Some((i, token)))
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token>, RequirementError> {
+        if self.peeked.is_none() {
+            self.peeked = self.next()?;
+        }
+        Ok(self.peeked.as_ref().map(|(_, t)| t))
+    }
+
+    fn word(&mut self) -> Result<String, RequirementError> {
+        match self.next()? {
+            Some((_, Token::Word(w))) => Ok(w),
+            found => Err(unexpected(found)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Requirement, RequirementError> {
+        let mut any = vec![self.and()?];
+        while self.peek()? == Some(&Token::Or) {
+            self.next()?;
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 { any.remove(0) } else { Requirement::Or(any) })
+    }
+
+    fn and(&mut self) -> Result<Requirement, RequirementError> {
+        let mut all = vec![self.unary()?];
+        while self.peek()? == Some(&Token::And) {
+            self.next()?;
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 { all.remove(0) } else { Requirement::And(all) })
+    }
+
+    fn unary(&mut self) -> Result<Requirement, RequirementError> {
+        let nested = match self.peek()? {
+            Some(Token::Not) => {
+                self.nest()?;
+                Ok(Requirement::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::Open) => {
+                self.nest()?;
+                let r = self.or()?;
+                match self.next()? {
+                    Some((_, Token::Close)) => Ok(r),
+                    found => Err(unexpected(found)),
+                }
+            }
+            _ => return self.term(),
+        };
+        self.depth -= 1;
+        nested
+    }
+
+    /// Consume the token opening a nested expression.
+    fn nest(&mut self) -> Result<(), RequirementError> {
+        let found = self.next()?;
+        self.depth += 1;
+        match self.depth > MAX_DEPTH {
+            true => Err(unexpected(found)),
+            false => Ok(()),
+        }
+    }
+
+    fn term(&mut self) -> Result<Requirement, RequirementError> {
+        let feature: Feature = self.word()?.parse()?;
+        match self.peek()? {
+            Some(Token::Compare(c)) => {
+                let c = *c;
+                self.next()?;
+                Ok(Requirement::Version(feature, c, self.word()?.parse()?))
+            }
+            _ => Ok(Requirement::Has(feature)),
+        }
+    }
+}
+
+fn unexpected(found: Option<(usize, Token)>) -> RequirementError {
+    match found {
+        None => RequirementError::UnexpectedEnd,
+        Some((position, token)) => RequirementError::Unexpected {
+            position,
+            found: match token {
+                Token::Word(w) => w,
+                Token::Compare(c) => c.to_string(),
+                Token::Not => "!".to_string(),
+                Token::And => "&&".to_string(),
+                Token::Or => "||".to_string(),
+                Token::Open => "(".to_string(),
+                Token::Close => ")".to_string(),
+            },
+        },
+    }
+}
+
+impl FromStr for Requirement {
+    type Err = RequirementError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: s.char_indices().peekable(),
+            peeked: None,
+            depth: 0,
+        };
+        let requirement = parser.or()?;
+        match parser.next()? {
+            None => Ok(requirement),
+            found => Err(unexpected(found)),
+        }
+    }
+}
+
+impl Serialize for Requirement {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Requirement {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        String::deserialize(d)?.parse().map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,176 @@
+//! Capacity summaries: total, claimed and free per resource class, with the
+//! power draw and the headroom left in power and cooling budgets.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use crate::models::data_centre::{Compute, Disk, DiskType, InterConnect, Storage};
+use crate::models::gpu::GPUClaim;
+use crate::models::power::Budget;
+use crate::models::units::{BitsPerSecond, Bytes, Hertz, Saturating, Watts};
+
+/// Total and claimed amount of a single resource class, counted in `T`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Amount<T> {
+    pub total: T,
+    pub claimed: T,
+}
+
+impl<T: Saturating> Amount<T> {
+    pub fn free(&self) -> T {
+        self.total.saturating_sub(self.claimed)
+    }
+
+    fn add(&mut self, total: T, claimed: T) {
+        self.total = self.total.saturating_add(total);
+        self.claimed = self.claimed.saturating_add(claimed);
+    }
+
+    fn merge(&mut self, other: &Amount<T>) {
+        self.add(other.total, other.claimed);
+    }
+}
+
+///
+/// Capacity of a set of resources
+///
+/// Compute is claimed whole, storage by size and interconnects by bandwidth.
+/// Disks of a storage resource count as claimed in the same proportion as its
+/// capacity.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct Capacity {
+    pub cores: Amount<u64>,
+    /// Cores weighted by their clock rate.
+    pub core_clock: Amount<Hertz>,
+    pub ram: Amount<Bytes>,
+    pub gpus: Amount<u64>,
+    pub gpu_ram: Amount<Bytes>,
+    pub disk: BTreeMap<DiskType, Amount<Bytes>>,
+    pub storage: Amount<Bytes>,
+    pub bandwidth: Amount<BitsPerSecond>,
+    /// Expected draw, claimed resources at peak and the others at nominal.
+    #[serde(default)]
+    pub power_draw: Watts,
+    /// Budgets of the scope, `None` is unlimited. Budgets of merged
+    /// capacities add up, and stay unlimited when either one is.
+    #[serde(default)]
+    pub budget: Budget,
+}
+
+impl Capacity {
+    /// Capacity of a scope without budgets.
+    pub fn new() -> Capacity {
+        Capacity::default()
+    }
+
+    /// Capacity of nothing, to merge others into. Its budgets are zero
+    /// rather than unlimited, so merging keeps the budgets of the others.
+    pub fn empty() -> Capacity {
+        Capacity {
+            budget: Budget::new(Some(Watts(0)), Some(Watts(0))),
+            ..Capacity::default()
+        }
+    }
+
+    /// * `c` - compute resource.
+    /// * `claimed` - whether the resource is claimed.
+    pub fn add_compute(&mut self, c: &Compute, claimed: bool) {
+        fn add<T: Saturating + Default>(a: &mut Amount<T>, v: T, claimed: bool) {
+            a.add(v, if claimed { v } else { T::default() });
+        }
+        let cores = c.cores.max(0) as u64;
+        add(&mut self.cores, cores, claimed);
+        add(&mut self.core_clock, c.core_clock * cores, claimed);
+        add(&mut self.ram, c.ram, claimed);
+        add(&mut self.gpus, c.gpu_count() as u64, claimed);
+        add(&mut self.gpu_ram, c.gpus.iter().map(|g| g.ram * g.count as u64).sum(), claimed);
+        for d in c.disks.iter() {
+            self.add_disk(d, if claimed { d.capacity } else { Bytes(0) });
+        }
+        self.power_draw += c.power.draw(claimed);
+    }
+
+    /// Claim GPUs of a compute resource that was added unclaimed, a device
+    /// counts as claimed once any partition of it is.
+    ///
+    /// * `c` - compute resource.
+    /// * `usage` - devices and partitions held by claims.
+    pub fn claim_gpus(&mut self, c: &Compute, usage: &GPUClaim) {
+        if !usage.is_empty() {
+            self.power_draw += c.power.peak.checked_sub(c.power.nominal).unwrap_or_default();
+        }
+        for i in usage.devices.iter().chain(usage.partitions.keys().filter(|i| !usage.devices.contains(i))) {
+            let Some(g) = c.gpu_device(*i) else { continue };
+            let memory = match usage.partitions.get(i) {
+                Some(profiles) if !usage.devices.contains(i) => {
+                    profiles.iter().filter_map(|p| g.profile(p)).map(|p| p.memory).sum()
+                }
+                _ => g.ram,
+            };
+            self.gpus.add(0, 1);
+            self.gpu_ram.add(Bytes(0), memory);
+        }
+    }
+
+    /// * `s` - storage resource.
+    /// * `claimed` - claimed capacity.
+    pub fn add_storage(&mut self, s: &Storage, claimed: Bytes) {
+        let claimed = claimed.min(s.capacity);
+        self.storage.add(s.capacity, claimed);
+        self.power_draw += s.power.draw(claimed > Bytes(0));
+        let mut by_type: BTreeMap<&DiskType, Bytes> = BTreeMap::new();
+        for d in s.disks.iter().flatten() {
+            *by_type.entry(&d.disk_type).or_default() += d.capacity;
+        }
+        for (t, size) in by_type {
+            let share = size.share(claimed.get(), s.capacity.get());
+            self.disk.entry(t.clone()).or_default().add(size, share);
+        }
+    }
+
+    /// * `i` - data centre interconnect.
+    /// * `claimed` - claimed bandwidth.
+    pub fn add_interconnect(&mut self, i: &InterConnect, claimed: BitsPerSecond) {
+        self.bandwidth.add(i.speed, claimed.min(i.speed));
+    }
+
+    fn add_disk(&mut self, d: &Disk, claimed: Bytes) {
+        self.disk.entry(d.disk_type.clone()).or_default().add(d.capacity, claimed);
+    }
+
+    pub fn merge(&mut self, other: &Capacity) {
+        self.cores.merge(&other.cores);
+        self.core_clock.merge(&other.core_clock);
+        self.ram.merge(&other.ram);
+        self.gpus.merge(&other.gpus);
+        self.gpu_ram.merge(&other.gpu_ram);
+        for (t, a) in other.disk.iter() {
+            self.disk.entry(t.clone()).or_default().merge(a);
+        }
+        self.storage.merge(&other.storage);
+        self.bandwidth.merge(&other.bandwidth);
+        self.power_draw += other.power_draw;
+        let add = |a: Option<Watts>, b: Option<Watts>| match (a, b) {
+            (Some(a), Some(b)) => Some(a + b),
+            _ => None,
+        };
+        self.budget = Budget::new(add(self.budget.power, other.budget.power), add(self.budget.cooling, other.budget.cooling));
+    }
+
+    /// Power left in the budget, `None` without a power budget.
+    pub fn power_headroom(&self) -> Option<Watts> {
+        self.budget.power.map(|p| p.checked_sub(self.power_draw).unwrap_or_default())
+    }
+
+    /// Cooling left in the budget, `None` without a cooling budget.
+    pub fn cooling_headroom(&self) -> Option<Watts> {
+        self.budget.cooling.map(|c| c.checked_sub(self.power_draw).unwrap_or_default())
+    }
+
+    /// Whether the draw exceeds the power or cooling budget.
+    pub fn over_budget(&self) -> bool {
+        !self.budget.allows(self.power_draw)
+    }
+}
@@ -0,0 +1,242 @@
+//! Name-keyed collections of resources.
+//!
+//! A `Collection` replicates like a `Map` from name to `MultiValue`, with the
+//! following semantics:
+//! - names are unique, adding a name this replica already holds is refused;
+//! - a remove only covers what the remover had observed, so a concurrent add
+//!   (or update) of the same name wins over the remove;
+//! - concurrent adds of the same name are both kept, `get` resolves them to
+//!   the write with the greatest clock on every replica, `conflicts` exposes
+//!   all of them until an update or remove that observed them resolves it.
+//!
+//! Resources name the indexes a collection keeps of them, see `Named`. The
+//! indexes follow the resolved resource under every name, through local
+//! changes, applied operations and merges, and are declared again when a
+//! collection is deserialized.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug, Display};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::crdt::index::Attribute;
+use crate::crdt::map::{self, CmRDTValidation, CvRDTValidation};
+use crate::crdt::multi_value::MultiValue;
+use crate::crdt::{CmRDT, CvRDT, Map};
+
+/// Resources that are identified by their name.
+pub trait Named {
+    /// Indexes collections keep of this kind of resource.
+    const INDEXES: &'static [&'static str] = &[];
+
+    fn name(&self) -> &str;
+
+    /// Attributes of the resource in index `index`.
+    fn attributes(&self, _index: &str) -> Vec<Attribute> {
+        Vec::new()
+    }
+}
+
+pub type Operation<T> = map::Operation<String, MultiValue<T, u64>, u64>;
+
+/// Reasons a collection refuses a change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CollectionError {
+    Duplicate(String),
+    NotFound(String),
+    /// The replica has no actor of its own to write as, actor 0 is reserved
+    /// for state that was deserialized and not assigned one yet.
+    NoActor,
+    /// The name stands for something else and cannot be given to an item.
+    Reserved(String),
+}
+
+impl Display for CollectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for CollectionError {}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Collection<T: Clone> {
+    entries: Map<String, MultiValue<T, u64>, u64>,
+}
+
+impl<T: Named + Clone + Debug + 'static> Default for Collection<T> {
+    fn default() -> Self {
+        Self::indexed(Map::new())
+    }
+}
+
+impl<'de, T: Named + Clone + Debug + Deserialize<'de> + 'static> Deserialize<'de> for Collection<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Map::deserialize(deserializer).map(Self::indexed)
+    }
+}
+
+impl<T: Named + Clone + Debug + 'static> Collection<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn indexed(mut entries: Map<String, MultiValue<T, u64>, u64>) -> Self {
+        for index in T::INDEXES {
+            entries.declare_index(index, move |mv| resolve(mv).map(|item| item.attributes(index)).unwrap_or_default());
+        }
+        Self { entries }
+    }
+}
+
+impl<T: Named + Clone + Debug> Collection<T> {
+    /// Resources with `attribute` in index `index`, in name order.
+    pub fn lookup(&self, index: &str, attribute: impl Into<Attribute>) -> Vec<&T> {
+        self.entries
+            .lookup(index, attribute)
+            .into_iter()
+            .filter_map(|name| self.get(name))
+            .collect()
+    }
+
+    /// Names of the resources with `attribute` in index `index`.
+    pub fn lookup_names(&self, index: &str, attribute: impl Into<Attribute>) -> Vec<&str> {
+        self.entries.lookup(index, attribute).into_iter().map(|name| name.as_str()).collect()
+    }
+
+    /// Attributes any resource has in index `index`, in order.
+    pub fn attributes(&self, index: &str) -> Vec<&Attribute> {
+        self.entries.index_attributes(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len().value
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty().value
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.get_value(&name.to_string()).is_some()
+    }
+
+    /// Get a resource by name, concurrent adds resolve to the same resource on
+    /// every replica.
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.entries.get_value(&name.to_string()).and_then(resolve)
+    }
+
+    /// All concurrently added resources under `name`, more than one means the
+    /// name was claimed twice.
+    pub fn conflicts(&self, name: &str) -> Vec<&T> {
+        self.entries
+            .get_value(&name.to_string())
+            .map(|mv| mv.values_with_clock().map(|(_, v)| v).collect())
+            .unwrap_or_default()
+    }
+
+    /// Resources in name order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.entries.values().filter_map(|read| resolve(read.value))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|read| read.value.as_str())
+    }
+
+    /// Add a resource under a name that is not taken yet.
+    ///
+    /// * `item` - resource to add.
+    /// * `actor` - replica making the change.
+    pub fn add(&self, item: T, actor: u64) -> Result<Operation<T>, CollectionError> {
+        if actor == 0 {
+            return Err(CollectionError::NoActor);
+        }
+        if self.contains(item.name()) {
+            return Err(CollectionError::Duplicate(item.name().to_string()));
+        }
+        Ok(self.write(item, actor))
+    }
+
+    /// Add resources under names that are not taken yet, as one operation.
+    /// Nothing is added if any name is taken or given twice.
+    ///
+    /// * `items` - resources to add.
+    /// * `actor` - replica making the change.
+    pub fn add_all(&self, items: impl IntoIterator<Item = T>, actor: u64) -> Result<Operation<T>, CollectionError> {
+        if actor == 0 {
+            return Err(CollectionError::NoActor);
+        }
+        let items: Vec<T> = items.into_iter().collect();
+        let mut names = BTreeSet::new();
+        for item in items.iter() {
+            if self.contains(item.name()) || !names.insert(item.name()) {
+                return Err(CollectionError::Duplicate(item.name().to_string()));
+            }
+        }
+        let updates = items.into_iter().map(|item| (item.name().to_string(), move |mv: &MultiValue<T, u64>, a| mv.write(item, a)));
+        Ok(self.entries.update_all(actor, updates))
+    }
+
+    /// Replace the resource with the same name, resolving any conflict.
+    ///
+    /// * `item` - new version of the resource.
+    /// * `actor` - replica making the change.
+    pub fn update(&self, item: T, actor: u64) -> Result<Operation<T>, CollectionError> {
+        if actor == 0 {
+            return Err(CollectionError::NoActor);
+        }
+        if !self.contains(item.name()) {
+            return Err(CollectionError::NotFound(item.name().to_string()));
+        }
+        Ok(self.write(item, actor))
+    }
+
+    pub fn remove(&self, name: &str) -> Result<Operation<T>, CollectionError> {
+        let name = name.to_string();
+        let read = self.entries.get(&name);
+        if read.value.is_none() {
+            return Err(CollectionError::NotFound(name));
+        }
+        Ok(self.entries.remove(name, read.derive_remove()))
+    }
+
+    fn write(&self, item: T, actor: u64) -> Operation<T> {
+        let name = item.name().to_string();
+        let add = self.entries.get(&name).derive_add(actor);
+        self.entries.update(name, add, |mv, a| mv.write(item, a))
+    }
+}
+
+/// The value written under the greatest clock.
+fn resolve<T>(mv: &MultiValue<T, u64>) -> Option<&T> {
+    mv.values_with_clock()
+        .max_by(|(left, _), (right, _)| left.versions.cmp(&right.versions))
+        .map(|(_, v)| v)
+}
+
+impl<T: Clone + Debug> CmRDT for Collection<T> {
+    type Operation = Operation<T>;
+    type Validation = CmRDTValidation<MultiValue<T, u64>, u64>;
+
+    fn validate_apply(&self, operation: &Self::Operation) -> Result<(), Self::Validation> {
+        self.entries.validate_apply(operation)
+    }
+
+    fn apply(&mut self, operation: Self::Operation) {
+        self.entries.apply(operation)
+    }
+}
+
+impl<T: Clone + Debug> CvRDT for Collection<T> {
+    type Validation = CvRDTValidation<String, MultiValue<T, u64>, u64>;
+
+    fn validate_merge(&self, other: &Self) -> Result<(), Self::Validation> {
+        self.entries.validate_merge(&other.entries)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.entries.merge(other.entries)
+    }
+}
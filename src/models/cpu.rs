@@ -0,0 +1,170 @@
+//! CPU generations and normalized compute units.
+//!
+//! Core counts and clocks do not compare across hardware generations, a core
+//! of 2024 does a lot more work per cycle than one of 2015. Each compute
+//! resource therefore carries the vendor, architecture and generation of its
+//! CPU and a performance score per core, normalized to a reference core
+//! scoring `1.0`. Cores times score gives the compute units of a resource,
+//! which claims can ask for instead of named resources.
+//!
+//! An `EquivalenceTable` holds the score of each generation, resources of a
+//! known generation without a score of their own take it from the table.
+//! Resources without any score count as reference cores. Scores have to be
+//! finite and above zero, anything else is refused.
+//!
+//! ```rust
+//! use libtheia::models::cpu::{Architecture, CPUVendor, Cpu, EquivalenceTable};
+//!
+//! let mut table = EquivalenceTable::new();
+//! table.set("broadwell", 0.6).unwrap();
+//! table.set("sapphire-rapids", 1.5).unwrap();
+//! assert!(table.set("netburst", f64::NAN).is_err());
+//!
+//! // 64 broadwell cores do the work of about 26 sapphire rapids cores
+//! assert_eq!(table.convert(64.0, "broadwell", "sapphire-rapids").map(f64::round), Some(26.0));
+//!
+//! let cpu = table.resolve(Cpu::new(CPUVendor::Intel, Architecture::X86_64, "broadwell".to_string()));
+//! assert_eq!(cpu.score, Some(0.6));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Display};
+
+use serde::{Deserialize, Serialize};
+
+/// Reasons a score is refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScoreError {
+    /// The score, as written, is not a finite number above zero.
+    Invalid(String),
+}
+
+impl Display for ScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for ScoreError {}
+
+/// `s` if it can be the score of a core.
+fn valid(s: f64) -> Result<f64, ScoreError> {
+    match s.is_finite() && s > 0.0 {
+        true => Ok(s),
+        false => Err(ScoreError::Invalid(s.to_string())),
+    }
+}
+
+///
+/// Instruction set architecture
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+#[allow(unused)]
+pub enum Architecture {
+    X86_64,
+    Aarch64,
+    Ppc64le,
+    RiscV64,
+    #[default]
+    Unknown,
+}
+
+///
+/// CPU vendors
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+#[allow(unused)]
+pub enum CPUVendor {
+    Intel,
+    Amd,
+    Ampere,
+    Arm,
+    Ibm,
+    #[default]
+    Unknown,
+}
+
+///
+/// CPU of a compute resource
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct Cpu {
+    #[serde(default)]
+    pub vendor: CPUVendor,
+    #[serde(default)]
+    pub architecture: Architecture,
+    /// Generation, or microarchitecture, as named in the equivalence table.
+    #[serde(default)]
+    pub generation: String,
+    /// Performance of a core relative to the reference core.
+    #[serde(default)]
+    pub score: Option<f64>,
+}
+
+impl Cpu {
+    /// Cpu instance
+    ///
+    /// * `v` - vendor.
+    /// * `a` - architecture.
+    /// * `g` - generation.
+    pub fn new(v: CPUVendor, a: Architecture, g: String) -> Cpu {
+        Cpu {
+            vendor: v,
+            architecture: a,
+            generation: g,
+            score: None,
+        }
+    }
+
+    pub fn set_score(&mut self, s: f64) -> Result<(), ScoreError> {
+        self.score = Some(valid(s)?);
+        Ok(())
+    }
+
+    /// Score per core, a reference core without a score.
+    pub fn score(&self) -> f64 {
+        self.score.unwrap_or(1.0)
+    }
+}
+
+///
+/// Scores per core by generation
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+#[serde(transparent)]
+#[allow(unused)]
+pub struct EquivalenceTable {
+    pub generations: BTreeMap<String, f64>,
+}
+
+impl EquivalenceTable {
+    pub fn new() -> EquivalenceTable {
+        EquivalenceTable::default()
+    }
+
+    /// * `g` - generation.
+    /// * `s` - score per core.
+    pub fn set(&mut self, g: &str, s: f64) -> Result<(), ScoreError> {
+        self.generations.insert(g.to_string(), valid(s)?);
+        Ok(())
+    }
+
+    pub fn score(&self, generation: &str) -> Option<f64> {
+        self.generations.get(generation).copied()
+    }
+
+    /// `cpu` with the score of its generation, unless it has its own.
+    pub fn resolve(&self, mut cpu: Cpu) -> Cpu {
+        cpu.score = cpu.score.or_else(|| self.score(&cpu.generation));
+        cpu
+    }
+
+    /// Cores of generation `to` doing the work of `cores` of generation
+    /// `from`, `None` if either generation is unknown.
+    pub fn convert(&self, cores: f64, from: &str, to: &str) -> Option<f64> {
+        let (from, to) = (self.score(from)?, self.score(to)?);
+        (to > 0.0).then(|| cores * from / to)
+    }
+}
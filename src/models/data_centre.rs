@@ -1,86 +1,174 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
-use crate::crdt::{List, CmRDT};
+use crate::crdt::{CmRDT, CvRDT};
+use crate::crdt::index::Attribute;
+use crate::error::TheiaError;
+use crate::models::collection::{Collection, CollectionError, Named};
+use crate::models::capability::{Capabilities, Capability, Capable, Requirement};
+use crate::models::capacity::Capacity;
+use crate::models::cpu::Cpu;
+use crate::models::gpu::{self, GPUClaim, GPULink, GPULinkType};
+use crate::models::hierarchy::{Domains, Level, Location, Room};
+use crate::models::labels::{self, Effect, Label, Labelled, Selector, Taint};
+use crate::models::lifecycle::{Evacuation, Lifecycle, LifecycleError, ResourceLifecycle, State};
+use crate::models::pool::{PoolError, Redundancy, Tier};
+use crate::models::power::{Budget, Power};
+use crate::models::resource::{Allocation, ClaimError};
+use crate::models::tenant::Usage;
+use crate::models::units::{legacy, BitsPerSecond, Bytes, Hertz, Iops, Watts};
+use crate::models::wan::Coordinates;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
-#[allow(unused)]
-pub enum GPUBusType {
-    PCIe,
-    NVLink,
-    ROCm,
-    InfinityFabric,
-}
+pub use crate::models::gpu::{GPUBusType, GPU};
 
 ///
-/// GPU data structure
+/// Network connections
 ///
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(try_from = "InterConnectFields")]
 #[allow(unused)]
-pub struct GPU {
+pub struct InterConnect {
     pub name: String,
-    pub clock_ghz: i32,
-    pub ram_gb: i32,
-    pub bus_type: GPUBusType,
+    pub speed: BitsPerSecond,
+    pub low_latency: bool,
+    /// Name of the switch, resource or data centre at the other end.
+    #[serde(default)]
+    pub peer: Option<String>,
+    #[serde(default)]
+    pub latency_us: Option<i32>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub taints: Vec<Taint>,
+    #[serde(default)]
+    pub capabilities: Capabilities,
 }
 
+/// `InterConnect` as written, with the old `speed_gb` in Gbps.
+#[derive(Deserialize)]
+struct InterConnectFields {
+    name: String,
+    #[serde(default)]
+    speed: Option<BitsPerSecond>,
+    #[serde(default, deserialize_with = "legacy::gbps")]
+    speed_gb: Option<BitsPerSecond>,
+    low_latency: bool,
+    #[serde(default)]
+    peer: Option<String>,
+    #[serde(default)]
+    latency_us: Option<i32>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    #[serde(default)]
+    taints: Vec<Taint>,
+    #[serde(default)]
+    capabilities: Capabilities,
+}
 
-impl GPU {
-    /// GPU instance
-    ///
-    /// * `n` - a gpu name.
-    /// * `c` - clock rate in GHz.
-    /// * `r` - ram size in GB.
-    /// * `b` - bus type
-    pub fn new(n: String, c: i32, r: i32, b: GPUBusType) -> GPU {
+impl TryFrom<InterConnectFields> for InterConnect {
+    type Error = String;
+
+    fn try_from(f: InterConnectFields) -> Result<Self, Self::Error> {
+        Ok(InterConnect {
+            name: f.name,
+            speed: legacy::require("speed", f.speed, "speed_gb", f.speed_gb)?,
+            low_latency: f.low_latency,
+            peer: f.peer,
+            latency_us: f.latency_us,
+            labels: f.labels,
+            taints: f.taints,
+            capabilities: f.capabilities,
+        })
+    }
+}
 
-        GPU {
+impl InterConnect {
+    /// Interconnect or NIC instance
+    ///
+    /// * `n` - an interconnect name.
+    /// * `s` - speed.
+    /// * `l` - low latency flag.
+    pub fn new(n: String, s: BitsPerSecond, l: bool) -> InterConnect {
+        InterConnect {
             name: n,
-            clock_ghz: c,
-            ram_gb: r,
-            bus_type: b,
+            speed: s,
+            low_latency: l,
+            peer: None,
+            latency_us: None,
+            labels: BTreeMap::new(),
+            taints: Vec::new(),
+            capabilities: Capabilities::new(),
         }
     }
+
+    pub fn set_peer(&mut self, p: String) {
+        self.peer = Some(p);
+    }
+
+    /// * `l` - one way latency in microseconds (us).
+    pub fn set_latency(&mut self, l: i32) {
+        self.latency_us = Some(l);
+    }
+
+    pub fn set_label(&mut self, k: &str, v: &str) {
+        self.labels.insert(k.to_string(), v.to_string());
+    }
+
+    pub fn add_taint(&mut self, t: Taint) {
+        self.taints.push(t);
+    }
+
+    pub fn add_capability(&mut self, c: Capability) {
+        self.capabilities.insert(c);
+    }
 }
 
 ///
-/// Network connections
+/// Network switch, links point at other switches, resources or the data centre
+/// itself (uplink)
 ///
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 #[allow(unused)]
-pub struct InterConnect {
+pub struct Switch {
     pub name: String,
-    pub speed_gb: i32,
-    pub low_latency: bool,
+    pub links: Vec<InterConnect>,
+    #[serde(default)]
+    pub location: Option<Location>,
 }
 
-impl InterConnect {
-    pub fn clone(&self) -> InterConnect {
-        InterConnect {
-            name: self.name.clone(),
-            speed_gb: self.speed_gb,
-            low_latency: self.low_latency,
+impl Switch {
+    /// Switch instance
+    ///
+    /// * `n` - switch name.
+    pub fn new(n: String) -> Switch {
+        Switch {
+            name: n,
+            links: Vec::new(),
+            location: None,
         }
     }
+
+    pub fn add_link(&mut self, l: InterConnect) {
+        self.links.push(l);
+    }
+
+    pub fn set_location(&mut self, l: Location) {
+        self.location = Some(l);
+    }
 }
 
-impl InterConnect {
-    /// Interconnect or NIC instance
-    ///
-    /// * `n` - an interconnect name.
-    /// * `s` - speed in Gigabit per second (Gbps).
-    /// * `l` - low latency flag.
-    pub fn new(n: String, s: i32, l: bool) -> InterConnect {
-        InterConnect {
-            name: n,
-            speed_gb: s,
-            low_latency: l,
-        }
+impl Named for Switch {
+    fn name(&self) -> &str {
+        &self.name
     }
 }
 
 ///
 /// Disk Types
 ///
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 #[allow(unused)]
 pub enum DiskType {
     Spinning,
@@ -89,6 +177,7 @@ pub enum DiskType {
 }
 
 impl DiskType {
+    #[allow(unused)]
     fn from_str(s: &str) -> DiskType {
         match s {
             "spinning" => DiskType::Spinning,
@@ -103,14 +192,56 @@ impl DiskType {
 /// Disks
 ///
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(try_from = "DiskFields")]
 #[allow(unused)]
 pub struct Disk {
     pub name: String,
     pub disk_type: DiskType,
-    pub capacity_tb: i32,
-    pub write_speed_mb: Option<i32>,
-    pub read_speed_mb: Option<i32>,
-    pub iops: Option<i32>,
+    pub capacity: Bytes,
+    #[serde(default)]
+    pub write_speed: Option<BitsPerSecond>,
+    #[serde(default)]
+    pub read_speed: Option<BitsPerSecond>,
+    #[serde(default)]
+    pub iops: Option<Iops>,
+}
+
+/// `Disk` as written, with the old `capacity_tb` in TB and the old speeds in
+/// MBps.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DiskFields {
+    name: String,
+    disk_type: DiskType,
+    #[serde(default)]
+    capacity: Option<Bytes>,
+    #[serde(default, deserialize_with = "legacy::tb")]
+    capacity_tb: Option<Bytes>,
+    #[serde(default)]
+    write_speed: Option<BitsPerSecond>,
+    #[serde(default, deserialize_with = "legacy::mb_per_second")]
+    write_speed_mb: Option<BitsPerSecond>,
+    #[serde(default)]
+    read_speed: Option<BitsPerSecond>,
+    #[serde(default, deserialize_with = "legacy::mb_per_second")]
+    read_speed_mb: Option<BitsPerSecond>,
+    #[serde(default)]
+    iops: Option<Iops>,
+}
+
+impl TryFrom<DiskFields> for Disk {
+    type Error = String;
+
+    fn try_from(f: DiskFields) -> Result<Self, Self::Error> {
+        Ok(Disk {
+            name: f.name,
+            disk_type: f.disk_type,
+            capacity: legacy::require("capacity", f.capacity, "capacity_tb", f.capacity_tb)?,
+            write_speed: legacy::pick("write_speed", f.write_speed, "write_speed_mb", f.write_speed_mb)?,
+            read_speed: legacy::pick("read_speed", f.read_speed, "read_speed_mb", f.read_speed_mb)?,
+            iops: f.iops,
+        })
+    }
 }
 
 impl Disk {
@@ -118,17 +249,17 @@ impl Disk {
     ///
     /// * `n` - a disk name.
     /// * `t` - type of disk.
-    /// * `c` - capacity in TerraByte (TB).
-    /// * `w` - optional write speed in MegaByte per second (MBps).
-    /// * `r` - optional read speed in MegaByte per second (MBps).
+    /// * `c` - capacity.
+    /// * `w` - optional write speed.
+    /// * `r` - optional read speed.
     /// * `i` - optional IOPS.
-    pub fn new(n: String, t: DiskType, c: i32, w: Option<i32>, r: Option<i32>, i: Option<i32>) -> Disk {
+    pub fn new(n: String, t: DiskType, c: Bytes, w: Option<BitsPerSecond>, r: Option<BitsPerSecond>, i: Option<Iops>) -> Disk {
         Disk {
             name: n,
             disk_type: t,
-            capacity_tb: c,
-            write_speed_mb: w,
-            read_speed_mb: r,
+            capacity: c,
+            write_speed: w,
+            read_speed: r,
             iops: i,
         }
     }
@@ -138,15 +269,86 @@ impl Disk {
 /// Main data structure for storing and retrieving compute resources
 ///
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(try_from = "ComputeFields")]
 #[allow(unused)]
 pub struct Compute {
     pub name: String,
     pub cores: i32,
-    pub core_ghz: i32,
-    pub ram_gb: i32,
+    pub core_clock: Hertz,
+    pub ram: Bytes,
+    #[serde(default)]
+    pub cpu: Cpu,
     pub disks: Vec<Disk>,
     pub links: Vec<InterConnect>,
     pub gpus: Vec<GPU>,
+    /// Links between GPU devices.
+    #[serde(default)]
+    pub gpu_links: Vec<GPULink>,
+    #[serde(default)]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub taints: Vec<Taint>,
+    #[serde(default)]
+    pub power: Power,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+/// `Compute` as written, with the old `core_ghz` in GHz and `ram_gb` in GiB.
+#[derive(Deserialize)]
+struct ComputeFields {
+    name: String,
+    cores: i32,
+    #[serde(default)]
+    core_clock: Option<Hertz>,
+    #[serde(default, deserialize_with = "legacy::ghz")]
+    core_ghz: Option<Hertz>,
+    #[serde(default)]
+    ram: Option<Bytes>,
+    #[serde(default, deserialize_with = "legacy::gib")]
+    ram_gb: Option<Bytes>,
+    #[serde(default)]
+    cpu: Cpu,
+    disks: Vec<Disk>,
+    links: Vec<InterConnect>,
+    gpus: Vec<GPU>,
+    #[serde(default)]
+    gpu_links: Vec<GPULink>,
+    #[serde(default)]
+    location: Option<Location>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    #[serde(default)]
+    taints: Vec<Taint>,
+    #[serde(default)]
+    power: Power,
+    #[serde(default)]
+    capabilities: Capabilities,
+}
+
+impl TryFrom<ComputeFields> for Compute {
+    type Error = String;
+
+    fn try_from(f: ComputeFields) -> Result<Self, Self::Error> {
+        Ok(Compute {
+            name: f.name,
+            cores: f.cores,
+            core_clock: legacy::require("core_clock", f.core_clock, "core_ghz", f.core_ghz)?,
+            ram: legacy::require("ram", f.ram, "ram_gb", f.ram_gb)?,
+            cpu: f.cpu,
+            disks: f.disks,
+            links: f.links,
+            gpus: f.gpus,
+            gpu_links: f.gpu_links,
+            location: f.location,
+            labels: f.labels,
+            taints: f.taints,
+            power: f.power,
+            capabilities: f.capabilities,
+        })
+    }
 }
 
 impl Compute {
@@ -154,21 +356,37 @@ impl Compute {
     ///
     /// * `n` - server name.
     /// * `c` - number of cores.
-    /// * `g` - core Gigaherz (GHz).
-    /// * `r` - amount of RAM in GigaBytes (GB).
-    pub fn new(n: String, c: i32, g: i32, r: i32) -> Compute {
+    /// * `g` - core clock rate.
+    /// * `r` - amount of RAM.
+    pub fn new(n: String, c: i32, g: Hertz, r: Bytes) -> Compute {
         Compute {
             name: n,
             cores: c,
-            core_ghz: g,
-            ram_gb: r,
+            core_clock: g,
+            ram: r,
+            cpu: Cpu::default(),
             disks: Vec::new(),
             links: Vec::new(),
             gpus: Vec::new(),
+            gpu_links: Vec::new(),
+            location: None,
+            labels: BTreeMap::new(),
+            taints: Vec::new(),
+            power: Power::default(),
+            capabilities: Capabilities::new(),
         }
     }
 
-    pub(crate) fn add_disk(&mut self, d: Disk) {
+    pub fn set_cpu(&mut self, c: Cpu) {
+        self.cpu = c;
+    }
+
+    /// Cores normalized to reference cores by the score of the CPU.
+    pub fn compute_units(&self) -> f64 {
+        self.cores.max(0) as f64 * self.cpu.score()
+    }
+
+    pub fn add_disk(&mut self, d: Disk) {
         self.disks.push(d);
     }
 
@@ -179,18 +397,116 @@ impl Compute {
     pub fn add_gpu(&mut self, g: GPU) {
         self.gpus.push(g);
     }
+
+    pub fn add_gpu_link(&mut self, l: GPULink) {
+        self.gpu_links.push(l);
+    }
+
+    /// Number of GPU devices.
+    pub fn gpu_count(&self) -> usize {
+        self.gpus.iter().map(|g| g.count as usize).sum()
+    }
+
+    /// The GPU entry device `i` belongs to.
+    pub fn gpu_device(&self, i: usize) -> Option<&GPU> {
+        self.gpus.iter().flat_map(|g| (0..g.count).map(move |_| g)).nth(i)
+    }
+
+    /// Whether every pair of `devices` has a direct link (of `kind`, if given).
+    pub fn gpus_connected(&self, devices: &[usize], kind: Option<GPULinkType>) -> bool {
+        gpu::fully_connected(&self.gpu_links, devices, kind)
+    }
+
+    pub fn set_location(&mut self, l: Location) {
+        self.location = Some(l);
+    }
+
+    pub fn set_label(&mut self, k: &str, v: &str) {
+        self.labels.insert(k.to_string(), v.to_string());
+    }
+
+    pub fn add_taint(&mut self, t: Taint) {
+        self.taints.push(t);
+    }
+
+    pub fn set_power(&mut self, p: Power) {
+        self.power = p;
+    }
+
+    pub fn add_capability(&mut self, c: Capability) {
+        self.capabilities.insert(c);
+    }
 }
 
 ///
-/// Storage, where capacity is either given, or the sum of all disks
+/// Storage, where capacity is either given, or follows from its disks
+///
+/// Disks form a pool with one tier per disk type, protected by `redundancy`
+/// unless a tier has its own scheme in `tier_redundancy`.
 ///
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(try_from = "StorageFields")]
 #[allow(unused)]
 pub struct Storage {
     pub name: String,
     pub disks: Option<Vec<Disk>>,
-    pub capacity_gb: i32,
+    pub capacity: Bytes,
+    #[serde(default)]
+    pub redundancy: Redundancy,
+    #[serde(default)]
+    pub tier_redundancy: BTreeMap<DiskType, Redundancy>,
     pub links: Vec<InterConnect>,
+    #[serde(default)]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub taints: Vec<Taint>,
+    #[serde(default)]
+    pub power: Power,
+}
+
+/// `Storage` as written, with the old `capacity_gb` in GB.
+#[derive(Deserialize)]
+struct StorageFields {
+    name: String,
+    disks: Option<Vec<Disk>>,
+    #[serde(default)]
+    capacity: Option<Bytes>,
+    #[serde(default, deserialize_with = "legacy::gb")]
+    capacity_gb: Option<Bytes>,
+    #[serde(default)]
+    redundancy: Redundancy,
+    #[serde(default)]
+    tier_redundancy: BTreeMap<DiskType, Redundancy>,
+    links: Vec<InterConnect>,
+    #[serde(default)]
+    location: Option<Location>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    #[serde(default)]
+    taints: Vec<Taint>,
+    #[serde(default)]
+    power: Power,
+}
+
+impl TryFrom<StorageFields> for Storage {
+    type Error = String;
+
+    fn try_from(f: StorageFields) -> Result<Self, Self::Error> {
+        Ok(Storage {
+            name: f.name,
+            disks: f.disks,
+            capacity: legacy::require("capacity", f.capacity, "capacity_gb", f.capacity_gb)?,
+            redundancy: f.redundancy,
+            tier_redundancy: f.tier_redundancy,
+            links: f.links,
+            location: f.location,
+            labels: f.labels,
+            taints: f.taints,
+            power: f.power,
+        })
+    }
 }
 
 impl Storage {
@@ -198,18 +514,132 @@ impl Storage {
     ///
     /// * `n` - storage name.
     /// * `d` - optional list of disks.
-    pub fn new(n: String, d: Option<Vec<Disk>>, c: i32) -> Storage {
+    /// * `c` - capacity.
+    pub fn new(n: String, d: Option<Vec<Disk>>, c: Bytes) -> Storage {
         Storage {
             name: n,
             disks: d,
-            capacity_gb: c,
+            capacity: c,
+            redundancy: Redundancy::None,
+            tier_redundancy: BTreeMap::new(),
             links: Vec::new(),
+            location: None,
+            labels: BTreeMap::new(),
+            taints: Vec::new(),
+            power: Power::default(),
+        }
+    }
+
+    pub fn set_power(&mut self, p: Power) {
+        self.power = p;
+    }
+
+    /// Storage instance with the effective capacity of its disks
+    ///
+    /// ```rust
+    /// use libtheia::models::data_centre::{Disk, DiskType, Storage};
+    /// use libtheia::models::pool::{RaidLevel, Redundancy};
+    /// use libtheia::models::units::{Bytes, Iops};
+    ///
+    /// let disks = (0..6).map(|i| Disk::new(format!("d{}", i), DiskType::SolidState, Bytes::tb(4), None, None, Some(Iops(100_000)))).collect();
+    /// let s = Storage::from_disks("s1".to_string(), disks, Redundancy::Raid(RaidLevel::Raid6)).unwrap();
+    ///
+    /// assert_eq!(s.capacity, Bytes::tb(16));
+    /// assert_eq!(s.write_iops(), Some(Iops(100_000)));
+    /// ```
+    pub fn from_disks(n: String, d: Vec<Disk>, r: Redundancy) -> Result<Storage, PoolError> {
+        let mut s = Storage::new(n, Some(d), Bytes(0));
+        s.redundancy = r;
+        s.capacity = s.effective_capacity()?;
+        Ok(s)
+    }
+
+    pub fn set_redundancy(&mut self, r: Redundancy) {
+        self.redundancy = r;
+    }
+
+    /// Protect the disks of type `t` with their own scheme.
+    pub fn set_tier_redundancy(&mut self, t: DiskType, r: Redundancy) {
+        self.tier_redundancy.insert(t, r);
+    }
+
+    /// Tiers of the pool in disk type order, empty without disks.
+    pub fn tiers(&self) -> Result<Vec<Tier>, PoolError> {
+        let mut by_type: BTreeMap<&DiskType, Vec<&Disk>> = BTreeMap::new();
+        for d in self.disks.iter().flatten() {
+            by_type.entry(&d.disk_type).or_default().push(d);
+        }
+        by_type
+            .into_iter()
+            .map(|(t, disks)| {
+                let r = self.tier_redundancy.get(t).copied().unwrap_or(self.redundancy);
+                Tier::new(t.clone(), r, &disks)
+            })
+            .collect()
+    }
+
+    /// Usable capacity of all tiers, the declared capacity without disks.
+    pub fn effective_capacity(&self) -> Result<Bytes, PoolError> {
+        match self.disks {
+            None => Ok(self.capacity),
+            Some(_) => Ok(self.tiers()?.iter().map(|t| t.effective).sum()),
+        }
+    }
+
+    /// Read IOPS of all tiers, `None` if any disk has no IOPS rating.
+    pub fn read_iops(&self) -> Option<Iops> {
+        self.tiers().ok()?.iter().map(|t| t.read_iops).sum()
+    }
+
+    /// Write IOPS of all tiers, `None` if any disk has no IOPS rating.
+    pub fn write_iops(&self) -> Option<Iops> {
+        self.tiers().ok()?.iter().map(|t| t.write_iops).sum()
+    }
+
+    /// Check the disks can form the pool and deliver the declared capacity.
+    pub fn validate(&self) -> Result<(), PoolError> {
+        let effective = self.effective_capacity()?;
+        if self.capacity > effective {
+            return Err(PoolError::OverDeclared {
+                declared: self.capacity,
+                effective,
+            });
         }
+        Ok(())
     }
 
-    pub(crate) fn add_link(&mut self, l: InterConnect) {
+    pub fn add_link(&mut self, l: InterConnect) {
         self.links.push(l);
     }
+
+    pub fn set_location(&mut self, l: Location) {
+        self.location = Some(l);
+    }
+
+    pub fn set_label(&mut self, k: &str, v: &str) {
+        self.labels.insert(k.to_string(), v.to_string());
+    }
+
+    pub fn add_taint(&mut self, t: Taint) {
+        self.taints.push(t);
+    }
+}
+
+/// A setting of the data centre itself, the unit it replicates its budget
+/// and coordinates in.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum Setting {
+    Budget(Budget),
+    Coordinates(Coordinates),
+}
+
+impl Named for Setting {
+    fn name(&self) -> &str {
+        match self {
+            Setting::Budget(_) => "budget",
+            Setting::Coordinates(_) => "coordinates",
+        }
+    }
 }
 
 ///
@@ -219,9 +649,34 @@ impl Storage {
 #[allow(unused)]
 pub struct DataCentre {
     pub name: String,
-    pub compute: List<Compute, u64>,
-    pub storage: List<Storage, u64>,
-    pub interconnects: List<InterConnect, u64>,
+    pub compute: Collection<Compute>,
+    pub storage: Collection<Storage>,
+    pub interconnects: Collection<InterConnect>,
+    #[serde(default)]
+    pub rooms: Collection<Room>,
+    #[serde(default)]
+    pub switches: Collection<Switch>,
+    #[serde(default)]
+    pub claims: Collection<Allocation>,
+    /// Lifecycle of compute resources, by resource name.
+    #[serde(default)]
+    pub compute_lifecycles: Collection<ResourceLifecycle>,
+    /// Lifecycle of storage resources, by resource name.
+    #[serde(default)]
+    pub storage_lifecycles: Collection<ResourceLifecycle>,
+    #[serde(default)]
+    pub labels: Collection<Label>,
+    /// Taints every resource of the data centre carries.
+    #[serde(default)]
+    pub taints: Collection<Taint>,
+    /// Power and cooling budget of the whole data centre and its
+    /// coordinates, see `budget` and `coordinates`.
+    #[serde(default)]
+    pub settings: Collection<Setting>,
+    /// Actor this replica writes as, local to the replica, 0 when it has
+    /// none yet.
+    #[serde(skip)]
+    actor: u64,
 }
 
 impl PartialEq for DataCentre {
@@ -230,114 +685,1221 @@ impl PartialEq for DataCentre {
     }
 }
 
+impl Named for Compute {
+    const INDEXES: &'static [&'static str] = &["labels"];
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self, index: &str) -> Vec<Attribute> {
+        match index {
+            "labels" => labels::label_attributes(self),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Named for Storage {
+    const INDEXES: &'static [&'static str] = &["labels"];
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self, index: &str) -> Vec<Attribute> {
+        match index {
+            "labels" => labels::label_attributes(self),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Named for InterConnect {
+    const INDEXES: &'static [&'static str] = &["labels"];
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self, index: &str) -> Vec<Attribute> {
+        match index {
+            "labels" => labels::label_attributes(self),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Labelled for Compute {
+    fn labels(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    fn taints(&self) -> &[Taint] {
+        &self.taints
+    }
+}
+
+impl Labelled for Storage {
+    fn labels(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    fn taints(&self) -> &[Taint] {
+        &self.taints
+    }
+}
+
+impl Labelled for InterConnect {
+    fn labels(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    fn taints(&self) -> &[Taint] {
+        &self.taints
+    }
+}
+
+impl Capable for Compute {
+    /// Capabilities of the resource and of its links.
+    fn capabilities(&self) -> Capabilities {
+        let mut capabilities = self.capabilities.clone();
+        for l in self.links.iter() {
+            capabilities.merge(&l.capabilities);
+        }
+        capabilities
+    }
+}
+
+impl Capable for InterConnect {
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities.clone()
+    }
+}
+
 impl DataCentre {
     /// DataCentre instance
     ///
-    /// * `n` - storage name.
-    pub fn new(n: String) -> DataCentre {
+    /// * `n` - data centre name.
+    /// * `a` - actor this replica writes as, the replica ID from the
+    ///   configuration. Every replica of a DC needs its own, writes are
+    ///   refused with actor 0.
+    pub fn new(n: String, a: u64) -> DataCentre {
         DataCentre{
             name: n,
-            compute: List::new(),
-            storage: List::new(),
-            interconnects: List::new(),
+            compute: Collection::new(),
+            storage: Collection::new(),
+            interconnects: Collection::new(),
+            rooms: Collection::new(),
+            switches: Collection::new(),
+            claims: Collection::new(),
+            compute_lifecycles: Collection::new(),
+            storage_lifecycles: Collection::new(),
+            labels: Collection::new(),
+            taints: Collection::new(),
+            settings: Collection::new(),
+            actor: a,
         }
     }
 
-    pub fn add_compute(&mut self, c: Compute) {
-        let r = self.compute.len() + 1;
-        self.compute.apply(self.compute.append(c, r as u64));
+    /// Write changes as `actor`, for a DC that was deserialized. The actor
+    /// is not part of the replicated state, writes are refused until one is
+    /// assigned.
+    pub fn with_actor(mut self, actor: u64) -> DataCentre {
+        self.actor = actor;
+        self
+    }
+
+    pub(crate) fn set_actor(&mut self, actor: u64) {
+        self.actor = actor;
+    }
+
+    /// Merge the collections of another replica of this data centre.
+    pub fn merge(&mut self, other: DataCentre) {
+        self.compute.merge(other.compute);
+        self.storage.merge(other.storage);
+        self.interconnects.merge(other.interconnects);
+        self.rooms.merge(other.rooms);
+        self.switches.merge(other.switches);
+        self.claims.merge(other.claims);
+        self.compute_lifecycles.merge(other.compute_lifecycles);
+        self.storage_lifecycles.merge(other.storage_lifecycles);
+        self.labels.merge(other.labels);
+        self.taints.merge(other.taints);
+        self.settings.merge(other.settings);
+    }
+
+    pub fn set_coordinates(&mut self, c: Coordinates) -> Result<(), TheiaError> {
+        Ok(upsert(&mut self.settings, Setting::Coordinates(c), self.actor)?)
+    }
+
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        match self.settings.get("coordinates") {
+            Some(Setting::Coordinates(c)) => Some(*c),
+            _ => None,
+        }
+    }
+
+    /// Set the power and cooling budget of the whole data centre.
+    pub fn set_budget(&mut self, b: Budget) -> Result<(), TheiaError> {
+        Ok(upsert(&mut self.settings, Setting::Budget(b), self.actor)?)
+    }
+
+    /// Power and cooling budget of the whole data centre, unlimited unless
+    /// one was set.
+    pub fn budget(&self) -> Budget {
+        match self.settings.get("budget") {
+            Some(Setting::Budget(b)) => *b,
+            _ => Budget::default(),
+        }
+    }
+
+    pub fn add_compute(&mut self, c: Compute) -> Result<(), TheiaError> {
+        self.compute.apply(self.compute.add(c, self.actor)?);
+        Ok(())
+    }
+
+    pub fn add_storage(&mut self, s: Storage) -> Result<(), TheiaError> {
+        self.storage.apply(self.storage.add(s, self.actor)?);
+        Ok(())
+    }
+
+    pub fn add_interconnect(&mut self, i: InterConnect) -> Result<(), TheiaError> {
+        self.interconnects.apply(self.interconnects.add(i, self.actor)?);
+        Ok(())
+    }
+
+    /// Replace a compute resource, e.g. after changing its labels or taints
+    pub fn update_compute(&mut self, c: Compute) -> Result<(), TheiaError> {
+        self.compute.apply(self.compute.update(c, self.actor)?);
+        Ok(())
+    }
+
+    pub fn update_storage(&mut self, s: Storage) -> Result<(), TheiaError> {
+        self.storage.apply(self.storage.update(s, self.actor)?);
+        Ok(())
     }
 
-    pub fn add_storage(&mut self, s: Storage) {
-        let r = self.compute.len() + 1;
-        self.storage.apply(self.storage.append(s, r as u64));
+    pub fn update_interconnect(&mut self, i: InterConnect) -> Result<(), TheiaError> {
+        self.interconnects.apply(self.interconnects.update(i, self.actor)?);
+        Ok(())
     }
 
-    pub fn add_interconnect(&mut self, i: InterConnect) {
-        let r = self.compute.len() + 1;
-        self.interconnects.apply(self.interconnects.append(i, r as u64));
+    /// Add a batch of compute resources as one operation, nothing is added if
+    /// any name is taken
+    ///
+    /// ```rust
+    /// use libtheia::models::data_centre::{Compute, DataCentre};
+    /// use libtheia::models::units::{Bytes, Hertz};
+    ///
+    /// let mut dc = DataCentre::new("test".to_string(), 1);
+    /// dc.add_computes((0..40).map(|i| Compute::new(format!("node{}", i), 64, Hertz::ghz(1), Bytes::gib(512)))).unwrap();
+    /// assert_eq!(dc.compute.len(), 40);
+    /// assert!(dc.add_computes(vec![Compute::new("node0".to_string(), 1, Hertz::ghz(1), Bytes::gib(1))]).is_err());
+    /// ```
+    pub fn add_computes(&mut self, c: impl IntoIterator<Item = Compute>) -> Result<(), TheiaError> {
+        self.compute.apply(self.compute.add_all(c, self.actor)?);
+        Ok(())
     }
 
+    /// Add a batch of storage resources as one operation, nothing is added if
+    /// any name is taken
+    pub fn add_storages(&mut self, s: impl IntoIterator<Item = Storage>) -> Result<(), TheiaError> {
+        self.storage.apply(self.storage.add_all(s, self.actor)?);
+        Ok(())
+    }
 
     /// Get a compute resource by name
     ///
     /// ```rust
     /// use libtheia::models::data_centre::DataCentre;
     /// use libtheia::models::data_centre::Compute;
+    /// use libtheia::models::units::{Bytes, Hertz};
     ///
-    /// let mut dc = DataCentre::new("test".to_string());
-    /// let mut c = Compute::new("test".to_string(), 1, 1, 1);
-    /// dc.add_compute(c.clone());
+    /// let mut dc = DataCentre::new("test".to_string(), 1);
+    /// let mut c = Compute::new("test".to_string(), 1, Hertz::ghz(1), Bytes::gib(1));
+    /// dc.add_compute(c.clone()).unwrap();
     /// let fc = dc.get_compute("test").unwrap();
     ///
     /// assert_eq!(c, fc.clone());
     /// ```
     pub fn get_compute(&self, name: &str) -> Option<&Compute> {
-        for c in self.compute.iter() {
-            if c.name == name {
-                return Some(c);
+        self.compute.get(name)
+    }
+
+    pub fn get_storage(&self, name: &str) -> Option<&Storage> {
+        self.storage.get(name)
+    }
+
+    pub fn get_interconnect(&self, name: &str) -> Option<&InterConnect> {
+        self.interconnects.get(name)
+    }
+
+    /// Remove a compute resource by name
+    ///
+    /// ```rust
+    /// use libtheia::models::data_centre::{Compute, DataCentre};
+    /// use libtheia::models::units::{Bytes, Hertz};
+    ///
+    /// let mut dc = DataCentre::new("test".to_string(), 1);
+    /// dc.add_compute(Compute::new("test".to_string(), 1, Hertz::ghz(1), Bytes::gib(1))).unwrap();
+    /// dc.remove_compute("test").unwrap();
+    ///
+    /// assert!(dc.get_compute("test").is_none());
+    /// assert!(dc.remove_compute("test").is_err());
+    /// ```
+    pub fn remove_compute(&mut self, name: &str) -> Result<(), TheiaError> {
+        self.compute.apply(self.compute.remove(name)?);
+        if let Ok(op) = self.compute_lifecycles.remove(name) {
+            self.compute_lifecycles.apply(op);
+        }
+        Ok(())
+    }
+
+    pub fn remove_storage(&mut self, name: &str) -> Result<(), TheiaError> {
+        self.storage.apply(self.storage.remove(name)?);
+        if let Ok(op) = self.storage_lifecycles.remove(name) {
+            self.storage_lifecycles.apply(op);
+        }
+        Ok(())
+    }
+
+    pub fn remove_interconnect(&mut self, name: &str) -> Result<(), TheiaError> {
+        self.interconnects.apply(self.interconnects.remove(name)?);
+        Ok(())
+    }
+
+    pub fn add_switch(&mut self, s: Switch) -> Result<(), TheiaError> {
+        self.switches.apply(self.switches.add(s, self.actor)?);
+        Ok(())
+    }
+
+    /// Replace a switch, e.g. after adding links to it
+    pub fn update_switch(&mut self, s: Switch) -> Result<(), TheiaError> {
+        self.switches.apply(self.switches.update(s, self.actor)?);
+        Ok(())
+    }
+
+    pub fn get_switch(&self, name: &str) -> Option<&Switch> {
+        self.switches.get(name)
+    }
+
+    pub fn remove_switch(&mut self, name: &str) -> Result<(), TheiaError> {
+        self.switches.apply(self.switches.remove(name)?);
+        Ok(())
+    }
+
+    pub fn add_room(&mut self, r: Room) -> Result<(), TheiaError> {
+        self.rooms.apply(self.rooms.add(r, self.actor)?);
+        Ok(())
+    }
+
+    /// Replace a room, e.g. after adding racks to it
+    pub fn update_room(&mut self, r: Room) -> Result<(), TheiaError> {
+        self.rooms.apply(self.rooms.update(r, self.actor)?);
+        Ok(())
+    }
+
+    pub fn get_room(&self, name: &str) -> Option<&Room> {
+        self.rooms.get(name)
+    }
+
+    pub fn remove_room(&mut self, name: &str) -> Result<(), TheiaError> {
+        self.rooms.apply(self.rooms.remove(name)?);
+        Ok(())
+    }
+
+    /// Power feeds and failure domains a resource at `location` inherits,
+    /// `None` when the location is not part of the hierarchy.
+    pub fn domains(&self, location: &Location) -> Option<Domains> {
+        self.rooms.iter().find_map(|room| room.domains(location))
+    }
+
+    /// Compute resources placed in the subtree below `scope`
+    ///
+    /// ```rust
+    /// use libtheia::models::data_centre::{Compute, DataCentre};
+    /// use libtheia::models::hierarchy::Location;
+    /// use libtheia::models::units::{Bytes, Hertz};
+    ///
+    /// let mut dc = DataCentre::new("dc1".to_string(), 1);
+    /// for (name, rack) in [("n1", "r1"), ("n2", "r2")] {
+    ///     let mut c = Compute::new(name.to_string(), 1, Hertz::ghz(1), Bytes::gib(1));
+    ///     c.set_location(Location::rack("hall", "a", rack));
+    ///     dc.add_compute(c).unwrap();
+    /// }
+    ///
+    /// let names: Vec<_> = dc.compute_in(&Location::rack("hall", "a", "r2")).iter().map(|c| c.name.as_str()).collect();
+    /// assert_eq!(names, vec!["n2"]);
+    /// assert_eq!(dc.compute_in(&Location::room("hall")).len(), 2);
+    /// ```
+    pub fn compute_in(&self, scope: &Location) -> Vec<&Compute> {
+        self.compute.iter().filter(|c| within(&c.location, scope)).collect()
+    }
+
+    /// Storage resources placed in the subtree below `scope`
+    pub fn storage_in(&self, scope: &Location) -> Vec<&Storage> {
+        self.storage.iter().filter(|s| within(&s.location, scope)).collect()
+    }
+
+    pub fn compute_in_failure_domain(&self, domain: &str) -> Vec<&Compute> {
+        self.compute
+            .iter()
+            .filter(|c| self.placed_in(&c.location, |d| d.failure_domains.contains(domain)))
+            .collect()
+    }
+
+    pub fn storage_in_failure_domain(&self, domain: &str) -> Vec<&Storage> {
+        self.storage
+            .iter()
+            .filter(|s| self.placed_in(&s.location, |d| d.failure_domains.contains(domain)))
+            .collect()
+    }
+
+    pub fn compute_on_power_feed(&self, feed: &str) -> Vec<&Compute> {
+        self.compute
+            .iter()
+            .filter(|c| self.placed_in(&c.location, |d| d.power_feeds.contains(feed)))
+            .collect()
+    }
+
+    pub fn storage_on_power_feed(&self, feed: &str) -> Vec<&Storage> {
+        self.storage
+            .iter()
+            .filter(|s| self.placed_in(&s.location, |d| d.power_feeds.contains(feed)))
+            .collect()
+    }
+
+    /// Names of compute and storage resources without a location, or with a
+    /// location that is not part of the hierarchy (any more).
+    pub fn unplaced(&self) -> Vec<&str> {
+        let compute = self.compute.iter().filter(|c| !self.placed(&c.location)).map(|c| c.name.as_str());
+        let storage = self.storage.iter().filter(|s| !self.placed(&s.location)).map(|s| s.name.as_str());
+        compute.chain(storage).collect()
+    }
+
+    /// Claim resources of this data centre
+    ///
+    /// Every resource has to exist, compute and storage have to be in a
+    /// schedulable state, compute cannot be claimed twice and storage and
+    /// interconnects cannot be claimed beyond their capacity. Resources have
+    /// to match the selector of the claim, and the claim has to tolerate
+    /// every `NoSchedule` taint of the resources and of the data centre.
+    /// Compute resources and interconnects have to meet the capability
+    /// requirement of the claim. A claim asking for compute units gets
+    /// unclaimed compute resources added until their units cover the request,
+    /// the smallest resource covering what is left first, else the largest.
+    /// Claimed resources draw their peak power, the claim is refused when
+    /// that exceeds the power or cooling budget of the data centre or of a
+    /// rack the resources sit in. A data centre does not know the quotas of
+    /// tenants, a claim of a tenant is refused here and made through
+    /// `LogicalInfrastructure::add_claim` instead.
+    ///
+    /// ```rust
+    /// use chrono::NaiveDate;
+    /// use libtheia::models::data_centre::{Compute, DataCentre};
+    /// use libtheia::models::resource::Allocation;
+    /// use libtheia::models::units::{Bytes, Hertz};
+    ///
+    /// let mut dc = DataCentre::new("dc1".to_string(), 1);
+    /// dc.add_compute(Compute::new("n1".to_string(), 32, Hertz::ghz(2), Bytes::gib(256))).unwrap();
+    /// dc.add_compute(Compute::new("n2".to_string(), 32, Hertz::ghz(2), Bytes::gib(256))).unwrap();
+    ///
+    /// let mut a = Allocation::new("tenant-a".to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), None);
+    /// a.add_compute("n1");
+    /// dc.add_claim(a).unwrap();
+    ///
+    /// let capacity = dc.capacity();
+    /// assert_eq!((capacity.cores.total, capacity.cores.claimed, capacity.cores.free()), (64, 32, 32));
+    /// ```
+    pub fn add_claim(&mut self, a: Allocation) -> Result<(), TheiaError> {
+        if let Some(tenant) = &a.tenant {
+            return Err(ClaimError::UnknownOwner(tenant.clone()).into());
+        }
+        self.insert_claim(a)
+    }
+
+    /// Make claim `a` after the checks of `add_claim`, whoever owns it. The
+    /// quotas of its owner are up to the caller.
+    pub(crate) fn insert_claim(&mut self, a: Allocation) -> Result<(), TheiaError> {
+        let a = self.check_claim(a)?;
+        self.claims.apply(self.claims.add(a, self.actor)?);
+        Ok(())
+    }
+
+    /// Check a claim like `add_claim` without making it, whoever owns it,
+    /// `a` with the compute resources placed for its compute units.
+    pub fn place_claim(&self, a: Allocation) -> Result<Allocation, TheiaError> {
+        Ok(self.check_claim(a)?)
+    }
+
+    fn check_claim(&self, mut a: Allocation) -> Result<Allocation, ClaimError> {
+        if self.claims.contains(&a.name) {
+            return Err(CollectionError::Duplicate(a.name.clone()).into());
+        }
+        if let Some(units) = a.compute_units {
+            let placed = self.place_units(&a, units)?;
+            a.compute.extend(placed);
+        }
+        if self.taints.iter().any(|t| t.effect == Effect::NoSchedule && !a.tolerations.iter().any(|tol| tol.tolerates(t))) {
+            return Err(ClaimError::Tainted(self.name.clone()));
+        }
+        let claimed = self.claimed_compute();
+        for c in a.compute.iter() {
+            let compute = self.compute.get(c).ok_or_else(|| ClaimError::UnknownResource(c.clone()))?;
+            if !self.compute_schedulable(c) {
+                return Err(ClaimError::Ineligible(c.clone()));
+            }
+            admit(&a, c, compute)?;
+            capable(&a, c, compute)?;
+            if claimed.contains(c.as_str()) || !self.gpu_usage(c).is_empty() {
+                return Err(ClaimError::AlreadyClaimed(c.clone()));
+            }
+        }
+        for (name, gpus) in a.gpus.iter() {
+            self.validate_gpu_claim(name, gpus, claimed.contains(name.as_str()))?;
+            if let Some(c) = self.compute.get(name) {
+                admit(&a, name, c)?;
+                capable(&a, name, c)?;
+            }
+        }
+        for (name, size) in a.storage.iter() {
+            let s = self.storage.get(name).ok_or_else(|| ClaimError::UnknownResource(name.clone()))?;
+            if !self.storage_schedulable(name) {
+                return Err(ClaimError::Ineligible(name.clone()));
+            }
+            admit(&a, name, s)?;
+            if self.claimed_storage(name).checked_add(*size).is_none_or(|claimed| claimed > s.capacity) {
+                return Err(ClaimError::InsufficientCapacity(name.clone()));
             }
         }
-        None
+        for (name, bandwidth) in a.interconnect.iter() {
+            let i = self.interconnects.get(name).ok_or_else(|| ClaimError::UnknownResource(name.clone()))?;
+            admit(&a, name, i)?;
+            capable(&a, name, i)?;
+            if self.claimed_interconnect(name).checked_add(*bandwidth).is_none_or(|claimed| claimed > i.speed) {
+                return Err(ClaimError::InsufficientCapacity(name.clone()));
+            }
+        }
+        self.admit_power(&a)?;
+        Ok(a)
     }
 
-    pub fn get_storage(&self, name: &str) -> Option<&Storage> {
-        for s in self.storage.iter() {
-            if s.name == name {
-                return Some(s);
+    /// Unclaimed compute resources that add up, with the named ones of `a`,
+    /// to `units` compute units.
+    fn place_units(&self, a: &Allocation, units: f64) -> Result<Vec<String>, ClaimError> {
+        if !units.is_finite() || units <= 0.0 {
+            return Err(ClaimError::InvalidUnits(units.to_string()));
+        }
+        let named: f64 = a.compute.iter().filter_map(|c| self.compute.get(c)).map(|c| c.compute_units()).sum();
+        let mut remaining = units - named;
+        let claimed = self.claimed_compute();
+        let mut candidates: Vec<&Compute> = self
+            .schedulable_compute_for(a)
+            .into_iter()
+            .filter(|c| !claimed.contains(c.name.as_str()) && !a.compute.contains(&c.name) && self.gpu_usage(&c.name).is_empty())
+            .filter(|c| a.selects(*c) && a.satisfied_by(*c) && a.tolerates(*c, Effect::NoSchedule) && c.compute_units() > 0.0)
+            .collect();
+        let preferred = |c: &Compute| !a.tolerates(c, Effect::PreferNoSchedule);
+        let mut placed = Vec::new();
+        while remaining > 0.0 {
+            let covering = candidates
+                .iter()
+                .filter(|c| c.compute_units() >= remaining)
+                .min_by(|x, y| preferred(x).cmp(&preferred(y)).then(x.compute_units().total_cmp(&y.compute_units())));
+            let pick = covering.or_else(|| {
+                candidates
+                    .iter()
+                    .min_by(|x, y| preferred(x).cmp(&preferred(y)).then(y.compute_units().total_cmp(&x.compute_units())))
+            });
+            let Some(c) = pick.copied() else {
+                return Err(ClaimError::InsufficientCapacity(format!("{} compute units", units)));
+            };
+            remaining -= c.compute_units();
+            placed.push(c.name.clone());
+            candidates.retain(|other| other.name != c.name);
+        }
+        Ok(placed)
+    }
+
+    /// Check the resources `a` moves from nominal to peak draw stay within
+    /// the budgets of the data centre and of their racks.
+    fn admit_power(&self, a: &Allocation) -> Result<(), ClaimError> {
+        let claimed = self.claimed_compute();
+        let mut extra: Vec<(&Option<Location>, Watts)> = Vec::new();
+        for name in a.compute.iter().chain(a.gpus.keys()).collect::<BTreeSet<_>>() {
+            if let Some(c) = self.compute.get(name) {
+                if !claimed.contains(name.as_str()) && self.gpu_usage(name).is_empty() {
+                    extra.push((&c.location, c.power.peak.checked_sub(c.power.nominal).unwrap_or_default()));
+                }
+            }
+        }
+        for name in a.storage.keys() {
+            if let Some(s) = self.storage.get(name) {
+                if self.claimed_storage(name) == Bytes(0) {
+                    extra.push((&s.location, s.power.peak.checked_sub(s.power.nominal).unwrap_or_default()));
+                }
+            }
+        }
+        let total: Watts = extra.iter().map(|(_, w)| *w).sum();
+        if !self.budget().allows(self.capacity().power_draw + total) {
+            return Err(ClaimError::OverBudget(self.name.clone()));
+        }
+        let racks: BTreeSet<Location> = extra
+            .iter()
+            .filter_map(|(l, _)| l.as_ref())
+            .filter(|l| l.level() >= Level::Rack)
+            .map(|l| l.truncate(Level::Rack))
+            .collect();
+        for rack in racks {
+            let Some(budget) = self.rack_budget(&rack) else { continue };
+            let draw: Watts = extra.iter().filter(|(l, _)| within(l, &rack)).map(|(_, w)| *w).sum();
+            if !budget.allows(self.capacity_at(|l| within(l, &rack)).power_draw + draw) {
+                return Err(ClaimError::OverBudget(rack.to_string()));
             }
         }
-        None
+        Ok(())
     }
 
-    pub fn get_interconnect(&self, name: &str) -> Option<&InterConnect> {
-        for i in self.interconnects.iter() {
-            if i.name == name {
-                return Some(i);
+    /// Budget of the rack at `location`, `None` if there is no such rack.
+    fn rack_budget(&self, location: &Location) -> Option<Budget> {
+        self.rooms.iter().find_map(|r| r.rack(location)).map(|r| r.budget)
+    }
+
+    fn validate_gpu_claim(&self, name: &str, claim: &GPUClaim, whole: bool) -> Result<(), ClaimError> {
+        let c = self.compute.get(name).ok_or_else(|| ClaimError::UnknownResource(name.to_string()))?;
+        if !self.compute_schedulable(name) {
+            return Err(ClaimError::Ineligible(name.to_string()));
+        }
+        if whole {
+            return Err(ClaimError::AlreadyClaimed(name.to_string()));
+        }
+        let usage = self.gpu_usage(name);
+        let device = |i: &usize| format!("{}/gpu{}", name, i);
+        for i in claim.devices.iter().chain(claim.partitions.keys()) {
+            if c.gpu_device(*i).is_none() {
+                return Err(ClaimError::UnknownResource(device(i)));
+            }
+            if usage.devices.contains(i) || (claim.devices.contains(i) && usage.partitions.contains_key(i)) {
+                return Err(ClaimError::AlreadyClaimed(device(i)));
             }
         }
-        None
+        for (i, profiles) in claim.partitions.iter() {
+            let g = c.gpu_device(*i).ok_or_else(|| ClaimError::UnknownResource(device(i)))?;
+            let mut used = Vec::new();
+            for p in usage.partitions.get(i).into_iter().chain([profiles]).flatten() {
+                used.push(g.profile(p).ok_or_else(|| ClaimError::UnknownResource(format!("{}/{}", device(i), p)))?);
+            }
+            if claim.devices.contains(i) || !g.fits(&used) {
+                return Err(ClaimError::InsufficientCapacity(device(i)));
+            }
+        }
+        Ok(())
     }
 
-    pub fn remove_compute(&mut self, name: &str) {
-        fn find_index(list: &List<Compute, u64>, name: &str) -> Option<usize> {
-            for (i, c) in list.iter().enumerate() {
-                if c.name == name {
-                    Some(i);
+    /// GPUs of compute resource `name` held by all claims together, a compute
+    /// resource claimed whole holds all of its devices.
+    pub fn gpu_usage(&self, name: &str) -> GPUClaim {
+        let mut usage = GPUClaim::default();
+        let claims: BTreeSet<&str> = self.claims.lookup_names("compute", name).into_iter().chain(self.claims.lookup_names("gpus", name)).collect();
+        for a in claims.into_iter().filter_map(|c| self.claims.get(c)) {
+            if a.compute.contains(name) {
+                let count = self.compute.get(name).map_or(0, |c| c.gpu_count());
+                usage.devices.extend(0..count);
+            }
+            if let Some(g) = a.gpus.get(name) {
+                usage.devices.extend(g.devices.iter().copied());
+                for (i, profiles) in g.partitions.iter() {
+                    usage.partitions.entry(*i).or_default().extend(profiles.iter().cloned());
                 }
             }
-            None
         }
-        if let Some(i) = find_index(&self.compute, name) {
-            self.compute.apply(self.compute.delete_index(i, i as u64).unwrap());
+        usage
+    }
+
+    /// GPU devices of compute resource `name` that are neither claimed nor
+    /// partitioned.
+    pub fn free_gpus(&self, name: &str) -> Vec<usize> {
+        let usage = self.gpu_usage(name);
+        let count = self.compute.get(name).map_or(0, |c| c.gpu_count());
+        (0..count)
+            .filter(|i| !usage.devices.contains(i) && !usage.partitions.contains_key(i))
+            .collect()
+    }
+
+    /// `k` free GPU devices of compute resource `name` that are all directly
+    /// linked to each other (by `kind`, if given)
+    ///
+    /// ```rust
+    /// use libtheia::models::data_centre::{Compute, DataCentre, GPUBusType, GPU};
+    /// use libtheia::models::gpu::{GPULink, GPULinkType};
+    /// use libtheia::models::units::{BitsPerSecond, Bytes, Hertz};
+    ///
+    /// let mut c = Compute::new("n1".to_string(), 64, Hertz::ghz(2), Bytes::gib(1024));
+    /// let mut g = GPU::new("h100".to_string(), Hertz::mhz(1980), Bytes::gib(80), GPUBusType::SXM);
+    /// g.set_count(4);
+    /// c.add_gpu(g);
+    /// for (a, b) in [(0, 1), (1, 2), (2, 3), (1, 3)] {
+    ///     c.add_gpu_link(GPULink::new(a, b, GPULinkType::NVLink, BitsPerSecond::gbps(900)));
+    /// }
+    /// let mut dc = DataCentre::new("dc1".to_string(), 1);
+    /// dc.add_compute(c).unwrap();
+    ///
+    /// assert_eq!(dc.find_gpus("n1", 3, Some(GPULinkType::NVLink)), Some(vec![1, 2, 3]));
+    /// assert_eq!(dc.find_gpus("n1", 4, None), None);
+    /// ```
+    pub fn find_gpus(&self, name: &str, k: usize, kind: Option<GPULinkType>) -> Option<Vec<usize>> {
+        let c = self.compute.get(name)?;
+        gpu::find_fully_connected(&c.gpu_links, &self.free_gpus(name), k, kind)
+    }
+
+    pub fn get_claim(&self, name: &str) -> Option<&Allocation> {
+        self.claims.get(name)
+    }
+
+    /// Release a claim, its resources become free again
+    pub fn release_claim(&mut self, name: &str) -> Result<(), TheiaError> {
+        self.claims.apply(self.claims.remove(name)?);
+        Ok(())
+    }
+
+    /// Compute resources new claims may be placed on.
+    pub fn schedulable_compute(&self) -> Vec<&Compute> {
+        let held: BTreeSet<&str> = self.compute_lifecycles.lookup_names("schedulable", false).into_iter().collect();
+        self.compute.iter().filter(|c| !held.contains(c.name.as_str())).collect()
+    }
+
+    /// Storage resources new claims may be placed on.
+    pub fn schedulable_storage(&self) -> Vec<&Storage> {
+        let held: BTreeSet<&str> = self.storage_lifecycles.lookup_names("schedulable", false).into_iter().collect();
+        self.storage.iter().filter(|s| !held.contains(s.name.as_str())).collect()
+    }
+
+    fn compute_schedulable(&self, name: &str) -> bool {
+        self.compute_lifecycles.get(name).is_none_or(|r| r.lifecycle.state.is_schedulable())
+    }
+
+    fn storage_schedulable(&self, name: &str) -> bool {
+        self.storage_lifecycles.get(name).is_none_or(|r| r.lifecycle.state.is_schedulable())
+    }
+
+    /// Lifecycle of compute resource `name`, `None` if there is no such
+    /// resource.
+    pub fn compute_lifecycle(&self, name: &str) -> Option<Lifecycle> {
+        self.compute.get(name)?;
+        Some(self.compute_lifecycles.get(name).map(|r| r.lifecycle.clone()).unwrap_or_default())
+    }
+
+    /// Lifecycle of storage resource `name`, `None` if there is no such
+    /// resource.
+    pub fn storage_lifecycle(&self, name: &str) -> Option<Lifecycle> {
+        self.storage.get(name)?;
+        Some(self.storage_lifecycles.get(name).map(|r| r.lifecycle.clone()).unwrap_or_default())
+    }
+
+    /// Start compute resource `name` out in lifecycle `l`, for resources
+    /// that are not `Available` when they are added.
+    pub(crate) fn set_compute_lifecycle(&mut self, name: &str, l: Lifecycle) -> Result<(), TheiaError> {
+        Ok(upsert(&mut self.compute_lifecycles, ResourceLifecycle::new(name.to_string(), l), self.actor)?)
+    }
+
+    /// Start storage resource `name` out in lifecycle `l`.
+    pub(crate) fn set_storage_lifecycle(&mut self, name: &str, l: Lifecycle) -> Result<(), TheiaError> {
+        Ok(upsert(&mut self.storage_lifecycles, ResourceLifecycle::new(name.to_string(), l), self.actor)?)
+    }
+
+    /// Schedulable compute resources the selector of `a`, if any, selects.
+    fn schedulable_compute_for(&self, a: &Allocation) -> Vec<&Compute> {
+        match &a.selector {
+            Some(selector) => self.select_compute(selector).into_iter().filter(|c| self.compute_schedulable(&c.name)).collect(),
+            None => self.schedulable_compute(),
         }
     }
 
-    pub fn remove_storage(&mut self, name: &str) {
-        fn find_index(list: &List<Storage, u64>, name: &str) -> Option<usize> {
-            for (i, s) in list.iter().enumerate() {
-                if s.name == name {
-                    Some(i);
+    /// Schedulable storage resources the selector of `a`, if any, selects.
+    fn schedulable_storage_for(&self, a: &Allocation) -> Vec<&Storage> {
+        match &a.selector {
+            Some(selector) => self.select_storage(selector).into_iter().filter(|s| self.storage_schedulable(&s.name)).collect(),
+            None => self.schedulable_storage(),
+        }
+    }
+
+    /// Set a label of the data centre itself.
+    pub fn set_label(&mut self, k: &str, v: &str) -> Result<(), TheiaError> {
+        let label = Label { key: k.to_string(), value: v.to_string() };
+        let op = match self.labels.contains(k) {
+            true => self.labels.update(label, self.actor)?,
+            false => self.labels.add(label, self.actor)?,
+        };
+        self.labels.apply(op);
+        Ok(())
+    }
+
+    pub fn remove_label(&mut self, k: &str) -> Result<(), TheiaError> {
+        self.labels.apply(self.labels.remove(k)?);
+        Ok(())
+    }
+
+    /// Taint the data centre, replacing any taint with the same key.
+    pub fn add_taint(&mut self, t: Taint) -> Result<(), TheiaError> {
+        let op = match self.taints.contains(&t.key) {
+            true => self.taints.update(t, self.actor)?,
+            false => self.taints.add(t, self.actor)?,
+        };
+        self.taints.apply(op);
+        Ok(())
+    }
+
+    pub fn remove_taint(&mut self, k: &str) -> Result<(), TheiaError> {
+        self.taints.apply(self.taints.remove(k)?);
+        Ok(())
+    }
+
+    /// Labels of the data centre as a map.
+    pub fn label_map(&self) -> BTreeMap<String, String> {
+        self.labels.iter().map(|l| (l.key.clone(), l.value.clone())).collect()
+    }
+
+    /// Compute resources whose labels match `selector`
+    ///
+    /// ```rust
+    /// use libtheia::models::data_centre::{Compute, DataCentre};
+    /// use libtheia::models::units::{Bytes, Hertz};
+    ///
+    /// let mut dc = DataCentre::new("dc1".to_string(), 1);
+    /// for (name, purpose) in [("n1", "gpu-training"), ("n2", "inference"), ("n3", "pci-dss")] {
+    ///     let mut c = Compute::new(name.to_string(), 32, Hertz::ghz(2), Bytes::gib(256));
+    ///     c.set_label("purpose", purpose);
+    ///     dc.add_compute(c).unwrap();
+    /// }
+    ///
+    /// let selected = dc.select_compute(&"purpose notin (pci-dss)".parse().unwrap());
+    /// assert_eq!(selected.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["n1", "n2"]);
+    /// ```
+    pub fn select_compute(&self, selector: &Selector) -> Vec<&Compute> {
+        labels::select_from(&self.compute, selector)
+    }
+
+    pub fn select_storage(&self, selector: &Selector) -> Vec<&Storage> {
+        labels::select_from(&self.storage, selector)
+    }
+
+    pub fn select_interconnects(&self, selector: &Selector) -> Vec<&InterConnect> {
+        labels::select_from(&self.interconnects, selector)
+    }
+
+    /// Compute resources, with their links, meeting `requirement`
+    ///
+    /// ```rust
+    /// use libtheia::models::data_centre::{Compute, DataCentre, InterConnect};
+    /// use libtheia::models::units::{BitsPerSecond, Bytes, Hertz};
+    ///
+    /// let mut dc = DataCentre::new("dc1".to_string(), 1);
+    /// for (name, nic) in [("n1", "rdma"), ("n2", "sriov")] {
+    ///     let mut c = Compute::new(name.to_string(), 32, Hertz::ghz(2), Bytes::gib(256));
+    ///     c.add_capability("avx512".parse().unwrap());
+    ///     let mut eth0 = InterConnect::new("eth0".to_string(), BitsPerSecond::gbps(100), true);
+    ///     eth0.add_capability(nic.parse().unwrap());
+    ///     c.add_link(eth0);
+    ///     dc.add_compute(c).unwrap();
+    /// }
+    ///
+    /// let capable = dc.compute_with(&"rdma && avx512".parse().unwrap());
+    /// assert_eq!(capable.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["n1"]);
+    /// ```
+    pub fn compute_with(&self, requirement: &Requirement) -> Vec<&Compute> {
+        self.compute.iter().filter(|c| requirement.matches(&c.capabilities())).collect()
+    }
+
+    pub fn interconnects_with(&self, requirement: &Requirement) -> Vec<&InterConnect> {
+        self.interconnects.iter().filter(|i| requirement.matches(&i.capabilities())).collect()
+    }
+
+    /// Move a compute resource to another lifecycle state
+    ///
+    /// Entering `Draining` moves its claims to the smallest schedulable,
+    /// unclaimed compute resource that is at least as large, and that the
+    /// claim selects and tolerates. Resources without `PreferNoSchedule`
+    /// taints the claim does not tolerate go first. The moved claim has to
+    /// pass the checks of `add_claim`, so resources with GPUs claimed or
+    /// partitioned, or over a power budget, are passed over. A claim of a
+    /// tenant only moves where it holds no more of any resource class than
+    /// before, `LogicalInfrastructure::transition_compute` admits it against
+    /// the quotas of its tenant instead. Maintenance and decommissioning are
+    /// refused while the resource is still claimed.
+    ///
+    /// ```rust
+    /// use chrono::{NaiveDate, Utc};
+    /// use libtheia::models::data_centre::{Compute, DataCentre};
+    /// use libtheia::models::lifecycle::State;
+    /// use libtheia::models::resource::Allocation;
+    /// use libtheia::models::units::{Bytes, Hertz};
+    ///
+    /// let mut dc = DataCentre::new("dc1".to_string(), 1);
+    /// dc.add_compute(Compute::new("n1".to_string(), 32, Hertz::ghz(2), Bytes::gib(256))).unwrap();
+    /// dc.add_compute(Compute::new("n2".to_string(), 32, Hertz::ghz(2), Bytes::gib(256))).unwrap();
+    /// let mut a = Allocation::new("tenant-a".to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), None);
+    /// a.add_compute("n1");
+    /// dc.add_claim(a).unwrap();
+    ///
+    /// let evacuation = dc.transition_compute("n1", State::Draining, "firmware update", Utc::now()).unwrap();
+    /// assert_eq!(evacuation.moved, vec![("tenant-a".to_string(), "n1".to_string(), "n2".to_string())]);
+    /// dc.transition_compute("n1", State::Maintenance, "firmware update", Utc::now()).unwrap();
+    /// ```
+    pub fn transition_compute(&mut self, name: &str, to: State, reason: &str, at: DateTime<Utc>) -> Result<Evacuation, TheiaError> {
+        self.transition_compute_with(name, to, reason, at, keeps_size)
+    }
+
+    /// `transition_compute` with the claims moved off the resource admitted
+    /// by `admit`, given this data centre, the claim and the claim moved.
+    pub(crate) fn transition_compute_with(
+        &mut self,
+        name: &str,
+        to: State,
+        reason: &str,
+        at: DateTime<Utc>,
+        admit: impl Fn(&DataCentre, &Allocation, &Allocation) -> bool,
+    ) -> Result<Evacuation, TheiaError> {
+        let mut lifecycle = self.compute_lifecycle(name).ok_or_else(|| CollectionError::NotFound(name.to_string()))?;
+        lifecycle.transition(to, reason, at)?;
+        if matches!(to, State::Maintenance | State::Decommissioned) && self.claimed_compute().contains(name) {
+            return Err(LifecycleError::Claimed(name.to_string()).into());
+        }
+        self.set_compute_lifecycle(name, lifecycle)?;
+        match to {
+            State::Draining => Ok(self.evacuate_compute(name, admit)?),
+            _ => Ok(Evacuation::default()),
+        }
+    }
+
+    /// Move a storage resource to another lifecycle state
+    ///
+    /// Entering `Draining` moves each claim on it to the schedulable storage
+    /// resource with the least free capacity that still fits the claim, with
+    /// the same selector, taint, `add_claim` and quota checks as for compute.
+    /// Maintenance and decommissioning are refused while it is still claimed.
+    pub fn transition_storage(&mut self, name: &str, to: State, reason: &str, at: DateTime<Utc>) -> Result<Evacuation, TheiaError> {
+        self.transition_storage_with(name, to, reason, at, keeps_size)
+    }
+
+    /// `transition_storage` with moved claims admitted by `admit`, as for
+    /// `transition_compute_with`.
+    pub(crate) fn transition_storage_with(
+        &mut self,
+        name: &str,
+        to: State,
+        reason: &str,
+        at: DateTime<Utc>,
+        admit: impl Fn(&DataCentre, &Allocation, &Allocation) -> bool,
+    ) -> Result<Evacuation, TheiaError> {
+        let mut lifecycle = self.storage_lifecycle(name).ok_or_else(|| CollectionError::NotFound(name.to_string()))?;
+        lifecycle.transition(to, reason, at)?;
+        if matches!(to, State::Maintenance | State::Decommissioned) && self.claimed_storage(name) > Bytes(0) {
+            return Err(LifecycleError::Claimed(name.to_string()).into());
+        }
+        self.set_storage_lifecycle(name, lifecycle)?;
+        match to {
+            State::Draining => Ok(self.evacuate_storage(name, admit)?),
+            _ => Ok(Evacuation::default()),
+        }
+    }
+
+    fn evacuate_compute(&mut self, name: &str, admit: impl Fn(&DataCentre, &Allocation, &Allocation) -> bool) -> Result<Evacuation, CollectionError> {
+        let mut evacuation = Evacuation::default();
+        let claims: Vec<Allocation> = self.claims.lookup("compute", name).into_iter().cloned().collect();
+        let Some(from) = self.compute.get(name).cloned() else {
+            return Ok(evacuation);
+        };
+        // GPU claims are bound to devices of this resource, they cannot move
+        for a in self.claims.lookup("gpus", name) {
+            evacuation.stranded.push((a.name.clone(), name.to_string()));
+        }
+        let mut scratch = self.clone();
+        for claim in claims {
+            let mut candidates: Vec<&Compute> = self
+                .schedulable_compute_for(&claim)
+                .into_iter()
+                .filter(|c| fits(&from, c) && claim.selects(*c) && claim.satisfied_by(*c) && claim.tolerates(*c, Effect::NoSchedule))
+                .collect();
+            candidates.sort_by_key(|c| (!claim.tolerates(*c, Effect::PreferNoSchedule), c.cores, c.ram, c.core_clock, c.gpus.len(), c.name.clone()));
+            let moves = candidates.into_iter().map(|c| {
+                let mut moved = claim.clone();
+                moved.compute.remove(name);
+                moved.compute.insert(c.name.clone());
+                (c.name.clone(), moved)
+            });
+            match self.admit_move(&mut scratch, &claim, moves, &admit)? {
+                Some((to, moved)) => {
+                    evacuation.moved.push((claim.name.clone(), name.to_string(), to));
+                    self.claims.apply(self.claims.update(moved, self.actor)?);
                 }
+                None => evacuation.stranded.push((claim.name, name.to_string())),
             }
-            None
         }
-        if let Some(i) = find_index(&self.storage, name) {
-            self.storage.apply(self.storage.delete_index(i, i as u64).unwrap());
+        Ok(evacuation)
+    }
+
+    fn evacuate_storage(&mut self, name: &str, admit: impl Fn(&DataCentre, &Allocation, &Allocation) -> bool) -> Result<Evacuation, CollectionError> {
+        let mut evacuation = Evacuation::default();
+        let claims: Vec<Allocation> = self.claims.lookup("storage", name).into_iter().cloned().collect();
+        let mut scratch = self.clone();
+        for claim in claims {
+            let size = claim.storage[name];
+            let mut candidates: Vec<(Bytes, &Storage)> = self
+                .schedulable_storage_for(&claim)
+                .into_iter()
+                .filter(|s| s.name != name && claim.selects(*s) && claim.tolerates(*s, Effect::NoSchedule))
+                .filter_map(|s| s.capacity.checked_sub(self.claimed_storage(&s.name)).map(|free| (free, s)))
+                .filter(|(free, _)| *free >= size)
+                .collect();
+            candidates.sort_by_key(|(free, s)| (!claim.tolerates(*s, Effect::PreferNoSchedule), *free, s.name.clone()));
+            let moves = candidates.into_iter().map(|(_, s)| {
+                let mut moved = claim.clone();
+                moved.storage.remove(name);
+                *moved.storage.entry(s.name.clone()).or_default() += size;
+                (s.name.clone(), moved)
+            });
+            match self.admit_move(&mut scratch, &claim, moves, &admit)? {
+                Some((to, moved)) => {
+                    evacuation.moved.push((claim.name.clone(), name.to_string(), to));
+                    self.claims.apply(self.claims.update(moved, self.actor)?);
+                }
+                None => evacuation.stranded.push((claim.name, name.to_string())),
+            }
         }
+        Ok(evacuation)
+    }
+
+    /// First of `moves`, `claim` rewritten onto another resource, that
+    /// `admit` lets through and that passes every check of `add_claim` in
+    /// place of the claim. `scratch` is a copy of this data centre taken once
+    /// per evacuation, the claim is taken off it for the checks and put back
+    /// as it ends up.
+    fn admit_move(
+        &self,
+        scratch: &mut DataCentre,
+        claim: &Allocation,
+        moves: impl Iterator<Item = (String, Allocation)>,
+        admit: impl Fn(&DataCentre, &Allocation, &Allocation) -> bool,
+    ) -> Result<Option<(String, Allocation)>, CollectionError> {
+        scratch.claims.apply(scratch.claims.remove(&claim.name)?);
+        let found = moves.into_iter().find(|(_, moved)| admit(self, claim, moved) && scratch.check_claim(moved.clone()).is_ok());
+        let kept = found.as_ref().map_or(claim, |(_, moved)| moved).clone();
+        scratch.claims.apply(scratch.claims.add(kept, scratch.actor)?);
+        Ok(found)
     }
 
-    pub fn remove_interconnect(&mut self, name: &str) {
-        fn find_index(list: &List<InterConnect, u64>, name: &str) -> Option<usize> {
-            for (i, s) in list.iter().enumerate() {
-                if s.name == name {
-                    Some(i);
+    /// Names of compute resources held by any claim.
+    pub fn claimed_compute(&self) -> BTreeSet<&str> {
+        self.claims
+            .attributes("compute")
+            .into_iter()
+            .filter_map(|attribute| match attribute {
+                Attribute::Text(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Capacity of storage resource `name` held by all claims together.
+    pub fn claimed_storage(&self, name: &str) -> Bytes {
+        self.claims.lookup("storage", name).into_iter().filter_map(|a| a.storage.get(name)).sum()
+    }
+
+    /// Bandwidth of interconnect `name` held by all claims together.
+    pub fn claimed_interconnect(&self, name: &str) -> BitsPerSecond {
+        self.claims.lookup("interconnect", name).into_iter().filter_map(|a| a.interconnect.get(name)).sum()
+    }
+
+    /// Total, claimed and free capacity of the whole data centre.
+    pub fn capacity(&self) -> Capacity {
+        let mut capacity = self.capacity_at(|_| true);
+        for i in self.interconnects.iter() {
+            capacity.add_interconnect(i, self.claimed_interconnect(&i.name));
+        }
+        capacity.budget = self.budget();
+        capacity
+    }
+
+    /// Capacity of the compute and storage placed below `scope`, with the
+    /// budget of the rack if `scope` is one.
+    pub fn capacity_in(&self, scope: &Location) -> Capacity {
+        let mut capacity = self.capacity_at(|l| within(l, scope));
+        if scope.level() == Level::Rack {
+            capacity.budget = self.rack_budget(scope).unwrap_or_default();
+        }
+        capacity
+    }
+
+    /// Capacity of compute and storage grouped by their location at `level`,
+    /// resources without a location are grouped under `None`.
+    pub fn capacity_by_location(&self, level: Level) -> BTreeMap<Option<Location>, Capacity> {
+        let keys: BTreeSet<Option<Location>> = self
+            .compute
+            .iter()
+            .map(|c| &c.location)
+            .chain(self.storage.iter().map(|s| &s.location))
+            .map(|l| l.as_ref().map(|l| l.truncate(level)))
+            .collect();
+        keys.into_iter()
+            .map(|key| {
+                let mut capacity = self.capacity_at(|l| l.as_ref().map(|l| l.truncate(level)) == key);
+                if let Some(rack) = key.as_ref().filter(|_| level == Level::Rack) {
+                    capacity.budget = self.rack_budget(rack).unwrap_or_default();
+                }
+                (key, capacity)
+            })
+            .collect()
+    }
+
+    /// Capacity of compute and storage per failure domain they inherit from
+    /// the hierarchy, a resource counts towards each of its domains.
+    pub fn capacity_by_failure_domain(&self) -> BTreeMap<String, Capacity> {
+        let mut by_domain: BTreeMap<String, Capacity> = BTreeMap::new();
+        let claimed = self.claimed_compute();
+        for c in self.compute.iter() {
+            let whole = claimed.contains(c.name.as_str());
+            let usage = self.gpu_usage(&c.name);
+            for domain in self.failure_domains(&c.location) {
+                let capacity = by_domain.entry(domain).or_default();
+                capacity.add_compute(c, whole);
+                if !whole {
+                    capacity.claim_gpus(c, &usage);
                 }
             }
-            None
         }
-        if let Some(i) = find_index(&self.interconnects, name) {
-            self.interconnects.apply(self.interconnects.delete_index(i, i as u64).unwrap());
+        for s in self.storage.iter() {
+            for domain in self.failure_domains(&s.location) {
+                by_domain.entry(domain).or_default().add_storage(s, self.claimed_storage(&s.name));
+            }
         }
+        by_domain
+    }
+
+    /// Capacity of the compute and storage whose labels match `selector`
+    ///
+    /// ```rust
+    /// use libtheia::models::data_centre::{Compute, DataCentre};
+    /// use libtheia::models::units::{Bytes, Hertz};
+    ///
+    /// let mut dc = DataCentre::new("dc1".to_string(), 1);
+    /// for (name, tier) in [("n1", "gold"), ("n2", "gold"), ("n3", "silver")] {
+    ///     let mut c = Compute::new(name.to_string(), 32, Hertz::ghz(2), Bytes::gib(256));
+    ///     c.set_label("tier", tier);
+    ///     dc.add_compute(c).unwrap();
+    /// }
+    ///
+    /// assert_eq!(dc.capacity_where(&"tier=gold".parse().unwrap()).cores.total, 64);
+    /// let by_tier = dc.capacity_by_label("tier");
+    /// assert_eq!(by_tier[&Some("silver".to_string())].cores.total, 32);
+    /// ```
+    pub fn capacity_where(&self, selector: &Selector) -> Capacity {
+        self.capacity_of(|c| selector.matches(&c.labels), |s| selector.matches(&s.labels))
     }
-}
\ No newline at end of file
+
+    /// Capacity of compute and storage grouped by the value of their label
+    /// `key`, resources without the label are grouped under `None`.
+    pub fn capacity_by_label(&self, key: &str) -> BTreeMap<Option<String>, Capacity> {
+        let values: BTreeSet<Option<&String>> = self
+            .compute
+            .iter()
+            .map(|c| c.labels.get(key))
+            .chain(self.storage.iter().map(|s| s.labels.get(key)))
+            .collect();
+        values
+            .into_iter()
+            .map(|value| {
+                let capacity = self.capacity_of(|c| c.labels.get(key) == value, |s| s.labels.get(key) == value);
+                (value.cloned(), capacity)
+            })
+            .collect()
+    }
+
+    fn capacity_at(&self, f: impl Fn(&Option<Location>) -> bool) -> Capacity {
+        self.capacity_of(|c| f(&c.location), |s| f(&s.location))
+    }
+
+    fn capacity_of(&self, compute: impl Fn(&Compute) -> bool, storage: impl Fn(&Storage) -> bool) -> Capacity {
+        let mut capacity = Capacity::new();
+        let claimed = self.claimed_compute();
+        for c in self.compute.iter().filter(|c| compute(c)) {
+            let whole = claimed.contains(c.name.as_str());
+            capacity.add_compute(c, whole);
+            if !whole {
+                capacity.claim_gpus(c, &self.gpu_usage(&c.name));
+            }
+        }
+        for s in self.storage.iter().filter(|s| storage(s)) {
+            capacity.add_storage(s, self.claimed_storage(&s.name));
+        }
+        capacity
+    }
+
+    fn failure_domains(&self, location: &Option<Location>) -> BTreeSet<String> {
+        location
+            .as_ref()
+            .and_then(|l| self.domains(l))
+            .map(|d| d.failure_domains)
+            .unwrap_or_default()
+    }
+
+    fn placed(&self, location: &Option<Location>) -> bool {
+        location.as_ref().and_then(|l| self.domains(l)).is_some()
+    }
+
+    fn placed_in(&self, location: &Option<Location>, f: impl Fn(&Domains) -> bool) -> bool {
+        location.as_ref().and_then(|l| self.domains(l)).is_some_and(|d| f(&d))
+    }
+}
+
+/// Check the selector and `NoSchedule` taints of resource `name` for claim `a`.
+fn admit(a: &Allocation, name: &str, r: &impl Labelled) -> Result<(), ClaimError> {
+    if !a.selects(r) {
+        return Err(ClaimError::Unselected(name.to_string()));
+    }
+    if !a.tolerates(r, Effect::NoSchedule) {
+        return Err(ClaimError::Tainted(name.to_string()));
+    }
+    Ok(())
+}
+
+fn capable(a: &Allocation, name: &str, r: &impl Capable) -> Result<(), ClaimError> {
+    match a.satisfied_by(r) {
+        true => Ok(()),
+        false => Err(ClaimError::Incapable(name.to_string())),
+    }
+}
+
+/// Add `item` to `collection`, or update it if its name is taken.
+fn upsert<T: Named + Clone + Debug>(collection: &mut Collection<T>, item: T, actor: u64) -> Result<(), CollectionError> {
+    let op = match collection.contains(item.name()) {
+        true => collection.update(item, actor)?,
+        false => collection.add(item, actor)?,
+    };
+    collection.apply(op);
+    Ok(())
+}
+
+/// Whether compute resource `to` can take over the work of `from`, across
+/// CPU generations by their compute units.
+fn fits(from: &Compute, to: &Compute) -> bool {
+    let cores = match from.cpu.generation == to.cpu.generation && from.cpu.score == to.cpu.score {
+        true => to.cores >= from.cores && to.core_clock >= from.core_clock,
+        false => to.compute_units() >= from.compute_units(),
+    };
+    to.name != from.name
+        && cores
+        && to.ram >= from.ram
+        && to.gpu_count() >= from.gpu_count()
+}
+
+/// Whether `claim` moved holds no more of any resource class than before if
+/// it has a tenant. A data centre does not know the quotas of tenants, the
+/// quotas the claim was admitted under keep holding.
+fn keeps_size(dc: &DataCentre, claim: &Allocation, moved: &Allocation) -> bool {
+    claim.tenant.is_none() || Usage::of(dc, moved).beyond(&Usage::of(dc, claim)) == Usage::new()
+}
+
+fn within(location: &Option<Location>, scope: &Location) -> bool {
+    location.as_ref().is_some_and(|l| scope.contains(l))
+}
@@ -0,0 +1,309 @@
+//! GPUs: vendor and model, partition profiles and GPU-to-GPU links.
+//!
+//! The GPUs of a `Compute` are numbered as devices: every `GPU` entry stands
+//! for `count` identical devices, numbered in the order of the entries. Links
+//! and claims refer to devices by that number.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use crate::models::units::{legacy, BitsPerSecond, Bytes, Hertz};
+
+/// Host bus a GPU is attached to. Older inventories used this for links and
+/// software stacks as well, those names still read as the matching bus.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[allow(unused)]
+pub enum GPUBusType {
+    #[serde(alias = "ROCm")]
+    PCIe,
+    #[serde(alias = "NVLink")]
+    SXM,
+    #[serde(alias = "InfinityFabric")]
+    OAM,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+#[allow(unused)]
+pub enum GPUVendor {
+    Nvidia,
+    Amd,
+    Intel,
+    #[default]
+    Unknown,
+}
+
+/// Software stack the GPU is driven with.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[allow(unused)]
+pub enum GPUStack {
+    CUDA,
+    ROCm,
+    OneAPI,
+}
+
+/// Direct GPU-to-GPU interconnect.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[allow(unused)]
+pub enum GPULinkType {
+    PCIe,
+    NVLink,
+    InfinityFabric,
+    XeLink,
+}
+
+///
+/// Partition profile, a slice of a GPU with its own memory and compute share
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct PartitionProfile {
+    pub name: String,
+    /// Compute slices out of the `compute_slices` of the GPU.
+    pub compute_slices: u32,
+    pub memory: Bytes,
+}
+
+impl PartitionProfile {
+    /// PartitionProfile instance
+    ///
+    /// * `n` - profile name (e.g. `1g.10gb`).
+    /// * `s` - compute slices.
+    /// * `m` - memory.
+    pub fn new(n: String, s: u32, m: Bytes) -> PartitionProfile {
+        PartitionProfile {
+            name: n,
+            compute_slices: s,
+            memory: m,
+        }
+    }
+}
+
+///
+/// GPU data structure
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(try_from = "GPUFields")]
+#[allow(unused)]
+pub struct GPU {
+    pub name: String,
+    pub clock: Hertz,
+    pub ram: Bytes,
+    pub bus_type: GPUBusType,
+    #[serde(default)]
+    pub vendor: GPUVendor,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub stack: Option<GPUStack>,
+    /// Number of identical devices.
+    #[serde(default = "one")]
+    pub count: u32,
+    /// Compute slices a device divides into for partitioning.
+    #[serde(default = "one")]
+    pub compute_slices: u32,
+    #[serde(default)]
+    pub profiles: Vec<PartitionProfile>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// `GPU` as written, with the old `clock_ghz` in GHz and `ram_gb` in GiB.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GPUFields {
+    name: String,
+    #[serde(default)]
+    clock: Option<Hertz>,
+    #[serde(default, deserialize_with = "legacy::ghz")]
+    clock_ghz: Option<Hertz>,
+    #[serde(default)]
+    ram: Option<Bytes>,
+    #[serde(default, deserialize_with = "legacy::gib")]
+    ram_gb: Option<Bytes>,
+    bus_type: GPUBusType,
+    #[serde(default)]
+    vendor: GPUVendor,
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    stack: Option<GPUStack>,
+    #[serde(default = "one")]
+    count: u32,
+    #[serde(default = "one")]
+    compute_slices: u32,
+    #[serde(default)]
+    profiles: Vec<PartitionProfile>,
+}
+
+impl TryFrom<GPUFields> for GPU {
+    type Error = String;
+
+    fn try_from(f: GPUFields) -> Result<Self, Self::Error> {
+        Ok(GPU {
+            name: f.name,
+            clock: legacy::require("clock", f.clock, "clock_ghz", f.clock_ghz)?,
+            ram: legacy::require("ram", f.ram, "ram_gb", f.ram_gb)?,
+            bus_type: f.bus_type,
+            vendor: f.vendor,
+            model: f.model,
+            stack: f.stack,
+            count: f.count,
+            compute_slices: f.compute_slices,
+            profiles: f.profiles,
+        })
+    }
+}
+
+impl GPU {
+    /// GPU instance
+    ///
+    /// * `n` - a gpu name.
+    /// * `c` - clock rate.
+    /// * `r` - ram size.
+    /// * `b` - bus type
+    pub fn new(n: String, c: Hertz, r: Bytes, b: GPUBusType) -> GPU {
+
+        GPU {
+            name: n,
+            clock: c,
+            ram: r,
+            bus_type: b,
+            vendor: GPUVendor::Unknown,
+            model: String::new(),
+            stack: None,
+            count: 1,
+            compute_slices: 1,
+            profiles: Vec::new(),
+        }
+    }
+
+    /// * `v` - vendor.
+    /// * `m` - model name.
+    pub fn set_model(&mut self, v: GPUVendor, m: String) {
+        self.vendor = v;
+        self.model = m;
+    }
+
+    pub fn set_stack(&mut self, s: GPUStack) {
+        self.stack = Some(s);
+    }
+
+    /// * `c` - number of identical devices.
+    pub fn set_count(&mut self, c: u32) {
+        self.count = c;
+    }
+
+    /// Allow partitioning into `s` compute slices with the given profiles.
+    pub fn set_partitioning(&mut self, s: u32, p: Vec<PartitionProfile>) {
+        self.compute_slices = s;
+        self.profiles = p;
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&PartitionProfile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// Whether `profiles` fit on one device together.
+    pub fn fits(&self, profiles: &[&PartitionProfile]) -> bool {
+        profiles.iter().map(|p| p.compute_slices).sum::<u32>() <= self.compute_slices
+            && profiles.iter().map(|p| p.memory).sum::<Bytes>() <= self.ram
+    }
+}
+
+///
+/// Link between two GPU devices of the same compute resource
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct GPULink {
+    pub a: usize,
+    pub b: usize,
+    pub link_type: GPULinkType,
+    pub bandwidth: BitsPerSecond,
+}
+
+impl GPULink {
+    /// GPULink instance
+    ///
+    /// * `a` - first device.
+    /// * `b` - second device.
+    /// * `t` - link type.
+    /// * `w` - bandwidth.
+    pub fn new(a: usize, b: usize, t: GPULinkType, w: BitsPerSecond) -> GPULink {
+        GPULink {
+            a,
+            b,
+            link_type: t,
+            bandwidth: w,
+        }
+    }
+
+    fn joins(&self, x: usize, y: usize, kind: Option<GPULinkType>) -> bool {
+        ((self.a, self.b) == (x, y) || (self.a, self.b) == (y, x)) && kind.is_none_or(|k| k == self.link_type)
+    }
+}
+
+/// Whether every pair of `devices` has a direct link (of `kind`, if given).
+pub fn fully_connected(links: &[GPULink], devices: &[usize], kind: Option<GPULinkType>) -> bool {
+    devices
+        .iter()
+        .enumerate()
+        .all(|(i, x)| devices[i + 1..].iter().all(|y| links.iter().any(|l| l.joins(*x, *y, kind))))
+}
+
+/// The first `k` of `candidates` (in order) that are fully connected.
+pub fn find_fully_connected(
+    links: &[GPULink],
+    candidates: &[usize],
+    k: usize,
+    kind: Option<GPULinkType>,
+) -> Option<Vec<usize>> {
+    fn extend(
+        links: &[GPULink],
+        candidates: &[usize],
+        k: usize,
+        kind: Option<GPULinkType>,
+        chosen: &mut Vec<usize>,
+    ) -> bool {
+        if chosen.len() == k {
+            return true;
+        }
+        for (i, d) in candidates.iter().enumerate() {
+            if chosen.iter().all(|c| links.iter().any(|l| l.joins(*c, *d, kind))) {
+                chosen.push(*d);
+                if extend(links, &candidates[i + 1..], k, kind, chosen) {
+                    return true;
+                }
+                chosen.pop();
+            }
+        }
+        false
+    }
+
+    let mut chosen = Vec::new();
+    extend(links, candidates, k, kind, &mut chosen).then_some(chosen)
+}
+
+///
+/// GPUs of one compute resource held by a claim
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+#[allow(unused)]
+pub struct GPUClaim {
+    /// Whole devices.
+    #[serde(default)]
+    pub devices: BTreeSet<usize>,
+    /// Partition profiles per device.
+    #[serde(default)]
+    pub partitions: BTreeMap<usize, Vec<String>>,
+}
+
+impl GPUClaim {
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty() && self.partitions.is_empty()
+    }
+}
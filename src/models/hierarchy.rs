@@ -0,0 +1,370 @@
+//! Physical hierarchy of a data centre: rooms, rows, racks and chassis.
+//!
+//! Every level carries power feed and failure-domain labels, a resource
+//! placed at a `Location` inherits the labels of every level on its path.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug, Display};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::models::collection::Named;
+use crate::models::power::Budget;
+
+/// Levels of the hierarchy, outermost first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Room,
+    Row,
+    Rack,
+    Chassis,
+}
+
+static LEVELS: [Level; 4] = [Level::Room, Level::Row, Level::Rack, Level::Chassis];
+
+/// Reasons a location cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocationError {
+    Empty,
+    EmptySegment(usize),
+    TooDeep(usize),
+}
+
+impl Display for LocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for LocationError {}
+
+///
+/// Position in the hierarchy, written as `room/row/rack/chassis`
+///
+/// A location that stops at a higher level (e.g. `room1/a`) selects the
+/// whole subtree below it.
+///
+/// ```rust
+/// use libtheia::models::hierarchy::{Level, Location};
+///
+/// let rack = Location::rack("hall1", "a", "07");
+/// let slot: Location = "hall1/a/07/c2".parse().unwrap();
+///
+/// assert!(rack.contains(&slot));
+/// assert_eq!(slot.level(), Level::Chassis);
+/// assert_eq!(rack.to_string(), "hall1/a/07");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    path: Vec<String>,
+}
+
+impl Location {
+    pub fn room(room: &str) -> Location {
+        Location::from_path(&[room])
+    }
+
+    pub fn row(room: &str, row: &str) -> Location {
+        Location::from_path(&[room, row])
+    }
+
+    pub fn rack(room: &str, row: &str, rack: &str) -> Location {
+        Location::from_path(&[room, row, rack])
+    }
+
+    pub fn chassis(room: &str, row: &str, rack: &str, chassis: &str) -> Location {
+        Location::from_path(&[room, row, rack, chassis])
+    }
+
+    fn from_path(path: &[&str]) -> Location {
+        Location {
+            path: path.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// The innermost level of this location.
+    pub fn level(&self) -> Level {
+        LEVELS[self.path.len() - 1]
+    }
+
+    /// Name at `level`, `None` when the location stops above it.
+    pub fn get(&self, level: Level) -> Option<&str> {
+        self.path.get(level as usize).map(|s| s.as_str())
+    }
+
+    /// Whether `other` is this location or lies below it.
+    pub fn contains(&self, other: &Location) -> bool {
+        other.path.starts_with(&self.path)
+    }
+
+    /// The ancestor at `level`, or this location when it stops above it.
+    pub fn truncate(&self, level: Level) -> Location {
+        Location {
+            path: self.path.iter().take(level as usize + 1).cloned().collect(),
+        }
+    }
+
+    pub fn parent(&self) -> Option<Location> {
+        match self.path.len() {
+            1 => None,
+            n => Some(Location {
+                path: self.path[..n - 1].to_vec(),
+            }),
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.join("/"))
+    }
+}
+
+impl FromStr for Location {
+    type Err = LocationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(LocationError::Empty);
+        }
+        let path: Vec<String> = s.split('/').map(|p| p.to_string()).collect();
+        if path.len() > LEVELS.len() {
+            return Err(LocationError::TooDeep(path.len()));
+        }
+        if let Some(i) = path.iter().position(|p| p.is_empty()) {
+            return Err(LocationError::EmptySegment(i));
+        }
+        Ok(Location { path })
+    }
+}
+
+impl Serialize for Location {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Location {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        String::deserialize(d)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+///
+/// Power feed and failure-domain labels of a level
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct Domains {
+    #[serde(default)]
+    pub power_feeds: BTreeSet<String>,
+    #[serde(default)]
+    pub failure_domains: BTreeSet<String>,
+}
+
+impl Domains {
+    /// Domains instance
+    ///
+    /// * `p` - power feeds.
+    /// * `f` - failure domains.
+    pub fn new(p: &[&str], f: &[&str]) -> Domains {
+        Domains {
+            power_feeds: p.iter().map(|s| s.to_string()).collect(),
+            failure_domains: f.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn extend(&mut self, other: &Domains) {
+        self.power_feeds.extend(other.power_feeds.iter().cloned());
+        self.failure_domains.extend(other.failure_domains.iter().cloned());
+    }
+}
+
+///
+/// Chassis (blade enclosure, multi-node server) in a rack
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct Chassis {
+    pub name: String,
+    #[serde(default)]
+    pub domains: Domains,
+}
+
+impl Chassis {
+    /// Chassis instance
+    ///
+    /// * `n` - chassis name.
+    /// * `d` - power feed and failure-domain labels.
+    pub fn new(n: String, d: Domains) -> Chassis {
+        Chassis { name: n, domains: d }
+    }
+}
+
+///
+/// Rack in a row
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct Rack {
+    pub name: String,
+    #[serde(default)]
+    pub domains: Domains,
+    #[serde(default)]
+    pub chassis: Vec<Chassis>,
+    #[serde(default)]
+    pub budget: Budget,
+}
+
+impl Rack {
+    /// Rack instance
+    ///
+    /// * `n` - rack name.
+    /// * `d` - power feed and failure-domain labels.
+    pub fn new(n: String, d: Domains) -> Rack {
+        Rack {
+            name: n,
+            domains: d,
+            chassis: Vec::new(),
+            budget: Budget::default(),
+        }
+    }
+
+    pub fn add_chassis(&mut self, c: Chassis) {
+        self.chassis.push(c);
+    }
+
+    pub fn set_budget(&mut self, b: Budget) {
+        self.budget = b;
+    }
+}
+
+///
+/// Row of racks in a room
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct Row {
+    pub name: String,
+    #[serde(default)]
+    pub domains: Domains,
+    #[serde(default)]
+    pub racks: Vec<Rack>,
+}
+
+impl Row {
+    /// Row instance
+    ///
+    /// * `n` - row name.
+    /// * `d` - power feed and failure-domain labels.
+    pub fn new(n: String, d: Domains) -> Row {
+        Row {
+            name: n,
+            domains: d,
+            racks: Vec::new(),
+        }
+    }
+
+    pub fn add_rack(&mut self, r: Rack) {
+        self.racks.push(r);
+    }
+}
+
+///
+/// Room (data hall) of a data centre
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct Room {
+    pub name: String,
+    #[serde(default)]
+    pub domains: Domains,
+    #[serde(default)]
+    pub rows: Vec<Row>,
+}
+
+impl Named for Room {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Room {
+    /// Room instance
+    ///
+    /// * `n` - room name.
+    /// * `d` - power feed and failure-domain labels.
+    pub fn new(n: String, d: Domains) -> Room {
+        Room {
+            name: n,
+            domains: d,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn add_row(&mut self, r: Row) {
+        self.rows.push(r);
+    }
+
+    /// Labels of every level on the path to `location`, outermost first,
+    /// `None` when the location does not exist in this room.
+    pub fn path(&self, location: &Location) -> Option<Vec<&Domains>> {
+        if location.get(Level::Room) != Some(self.name.as_str()) {
+            return None;
+        }
+        let mut domains = vec![&self.domains];
+        if let Some(row) = location.get(Level::Row) {
+            let row = self.rows.iter().find(|r| r.name == row)?;
+            domains.push(&row.domains);
+            if let Some(rack) = location.get(Level::Rack) {
+                let rack = row.racks.iter().find(|r| r.name == rack)?;
+                domains.push(&rack.domains);
+                if let Some(chassis) = location.get(Level::Chassis) {
+                    let chassis = rack.chassis.iter().find(|c| c.name == chassis)?;
+                    domains.push(&chassis.domains);
+                }
+            }
+        }
+        Some(domains)
+    }
+
+    /// The rack `location` lies in, `None` above rack level or outside this
+    /// room.
+    pub fn rack(&self, location: &Location) -> Option<&Rack> {
+        if location.get(Level::Room) != Some(self.name.as_str()) {
+            return None;
+        }
+        let row = self.rows.iter().find(|r| Some(r.name.as_str()) == location.get(Level::Row))?;
+        row.racks.iter().find(|r| Some(r.name.as_str()) == location.get(Level::Rack))
+    }
+
+    /// Labels a resource at `location` inherits from all levels above it.
+    pub fn domains(&self, location: &Location) -> Option<Domains> {
+        self.path(location).map(|path| {
+            path.into_iter().fold(Domains::default(), |mut all, d| {
+                all.extend(d);
+                all
+            })
+        })
+    }
+
+    /// Every location in this room, outermost first.
+    pub fn locations(&self) -> Vec<Location> {
+        let mut locations = vec![Location::room(&self.name)];
+        for row in self.rows.iter() {
+            locations.push(Location::row(&self.name, &row.name));
+            for rack in row.racks.iter() {
+                locations.push(Location::rack(&self.name, &row.name, &rack.name));
+                for chassis in rack.chassis.iter() {
+                    locations.push(Location::chassis(&self.name, &row.name, &rack.name, &chassis.name));
+                }
+            }
+        }
+        locations
+    }
+}
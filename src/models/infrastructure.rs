@@ -1,23 +1,114 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use crate::crdt::{CmRDT, List};
+use crate::crdt::{CmRDT, CvRDT, List};
+use crate::error::TheiaError;
+use crate::models::capacity::Capacity;
+use crate::models::collection::{Collection, CollectionError};
 use crate::models::data_centre::DataCentre;
+use crate::models::hierarchy::{Level, Location};
+use crate::models::labels::Selector;
+use crate::models::lifecycle::{Evacuation, State};
+use crate::models::resource::{Allocation, ClaimError, UNOWNED};
+use crate::models::tenant::{QuotaUsage, Tenant, Usage};
+use crate::models::units::{BitsPerSecond, Bytes};
+use crate::models::wan::SiteLink;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[allow(unused)]
 pub struct LogicalInfrastructure {
     pub data_centres: List<DataCentre, u64>,
+    /// Wide area links between the data centres.
+    #[serde(default)]
+    pub site_links: Collection<SiteLink>,
+    /// Tenants owning the claims, with their projects and quotas.
+    #[serde(default)]
+    pub tenants: Collection<Tenant>,
+    /// Actor this replica writes as, local to the replica, 0 when it has
+    /// none yet.
+    #[serde(skip)]
+    actor: u64,
 }
 
 impl LogicalInfrastructure {
-    pub fn new() -> LogicalInfrastructure {
+    /// LogicalInfrastructure instance
+    ///
+    /// * `a` - actor this replica writes as, the replica ID from the
+    ///   configuration. Every replica needs its own, writes are refused with
+    ///   actor 0.
+    pub fn new(a: u64) -> LogicalInfrastructure {
         LogicalInfrastructure {
             data_centres: List::new(),
+            site_links: Collection::new(),
+            tenants: Collection::new(),
+            actor: a,
         }
     }
 
-    pub fn add_data_centre(&mut self, dc: DataCentre) {
-        let index = self.data_centres.len() + 1;
-        self.data_centres.apply(self.data_centres.append(dc, index as u64));
+    /// Write changes as `actor`, for an infrastructure that was
+    /// deserialized. Its data centres write as `actor` as well.
+    pub fn with_actor(mut self, actor: u64) -> LogicalInfrastructure {
+        self.actor = actor;
+        for dc in self.data_centres.iter_mut() {
+            dc.set_actor(actor);
+        }
+        self
+    }
+
+    pub(crate) fn actor(&self) -> u64 {
+        self.actor
+    }
+
+    /// Merge the state of another replica
+    ///
+    /// `List::merge` keeps one copy of each data centre, so the contents of
+    /// the data centres both replicas hold are merged collection by
+    /// collection. Merged data centres write as this replica.
+    ///
+    /// ```rust
+    /// use libtheia::models::data_centre::{Compute, DataCentre, Storage};
+    /// use libtheia::models::infrastructure::LogicalInfrastructure;
+    /// use libtheia::models::units::{Bytes, Hertz};
+    ///
+    /// let mut r1 = LogicalInfrastructure::new(1);
+    /// r1.add_data_centre(DataCentre::new("dc1".to_string(), 1)).unwrap();
+    /// let mut r2 = r1.clone().with_actor(2);
+    ///
+    /// let n1 = Compute::new("n1".to_string(), 32, Hertz::ghz(2), Bytes::gib(256));
+    /// r1.get_data_centre_mut("dc1").unwrap().add_compute(n1).unwrap();
+    /// let s1 = Storage::new("s1".to_string(), None, Bytes::tb(10));
+    /// r2.get_data_centre_mut("dc1").unwrap().add_storage(s1).unwrap();
+    ///
+    /// r1.merge(r2);
+    /// let dc1 = r1.get_data_centre("dc1").unwrap();
+    /// assert!(dc1.get_compute("n1").is_some() && dc1.get_storage("s1").is_some());
+    /// ```
+    pub fn merge(&mut self, other: LogicalInfrastructure) {
+        let theirs: Vec<DataCentre> = other.data_centres.iter().cloned().collect();
+        self.data_centres.merge(other.data_centres);
+        self.site_links.merge(other.site_links);
+        self.tenants.merge(other.tenants);
+        for dc in theirs {
+            if let Some(ours) = self.get_data_centre_mut(&dc.name) {
+                ours.merge(dc);
+            }
+        }
+        let actor = self.actor;
+        for dc in self.data_centres.iter_mut() {
+            dc.set_actor(actor);
+        }
+    }
+
+    /// Add a data centre, it writes as this replica from then on.
+    pub fn add_data_centre(&mut self, mut dc: DataCentre) -> Result<(), TheiaError> {
+        if self.actor == 0 {
+            return Err(CollectionError::NoActor.into());
+        }
+        dc.set_actor(self.actor);
+        self.data_centres.apply(self.data_centres.append(dc, self.actor));
+        Ok(())
     }
 
     /// Get a data centre by name
@@ -26,32 +117,354 @@ impl LogicalInfrastructure {
     /// use libtheia::models::infrastructure::LogicalInfrastructure;
     /// use libtheia::models::data_centre::DataCentre;
     ///
-    /// let mut infra = LogicalInfrastructure::new();
-    /// let mut dc = DataCentre::new("test".to_string());
-    /// infra.add_data_centre(dc.clone());
+    /// let mut infra = LogicalInfrastructure::new(1);
+    /// let mut dc = DataCentre::new("test".to_string(), 1);
+    /// infra.add_data_centre(dc.clone()).unwrap();
     /// let fdc = infra.get_data_centre("test").unwrap();
     ///
     /// assert_eq!(dc, fdc.clone());
     pub fn get_data_centre(&self, name: &str) -> Option<&DataCentre> {
+        self.data_centres.iter().find(|dc| dc.name == name)
+    }
+
+    /// Mutable access to a data centre, for changes through its collections.
+    pub fn get_data_centre_mut(&mut self, name: &str) -> Option<&mut DataCentre> {
+        self.data_centres.iter_mut().find(|dc| dc.name == name)
+    }
+
+    pub fn remove_data_centre(&mut self, name: &str) -> Result<(), TheiaError> {
+        if self.actor == 0 {
+            return Err(CollectionError::NoActor.into());
+        }
+        let op = self
+            .data_centres
+            .iter()
+            .position(|dc| dc.name == name)
+            .and_then(|i| self.data_centres.delete_index(i, self.actor))
+            .ok_or_else(|| CollectionError::NotFound(name.to_string()))?;
+        self.data_centres.apply(op);
+        Ok(())
+    }
+
+    /// Data centres whose own labels match `selector`.
+    pub fn select_data_centres(&self, selector: &Selector) -> Vec<&DataCentre> {
+        self.data_centres.iter().filter(|dc| selector.matches(&dc.label_map())).collect()
+    }
+
+    /// Total, claimed and free capacity over all data centres.
+    pub fn capacity(&self) -> Capacity {
+        self.data_centres.iter().fold(Capacity::empty(), |mut all, dc| {
+            all.merge(&dc.capacity());
+            all
+        })
+    }
+
+    pub fn capacity_by_data_centre(&self) -> BTreeMap<String, Capacity> {
+        self.data_centres.iter().map(|dc| (dc.name.clone(), dc.capacity())).collect()
+    }
+
+    /// Capacity per data centre and location at `level`.
+    pub fn capacity_by_location(&self, level: Level) -> BTreeMap<(String, Option<Location>), Capacity> {
+        self.data_centres
+            .iter()
+            .flat_map(|dc| {
+                dc.capacity_by_location(level)
+                    .into_iter()
+                    .map(|(location, capacity)| ((dc.name.clone(), location), capacity))
+            })
+            .collect()
+    }
+
+    /// Capacity per failure domain, domains with the same label in different
+    /// data centres are summed.
+    pub fn capacity_by_failure_domain(&self) -> BTreeMap<String, Capacity> {
+        let mut by_domain: BTreeMap<String, Capacity> = BTreeMap::new();
         for dc in self.data_centres.iter() {
-            if dc.name == name {
-                return Some(dc);
+            for (domain, capacity) in dc.capacity_by_failure_domain() {
+                by_domain.entry(domain).or_insert_with(Capacity::empty).merge(&capacity);
             }
         }
-        None
+        by_domain
+    }
+
+    pub fn add_site_link(&mut self, l: SiteLink) -> Result<(), TheiaError> {
+        self.site_links.apply(self.site_links.add(l, self.actor)?);
+        Ok(())
+    }
+
+    pub fn update_site_link(&mut self, l: SiteLink) -> Result<(), TheiaError> {
+        self.site_links.apply(self.site_links.update(l, self.actor)?);
+        Ok(())
+    }
+
+    pub fn get_site_link(&self, a: &str, b: &str) -> Option<&SiteLink> {
+        self.site_links.get(&SiteLink::key(a, b))
+    }
+
+    pub fn remove_site_link(&mut self, a: &str, b: &str) -> Result<(), TheiaError> {
+        self.site_links.apply(self.site_links.remove(&SiteLink::key(a, b))?);
+        Ok(())
+    }
+
+    /// Record a measured latency, it takes precedence over the declared one.
+    pub fn record_latency(&mut self, a: &str, b: &str, us: i32) -> Result<(), TheiaError> {
+        let mut l = self.get_site_link(a, b).cloned().ok_or_else(|| CollectionError::NotFound(SiteLink::key(a, b)))?;
+        l.set_measured_latency(us);
+        self.update_site_link(l)
+    }
+
+    /// Latency of a link, estimated from the coordinates of its ends when
+    /// neither declared nor measured.
+    pub fn site_link_latency_us(&self, l: &SiteLink) -> Option<i32> {
+        l.latency_us().or_else(|| {
+            let a = self.get_data_centre(&l.a)?.coordinates()?;
+            let b = self.get_data_centre(&l.b)?.coordinates()?;
+            Some(a.latency_us(&b))
+        })
     }
 
-    pub fn remove_data_centre(&mut self, name: &str) {
-        fn find_index(list: &List<DataCentre, u64>, name: &str) -> Option<usize> {
-            for (i, s) in list.iter().enumerate() {
-                if s.name == name {
-                    Some(i);
+    /// Lowest latency in microseconds from `from` to every data centre it
+    /// reaches over site links, links without a latency are not used.
+    fn latencies(&self, from: &str) -> BTreeMap<String, i32> {
+        let mut best: BTreeMap<String, i32> = BTreeMap::new();
+        if self.get_data_centre(from).is_none() {
+            return best;
+        }
+        best.insert(from.to_string(), 0);
+        let mut heap = BinaryHeap::from([(Reverse(0), from.to_string())]);
+        while let Some((Reverse(latency), dc)) = heap.pop() {
+            if best.get(&dc).is_some_and(|b| *b < latency) {
+                continue;
+            }
+            for l in self.site_links.iter() {
+                let (Some(next), Some(us)) = (l.other(&dc), self.site_link_latency_us(l)) else {
+                    continue;
+                };
+                let total = latency.saturating_add(us);
+                if best.get(next).is_none_or(|b| total < *b) {
+                    best.insert(next.to_string(), total);
+                    heap.push((Reverse(total), next.to_string()));
                 }
             }
-            None
         }
-        if let Some(i) = find_index(&self.data_centres, name) {
-            self.data_centres.apply(self.data_centres.delete_index(i, i as u64).unwrap());
+        best
+    }
+
+    /// Lowest latency in microseconds between two data centres over site
+    /// links, `None` when no path with known latencies joins them.
+    pub fn latency_us(&self, from: &str, to: &str) -> Option<i32> {
+        self.latencies(from).get(to).copied()
+    }
+
+    /// Other data centres reachable from `dc` within `max_us` microseconds,
+    /// nearest first.
+    ///
+    /// ```rust
+    /// use libtheia::models::data_centre::DataCentre;
+    /// use libtheia::models::infrastructure::LogicalInfrastructure;
+    /// use libtheia::models::units::BitsPerSecond;
+    /// use libtheia::models::wan::SiteLink;
+    ///
+    /// let mut infra = LogicalInfrastructure::new(1);
+    /// for dc in ["dc1", "dc2", "dc3"] {
+    ///     infra.add_data_centre(DataCentre::new(dc.to_string(), 1)).unwrap();
+    /// }
+    /// for (a, b, us) in [("dc1", "dc2", 1_500), ("dc2", "dc3", 4_000)] {
+    ///     let mut l = SiteLink::new(a.to_string(), b.to_string(), BitsPerSecond::gbps(100));
+    ///     l.set_declared_latency(us);
+    ///     infra.add_site_link(l).unwrap();
+    /// }
+    ///
+    /// assert_eq!(infra.within_latency("dc1", 5_000), vec![("dc2".to_string(), 1_500)]);
+    /// assert_eq!(infra.latency_us("dc1", "dc3"), Some(5_500));
+    /// ```
+    pub fn within_latency(&self, dc: &str, max_us: i32) -> Vec<(String, i32)> {
+        let mut within: Vec<(String, i32)> = self
+            .latencies(dc)
+            .into_iter()
+            .filter(|(name, us)| name != dc && *us <= max_us)
+            .collect();
+        within.sort_by_key(|(name, us)| (*us, name.clone()));
+        within
+    }
+
+    /// Lowest latency between every pair of data centres that reach each other.
+    pub fn latency_matrix(&self) -> BTreeMap<(String, String), i32> {
+        self.data_centres
+            .iter()
+            .flat_map(|dc| self.latencies(&dc.name).into_iter().map(|(to, us)| ((dc.name.clone(), to), us)))
+            .collect()
+    }
+
+    /// Bandwidth of the direct link between two data centres.
+    pub fn site_bandwidth(&self, a: &str, b: &str) -> Option<BitsPerSecond> {
+        self.get_site_link(a, b).map(|l| l.bandwidth)
+    }
+
+    /// Cost of sending `size` over the direct link between two data centres.
+    pub fn egress_cost(&self, a: &str, b: &str, size: Bytes) -> Option<f64> {
+        self.get_site_link(a, b).map(|l| l.cost(size))
+    }
+
+    /// Register a tenant, `UNOWNED` is reserved for claims without one.
+    pub fn add_tenant(&mut self, t: Tenant) -> Result<(), TheiaError> {
+        if t.name == UNOWNED {
+            return Err(CollectionError::Reserved(t.name).into());
+        }
+        self.tenants.apply(self.tenants.add(t, self.actor)?);
+        Ok(())
+    }
+
+    pub fn update_tenant(&mut self, t: Tenant) -> Result<(), TheiaError> {
+        self.tenants.apply(self.tenants.update(t, self.actor)?);
+        Ok(())
+    }
+
+    pub fn get_tenant(&self, name: &str) -> Option<&Tenant> {
+        self.tenants.get(name)
+    }
+
+    pub fn remove_tenant(&mut self, name: &str) -> Result<(), TheiaError> {
+        self.tenants.apply(self.tenants.remove(name)?);
+        Ok(())
+    }
+
+    /// Claim resources of data centre `dc`
+    ///
+    /// The claim is checked like `DataCentre::add_claim`, and is refused when
+    /// it would take its tenant or project beyond any of their quotas. A
+    /// claim naming a tenant or project that is not registered is refused,
+    /// claims without a tenant are unowned and not limited.
+    pub fn add_claim(&mut self, dc: &str, a: Allocation) -> Result<(), TheiaError> {
+        let data_centre = self.get_data_centre(dc).ok_or_else(|| CollectionError::NotFound(dc.to_string()))?;
+        let a = data_centre.place_claim(a)?;
+        self.admit_quotas(data_centre, &a, None)?;
+        self.get_data_centre_mut(dc)
+            .ok_or_else(|| CollectionError::NotFound(dc.to_string()))?
+            .insert_claim(a)?;
+        Ok(())
+    }
+
+    /// Move a compute resource of data centre `dc` to another lifecycle state
+    ///
+    /// Like `DataCentre::transition_compute`, but a claim of a tenant moved
+    /// off a draining resource is admitted against the quotas of its tenant
+    /// and project again, in all data centres and in `dc`.
+    pub fn transition_compute(&mut self, dc: &str, name: &str, to: State, reason: &str, at: DateTime<Utc>) -> Result<Evacuation, TheiaError> {
+        let mut data_centre = self.get_data_centre(dc).ok_or_else(|| CollectionError::NotFound(dc.to_string()))?.clone();
+        let evacuation = data_centre.transition_compute_with(name, to, reason, at, |state, claim, moved| {
+            self.admit_quotas(state, moved, Some(claim)).is_ok()
+        })?;
+        self.replace_data_centre(data_centre);
+        Ok(evacuation)
+    }
+
+    /// Move a storage resource of data centre `dc` to another lifecycle state
+    ///
+    /// Like `DataCentre::transition_storage`, with moved claims admitted
+    /// against quotas as for `transition_compute`.
+    pub fn transition_storage(&mut self, dc: &str, name: &str, to: State, reason: &str, at: DateTime<Utc>) -> Result<Evacuation, TheiaError> {
+        let mut data_centre = self.get_data_centre(dc).ok_or_else(|| CollectionError::NotFound(dc.to_string()))?.clone();
+        let evacuation = data_centre.transition_storage_with(name, to, reason, at, |state, claim, moved| {
+            self.admit_quotas(state, moved, Some(claim)).is_ok()
+        })?;
+        self.replace_data_centre(data_centre);
+        Ok(evacuation)
+    }
+
+    /// Put `dc`, changed through its own collections, in place of the data
+    /// centre of the same name.
+    pub(crate) fn replace_data_centre(&mut self, dc: DataCentre) {
+        if let Some(d) = self.get_data_centre_mut(&dc.name) {
+            *d = dc;
+        }
+    }
+
+    /// Check the quotas of the tenant and project of `a` hold with `a`
+    /// claimed in `state`, in place of `old` if it replaces a claim there.
+    /// `state` stands in for the data centre of the same name.
+    pub(crate) fn admit_quotas(&self, state: &DataCentre, a: &Allocation, old: Option<&Allocation>) -> Result<(), ClaimError> {
+        let Some(owner) = &a.tenant else {
+            return Ok(());
+        };
+        let tenant = self.get_tenant(owner).ok_or_else(|| ClaimError::UnknownOwner(owner.clone()))?;
+        let project = a.project.as_ref().map(|p| format!("{}/{}", tenant.name, p));
+        if a.project.as_ref().is_some_and(|p| tenant.get_project(p).is_none()) {
+            return Err(ClaimError::UnknownOwner(project.unwrap_or_default()));
+        }
+        let extra = Usage::of(state, a);
+        let held = old.map(|o| Usage::of(state, o)).unwrap_or_default();
+        for mut q in self.quota_usage_in(&tenant.name, Some(state)) {
+            if q.owner != tenant.name && Some(&q.owner) != project.as_ref() {
+                continue;
+            }
+            if q.data_centre.as_ref().is_some_and(|d| *d != state.name) {
+                continue;
+            }
+            q.used = q.used.beyond(&held);
+            q.used.merge(&extra);
+            if let Some(class) = q.exceeded().first() {
+                let scope = match &q.data_centre {
+                    Some(d) => format!("{} in {}", q.owner, d),
+                    None => q.owner.clone(),
+                };
+                return Err(ClaimError::OverQuota(format!("{}: {}", scope, class)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Resources held by the claims of a tenant, of one of its projects if
+    /// `project` is given, in data centre `dc` or in all of them.
+    pub fn usage(&self, tenant: &str, project: Option<&str>, dc: Option<&str>) -> Usage {
+        self.usage_in(tenant, project, dc, None)
+    }
+
+    /// `usage` with `state` standing in for the data centre of the same name.
+    fn usage_in(&self, tenant: &str, project: Option<&str>, dc: Option<&str>, state: Option<&DataCentre>) -> Usage {
+        let mut usage = Usage::new();
+        for d in self.data_centres.iter().filter(|d| dc.is_none_or(|dc| d.name == dc)) {
+            let d = state.filter(|s| s.name == d.name).unwrap_or(d);
+            for a in d.claims.iter().filter(|a| a.tenant.as_deref() == Some(tenant)) {
+                if project.is_none_or(|p| a.project.as_deref() == Some(p)) {
+                    usage.merge(&Usage::of(d, a));
+                }
+            }
+        }
+        usage
+    }
+
+    /// Every quota of a tenant and of its projects with what is held against
+    /// it, the tenant first, totals before data centres.
+    pub fn quota_usage(&self, tenant: &str) -> Vec<QuotaUsage> {
+        self.quota_usage_in(tenant, None)
+    }
+
+    /// `quota_usage` with `state` standing in for the data centre of the
+    /// same name.
+    fn quota_usage_in(&self, tenant: &str, state: Option<&DataCentre>) -> Vec<QuotaUsage> {
+        let Some(t) = self.get_tenant(tenant) else {
+            return Vec::new();
+        };
+        let owners = std::iter::once((t.name.clone(), None, &t.quotas))
+            .chain(t.projects.values().map(|p| (format!("{}/{}", t.name, p.name), Some(p.name.as_str()), &p.quotas)));
+        let mut usage = Vec::new();
+        for (owner, project, quotas) in owners {
+            usage.push(QuotaUsage {
+                owner: owner.clone(),
+                data_centre: None,
+                quota: quotas.total,
+                used: self.usage_in(tenant, project, None, state),
+            });
+            for (dc, quota) in quotas.data_centres.iter() {
+                usage.push(QuotaUsage {
+                    owner: owner.clone(),
+                    data_centre: Some(dc.clone()),
+                    quota: *quota,
+                    used: self.usage_in(tenant, project, Some(dc), state),
+                });
+            }
         }
+        usage
     }
 }
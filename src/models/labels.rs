@@ -0,0 +1,491 @@
+//! Labels, taints and label selectors.
+//!
+//! Selectors are written as expressions over labels:
+//! - `key=value`, `key==value` and `key!=value` compare a label;
+//! - `key in (a, b)` and `key notin (a, b)` test set membership;
+//! - `key` and `!key` test whether a label exists;
+//! - `!(...)` negates, `&&` (or `,`) and `||` combine, `&&` binds tighter.
+//!
+//! As with Kubernetes selectors `!=` and `notin` also match resources without
+//! the label. Values are bare words (letters, digits and `-_./:`) or double
+//! quoted strings.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug, Display};
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::crdt::index::Attribute;
+use crate::models::collection::{Collection, Named};
+
+/// Resources that carry labels and taints.
+pub trait Labelled {
+    fn labels(&self) -> &BTreeMap<String, String>;
+
+    fn taints(&self) -> &[Taint];
+}
+
+/// A single label, the unit a data centre replicates its labels in.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Label {
+    pub key: String,
+    pub value: String,
+}
+
+impl Named for Label {
+    fn name(&self) -> &str {
+        &self.key
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[allow(unused)]
+pub enum Effect {
+    /// Only claims that tolerate the taint may use the resource.
+    NoSchedule,
+    /// Claims avoid the resource when there is an alternative.
+    PreferNoSchedule,
+}
+
+///
+/// Taint, repels claims that do not tolerate it
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct Taint {
+    pub key: String,
+    #[serde(default)]
+    pub value: Option<String>,
+    pub effect: Effect,
+}
+
+impl Named for Taint {
+    fn name(&self) -> &str {
+        &self.key
+    }
+}
+
+impl Taint {
+    /// Taint instance
+    ///
+    /// * `k` - key.
+    /// * `v` - optional value.
+    /// * `e` - effect.
+    pub fn new(k: String, v: Option<String>, e: Effect) -> Taint {
+        Taint { key: k, value: v, effect: e }
+    }
+}
+
+///
+/// Toleration of a claim, a toleration without value tolerates every taint
+/// with its key
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[allow(unused)]
+pub struct Toleration {
+    pub key: String,
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+impl Toleration {
+    /// Toleration instance
+    ///
+    /// * `k` - key.
+    /// * `v` - optional value.
+    pub fn new(k: String, v: Option<String>) -> Toleration {
+        Toleration { key: k, value: v }
+    }
+
+    pub fn tolerates(&self, taint: &Taint) -> bool {
+        self.key == taint.key && (self.value.is_none() || self.value == taint.value)
+    }
+}
+
+/// Taints of `resource` with `effect` that none of `tolerations` tolerates.
+pub fn untolerated<'a>(resource: &'a impl Labelled, tolerations: &'a [Toleration], effect: Effect) -> impl Iterator<Item = &'a Taint> {
+    resource
+        .taints()
+        .iter()
+        .filter(move |t| t.effect == effect && !tolerations.iter().any(|tol| tol.tolerates(t)))
+}
+
+/// Resources of `resources` whose labels match `selector`.
+pub fn select<'a, T: Labelled>(resources: impl Iterator<Item = &'a T>, selector: &Selector) -> Vec<&'a T> {
+    resources.filter(|r| selector.matches(r.labels())).collect()
+}
+
+/// Resources of `collection` whose labels match `selector`, looked up in the
+/// `labels` index of the collection where the selector names labels it
+/// requires.
+pub fn select_from<'a, T: Named + Labelled + Clone + Debug>(collection: &'a Collection<T>, selector: &Selector) -> Vec<&'a T> {
+    match selector.candidates(&|label| collection.lookup_names("labels", label).into_iter().collect()) {
+        Some(names) => names
+            .into_iter()
+            .filter_map(|name| collection.get(name))
+            .filter(|r| selector.matches(r.labels()))
+            .collect(),
+        None => select(collection.iter(), selector),
+    }
+}
+
+/// Attributes of `r` in a `labels` index, each key and each `key=value`.
+pub fn label_attributes(r: &impl Labelled) -> Vec<Attribute> {
+    r.labels()
+        .iter()
+        .flat_map(|(k, v)| [Attribute::from(k.as_str()), Attribute::from(format!("{}={}", k, v))])
+        .collect()
+}
+
+/// Reasons a selector cannot be parsed, positions are byte offsets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectorError {
+    Unexpected { position: usize, found: String },
+    UnexpectedEnd,
+    UnterminatedString(usize),
+}
+
+impl Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+///
+/// Label selector
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+/// use libtheia::models::labels::Selector;
+///
+/// let selector: Selector = "tier in (gold, silver) && !customer-x-only && zone != lab".parse().unwrap();
+/// let labels = BTreeMap::from([("tier".to_string(), "gold".to_string())]);
+///
+/// assert!(selector.matches(&labels));
+/// assert_eq!(selector.to_string(), "tier in (gold, silver) && !customer-x-only && zone != lab");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    Eq(String, String),
+    Ne(String, String),
+    In(String, BTreeSet<String>),
+    NotIn(String, BTreeSet<String>),
+    Exists(String),
+    Not(Box<Selector>),
+    And(Vec<Selector>),
+    Or(Vec<Selector>),
+}
+
+impl Selector {
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        match self {
+            Selector::Eq(k, v) => labels.get(k) == Some(v),
+            Selector::Ne(k, v) => labels.get(k) != Some(v),
+            Selector::In(k, vs) => labels.get(k).is_some_and(|v| vs.contains(v)),
+            Selector::NotIn(k, vs) => labels.get(k).is_none_or(|v| !vs.contains(v)),
+            Selector::Exists(k) => labels.contains_key(k),
+            Selector::Not(s) => !s.matches(labels),
+            Selector::And(all) => all.iter().all(|s| s.matches(labels)),
+            Selector::Or(any) => any.iter().any(|s| s.matches(labels)),
+        }
+    }
+}
+
+impl Selector {
+    /// Names of the resources the selector can match at most, from a lookup
+    /// of `key` and `key=value` labels. `None` when it matches resources
+    /// without a given label, and every resource has to be checked.
+    fn candidates<'a>(&self, lookup: &dyn Fn(String) -> BTreeSet<&'a str>) -> Option<BTreeSet<&'a str>> {
+        match self {
+            Selector::Eq(k, v) => Some(lookup(format!("{}={}", k, v))),
+            Selector::In(k, vs) => Some(vs.iter().flat_map(|v| lookup(format!("{}={}", k, v))).collect()),
+            Selector::Exists(k) => Some(lookup(k.clone())),
+            Selector::And(all) => all
+                .iter()
+                .filter_map(|s| s.candidates(lookup))
+                .reduce(|left, right| left.intersection(&right).copied().collect()),
+            Selector::Or(any) => any
+                .iter()
+                .map(|s| s.candidates(lookup))
+                .try_fold(BTreeSet::new(), |mut all, names| {
+                    all.extend(names?);
+                    Some(all)
+                }),
+            Selector::Ne(..) | Selector::NotIn(..) | Selector::Not(_) => None,
+        }
+    }
+}
+
+impl Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn set(vs: &BTreeSet<String>) -> String {
+            vs.iter().map(|v| quote(v)).collect::<Vec<_>>().join(", ")
+        }
+
+        match self {
+            Selector::Eq(k, v) => write!(f, "{} = {}", quote(k), quote(v)),
+            Selector::Ne(k, v) => write!(f, "{} != {}", quote(k), quote(v)),
+            Selector::In(k, vs) => write!(f, "{} in ({})", quote(k), set(vs)),
+            Selector::NotIn(k, vs) => write!(f, "{} notin ({})", quote(k), set(vs)),
+            Selector::Exists(k) => write!(f, "{}", quote(k)),
+            Selector::Not(s) => match s.as_ref() {
+                Selector::Exists(_) => write!(f, "!{}", s),
+                _ => write!(f, "!({})", s),
+            },
+            Selector::And(all) => {
+                let parts: Vec<String> = all
+                    .iter()
+                    .map(|s| match s {
+                        Selector::Or(_) => format!("({})", s),
+                        _ => s.to_string(),
+                    })
+                    .collect();
+                write!(f, "{}", parts.join(" && "))
+            }
+            Selector::Or(any) => write!(f, "{}", any.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(" || ")),
+        }
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_./:".contains(c)
+}
+
+fn quote(s: &str) -> String {
+    if !s.is_empty() && s.chars().all(is_word) && s != "in" && s != "notin" {
+        s.to_string()
+    } else {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Eq,
+    Ne,
+    Not,
+    And,
+    Or,
+    Open,
+    Close,
+    Comma,
+}
+
+/// Deepest nesting of `!` and parentheses, deeper expressions are refused
+/// rather than parsed with unbounded recursion.
+const MAX_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    peeked: Option<(usize, Token)>,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Result<Option<(usize, Token)>, SelectorError> {
+        if let Some(t) = self.peeked.take() {
+            return Ok(Some(t));
+        }
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let Some((i, c)) = self.chars.next() else {
+            return Ok(None);
+        };
+        let token = match c {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '=' => {
+                self.chars.next_if(|(_, c)| *c == '=');
+                Token::Eq
+            }
+            '!' if self.chars.next_if(|(_, c)| *c == '=').is_some() => Token::Ne,
+            '!' => Token::Not,
+            '&' if self.chars.next_if(|(_, c)| *c == '&').is_some() => Token::And,
+            '|' if self.chars.next_if(|(_, c)| *c == '|').is_some() => Token::Or,
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match self.chars.next() {
+                            Some((_, c)) => word.push(c),
+                            None => return Err(SelectorError::UnterminatedString(i)),
+                        },
+                        Some((_, c)) => word.push(c),
+                        None => return Err(SelectorError::UnterminatedString(i)),
+                    }
+                }
+                // a quoted word is never an operator
+                return Ok(Some((i, Token::Word(word))));
+            }
+            c if is_word(c) => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = self.chars.next_if(|(_, c)| is_word(*c)) {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            c => {
+                return Err(SelectorError::Unexpected {
+                    position: i,
+                    found: c.to_string(),
+                })
+            }
+        };
+        Ok(Some((i, token)))
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token>, SelectorError> {
+        if self.peeked.is_none() {
+            self.peeked = self.next()?;
+        }
+        Ok(self.peeked.as_ref().map(|(_, t)| t))
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), SelectorError> {
+        match self.next()? {
+            Some((_, t)) if t == expected => Ok(()),
+            found => Err(unexpected(found)),
+        }
+    }
+
+    fn word(&mut self) -> Result<String, SelectorError> {
+        match self.next()? {
+            Some((_, Token::Word(w))) => Ok(w),
+            found => Err(unexpected(found)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Selector, SelectorError> {
+        let mut any = vec![self.and()?];
+        while self.peek()? == Some(&Token::Or) {
+            self.next()?;
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 { any.remove(0) } else { Selector::Or(any) })
+    }
+
+    fn and(&mut self) -> Result<Selector, SelectorError> {
+        let mut all = vec![self.unary()?];
+        while matches!(self.peek()?, Some(Token::And) | Some(Token::Comma)) {
+            self.next()?;
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 { all.remove(0) } else { Selector::And(all) })
+    }
+
+    fn unary(&mut self) -> Result<Selector, SelectorError> {
+        let nested = match self.peek()? {
+            Some(Token::Not) => {
+                self.nest()?;
+                Ok(Selector::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::Open) => {
+                self.nest()?;
+                let s = self.or()?;
+                self.expect(Token::Close)?;
+                Ok(s)
+            }
+            _ => return self.term(),
+        };
+        self.depth -= 1;
+        nested
+    }
+
+    /// Consume the token opening a nested expression.
+    fn nest(&mut self) -> Result<(), SelectorError> {
+        let found = self.next()?;
+        self.depth += 1;
+        match self.depth > MAX_DEPTH {
+            true => Err(unexpected(found)),
+            false => Ok(()),
+        }
+    }
+
+    fn term(&mut self) -> Result<Selector, SelectorError> {
+        let key = self.word()?;
+        match self.peek()? {
+            Some(Token::Eq) => {
+                self.next()?;
+                Ok(Selector::Eq(key, self.word()?))
+            }
+            Some(Token::Ne) => {
+                self.next()?;
+                Ok(Selector::Ne(key, self.word()?))
+            }
+            Some(Token::Word(w)) if w == "in" || w == "notin" => {
+                let negated = w == "notin";
+                self.next()?;
+                let values = self.set()?;
+                Ok(if negated { Selector::NotIn(key, values) } else { Selector::In(key, values) })
+            }
+            _ => Ok(Selector::Exists(key)),
+        }
+    }
+
+    fn set(&mut self) -> Result<BTreeSet<String>, SelectorError> {
+        self.expect(Token::Open)?;
+        let mut values = BTreeSet::from([self.word()?]);
+        loop {
+            match self.next()? {
+                Some((_, Token::Comma)) => values.insert(self.word()?),
+                Some((_, Token::Close)) => return Ok(values),
+                found => return Err(unexpected(found)),
+            };
+        }
+    }
+}
+
+fn unexpected(found: Option<(usize, Token)>) -> SelectorError {
+    match found {
+        None => SelectorError::UnexpectedEnd,
+        Some((position, token)) => SelectorError::Unexpected {
+            position,
+            found: match token {
+                Token::Word(w) => w,
+                Token::Eq => "=".to_string(),
+                Token::Ne => "!=".to_string(),
+                Token::Not => "!".to_string(),
+                Token::And => "&&".to_string(),
+                Token::Or => "||".to_string(),
+                Token::Open => "(".to_string(),
+                Token::Close => ")".to_string(),
+                Token::Comma => ",".to_string(),
+            },
+        },
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: s.char_indices().peekable(),
+            peeked: None,
+            depth: 0,
+        };
+        let selector = parser.or()?;
+        match parser.next()? {
+            None => Ok(selector),
+            found => Err(unexpected(found)),
+        }
+    }
+}
+
+impl Serialize for Selector {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Selector {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        String::deserialize(d)?.parse().map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,198 @@
+//! Lifecycle of compute and storage resources.
+//!
+//! A data centre keeps the lifecycle of each resource in its own
+//! `Collection`, apart from the resource, so a transition and a concurrent
+//! update of the spec or labels of the resource never conflict. Concurrent
+//! transitions of the same resource resolve to the one with the greatest
+//! clock on every replica, the other stays visible through
+//! `Collection::conflicts` until the next transition.
+
+use std::fmt::{self, Debug, Display};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::crdt::index::Attribute;
+use crate::models::collection::{CollectionError, Named};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum State {
+    Provisioning,
+    Available,
+    /// Held back for a pending claim, not offered to other claims.
+    Reserved,
+    /// Claims are moved elsewhere before maintenance or decommissioning.
+    Draining,
+    Maintenance,
+    Failed,
+    Decommissioned,
+}
+
+impl State {
+    /// States reachable from this one in a single transition.
+    pub fn successors(self) -> &'static [State] {
+        use State::*;
+        match self {
+            Provisioning => &[Available, Failed, Decommissioned],
+            Available => &[Reserved, Draining, Maintenance, Failed],
+            Reserved => &[Available, Draining, Failed],
+            Draining => &[Available, Maintenance, Failed, Decommissioned],
+            Maintenance => &[Available, Failed, Decommissioned],
+            Failed => &[Maintenance, Decommissioned],
+            Decommissioned => &[],
+        }
+    }
+
+    pub fn can_transition(self, to: State) -> bool {
+        self.successors().contains(&to)
+    }
+
+    /// Whether new claims may be placed on a resource in this state.
+    pub fn is_schedulable(self) -> bool {
+        self == State::Available
+    }
+}
+
+/// Reasons a transition is refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifecycleError {
+    Collection(CollectionError),
+    Invalid { from: State, to: State },
+    /// The resource still holds claims, drain it first.
+    Claimed(String),
+}
+
+impl From<CollectionError> for LifecycleError {
+    fn from(e: CollectionError) -> Self {
+        LifecycleError::Collection(e)
+    }
+}
+
+impl Display for LifecycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for LifecycleError {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+    pub from: State,
+    pub to: State,
+    pub reason: String,
+    pub at: DateTime<Utc>,
+}
+
+///
+/// Current state of a resource and the transitions that led to it
+///
+/// Resources start out `Available`, so inventories without a lifecycle keep
+/// being schedulable.
+///
+/// ```rust
+/// use chrono::Utc;
+/// use libtheia::models::lifecycle::{Lifecycle, LifecycleError, State};
+///
+/// let mut lifecycle = Lifecycle::new(State::Provisioning);
+/// lifecycle.transition(State::Available, "racked and burned in", Utc::now()).unwrap();
+///
+/// assert_eq!(lifecycle.state, State::Available);
+/// assert_eq!(lifecycle.history.len(), 1);
+/// assert_eq!(
+///     lifecycle.transition(State::Decommissioned, "end of life", Utc::now()),
+///     Err(LifecycleError::Invalid { from: State::Available, to: State::Decommissioned })
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct Lifecycle {
+    pub state: State,
+    #[serde(default)]
+    pub history: Vec<Transition>,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Lifecycle::new(State::Available)
+    }
+}
+
+impl Lifecycle {
+    /// Lifecycle instance
+    ///
+    /// * `s` - initial state.
+    pub fn new(s: State) -> Lifecycle {
+        Lifecycle {
+            state: s,
+            history: Vec::new(),
+        }
+    }
+
+    /// Move to `to` if the current state allows it, recording why and when.
+    ///
+    /// * `to` - next state.
+    /// * `reason` - why the transition is made.
+    /// * `at` - when the transition is made.
+    pub fn transition(&mut self, to: State, reason: &str, at: DateTime<Utc>) -> Result<(), LifecycleError> {
+        if !self.state.can_transition(to) {
+            return Err(LifecycleError::Invalid { from: self.state, to });
+        }
+        self.history.push(Transition {
+            from: self.state,
+            to,
+            reason: reason.to_string(),
+            at,
+        });
+        self.state = to;
+        Ok(())
+    }
+}
+
+///
+/// Lifecycle of the resource `name`, replicated on its own
+///
+/// Resources without one are `Available`.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct ResourceLifecycle {
+    pub name: String,
+    pub lifecycle: Lifecycle,
+}
+
+impl Named for ResourceLifecycle {
+    const INDEXES: &'static [&'static str] = &["schedulable"];
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self, index: &str) -> Vec<Attribute> {
+        match index {
+            "schedulable" => vec![self.lifecycle.state.is_schedulable().into()],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl ResourceLifecycle {
+    /// ResourceLifecycle instance
+    ///
+    /// * `n` - name of the resource.
+    /// * `l` - its lifecycle.
+    pub fn new(n: String, l: Lifecycle) -> ResourceLifecycle {
+        ResourceLifecycle { name: n, lifecycle: l }
+    }
+}
+
+///
+/// Claims moved off a draining resource
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Evacuation {
+    /// Claim, old and new resource.
+    pub moved: Vec<(String, String, String)>,
+    /// Claim and resource for which no eligible replacement was found, the
+    /// claim keeps the draining resource.
+    pub stranded: Vec<(String, String)>,
+}
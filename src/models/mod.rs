@@ -1,6 +1,20 @@
+pub mod capability;
+pub mod capacity;
+pub mod collection;
+pub mod cpu;
 pub mod data_centre;
-pub(crate) mod resource;
+pub mod gpu;
+pub mod hierarchy;
+pub mod resource;
 pub mod infrastructure;
+pub mod labels;
+pub mod lifecycle;
+pub mod pool;
+pub mod power;
+pub mod tenant;
+pub mod topology;
+pub mod units;
+pub mod wan;
 
 
 pub struct RedisInstance {
@@ -0,0 +1,161 @@
+//! Storage pools: redundancy schemes and per disk type tiers.
+//!
+//! Disks of a `Storage` are grouped into one tier per `DiskType`, each tier
+//! protected by a redundancy scheme. Effective capacity and IOPS of a tier
+//! follow from its disks and scheme:
+//! - RAID levels stripe over equally sized members, a larger disk only
+//!   contributes the size of the smallest one;
+//! - replication and erasure coding spread over the raw capacity of all disks.
+//!
+//! Write IOPS are divided by the write penalty, the number of disk writes a
+//! logical write costs (for erasure coding the cost of full-stripe writes).
+
+use std::fmt::{self, Debug, Display};
+
+use serde::{Deserialize, Serialize};
+use crate::models::data_centre::{Disk, DiskType};
+use crate::models::units::{Bytes, Iops};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[allow(unused)]
+pub enum RaidLevel {
+    Raid0,
+    Raid1,
+    Raid5,
+    Raid6,
+    Raid10,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+#[allow(unused)]
+pub enum Redundancy {
+    /// Disks are used as they are.
+    #[default]
+    None,
+    Replication { factor: u32 },
+    ErasureCoding { data: u32, parity: u32 },
+    Raid(RaidLevel),
+}
+
+impl Redundancy {
+    /// Fewest disks the scheme can be built from.
+    pub fn min_disks(&self) -> usize {
+        match self {
+            Redundancy::None => 1,
+            Redundancy::Replication { factor } => (*factor).max(1) as usize,
+            Redundancy::ErasureCoding { data, parity } => (data + parity).max(1) as usize,
+            Redundancy::Raid(RaidLevel::Raid0) => 1,
+            Redundancy::Raid(RaidLevel::Raid1) => 2,
+            Redundancy::Raid(RaidLevel::Raid5) => 3,
+            Redundancy::Raid(RaidLevel::Raid6) => 4,
+            Redundancy::Raid(RaidLevel::Raid10) => 4,
+        }
+    }
+
+    /// Disks that may fail without losing data.
+    pub fn fault_tolerance(&self, disks: usize) -> usize {
+        match self {
+            Redundancy::None | Redundancy::Raid(RaidLevel::Raid0) => 0,
+            Redundancy::Replication { factor } => factor.saturating_sub(1) as usize,
+            Redundancy::ErasureCoding { parity, .. } => *parity as usize,
+            Redundancy::Raid(RaidLevel::Raid1) => disks.saturating_sub(1),
+            Redundancy::Raid(RaidLevel::Raid5) => 1,
+            Redundancy::Raid(RaidLevel::Raid6) => 2,
+            Redundancy::Raid(RaidLevel::Raid10) => 1,
+        }
+    }
+
+    /// Usable capacity of `disks`.
+    fn effective(&self, disks: &[&Disk]) -> Bytes {
+        let n = disks.len() as u64;
+        let raw: Bytes = disks.iter().map(|d| d.capacity).sum();
+        let smallest = disks.iter().map(|d| d.capacity).min().unwrap_or_default();
+        match self {
+            Redundancy::None => raw,
+            Redundancy::Replication { factor } => raw.share(1, *factor as u64),
+            Redundancy::ErasureCoding { data, parity } => raw.share(*data as u64, (data + parity) as u64),
+            Redundancy::Raid(RaidLevel::Raid0) => smallest * n,
+            Redundancy::Raid(RaidLevel::Raid1) => smallest,
+            Redundancy::Raid(RaidLevel::Raid5) => smallest * (n - 1),
+            Redundancy::Raid(RaidLevel::Raid6) => smallest * (n - 2),
+            Redundancy::Raid(RaidLevel::Raid10) => smallest * (n / 2),
+        }
+    }
+
+    /// Write IOPS of `raw` IOPS over `disks` disks.
+    fn write_iops(&self, raw: Iops, disks: usize) -> Iops {
+        match self {
+            Redundancy::None | Redundancy::Raid(RaidLevel::Raid0) => raw,
+            Redundancy::Replication { factor } => raw.share(1, *factor as u64),
+            Redundancy::ErasureCoding { data, parity } => raw.share(*data as u64, (data + parity) as u64),
+            Redundancy::Raid(RaidLevel::Raid1) => raw.share(1, disks as u64),
+            Redundancy::Raid(RaidLevel::Raid5) => raw.share(1, 4),
+            Redundancy::Raid(RaidLevel::Raid6) => raw.share(1, 6),
+            Redundancy::Raid(RaidLevel::Raid10) => raw.share(1, 2),
+        }
+    }
+}
+
+/// Reasons a pool is inconsistent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolError {
+    TooFewDisks { disk_type: DiskType, required: usize, found: usize },
+    /// RAID 10 mirrors pairs, it needs an even number of disks.
+    UnpairedDisks { disk_type: DiskType, found: usize },
+    InvalidRedundancy(Redundancy),
+    OverDeclared { declared: Bytes, effective: Bytes },
+}
+
+impl Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for PoolError {}
+
+///
+/// Disks of one type in a pool and what they deliver
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Tier {
+    pub disk_type: DiskType,
+    pub redundancy: Redundancy,
+    pub disks: usize,
+    pub raw: Bytes,
+    pub effective: Bytes,
+    /// `None` when a disk of the tier has no IOPS rating.
+    pub read_iops: Option<Iops>,
+    pub write_iops: Option<Iops>,
+}
+
+impl Tier {
+    /// Tier of `disks` (all of `disk_type`) protected by `redundancy`.
+    pub fn new(disk_type: DiskType, redundancy: Redundancy, disks: &[&Disk]) -> Result<Tier, PoolError> {
+        let valid = match redundancy {
+            Redundancy::Replication { factor } => factor > 0,
+            Redundancy::ErasureCoding { data, .. } => data > 0,
+            _ => true,
+        };
+        if !valid {
+            return Err(PoolError::InvalidRedundancy(redundancy));
+        }
+        let (required, found) = (redundancy.min_disks(), disks.len());
+        if found < required {
+            return Err(PoolError::TooFewDisks { disk_type, required, found });
+        }
+        if redundancy == Redundancy::Raid(RaidLevel::Raid10) && found % 2 == 1 {
+            return Err(PoolError::UnpairedDisks { disk_type, found });
+        }
+        let raw_iops: Option<Iops> = disks.iter().map(|d| d.iops).sum();
+        Ok(Tier {
+            disk_type,
+            redundancy,
+            disks: found,
+            raw: disks.iter().map(|d| d.capacity).sum(),
+            effective: redundancy.effective(disks),
+            read_iops: raw_iops,
+            write_iops: raw_iops.map(|iops| redundancy.write_iops(iops, found)),
+        })
+    }
+}
@@ -0,0 +1,69 @@
+//! Power draw of resources and power and cooling budgets.
+//!
+//! A resource draws its `nominal` power while idle and its `peak` power once
+//! claimed, so the expected draw of a rack or data centre is the peak of its
+//! claimed resources plus the nominal of the others. Everything drawn ends up
+//! as heat, the same draw counts against the cooling budget.
+
+use serde::{Deserialize, Serialize};
+use crate::models::units::Watts;
+
+///
+/// Power draw of a resource
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct Power {
+    #[serde(default)]
+    pub nominal: Watts,
+    #[serde(default)]
+    pub peak: Watts,
+}
+
+impl Power {
+    /// Power instance
+    ///
+    /// * `n` - nominal (idle) draw.
+    /// * `p` - peak draw.
+    pub fn new(n: Watts, p: Watts) -> Power {
+        Power { nominal: n, peak: p }
+    }
+
+    /// Draw of the resource, depending on whether it is claimed.
+    pub fn draw(&self, claimed: bool) -> Watts {
+        if claimed {
+            self.peak
+        } else {
+            self.nominal
+        }
+    }
+}
+
+///
+/// Power and cooling budget of a rack or data centre, `None` is unlimited
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct Budget {
+    #[serde(default)]
+    pub power: Option<Watts>,
+    #[serde(default)]
+    pub cooling: Option<Watts>,
+}
+
+impl Budget {
+    /// Budget instance
+    ///
+    /// * `p` - power that can be delivered.
+    /// * `c` - heat that can be removed.
+    pub fn new(p: Option<Watts>, c: Option<Watts>) -> Budget {
+        Budget { power: p, cooling: c }
+    }
+
+    /// Whether the budget covers drawing `draw`.
+    pub fn allows(&self, draw: Watts) -> bool {
+        self.power.is_none_or(|p| draw <= p) && self.cooling.is_none_or(|c| draw <= c)
+    }
+}
@@ -1,9 +1,20 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug, Display};
+
+use crate::crdt::index::Attribute;
+use crate::models::capability::{Capable, Requirement};
+use crate::models::collection::{CollectionError, Named};
 use crate::models::data_centre::{Compute, Storage, InterConnect};
+use crate::models::gpu::GPUClaim;
+use crate::models::labels::{self, Effect, Labelled, Selector, Toleration};
+use crate::models::units::{BitsPerSecond, Bytes};
 use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
 
 ///
 /// Claim resources from a logical infrastructure
 ///
+#[allow(unused)]
 pub struct Claim {
     pub compute: Vec<Compute>,
     pub storage: Vec<Storage>,
@@ -15,6 +26,7 @@ pub struct Claim {
 ///
 /// Possible claim response types
 ///
+#[allow(unused)]
 pub enum ResponseType {
     Success,
     ResourceFailure,
@@ -24,7 +36,206 @@ pub enum ResponseType {
 ///
 /// Response to a claim request
 ///
+#[allow(unused)]
 pub struct ClaimResponse {
     pub result: ResponseType,
     pub message: String,
 }
+
+/// Tenant that claims without a tenant of their own are charged to.
+pub const UNOWNED: &str = "unowned";
+
+///
+/// Resources of a data centre assigned to a claim, by name
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[allow(unused)]
+pub struct Allocation {
+    pub name: String,
+    /// Tenant the claim is charged to, `UNOWNED` without one.
+    #[serde(default)]
+    pub tenant: Option<String>,
+    /// Project of the tenant the claim counts towards.
+    #[serde(default)]
+    pub project: Option<String>,
+    /// Compute resources are claimed whole.
+    #[serde(default)]
+    pub compute: BTreeSet<String>,
+    /// Normalized compute units asked for, the planner adds compute
+    /// resources until their units cover it.
+    #[serde(default)]
+    pub compute_units: Option<f64>,
+    /// GPU devices and partitions per compute resource not claimed whole.
+    #[serde(default)]
+    pub gpus: BTreeMap<String, GPUClaim>,
+    /// Claimed capacity per storage resource.
+    #[serde(default)]
+    pub storage: BTreeMap<String, Bytes>,
+    /// Claimed bandwidth per data centre interconnect.
+    #[serde(default)]
+    pub interconnect: BTreeMap<String, BitsPerSecond>,
+    /// Labels every claimed resource has to match.
+    #[serde(default)]
+    pub selector: Option<Selector>,
+    /// Capabilities every claimed compute resource and interconnect needs.
+    #[serde(default)]
+    pub requires: Option<Requirement>,
+    #[serde(default)]
+    pub tolerations: Vec<Toleration>,
+    pub from: NaiveDate,
+    pub till: Option<NaiveDate>,
+}
+
+impl Named for Allocation {
+    const INDEXES: &'static [&'static str] = &["compute", "gpus", "storage", "interconnect"];
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Names of the resources the claim holds of a kind.
+    fn attributes(&self, index: &str) -> Vec<Attribute> {
+        let names: Vec<&String> = match index {
+            "compute" => self.compute.iter().collect(),
+            "gpus" => self.gpus.keys().collect(),
+            "storage" => self.storage.keys().collect(),
+            "interconnect" => self.interconnect.keys().collect(),
+            _ => Vec::new(),
+        };
+        names.into_iter().map(|n| Attribute::from(n.as_str())).collect()
+    }
+}
+
+impl Allocation {
+    /// Allocation instance
+    ///
+    /// * `n` - claim name.
+    /// * `f` - start of the claim.
+    /// * `t` - optional end of the claim.
+    pub fn new(n: String, f: NaiveDate, t: Option<NaiveDate>) -> Allocation {
+        Allocation {
+            name: n,
+            tenant: None,
+            project: None,
+            compute: BTreeSet::new(),
+            compute_units: None,
+            gpus: BTreeMap::new(),
+            storage: BTreeMap::new(),
+            interconnect: BTreeMap::new(),
+            selector: None,
+            requires: None,
+            tolerations: Vec::new(),
+            from: f,
+            till: t,
+        }
+    }
+
+    pub fn set_tenant(&mut self, t: &str) {
+        self.tenant = Some(t.to_string());
+    }
+
+    /// Tenant the claim is charged to.
+    pub fn tenant(&self) -> &str {
+        self.tenant.as_deref().unwrap_or(UNOWNED)
+    }
+
+    pub fn set_project(&mut self, p: &str) {
+        self.project = Some(p.to_string());
+    }
+
+    pub fn add_compute(&mut self, c: &str) {
+        self.compute.insert(c.to_string());
+    }
+
+    /// Ask for `u` normalized compute units rather than named resources.
+    pub fn set_compute_units(&mut self, u: f64) {
+        self.compute_units = Some(u);
+    }
+
+    /// Claim whole GPU devices of a compute resource.
+    pub fn add_gpus(&mut self, c: &str, devices: impl IntoIterator<Item = usize>) {
+        self.gpus.entry(c.to_string()).or_default().devices.extend(devices);
+    }
+
+    /// Claim a partition of a GPU device of a compute resource.
+    pub fn add_partition(&mut self, c: &str, device: usize, profile: &str) {
+        let claim = self.gpus.entry(c.to_string()).or_default();
+        claim.partitions.entry(device).or_default().push(profile.to_string());
+    }
+
+    pub fn add_storage(&mut self, s: &str, size: Bytes) {
+        *self.storage.entry(s.to_string()).or_default() += size;
+    }
+
+    pub fn add_interconnect(&mut self, i: &str, bandwidth: BitsPerSecond) {
+        *self.interconnect.entry(i.to_string()).or_default() += bandwidth;
+    }
+
+    pub fn set_selector(&mut self, s: Selector) {
+        self.selector = Some(s);
+    }
+
+    pub fn set_requirement(&mut self, r: Requirement) {
+        self.requires = Some(r);
+    }
+
+    pub fn add_toleration(&mut self, t: Toleration) {
+        self.tolerations.push(t);
+    }
+
+    /// Whether the labels of `r` match the selector, if any.
+    pub fn selects(&self, r: &impl Labelled) -> bool {
+        self.selector.as_ref().is_none_or(|s| s.matches(r.labels()))
+    }
+
+    /// Whether the capabilities of `r` meet the requirement, if any.
+    pub fn satisfied_by(&self, r: &impl Capable) -> bool {
+        self.requires.as_ref().is_none_or(|req| req.matches(&r.capabilities()))
+    }
+
+    /// Whether every taint of `r` with `effect` is tolerated.
+    pub fn tolerates(&self, r: &impl Labelled, effect: Effect) -> bool {
+        labels::untolerated(r, &self.tolerations, effect).next().is_none()
+    }
+}
+
+/// Reasons an allocation is refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClaimError {
+    Collection(CollectionError),
+    UnknownResource(String),
+    AlreadyClaimed(String),
+    InsufficientCapacity(String),
+    /// The compute units of the claim are not a finite amount above zero.
+    InvalidUnits(String),
+    /// The resource is not in a schedulable lifecycle state.
+    Ineligible(String),
+    /// The resource does not match the selector of the claim.
+    Unselected(String),
+    /// The resource, or its data centre, has a taint the claim does not tolerate.
+    Tainted(String),
+    /// The resource lacks capabilities the claim requires.
+    Incapable(String),
+    /// The claim would exceed the power or cooling budget of the data centre
+    /// or of the rack at this location.
+    OverBudget(String),
+    /// The tenant, or the project of the tenant, does not exist, or is not
+    /// known where the claim is made.
+    UnknownOwner(String),
+    /// The claim would exceed a quota of its tenant or project.
+    OverQuota(String),
+}
+
+impl From<CollectionError> for ClaimError {
+    fn from(e: CollectionError) -> Self {
+        ClaimError::Collection(e)
+    }
+}
+
+impl Display for ClaimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for ClaimError {}
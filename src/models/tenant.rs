@@ -0,0 +1,383 @@
+//! Tenants, their projects and quotas.
+//!
+//! A `Tenant` owns claims, groups them into projects and lists the contacts
+//! responsible for them. Tenants replicate as a collection of the logical
+//! infrastructure, a claim names its tenant and optionally one of its
+//! projects.
+//!
+//! Quotas limit what a tenant or a project holds per resource class, over all
+//! data centres and per data centre. Claims made through the infrastructure
+//! are refused when they would exceed any quota of their tenant or project,
+//! and claims of tenants that are not registered are refused. Claims moved
+//! off a draining resource through the infrastructure, or onto a resource
+//! renamed by reconciliation, are admitted again. A data centre on its own
+//! refuses claims of a tenant. Claims without a tenant are unowned and not
+//! limited, their tenant name `UNOWNED` cannot be registered.
+//!
+//! ```rust
+//! use chrono::NaiveDate;
+//! use libtheia::error::TheiaError;
+//! use libtheia::models::data_centre::{Compute, DataCentre};
+//! use libtheia::models::infrastructure::LogicalInfrastructure;
+//! use libtheia::models::resource::{Allocation, ClaimError};
+//! use libtheia::models::tenant::{Quota, Tenant};
+//! use libtheia::models::units::{Bytes, Hertz};
+//!
+//! let mut dc = DataCentre::new("dc1".to_string(), 1);
+//! for n in ["n1", "n2"] {
+//!     dc.add_compute(Compute::new(n.to_string(), 32, Hertz::ghz(2), Bytes::gib(256))).unwrap();
+//! }
+//! let mut infra = LogicalInfrastructure::new(1);
+//! infra.add_data_centre(dc).unwrap();
+//!
+//! let mut tenant = Tenant::new("acme".to_string());
+//! let mut quota = Quota::new();
+//! quota.set_cores(48);
+//! tenant.quotas.set_total(quota);
+//! infra.add_tenant(tenant).unwrap();
+//!
+//! let claim = |name: &str, node: &str| {
+//!     let mut a = Allocation::new(name.to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), None);
+//!     a.set_tenant("acme");
+//!     a.add_compute(node);
+//!     a
+//! };
+//! infra.add_claim("dc1", claim("web", "n1")).unwrap();
+//! assert_eq!(
+//!     infra.add_claim("dc1", claim("batch", "n2")),
+//!     Err(TheiaError::Claim(ClaimError::OverQuota("acme: cores".to_string())))
+//! );
+//! assert_eq!(infra.usage("acme", None, None).cores, 32);
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+use crate::models::collection::Named;
+use crate::models::data_centre::DataCentre;
+use crate::models::resource::Allocation;
+use crate::models::units::{BitsPerSecond, Bytes, Saturating};
+
+///
+/// Resource classes quotas limit
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceClass {
+    Cores,
+    Ram,
+    Gpus,
+    Storage,
+    Bandwidth,
+}
+
+impl Display for ResourceClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let class = match self {
+            ResourceClass::Cores => "cores",
+            ResourceClass::Ram => "ram",
+            ResourceClass::Gpus => "gpus",
+            ResourceClass::Storage => "storage",
+            ResourceClass::Bandwidth => "bandwidth",
+        };
+        write!(f, "{}", class)
+    }
+}
+
+///
+/// Resources held by claims
+///
+/// Compute is held whole, with its RAM and GPUs. A GPU device counts once
+/// however many of its partitions are held.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct Usage {
+    pub cores: u64,
+    pub ram: Bytes,
+    pub gpus: u64,
+    pub storage: Bytes,
+    pub bandwidth: BitsPerSecond,
+}
+
+impl Usage {
+    pub fn new() -> Usage {
+        Usage::default()
+    }
+
+    /// Resources claim `a` holds in `dc`.
+    pub fn of(dc: &DataCentre, a: &Allocation) -> Usage {
+        let mut usage = Usage::new();
+        for c in a.compute.iter().filter_map(|c| dc.get_compute(c)) {
+            usage.cores = usage.cores.saturating_add(c.cores.max(0) as u64);
+            usage.ram += c.ram;
+            usage.gpus = usage.gpus.saturating_add(c.gpu_count() as u64);
+        }
+        for (name, g) in a.gpus.iter() {
+            if !a.compute.contains(name) {
+                let devices = g.devices.len() + g.partitions.keys().filter(|i| !g.devices.contains(i)).count();
+                usage.gpus = usage.gpus.saturating_add(devices as u64);
+            }
+        }
+        usage.storage = a.storage.values().sum();
+        usage.bandwidth = a.interconnect.values().sum();
+        usage
+    }
+
+    pub fn merge(&mut self, other: &Usage) {
+        self.cores = self.cores.saturating_add(other.cores);
+        self.ram += other.ram;
+        self.gpus = self.gpus.saturating_add(other.gpus);
+        self.storage += other.storage;
+        self.bandwidth += other.bandwidth;
+    }
+
+    /// What this holds beyond `other`, per resource class.
+    pub fn beyond(&self, other: &Usage) -> Usage {
+        Usage {
+            cores: self.cores.saturating_sub(other.cores),
+            ram: self.ram.saturating_sub(other.ram),
+            gpus: self.gpus.saturating_sub(other.gpus),
+            storage: self.storage.saturating_sub(other.storage),
+            bandwidth: self.bandwidth.saturating_sub(other.bandwidth),
+        }
+    }
+}
+
+///
+/// Limits per resource class, classes without a limit are not limited
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct Quota {
+    #[serde(default)]
+    pub cores: Option<u64>,
+    #[serde(default)]
+    pub ram: Option<Bytes>,
+    #[serde(default)]
+    pub gpus: Option<u64>,
+    #[serde(default)]
+    pub storage: Option<Bytes>,
+    #[serde(default)]
+    pub bandwidth: Option<BitsPerSecond>,
+}
+
+impl Quota {
+    pub fn new() -> Quota {
+        Quota::default()
+    }
+
+    pub fn set_cores(&mut self, n: u64) {
+        self.cores = Some(n);
+    }
+
+    pub fn set_ram(&mut self, b: Bytes) {
+        self.ram = Some(b);
+    }
+
+    pub fn set_gpus(&mut self, n: u64) {
+        self.gpus = Some(n);
+    }
+
+    pub fn set_storage(&mut self, b: Bytes) {
+        self.storage = Some(b);
+    }
+
+    pub fn set_bandwidth(&mut self, b: BitsPerSecond) {
+        self.bandwidth = Some(b);
+    }
+
+    /// Classes in which `usage` goes beyond the limit.
+    pub fn exceeded(&self, usage: &Usage) -> Vec<ResourceClass> {
+        let checks = [
+            (ResourceClass::Cores, self.cores.is_some_and(|q| usage.cores > q)),
+            (ResourceClass::Ram, self.ram.is_some_and(|q| usage.ram > q)),
+            (ResourceClass::Gpus, self.gpus.is_some_and(|q| usage.gpus > q)),
+            (ResourceClass::Storage, self.storage.is_some_and(|q| usage.storage > q)),
+            (ResourceClass::Bandwidth, self.bandwidth.is_some_and(|q| usage.bandwidth > q)),
+        ];
+        checks.into_iter().filter(|(_, over)| *over).map(|(class, _)| class).collect()
+    }
+
+    /// What is left of every limit after `usage`, nothing of a limit that is
+    /// exceeded.
+    pub fn remaining(&self, usage: &Usage) -> Quota {
+        Quota {
+            cores: self.cores.map(|q| q.saturating_sub(usage.cores)),
+            ram: self.ram.map(|q| q.checked_sub(usage.ram).unwrap_or_default()),
+            gpus: self.gpus.map(|q| q.saturating_sub(usage.gpus)),
+            storage: self.storage.map(|q| q.checked_sub(usage.storage).unwrap_or_default()),
+            bandwidth: self.bandwidth.map(|q| q.checked_sub(usage.bandwidth).unwrap_or_default()),
+        }
+    }
+}
+
+///
+/// Quotas over all data centres and per data centre
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct Quotas {
+    #[serde(default)]
+    pub total: Quota,
+    #[serde(default)]
+    pub data_centres: BTreeMap<String, Quota>,
+}
+
+impl Quotas {
+    pub fn new() -> Quotas {
+        Quotas::default()
+    }
+
+    pub fn set_total(&mut self, q: Quota) {
+        self.total = q;
+    }
+
+    /// * `dc` - data centre the quota applies in.
+    /// * `q` - quota.
+    pub fn set_data_centre(&mut self, dc: &str, q: Quota) {
+        self.data_centres.insert(dc.to_string(), q);
+    }
+}
+
+///
+/// Person or team responsible for a tenant
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[allow(unused)]
+pub struct Contact {
+    pub name: String,
+    pub email: String,
+    /// Role towards the tenant, `owner`, `billing` or `security` for example.
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+impl Contact {
+    /// Contact instance
+    ///
+    /// * `n` - name.
+    /// * `e` - email address.
+    pub fn new(n: String, e: String) -> Contact {
+        Contact {
+            name: n,
+            email: e,
+            role: None,
+        }
+    }
+
+    pub fn set_role(&mut self, r: &str) {
+        self.role = Some(r.to_string());
+    }
+}
+
+///
+/// Project of a tenant
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[allow(unused)]
+pub struct Project {
+    pub name: String,
+    #[serde(default)]
+    pub quotas: Quotas,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Project {
+    /// Project instance
+    ///
+    /// * `n` - project name, unique within its tenant.
+    pub fn new(n: String) -> Project {
+        Project {
+            name: n,
+            quotas: Quotas::new(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn set_metadata(&mut self, k: &str, v: &str) {
+        self.metadata.insert(k.to_string(), v.to_string());
+    }
+}
+
+///
+/// Tenant claiming resources
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[allow(unused)]
+pub struct Tenant {
+    pub name: String,
+    #[serde(default)]
+    pub contacts: Vec<Contact>,
+    #[serde(default)]
+    pub projects: BTreeMap<String, Project>,
+    /// Quotas of the tenant, over all of its projects.
+    #[serde(default)]
+    pub quotas: Quotas,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Tenant {
+    /// Tenant instance
+    ///
+    /// * `n` - tenant name.
+    pub fn new(n: String) -> Tenant {
+        Tenant {
+            name: n,
+            contacts: Vec::new(),
+            projects: BTreeMap::new(),
+            quotas: Quotas::new(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_contact(&mut self, c: Contact) {
+        self.contacts.push(c);
+    }
+
+    /// Add a project, replacing the one with the same name.
+    pub fn add_project(&mut self, p: Project) {
+        self.projects.insert(p.name.clone(), p);
+    }
+
+    pub fn get_project(&self, name: &str) -> Option<&Project> {
+        self.projects.get(name)
+    }
+
+    pub fn set_metadata(&mut self, k: &str, v: &str) {
+        self.metadata.insert(k.to_string(), v.to_string());
+    }
+}
+
+impl Named for Tenant {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+///
+/// A quota with what its tenant or project holds against it
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[allow(unused)]
+pub struct QuotaUsage {
+    /// `tenant` or `tenant/project`.
+    pub owner: String,
+    /// Data centre the quota applies in, all of them without one.
+    pub data_centre: Option<String>,
+    pub quota: Quota,
+    pub used: Usage,
+}
+
+impl QuotaUsage {
+    pub fn exceeded(&self) -> Vec<ResourceClass> {
+        self.quota.exceeded(&self.used)
+    }
+
+    pub fn remaining(&self) -> Quota {
+        self.quota.remaining(&self.used)
+    }
+}
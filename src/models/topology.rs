@@ -0,0 +1,310 @@
+//! Network topology graph built from `InterConnect` links.
+//!
+//! Nodes are compute, storage, switches and a gateway per data centre. A link
+//! declared on a resource or switch connects it to the node its `peer` names
+//! within the same data centre (switches first, then compute, then storage),
+//! a peer naming the data centre itself is an uplink to its gateway. The
+//! site links of an infrastructure, and the `interconnects` of a data centre
+//! whose peer names another data centre, connect the gateways of the data
+//! centres. Links are undirected, a link declared on both ends counts once.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+use crate::models::data_centre::{DataCentre, InterConnect};
+use crate::models::infrastructure::LogicalInfrastructure;
+use crate::models::units::BitsPerSecond;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum NodeKind {
+    DataCentre,
+    Switch,
+    Compute,
+    Storage,
+}
+
+/// A node of the topology, unique per data centre, kind and name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Node {
+    pub data_centre: String,
+    pub kind: NodeKind,
+    pub name: String,
+}
+
+impl Node {
+    pub fn data_centre(dc: &str) -> Node {
+        Node::new(dc, NodeKind::DataCentre, dc)
+    }
+
+    pub fn switch(dc: &str, name: &str) -> Node {
+        Node::new(dc, NodeKind::Switch, name)
+    }
+
+    pub fn compute(dc: &str, name: &str) -> Node {
+        Node::new(dc, NodeKind::Compute, name)
+    }
+
+    pub fn storage(dc: &str, name: &str) -> Node {
+        Node::new(dc, NodeKind::Storage, name)
+    }
+
+    fn new(dc: &str, kind: NodeKind, name: &str) -> Node {
+        Node {
+            data_centre: dc.to_string(),
+            kind,
+            name: name.to_string(),
+        }
+    }
+}
+
+/// An undirected link, parallel declarations keep the fastest speed and the
+/// lowest latency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edge {
+    pub speed: BitsPerSecond,
+    pub latency_us: Option<i32>,
+}
+
+impl Edge {
+    fn join(&mut self, other: Edge) {
+        self.speed = self.speed.max(other.speed);
+        self.latency_us = match (self.latency_us, other.latency_us) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+///
+/// Network topology of one or more data centres
+///
+/// ```rust
+/// use libtheia::models::data_centre::{Compute, DataCentre, InterConnect, Switch};
+/// use libtheia::models::topology::{Node, Topology};
+/// use libtheia::models::units::{BitsPerSecond, Bytes, Hertz};
+///
+/// let mut dc = DataCentre::new("dc1".to_string(), 1);
+/// let mut tor = Switch::new("tor1".to_string());
+/// let mut uplink = InterConnect::new("uplink".to_string(), BitsPerSecond::gbps(40), false);
+/// uplink.set_peer("dc1".to_string());
+/// tor.add_link(uplink);
+/// dc.add_switch(tor).unwrap();
+/// for name in ["n1", "n2"] {
+///     let mut c = Compute::new(name.to_string(), 1, Hertz::ghz(1), Bytes::gib(1));
+///     let mut nic = InterConnect::new("eth0".to_string(), BitsPerSecond::gbps(25), false);
+///     nic.set_peer("tor1".to_string());
+///     c.add_link(nic);
+///     dc.add_compute(c).unwrap();
+/// }
+///
+/// let topology = Topology::from_data_centre(&dc);
+/// let (n1, n2) = (Node::compute("dc1", "n1"), Node::compute("dc1", "n2"));
+/// assert_eq!(topology.hops(&n1, &n2), Some(2));
+/// assert_eq!(topology.bottleneck(&n1, &n2), Some(BitsPerSecond::gbps(25)));
+/// assert!(!topology.supports(&n1, &n2, BitsPerSecond::gbps(100)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Topology {
+    edges: BTreeMap<Node, BTreeMap<Node, Edge>>,
+    dangling: Vec<(Node, String)>,
+}
+
+impl Topology {
+    pub fn new() -> Topology {
+        Topology::default()
+    }
+
+    pub fn from_data_centre(dc: &DataCentre) -> Topology {
+        let mut topology = Topology::new();
+        topology.add_data_centre(dc, &BTreeSet::new());
+        topology
+    }
+
+    pub fn from_infrastructure(infrastructure: &LogicalInfrastructure) -> Topology {
+        let names: BTreeSet<&str> = infrastructure.data_centres.iter().map(|dc| dc.name.as_str()).collect();
+        let mut topology = Topology::new();
+        for dc in infrastructure.data_centres.iter() {
+            topology.add_data_centre(dc, &names);
+        }
+        for l in infrastructure.site_links.iter().filter(|l| names.contains(l.a.as_str()) && names.contains(l.b.as_str())) {
+            let edge = Edge {
+                speed: l.bandwidth,
+                latency_us: infrastructure.site_link_latency_us(l),
+            };
+            topology.join(Node::data_centre(&l.a), Node::data_centre(&l.b), edge);
+        }
+        topology
+    }
+
+    fn add_data_centre(&mut self, dc: &DataCentre, data_centres: &BTreeSet<&str>) {
+        let gateway = Node::data_centre(&dc.name);
+        self.add_node(gateway.clone());
+
+        let mut declared: Vec<(Node, &InterConnect)> = Vec::new();
+        for switch in dc.switches.iter() {
+            let node = Node::switch(&dc.name, &switch.name);
+            self.add_node(node.clone());
+            declared.extend(switch.links.iter().map(|l| (node.clone(), l)));
+        }
+        for compute in dc.compute.iter() {
+            let node = Node::compute(&dc.name, &compute.name);
+            self.add_node(node.clone());
+            declared.extend(compute.links.iter().map(|l| (node.clone(), l)));
+        }
+        for storage in dc.storage.iter() {
+            let node = Node::storage(&dc.name, &storage.name);
+            self.add_node(node.clone());
+            declared.extend(storage.links.iter().map(|l| (node.clone(), l)));
+        }
+        for link in dc.interconnects.iter() {
+            match link.peer.as_deref() {
+                Some(peer) if peer != dc.name && data_centres.contains(peer) => {
+                    self.connect(gateway.clone(), Node::data_centre(peer), link)
+                }
+                _ => declared.push((gateway.clone(), link)),
+            }
+        }
+
+        for (node, link) in declared {
+            match link.peer.as_deref().and_then(|peer| resolve(dc, peer)) {
+                Some(peer) => self.connect(node, peer, link),
+                None => self.dangling.push((node, link.name.clone())),
+            }
+        }
+    }
+
+    pub fn add_node(&mut self, node: Node) {
+        self.edges.entry(node).or_default();
+    }
+
+    /// Connect two nodes with `link`, adding them if needed.
+    pub fn connect(&mut self, a: Node, b: Node, link: &InterConnect) {
+        let edge = Edge {
+            speed: link.speed,
+            latency_us: link.latency_us,
+        };
+        self.join(a, b, edge);
+    }
+
+    fn join(&mut self, a: Node, b: Node, edge: Edge) {
+        if a == b {
+            return;
+        }
+        for (from, to) in [(a.clone(), b.clone()), (b, a)] {
+            self.edges
+                .entry(from)
+                .or_default()
+                .entry(to)
+                .and_modify(|e| e.join(edge))
+                .or_insert(edge);
+        }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.edges.keys()
+    }
+
+    pub fn neighbours(&self, node: &Node) -> impl Iterator<Item = (&Node, &Edge)> {
+        self.edges.get(node).into_iter().flat_map(|e| e.iter())
+    }
+
+    /// Links whose peer is missing or names nothing known, with the node that
+    /// declared them.
+    pub fn dangling(&self) -> &[(Node, String)] {
+        &self.dangling
+    }
+
+    /// Fewest links between two nodes, `None` when they are not connected.
+    pub fn hops(&self, from: &Node, to: &Node) -> Option<usize> {
+        if !self.edges.contains_key(from) {
+            return None;
+        }
+        let mut seen = BTreeSet::from([from]);
+        let mut queue = VecDeque::from([(from, 0)]);
+        while let Some((node, hops)) = queue.pop_front() {
+            if node == to {
+                return Some(hops);
+            }
+            for (next, _) in self.neighbours(node) {
+                if seen.insert(next) {
+                    queue.push_back((next, hops + 1));
+                }
+            }
+        }
+        None
+    }
+
+    /// Highest speed a single path between two distinct nodes can carry (the
+    /// widest path), `None` when they are not connected.
+    pub fn bottleneck(&self, from: &Node, to: &Node) -> Option<BitsPerSecond> {
+        if from == to || !self.edges.contains_key(from) {
+            return None;
+        }
+        let widest = BitsPerSecond(u64::MAX);
+        let mut best: BTreeMap<&Node, BitsPerSecond> = BTreeMap::from([(from, widest)]);
+        let mut heap = BinaryHeap::from([(widest, from)]);
+        while let Some((width, node)) = heap.pop() {
+            if node == to {
+                return Some(width);
+            }
+            if best.get(node).is_some_and(|b| *b > width) {
+                continue;
+            }
+            for (next, edge) in self.neighbours(node) {
+                let w = width.min(edge.speed);
+                if best.get(next).is_none_or(|b| w > *b) {
+                    best.insert(next, w);
+                    heap.push((w, next));
+                }
+            }
+        }
+        None
+    }
+
+    /// Lowest total latency in microseconds between two nodes, links without
+    /// a known latency count as zero.
+    pub fn latency_us(&self, from: &Node, to: &Node) -> Option<i32> {
+        if !self.edges.contains_key(from) {
+            return None;
+        }
+        let mut best: BTreeMap<&Node, i32> = BTreeMap::from([(from, 0)]);
+        let mut heap = BinaryHeap::from([(Reverse(0), from)]);
+        while let Some((Reverse(latency), node)) = heap.pop() {
+            if node == to {
+                return Some(latency);
+            }
+            if best.get(node).is_some_and(|b| *b < latency) {
+                continue;
+            }
+            for (next, edge) in self.neighbours(node) {
+                let l = latency.saturating_add(edge.latency_us.unwrap_or(0));
+                if best.get(next).is_none_or(|b| l < *b) {
+                    best.insert(next, l);
+                    heap.push((Reverse(l), next));
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether two nodes can talk at `speed`, a node always reaches itself.
+    pub fn supports(&self, from: &Node, to: &Node, speed: BitsPerSecond) -> bool {
+        from == to || self.bottleneck(from, to).is_some_and(|b| b >= speed)
+    }
+}
+
+/// The node `peer` names within `dc`.
+fn resolve(dc: &DataCentre, peer: &str) -> Option<Node> {
+    if dc.switches.contains(peer) {
+        Some(Node::switch(&dc.name, peer))
+    } else if dc.compute.contains(peer) {
+        Some(Node::compute(&dc.name, peer))
+    } else if dc.storage.contains(peer) {
+        Some(Node::storage(&dc.name, peer))
+    } else if dc.name == peer {
+        Some(Node::data_centre(peer))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,526 @@
+//! Unit-safe quantities: bytes, bits per second, hertz, IOPS and watts.
+//!
+//! Every quantity is an integer count of its base unit, parsed from and
+//! formatted as a number with a unit suffix ("512GiB", "25Gbps", "2.45GHz").
+//! Fractions are accepted as long as they are exact in the base unit, and
+//! formatting picks the largest unit that shows the value with at most three
+//! decimals, so every value round-trips through its string form.
+//!
+//! Arithmetic saturates at the bounds of `u64` rather than overflowing, use
+//! `checked_add` and `checked_sub` where an overflow has to be noticed.
+//!
+//! Serde writes the string form and reads either a string or a bare integer
+//! in the base unit, `ram: 512` is 512 bytes. Model fields that used to be
+//! bare integers with the unit in their name (`ram_gb`, `speed_gb`, ...) are
+//! still read under that old name, and only there a bare integer is taken in
+//! the old unit.
+
+use std::cell::Cell;
+use std::fmt::{self, Debug, Display};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Sub};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Reasons a quantity cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnitError {
+    Empty,
+    InvalidNumber(String),
+    UnknownUnit(String),
+    /// A number without a unit where the unit has to be written out.
+    MissingUnit(String),
+    /// The value is not a whole number of the base unit.
+    Inexact(String),
+    Overflow(String),
+}
+
+impl Display for UnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for UnitError {}
+
+/// A unit suffix, its size in the base unit and whether it is used when
+/// formatting.
+type Suffix = (&'static str, u64, bool);
+
+const KIB: u64 = 1 << 10;
+const MIB: u64 = 1 << 20;
+const GIB: u64 = 1 << 30;
+const TIB: u64 = 1 << 40;
+const PIB: u64 = 1 << 50;
+
+const K: u64 = 1_000;
+const M: u64 = 1_000_000;
+const G: u64 = 1_000_000_000;
+const T: u64 = 1_000_000_000_000;
+const P: u64 = 1_000_000_000_000_000;
+
+static BYTES: [Suffix; 13] = [
+    ("PiB", PIB, true),
+    ("PB", P, true),
+    ("TiB", TIB, true),
+    ("TB", T, true),
+    ("GiB", GIB, true),
+    ("GB", G, true),
+    ("MiB", MIB, true),
+    ("MB", M, true),
+    ("KiB", KIB, true),
+    ("kB", K, true),
+    ("B", 1, true),
+    ("KB", K, false),
+    ("", 1, false),
+];
+
+static BITS_PER_SECOND: [Suffix; 12] = [
+    ("Tbps", T, true),
+    ("Gbps", G, true),
+    ("Mbps", M, true),
+    ("kbps", K, true),
+    ("bps", 1, true),
+    ("TB/s", 8 * T, false),
+    ("GB/s", 8 * G, false),
+    ("MB/s", 8 * M, false),
+    ("kB/s", 8 * K, false),
+    ("B/s", 8, false),
+    ("Kbps", K, false),
+    ("", 1, false),
+];
+
+static HERTZ: [Suffix; 6] = [
+    ("THz", T, true),
+    ("GHz", G, true),
+    ("MHz", M, true),
+    ("kHz", K, true),
+    ("Hz", 1, true),
+    ("", 1, false),
+];
+
+static IOPS: [Suffix; 6] = [
+    ("MIOPS", M, true),
+    ("kIOPS", K, true),
+    ("IOPS", 1, true),
+    ("M", M, false),
+    ("k", K, false),
+    ("", 1, false),
+];
+
+static WATTS: [Suffix; 5] = [
+    ("MW", M, true),
+    ("kW", K, true),
+    ("W", 1, true),
+    ("KW", K, false),
+    ("", 1, false),
+];
+
+fn parse(s: &str, suffixes: &[Suffix]) -> Result<u64, UnitError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(UnitError::Empty);
+    }
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, suffix) = (&s[..split], s[split..].trim_start());
+    let factor = suffixes
+        .iter()
+        .find(|(name, _, _)| *name == suffix)
+        .map(|(_, factor, _)| *factor as u128)
+        .ok_or_else(|| UnitError::UnknownUnit(suffix.to_string()))?;
+
+    let invalid = || UnitError::InvalidNumber(number.to_string());
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() || fraction.contains('.') || fraction.len() > 18 {
+        return Err(invalid());
+    }
+    let whole: u128 = whole.parse().map_err(|_| invalid())?;
+    let scale = 10u128.pow(fraction.len() as u32);
+    let fraction: u128 = if fraction.is_empty() { 0 } else { fraction.parse().map_err(|_| invalid())? };
+    if !(fraction * factor).is_multiple_of(scale) {
+        return Err(UnitError::Inexact(s.to_string()));
+    }
+    whole
+        .checked_mul(factor)
+        .map(|v| v + fraction * factor / scale)
+        .and_then(|v| u64::try_from(v).ok())
+        .ok_or_else(|| UnitError::Overflow(s.to_string()))
+}
+
+fn format(value: u64, suffixes: &[Suffix], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut shown = suffixes.iter().filter(|(_, _, shown)| *shown);
+    let (name, factor) = shown
+        .clone()
+        .find(|(_, factor, _)| value >= *factor && (value as u128 * 1000).is_multiple_of(*factor as u128))
+        .or_else(|| shown.next_back())
+        .map(|(name, factor, _)| (*name, *factor))
+        .unwrap_or(("", 1));
+    let (whole, rest) = (value / factor, value % factor);
+    if rest == 0 {
+        write!(f, "{}{}", whole, name)
+    } else {
+        let millis = format!("{:03}", rest as u128 * 1000 / factor as u128);
+        write!(f, "{}.{}{}", whole, millis.trim_end_matches('0'), name)
+    }
+}
+
+/// A quantity as written: a bare number in a given unit, or a number with a
+/// unit suffix.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Repr {
+    Number(u64),
+    Text(String),
+}
+
+impl Repr {
+    fn resolve(self, suffixes: &[Suffix], factor: u64) -> Result<u64, UnitError> {
+        match self {
+            Repr::Number(n) if EXPLICIT.get() => Err(UnitError::MissingUnit(n.to_string())),
+            Repr::Number(n) => n.checked_mul(factor).ok_or_else(|| UnitError::Overflow(n.to_string())),
+            Repr::Text(s) if EXPLICIT.get() && s.trim_end().ends_with(|c: char| c.is_ascii_digit() || c == '.') => {
+                Err(UnitError::MissingUnit(s))
+            }
+            Repr::Text(s) => parse(&s, suffixes),
+        }
+    }
+}
+
+thread_local! {
+    static EXPLICIT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` with every quantity it deserializes required to carry its unit,
+/// for documents written by hand where a bare `512` is most likely not
+/// meant in the base unit.
+pub(crate) fn explicit<T>(f: impl FnOnce() -> T) -> T {
+    let outer = EXPLICIT.replace(true);
+    let result = f();
+    EXPLICIT.set(outer);
+    result
+}
+
+/// Deserialize a string, or a bare integer in units of `factor`.
+fn deserialize<'de, D: Deserializer<'de>>(d: D, suffixes: &[Suffix], factor: u64) -> Result<u64, D::Error> {
+    Repr::deserialize(d)?.resolve(suffixes, factor).map_err(serde::de::Error::custom)
+}
+
+/// Arithmetic that stops at the bounds of the type instead of overflowing.
+///
+/// Quantities saturate with their operators as well, `checked_add` and
+/// `checked_sub` tell an overflow apart where it matters.
+pub trait Saturating: Copy {
+    fn saturating_add(self, other: Self) -> Self;
+
+    fn saturating_sub(self, other: Self) -> Self;
+}
+
+impl Saturating for u64 {
+    fn saturating_add(self, other: u64) -> u64 {
+        u64::saturating_add(self, other)
+    }
+
+    fn saturating_sub(self, other: u64) -> u64 {
+        u64::saturating_sub(self, other)
+    }
+}
+
+macro_rules! quantity {
+    ($(#[$meta:meta])* $name:ident, $suffixes:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(pub u64);
+
+        impl $name {
+            /// Value in the base unit.
+            pub fn get(self) -> u64 {
+                self.0
+            }
+
+            pub fn checked_add(self, other: $name) -> Option<$name> {
+                self.0.checked_add(other.0).map($name)
+            }
+
+            pub fn checked_sub(self, other: $name) -> Option<$name> {
+                self.0.checked_sub(other.0).map($name)
+            }
+
+            /// Share `numerator / denominator` of this quantity, rounded down.
+            pub fn share(self, numerator: u64, denominator: u64) -> $name {
+                match denominator {
+                    0 => $name(0),
+                    d => $name((self.0 as u128 * numerator as u128 / d as u128) as u64),
+                }
+            }
+        }
+
+        impl Add for $name {
+            type Output = $name;
+
+            fn add(self, other: $name) -> $name {
+                $name(self.0.saturating_add(other.0))
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, other: $name) {
+                self.0 = self.0.saturating_add(other.0);
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+
+            fn sub(self, other: $name) -> $name {
+                $name(self.0.saturating_sub(other.0))
+            }
+        }
+
+        impl Mul<u64> for $name {
+            type Output = $name;
+
+            fn mul(self, n: u64) -> $name {
+                $name(self.0.saturating_mul(n))
+            }
+        }
+
+        impl Sum for $name {
+            fn sum<I: Iterator<Item = $name>>(iter: I) -> $name {
+                $name(iter.fold(0, |sum, q| sum.saturating_add(q.0)))
+            }
+        }
+
+        impl<'a> Sum<&'a $name> for $name {
+            fn sum<I: Iterator<Item = &'a $name>>(iter: I) -> $name {
+                iter.copied().sum()
+            }
+        }
+
+        impl Saturating for $name {
+            fn saturating_add(self, other: $name) -> $name {
+                self + other
+            }
+
+            fn saturating_sub(self, other: $name) -> $name {
+                self - other
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                format(self.0, &$suffixes, f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = UnitError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                parse(s, &$suffixes).map($name)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                deserialize(d, &$suffixes, 1).map($name)
+            }
+        }
+    };
+}
+
+quantity!(
+    ///
+    /// Amount of data
+    ///
+    /// ```rust
+    /// use libtheia::models::units::Bytes;
+    ///
+    /// let ram: Bytes = "512GiB".parse().unwrap();
+    ///
+    /// assert_eq!(ram, Bytes::gib(512));
+    /// assert_eq!(Bytes::gb(1500).to_string(), "1.5TB");
+    /// assert_eq!(serde_json::to_string(&ram).unwrap(), "\"512GiB\"");
+    /// ```
+    Bytes,
+    BYTES
+);
+
+quantity!(
+    ///
+    /// Data rate
+    ///
+    /// ```rust
+    /// use libtheia::models::units::BitsPerSecond;
+    ///
+    /// assert_eq!("25Gbps".parse::<BitsPerSecond>().unwrap(), BitsPerSecond::gbps(25));
+    /// assert_eq!("500MB/s".parse::<BitsPerSecond>().unwrap().to_string(), "4Gbps");
+    /// ```
+    BitsPerSecond,
+    BITS_PER_SECOND
+);
+
+quantity!(
+    ///
+    /// Frequency
+    ///
+    /// ```rust
+    /// use libtheia::models::units::Hertz;
+    ///
+    /// let clock: Hertz = "2.45GHz".parse().unwrap();
+    ///
+    /// assert_eq!(clock, Hertz::mhz(2450));
+    /// assert_eq!(clock.to_string(), "2.45GHz");
+    /// ```
+    Hertz,
+    HERTZ
+);
+
+quantity!(
+    ///
+    /// Input/output operations per second
+    ///
+    /// ```rust
+    /// use libtheia::models::units::Iops;
+    ///
+    /// assert_eq!("50k".parse::<Iops>().unwrap(), Iops(50_000));
+    /// assert_eq!(Iops(1_200_000).to_string(), "1.2MIOPS");
+    /// ```
+    Iops,
+    IOPS
+);
+
+quantity!(
+    ///
+    /// Electrical power, or heat to remove
+    ///
+    /// ```rust
+    /// use libtheia::models::units::Watts;
+    ///
+    /// assert_eq!("12.5kW".parse::<Watts>().unwrap(), Watts(12_500));
+    /// assert_eq!(Watts::kw(1500).to_string(), "1.5MW");
+    /// ```
+    Watts,
+    WATTS
+);
+
+impl Bytes {
+    pub fn kib(n: u64) -> Bytes {
+        Bytes(n.saturating_mul(KIB))
+    }
+
+    pub fn mib(n: u64) -> Bytes {
+        Bytes(n.saturating_mul(MIB))
+    }
+
+    pub fn gib(n: u64) -> Bytes {
+        Bytes(n.saturating_mul(GIB))
+    }
+
+    pub fn tib(n: u64) -> Bytes {
+        Bytes(n.saturating_mul(TIB))
+    }
+
+    pub fn mb(n: u64) -> Bytes {
+        Bytes(n.saturating_mul(M))
+    }
+
+    pub fn gb(n: u64) -> Bytes {
+        Bytes(n.saturating_mul(G))
+    }
+
+    pub fn tb(n: u64) -> Bytes {
+        Bytes(n.saturating_mul(T))
+    }
+}
+
+impl BitsPerSecond {
+    pub fn mbps(n: u64) -> BitsPerSecond {
+        BitsPerSecond(n.saturating_mul(M))
+    }
+
+    pub fn gbps(n: u64) -> BitsPerSecond {
+        BitsPerSecond(n.saturating_mul(G))
+    }
+
+    /// Rate of `n` MegaBytes per second (MBps).
+    pub fn mb_per_second(n: u64) -> BitsPerSecond {
+        BitsPerSecond(n.saturating_mul(8 * M))
+    }
+}
+
+impl Hertz {
+    pub fn mhz(n: u64) -> Hertz {
+        Hertz(n.saturating_mul(M))
+    }
+
+    pub fn ghz(n: u64) -> Hertz {
+        Hertz(n.saturating_mul(G))
+    }
+}
+
+impl Watts {
+    pub fn kw(n: u64) -> Watts {
+        Watts(n.saturating_mul(K))
+    }
+}
+
+/// Readers for the old names of fields that used to be bare integers with
+/// the unit in their name. A model reads the old name into a field of its
+/// own, where a bare integer is in the old unit, and settles it with the
+/// current name through `pick`.
+pub(crate) mod legacy {
+    use super::*;
+
+    fn old<'de, D: Deserializer<'de>>(d: D, suffixes: &[Suffix], factor: u64) -> Result<Option<u64>, D::Error> {
+        Option::<Repr>::deserialize(d)?
+            .map(|r| r.resolve(suffixes, factor))
+            .transpose()
+            .map_err(serde::de::Error::custom)
+    }
+
+    pub fn gib<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Bytes>, D::Error> {
+        old(d, &BYTES, GIB).map(|n| n.map(Bytes))
+    }
+
+    pub fn gb<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Bytes>, D::Error> {
+        old(d, &BYTES, G).map(|n| n.map(Bytes))
+    }
+
+    pub fn tb<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Bytes>, D::Error> {
+        old(d, &BYTES, T).map(|n| n.map(Bytes))
+    }
+
+    pub fn ghz<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Hertz>, D::Error> {
+        old(d, &HERTZ, G).map(|n| n.map(Hertz))
+    }
+
+    pub fn gbps<'de, D: Deserializer<'de>>(d: D) -> Result<Option<BitsPerSecond>, D::Error> {
+        old(d, &BITS_PER_SECOND, G).map(|n| n.map(BitsPerSecond))
+    }
+
+    /// A bare integer is in MegaBytes per second (MBps).
+    pub fn mb_per_second<'de, D: Deserializer<'de>>(d: D) -> Result<Option<BitsPerSecond>, D::Error> {
+        old(d, &BITS_PER_SECOND, 8 * M).map(|n| n.map(BitsPerSecond))
+    }
+
+    /// The value given under the current name `name` or the old name
+    /// `old_name`, giving both is an error.
+    pub fn pick<T>(name: &str, current: Option<T>, old_name: &str, old: Option<T>) -> Result<Option<T>, String> {
+        match (current, old) {
+            (Some(_), Some(_)) => Err(format!("both `{}` and `{}` given", name, old_name)),
+            (current, old) => Ok(current.or(old)),
+        }
+    }
+
+    /// Like `pick`, for a field that has to be given.
+    pub fn require<T>(name: &str, current: Option<T>, old_name: &str, old: Option<T>) -> Result<T, String> {
+        pick(name, current, old_name, old)?.ok_or_else(|| format!("missing field `{}`", name))
+    }
+}
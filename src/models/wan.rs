@@ -0,0 +1,147 @@
+//! Wide area links between data centres.
+//!
+//! A `SiteLink` joins two data centres with a bandwidth, a latency and the
+//! cost of sending data over it. Latency is declared (from a contract or an
+//! inventory) or measured, a measurement wins over the declaration. Without
+//! either, the latency of a link between data centres with known coordinates
+//! is estimated from the great circle distance as light in fibre.
+//!
+//! Site links are named by their ends in order, `dc1<->dc2` is the link
+//! between `dc1` and `dc2` whichever way it was declared.
+
+use serde::{Deserialize, Serialize};
+use crate::models::collection::Named;
+use crate::models::units::{BitsPerSecond, Bytes};
+
+/// Mean earth radius in km.
+const EARTH_RADIUS: f64 = 6371.0;
+/// One way latency of fibre in microseconds per km, light covers about
+/// 200km per millisecond in glass.
+const FIBRE_US_PER_KM: f64 = 5.0;
+
+///
+/// Geographic position of a site in decimal degrees
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+#[allow(unused)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Coordinates {
+    /// Coordinates instance
+    ///
+    /// * `lat` - latitude, north positive.
+    /// * `lon` - longitude, east positive.
+    pub fn new(lat: f64, lon: f64) -> Coordinates {
+        Coordinates {
+            latitude: lat,
+            longitude: lon,
+        }
+    }
+
+    /// Great circle distance in km.
+    pub fn distance_km(&self, other: &Coordinates) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+        let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * h.sqrt().asin()
+    }
+
+    /// One way latency in microseconds of fibre laid along the great circle,
+    /// a lower bound for any real route.
+    pub fn latency_us(&self, other: &Coordinates) -> i32 {
+        (self.distance_km(other) * FIBRE_US_PER_KM).round() as i32
+    }
+}
+
+///
+/// Link between two data centres
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[allow(unused)]
+pub struct SiteLink {
+    pub name: String,
+    pub a: String,
+    pub b: String,
+    pub bandwidth: BitsPerSecond,
+    #[serde(default)]
+    pub declared_latency_us: Option<i32>,
+    #[serde(default)]
+    pub measured_latency_us: Option<i32>,
+    /// Cost per GB sent over the link, either way.
+    #[serde(default)]
+    pub egress_cost: f64,
+}
+
+impl SiteLink {
+    /// SiteLink instance
+    ///
+    /// * `a` - data centre at one end.
+    /// * `b` - data centre at the other end.
+    /// * `w` - bandwidth.
+    pub fn new(a: String, b: String, w: BitsPerSecond) -> SiteLink {
+        let (a, b) = if a <= b { (a, b) } else { (b, a) };
+        SiteLink {
+            name: SiteLink::key(&a, &b),
+            a,
+            b,
+            bandwidth: w,
+            declared_latency_us: None,
+            measured_latency_us: None,
+            egress_cost: 0.0,
+        }
+    }
+
+    /// Name of the link between two data centres.
+    pub fn key(a: &str, b: &str) -> String {
+        if a <= b {
+            format!("{}<->{}", a, b)
+        } else {
+            format!("{}<->{}", b, a)
+        }
+    }
+
+    pub fn set_declared_latency(&mut self, us: i32) {
+        self.declared_latency_us = Some(us);
+    }
+
+    pub fn set_measured_latency(&mut self, us: i32) {
+        self.measured_latency_us = Some(us);
+    }
+
+    /// * `c` - cost per GB.
+    pub fn set_egress_cost(&mut self, c: f64) {
+        self.egress_cost = c;
+    }
+
+    /// Measured latency, else the declared one.
+    pub fn latency_us(&self) -> Option<i32> {
+        self.measured_latency_us.or(self.declared_latency_us)
+    }
+
+    /// The end opposite of `dc`, `None` if the link does not reach `dc`.
+    pub fn other(&self, dc: &str) -> Option<&str> {
+        if self.a == dc {
+            Some(&self.b)
+        } else if self.b == dc {
+            Some(&self.a)
+        } else {
+            None
+        }
+    }
+
+    /// Cost of sending `size` over the link.
+    pub fn cost(&self, size: Bytes) -> f64 {
+        self.egress_cost * size.0 as f64 / Bytes::gb(1).0 as f64
+    }
+}
+
+impl Named for SiteLink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
@@ -0,0 +1,419 @@
+//! Pricing of resources and chargeback of claims to tenants.
+//!
+//! A `PriceList` holds hourly rates per resource class: per core, per GB of
+//! RAM, per GPU by model, per TB by disk type and per Gbps of interconnect.
+//! Data centres can override any of these, rates they do not override come
+//! from the list itself and a class without a rate is free.
+//!
+//! A claim is charged for every hour of its window `[from, till)`, clipped to
+//! the period asked for. Compute claimed whole is charged with its cores, RAM,
+//! GPUs and local disks, GPU partitions for their share of the compute slices
+//! of the device, and storage by its tiers with the disk type rates weighted
+//! by effective capacity.
+//!
+//! ```rust
+//! use chrono::NaiveDate;
+//! use libtheia::models::data_centre::{Compute, DataCentre};
+//! use libtheia::models::resource::Allocation;
+//! use libtheia::models::units::{Bytes, Hertz};
+//! use libtheia::pricing::{PriceList, Rates};
+//!
+//! let mut dc = DataCentre::new("dc1".to_string(), 1);
+//! dc.add_compute(Compute::new("n1".to_string(), 64, Hertz::ghz(2), Bytes::gb(512))).unwrap();
+//! let mut a = Allocation::new("job-1".to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), None);
+//! a.add_compute("n1");
+//!
+//! let mut rates = Rates::default();
+//! rates.set_core(0.01);
+//! rates.set_ram_gb(0.001);
+//! let prices = PriceList::new(rates);
+//!
+//! let day = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+//! let cost = prices.cost(&dc, &a, day, day.succ_opt().unwrap());
+//! assert_eq!((cost.hours, format!("{:.3}", cost.total())), (24, "27.648".to_string()));
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use crate::models::data_centre::{Compute, DataCentre, DiskType, Storage, GPU};
+use crate::models::gpu::GPUClaim;
+use crate::models::infrastructure::LogicalInfrastructure;
+use crate::models::resource::{Allocation, Claim};
+use crate::models::units::{BitsPerSecond, Bytes};
+
+///
+/// Hourly rates per resource class, `None` or missing entries are unset
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+#[allow(unused)]
+pub struct Rates {
+    #[serde(default)]
+    pub core: Option<f64>,
+    #[serde(default)]
+    pub ram_gb: Option<f64>,
+    /// Per device, by GPU model (or name for GPUs without a model).
+    #[serde(default)]
+    pub gpu: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub disk_tb: BTreeMap<DiskType, f64>,
+    #[serde(default)]
+    pub interconnect_gbps: Option<f64>,
+}
+
+impl Rates {
+    pub fn set_core(&mut self, r: f64) {
+        self.core = Some(r);
+    }
+
+    pub fn set_ram_gb(&mut self, r: f64) {
+        self.ram_gb = Some(r);
+    }
+
+    /// * `m` - GPU model.
+    /// * `r` - rate per device.
+    pub fn set_gpu(&mut self, m: &str, r: f64) {
+        self.gpu.insert(m.to_string(), r);
+    }
+
+    pub fn set_disk_tb(&mut self, t: DiskType, r: f64) {
+        self.disk_tb.insert(t, r);
+    }
+
+    pub fn set_interconnect_gbps(&mut self, r: f64) {
+        self.interconnect_gbps = Some(r);
+    }
+
+    /// These rates with the ones `over` sets replacing them.
+    pub fn overridden(&self, over: &Rates) -> Rates {
+        let mut rates = self.clone();
+        rates.core = over.core.or(self.core);
+        rates.ram_gb = over.ram_gb.or(self.ram_gb);
+        rates.gpu.extend(over.gpu.iter().map(|(k, v)| (k.clone(), *v)));
+        rates.disk_tb.extend(over.disk_tb.iter().map(|(k, v)| (k.clone(), *v)));
+        rates.interconnect_gbps = over.interconnect_gbps.or(self.interconnect_gbps);
+        rates
+    }
+
+    fn gpus(&self, g: &GPU, devices: f64) -> f64 {
+        let model = if g.model.is_empty() { &g.name } else { &g.model };
+        self.gpu.get(model).copied().unwrap_or_default() * devices
+    }
+
+    fn disk(&self, t: &DiskType, size: Bytes) -> f64 {
+        self.disk_tb.get(t).copied().unwrap_or_default() * tb(size)
+    }
+
+    /// Hourly cost of compute claimed whole.
+    fn compute(&self, c: &Compute) -> Cost {
+        Cost {
+            cores: self.core.unwrap_or_default() * c.cores.max(0) as f64,
+            ram: self.ram_gb.unwrap_or_default() * gb(c.ram),
+            gpus: c.gpus.iter().map(|g| self.gpus(g, g.count as f64)).sum(),
+            storage: c.disks.iter().map(|d| self.disk(&d.disk_type, d.capacity)).sum(),
+            ..Cost::default()
+        }
+    }
+
+    /// Hourly cost of GPU devices and partitions of compute resource `c`.
+    fn gpu_claim(&self, c: &Compute, claim: &GPUClaim) -> Cost {
+        let mut gpus = 0.0;
+        for g in claim.devices.iter().filter_map(|i| c.gpu_device(*i)) {
+            gpus += self.gpus(g, 1.0);
+        }
+        for (i, profiles) in claim.partitions.iter() {
+            let Some(g) = c.gpu_device(*i) else { continue };
+            let slices: u32 = profiles.iter().filter_map(|p| g.profile(p)).map(|p| p.compute_slices).sum();
+            gpus += self.gpus(g, slices as f64 / g.compute_slices.max(1) as f64);
+        }
+        Cost { gpus, ..Cost::default() }
+    }
+
+    /// Hourly cost of `size` of storage `s`, storage without disks is
+    /// charged as spinning disks.
+    fn storage(&self, s: &Storage, size: Bytes) -> Cost {
+        let tiers = s.tiers().unwrap_or_default();
+        let effective: u64 = tiers.iter().map(|t| t.effective.0).sum();
+        let storage = if effective == 0 {
+            self.disk(&DiskType::Spinning, size)
+        } else {
+            tiers.iter().map(|t| self.disk(&t.disk_type, size) * t.effective.0 as f64 / effective as f64).sum()
+        };
+        Cost { storage, ..Cost::default() }
+    }
+
+    fn interconnect(&self, bandwidth: BitsPerSecond) -> Cost {
+        Cost {
+            interconnect: self.interconnect_gbps.unwrap_or_default() * bandwidth.0 as f64 / BitsPerSecond::gbps(1).0 as f64,
+            ..Cost::default()
+        }
+    }
+}
+
+fn gb(b: Bytes) -> f64 {
+    b.0 as f64 / Bytes::gb(1).0 as f64
+}
+
+fn tb(b: Bytes) -> f64 {
+    b.0 as f64 / Bytes::tb(1).0 as f64
+}
+
+///
+/// Cost per resource class over a number of hours
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+#[allow(unused)]
+pub struct Cost {
+    pub hours: i64,
+    pub cores: f64,
+    pub ram: f64,
+    pub gpus: f64,
+    pub storage: f64,
+    pub interconnect: f64,
+}
+
+impl Cost {
+    pub fn total(&self) -> f64 {
+        self.cores + self.ram + self.gpus + self.storage + self.interconnect
+    }
+
+    /// Add the cost of `other`, the hours are the longest of both.
+    pub fn merge(&mut self, other: &Cost) {
+        self.hours = self.hours.max(other.hours);
+        self.cores += other.cores;
+        self.ram += other.ram;
+        self.gpus += other.gpus;
+        self.storage += other.storage;
+        self.interconnect += other.interconnect;
+    }
+
+    fn over(mut self, hours: i64) -> Cost {
+        let h = hours as f64;
+        self.hours = hours;
+        self.cores *= h;
+        self.ram *= h;
+        self.gpus *= h;
+        self.storage *= h;
+        self.interconnect *= h;
+        self
+    }
+}
+
+///
+/// Rates with overrides per data centre
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+#[allow(unused)]
+pub struct PriceList {
+    pub rates: Rates,
+    /// Overrides by data centre name.
+    #[serde(default)]
+    pub data_centres: BTreeMap<String, Rates>,
+}
+
+impl PriceList {
+    /// PriceList instance
+    ///
+    /// * `r` - rates of every data centre without an override.
+    pub fn new(r: Rates) -> PriceList {
+        PriceList {
+            rates: r,
+            data_centres: BTreeMap::new(),
+        }
+    }
+
+    /// Override rates for data centre `dc`, replacing an earlier override.
+    pub fn set_override(&mut self, dc: &str, r: Rates) {
+        self.data_centres.insert(dc.to_string(), r);
+    }
+
+    /// Rates in effect in data centre `dc`.
+    pub fn rates(&self, dc: &str) -> Rates {
+        match self.data_centres.get(dc) {
+            Some(over) => self.rates.overridden(over),
+            None => self.rates.clone(),
+        }
+    }
+
+    /// Cost of allocation `a` of data centre `dc` over the part of its window
+    /// within `[from, till)`, resources the data centre lacks are free.
+    pub fn cost(&self, dc: &DataCentre, a: &Allocation, from: NaiveDate, till: NaiveDate) -> Cost {
+        let rates = self.rates(&dc.name);
+        let mut cost = Cost::default();
+        for c in a.compute.iter().filter_map(|c| dc.get_compute(c)) {
+            cost.merge(&rates.compute(c));
+        }
+        for (name, claim) in a.gpus.iter() {
+            if let Some(c) = dc.get_compute(name) {
+                cost.merge(&rates.gpu_claim(c, claim));
+            }
+        }
+        for (name, size) in a.storage.iter() {
+            if let Some(s) = dc.get_storage(name) {
+                cost.merge(&rates.storage(s, *size));
+            }
+        }
+        for bandwidth in a.interconnect.values() {
+            cost.merge(&rates.interconnect(*bandwidth));
+        }
+        cost.over(hours(a.from, a.till, from, till))
+    }
+
+    /// Cost of claim `c` in data centre `dc` over the part of its window
+    /// within `[from, till)`, with its storage claimed in full.
+    pub fn claim_cost(&self, dc: &str, c: &Claim, from: NaiveDate, till: NaiveDate) -> Cost {
+        let rates = self.rates(dc);
+        let mut cost = Cost::default();
+        for compute in c.compute.iter() {
+            cost.merge(&rates.compute(compute));
+        }
+        for s in c.storage.iter() {
+            cost.merge(&rates.storage(s, s.capacity));
+        }
+        cost.merge(&rates.interconnect(c.network.speed));
+        cost.over(hours(c.from, c.till, from, till))
+    }
+
+    /// Chargeback of every claim of `infra` for the month `month` falls in,
+    /// one statement per tenant in name order, unowned claims on the
+    /// `UNOWNED` one.
+    pub fn chargeback(&self, infra: &LogicalInfrastructure, month: NaiveDate) -> Chargeback {
+        let from = month.with_day(1).unwrap_or(month);
+        let till = from.checked_add_months(chrono::Months::new(1)).unwrap_or(NaiveDate::MAX);
+        let period = from.format("%Y-%m").to_string();
+        let mut statements: BTreeMap<&str, Statement> = BTreeMap::new();
+        for dc in infra.data_centres.iter() {
+            for a in dc.claims.iter() {
+                let cost = self.cost(dc, a, from, till);
+                if cost.hours == 0 {
+                    continue;
+                }
+                let statement = statements.entry(a.tenant()).or_insert_with(|| Statement {
+                    tenant: a.tenant().to_string(),
+                    month: period.clone(),
+                    lines: Vec::new(),
+                    total: 0.0,
+                });
+                statement.total += cost.total();
+                statement.lines.push(Line {
+                    data_centre: dc.name.clone(),
+                    claim: a.name.clone(),
+                    total: cost.total(),
+                    cost,
+                });
+            }
+        }
+        Chargeback {
+            month: period,
+            statements: statements.into_values().collect(),
+        }
+    }
+}
+
+/// Hours of the window `[a_from, a_till)` within `[from, till)`.
+fn hours(a_from: NaiveDate, a_till: Option<NaiveDate>, from: NaiveDate, till: NaiveDate) -> i64 {
+    let start = a_from.max(from);
+    let end = a_till.map_or(till, |t| t.min(till));
+    (end - start).num_days().max(0) * 24
+}
+
+///
+/// A charged claim
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[allow(unused)]
+pub struct Line {
+    pub data_centre: String,
+    pub claim: String,
+    pub cost: Cost,
+    pub total: f64,
+}
+
+///
+/// Claims charged to a tenant in a month
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[allow(unused)]
+pub struct Statement {
+    pub tenant: String,
+    /// Month as `YYYY-MM`.
+    pub month: String,
+    pub lines: Vec<Line>,
+    pub total: f64,
+}
+
+const CSV_HEADER: &str = "month,tenant,data_centre,claim,hours,cores,ram,gpus,storage,interconnect,total\n";
+
+impl Statement {
+    /// The statement as CSV, one row per claim with amounts to the cent.
+    pub fn to_csv(&self) -> String {
+        CSV_HEADER.to_string() + &self.rows()
+    }
+
+    pub fn to_json(&self) -> String {
+        // strings and numbers always serialize
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    fn rows(&self) -> String {
+        let mut csv = String::new();
+        for l in self.lines.iter() {
+            let c = &l.cost;
+            csv += &format!(
+                "{},{},{},{},{},{:.2},{:.2},{:.2},{:.2},{:.2},{:.2}\n",
+                self.month,
+                field(&self.tenant),
+                field(&l.data_centre),
+                field(&l.claim),
+                c.hours,
+                c.cores,
+                c.ram,
+                c.gpus,
+                c.storage,
+                c.interconnect,
+                l.total
+            );
+        }
+        csv
+    }
+}
+
+/// A CSV field, quoted when it holds a separator or quote.
+fn field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+///
+/// Statements of all tenants for a month
+///
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[allow(unused)]
+pub struct Chargeback {
+    pub month: String,
+    pub statements: Vec<Statement>,
+}
+
+impl Chargeback {
+    pub fn statement(&self, tenant: &str) -> Option<&Statement> {
+        self.statements.iter().find(|s| s.tenant == tenant)
+    }
+
+    pub fn total(&self) -> f64 {
+        self.statements.iter().map(|s| s.total).sum()
+    }
+
+    /// All statements as one CSV document.
+    pub fn to_csv(&self) -> String {
+        CSV_HEADER.to_string() + &self.statements.iter().map(|s| s.rows()).collect::<String>()
+    }
+
+    pub fn to_json(&self) -> String {
+        // strings and numbers always serialize
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
@@ -0,0 +1,496 @@
+//! Desired-state reconciliation of inventory.
+//!
+//! `plan` compares a desired infrastructure, typically loaded from an
+//! inventory file, with the live replicated one and derives the operations
+//! that bring the live state in line:
+//! - data centres are appended to or deleted from the `List` of data centres,
+//!   a new one is appended empty and filled like the others;
+//! - rooms, switches, compute, storage, interconnects, labels, taints and
+//!   the budget and coordinates of a data centre are added, updated or
+//!   removed through the `Map` behind their collection, site links through
+//!   the collection of the infrastructure;
+//! - a resource that is removed while one with otherwise equal contents is
+//!   added is renamed, its claims and lifecycle follow the new name.
+//!
+//! Every operation of a `Plan` is public and serializable, so the replica
+//! that made the plan can send it to the others.
+//!
+//! Lifecycle states and claims are live state, the reconciler keeps them.
+//! Removing a claimed resource, or a data centre with claims, is refused. A
+//! claim following a renamed resource is admitted against the quotas of its
+//! tenant again.
+//!
+//! ```rust
+//! use libtheia::inventory::{self, Format};
+//! use libtheia::reconcile;
+//!
+//! let mut live = inventory::parse("data_centres: [{name: dc1}]", Format::Yaml, 1).unwrap();
+//! let desired = inventory::parse(r#"
+//! data_centres:
+//!   - name: dc1
+//!     interconnects: [{name: wan, speed: 10Gbps}]
+//! "#, Format::Yaml, 1).unwrap();
+//!
+//! let plan = reconcile::plan(&live, &desired, 1).unwrap();
+//! assert_eq!(plan.to_string(), "+ dc1/interconnect/wan\n");
+//! plan.apply(&mut live);
+//! assert!(reconcile::plan(&live, &desired, 1).unwrap().is_empty());
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug, Display};
+
+use serde::{Deserialize, Serialize};
+use crate::crdt::{list, CmRDT};
+use crate::models::collection::{Collection, CollectionError, Named, Operation};
+use crate::models::data_centre::{Compute, DataCentre, InterConnect, Setting, Storage, Switch};
+use crate::models::hierarchy::Room;
+use crate::models::infrastructure::LogicalInfrastructure;
+use crate::models::labels::{Label, Taint};
+use crate::models::lifecycle::ResourceLifecycle;
+use crate::models::resource::{Allocation, ClaimError};
+use crate::models::wan::SiteLink;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Room,
+    Switch,
+    Compute,
+    Storage,
+    InterConnect,
+    Label,
+    Taint,
+    Setting,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            Kind::Room => "room",
+            Kind::Switch => "switch",
+            Kind::Compute => "compute",
+            Kind::Storage => "storage",
+            Kind::InterConnect => "interconnect",
+            Kind::Label => "label",
+            Kind::Taint => "taint",
+            Kind::Setting => "setting",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// A change a plan makes, rendered as a line of a diff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Change {
+    AddDataCentre(String),
+    RemoveDataCentre(String),
+    AddSiteLink(String),
+    UpdateSiteLink(String),
+    RemoveSiteLink(String),
+    Add { data_centre: String, kind: Kind, name: String },
+    Update { data_centre: String, kind: Kind, name: String },
+    Remove { data_centre: String, kind: Kind, name: String },
+    Rename { data_centre: String, kind: Kind, from: String, to: String },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::AddDataCentre(dc) => write!(f, "+ {}", dc),
+            Change::RemoveDataCentre(dc) => write!(f, "- {}", dc),
+            Change::AddSiteLink(l) => write!(f, "+ site_link/{}", l),
+            Change::UpdateSiteLink(l) => write!(f, "~ site_link/{}", l),
+            Change::RemoveSiteLink(l) => write!(f, "- site_link/{}", l),
+            Change::Add { data_centre, kind, name } => write!(f, "+ {}/{}/{}", data_centre, kind, name),
+            Change::Update { data_centre, kind, name } => write!(f, "~ {}/{}/{}", data_centre, kind, name),
+            Change::Remove { data_centre, kind, name } => write!(f, "- {}/{}/{}", data_centre, kind, name),
+            Change::Rename { data_centre, kind, from, to } => write!(f, "> {}/{}/{} -> {}", data_centre, kind, from, to),
+        }
+    }
+}
+
+/// Reasons a plan cannot be made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconcileError {
+    /// The resource (or data centre) to remove is held by a claim.
+    Claimed { data_centre: String, name: String },
+    /// The claim, rewritten onto renamed resources, is refused by the quotas
+    /// of its tenant or project.
+    Claim { data_centre: String, name: String, error: ClaimError },
+}
+
+impl Display for ReconcileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+impl std::error::Error for ReconcileError {}
+
+/// Operation of a plan, addressed to the `List` of data centres or to a
+/// collection of the data centre it names. Other replicas apply the
+/// operations of a plan made on one of them to reach the same state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Op {
+    /// Data centres are appended empty, their contents follow as
+    /// operations on their collections.
+    DataCentres(Box<list::Operation<DataCentre, u64>>),
+    SiteLink(Operation<SiteLink>),
+    Room(String, Operation<Room>),
+    Switch(String, Operation<Switch>),
+    Compute(String, Operation<Compute>),
+    Storage(String, Operation<Storage>),
+    InterConnect(String, Operation<InterConnect>),
+    Label(String, Operation<Label>),
+    Taint(String, Operation<Taint>),
+    Setting(String, Operation<Setting>),
+    Claim(String, Operation<Allocation>),
+    ComputeLifecycle(String, Operation<ResourceLifecycle>),
+    StorageLifecycle(String, Operation<ResourceLifecycle>),
+}
+
+///
+/// Changes that bring live state in line with the desired state, with the
+/// operations that make them
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Plan {
+    pub changes: Vec<Change>,
+    pub ops: Vec<Op>,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Apply the plan to the live state it was made for, or to another
+    /// replica of it.
+    pub fn apply(self, live: &mut LogicalInfrastructure) {
+        for op in self.ops {
+            op.apply(live);
+        }
+    }
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.changes.iter() {
+            writeln!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+impl Op {
+    /// Apply the operation to a replica, operations on a data centre it does
+    /// not hold are dropped.
+    pub fn apply(self, infra: &mut LogicalInfrastructure) {
+        fn on(infra: &mut LogicalInfrastructure, name: &str, f: impl FnOnce(&mut DataCentre)) {
+            if let Some(dc) = infra.get_data_centre_mut(name) {
+                f(dc);
+            }
+        }
+
+        match self {
+            Op::DataCentres(op) => {
+                infra.data_centres.apply(*op);
+                let actor = infra.actor();
+                for dc in infra.data_centres.iter_mut() {
+                    dc.set_actor(actor);
+                }
+            }
+            Op::SiteLink(op) => infra.site_links.apply(op),
+            Op::Room(dc, op) => on(infra, &dc, |dc| dc.rooms.apply(op)),
+            Op::Switch(dc, op) => on(infra, &dc, |dc| dc.switches.apply(op)),
+            Op::Compute(dc, op) => on(infra, &dc, |dc| dc.compute.apply(op)),
+            Op::Storage(dc, op) => on(infra, &dc, |dc| dc.storage.apply(op)),
+            Op::InterConnect(dc, op) => on(infra, &dc, |dc| dc.interconnects.apply(op)),
+            Op::Label(dc, op) => on(infra, &dc, |dc| dc.labels.apply(op)),
+            Op::Taint(dc, op) => on(infra, &dc, |dc| dc.taints.apply(op)),
+            Op::Setting(dc, op) => on(infra, &dc, |dc| dc.settings.apply(op)),
+            Op::Claim(dc, op) => on(infra, &dc, |dc| dc.claims.apply(op)),
+            Op::ComputeLifecycle(dc, op) => on(infra, &dc, |dc| dc.compute_lifecycles.apply(op)),
+            Op::StorageLifecycle(dc, op) => on(infra, &dc, |dc| dc.storage_lifecycles.apply(op)),
+        }
+    }
+}
+
+/// Plan the changes that make `live` match `desired`
+///
+/// * `live` - replicated state.
+/// * `desired` - state to reach.
+/// * `actor` - replica the operations are written as.
+pub fn plan(live: &LogicalInfrastructure, desired: &LogicalInfrastructure, actor: u64) -> Result<Plan, ReconcileError> {
+    let mut plan = Plan::default();
+    // operations derive from the state the previous ones left behind
+    let mut scratch = live.clone();
+
+    for dc in live.data_centres.iter() {
+        if desired.get_data_centre(&dc.name).is_some() {
+            continue;
+        }
+        if !dc.claims.is_empty() {
+            return Err(ReconcileError::Claimed {
+                data_centre: dc.name.clone(),
+                name: dc.name.clone(),
+            });
+        }
+        let Some(i) = scratch.data_centres.iter().position(|d| d.name == dc.name) else {
+            continue;
+        };
+        if let Some(op) = scratch.data_centres.delete_index(i, actor) {
+            scratch.data_centres.apply(op.clone());
+            plan.ops.push(Op::DataCentres(Box::new(op)));
+            plan.changes.push(Change::RemoveDataCentre(dc.name.clone()));
+        }
+    }
+
+    for d in desired.data_centres.iter() {
+        if scratch.get_data_centre(&d.name).is_none() {
+            let op = scratch.data_centres.append(DataCentre::new(d.name.clone(), actor), actor);
+            scratch.data_centres.apply(op.clone());
+            plan.ops.push(Op::DataCentres(Box::new(op)));
+            plan.changes.push(Change::AddDataCentre(d.name.clone()));
+        }
+        if let Some(mut dc) = scratch.get_data_centre(&d.name).cloned() {
+            data_centre(&mut plan, &mut dc, d, actor, |state, claim, moved| scratch.admit_quotas(state, moved, Some(claim)))?;
+            scratch.replace_data_centre(dc);
+        }
+    }
+    site_links(&mut plan, &mut scratch.site_links, &desired.site_links, actor);
+    Ok(plan)
+}
+
+/// Site links are added, updated and removed by the pair of data centres
+/// they join, their measured latency is live state and kept.
+fn site_links(plan: &mut Plan, live: &mut Collection<SiteLink>, desired: &Collection<SiteLink>, actor: u64) {
+    let mut push = |live: &mut Collection<SiteLink>, op: Result<Operation<SiteLink>, CollectionError>, change: Change| {
+        if let Ok(op) = op {
+            live.apply(op.clone());
+            plan.ops.push(Op::SiteLink(op));
+            plan.changes.push(change);
+        }
+    };
+    let removed: Vec<String> = live.names().filter(|n| !desired.contains(n)).map(|n| n.to_string()).collect();
+    for name in removed {
+        push(live, live.remove(&name), Change::RemoveSiteLink(name.clone()));
+    }
+    for d in desired.iter() {
+        let Some(old) = live.get(&d.name).cloned() else {
+            push(live, live.add(d.clone(), actor), Change::AddSiteLink(d.name.clone()));
+            continue;
+        };
+        let mut new = d.clone();
+        new.measured_latency_us = old.measured_latency_us;
+        if new != old || live.conflicts(&new.name).len() > 1 {
+            push(live, live.update(new, actor), Change::UpdateSiteLink(d.name.clone()));
+        }
+    }
+}
+
+/// Reconcile one data centre, `admit` checks a claim rewritten onto renamed
+/// resources against the quotas of its owner.
+fn data_centre(
+    plan: &mut Plan,
+    dc: &mut DataCentre,
+    desired: &DataCentre,
+    actor: u64,
+    admit: impl Fn(&DataCentre, &Allocation, &Allocation) -> Result<(), ClaimError>,
+) -> Result<(), ReconcileError> {
+    let name = dc.name.clone();
+    let mut compute: BTreeSet<String> = dc.claimed_compute().into_iter().map(|c| c.to_string()).collect();
+    compute.extend(dc.claims.iter().flat_map(|a| a.gpus.keys().cloned()));
+    let storage: BTreeSet<String> = dc.claims.iter().flat_map(|a| a.storage.keys().cloned()).collect();
+    let interconnects: BTreeSet<String> = dc.claims.iter().flat_map(|a| a.interconnect.keys().cloned()).collect();
+
+    let mut diff = Diff { plan, data_centre: &name, actor };
+    diff.collection(&mut dc.labels, &desired.labels, Kind::Label, Op::Label, |_, d| d, |_| false)?;
+    diff.collection(&mut dc.taints, &desired.taints, Kind::Taint, Op::Taint, |_, d| d, |_| false)?;
+    diff.collection(&mut dc.settings, &desired.settings, Kind::Setting, Op::Setting, |_, d| d, |_| false)?;
+    diff.collection(&mut dc.rooms, &desired.rooms, Kind::Room, Op::Room, |_, d| d, |_| false)?;
+    diff.collection(&mut dc.switches, &desired.switches, Kind::Switch, Op::Switch, |_, d| d, |_| false)?;
+    let new_compute = added(&dc.compute, &desired.compute);
+    let new_storage = added(&dc.storage, &desired.storage);
+    let compute_renames = diff.collection(&mut dc.compute, &desired.compute, Kind::Compute, Op::Compute, |_, d| d, |n| compute.contains(n))?;
+    let storage_renames = diff.collection(&mut dc.storage, &desired.storage, Kind::Storage, Op::Storage, |_, d| d, |n| storage.contains(n))?;
+    let live_compute: BTreeSet<String> = dc.compute.names().map(|n| n.to_string()).collect();
+    let live_storage: BTreeSet<String> = dc.storage.names().map(|n| n.to_string()).collect();
+    diff.lifecycles(&mut dc.compute_lifecycles, &desired.compute_lifecycles, &live_compute, &new_compute, &compute_renames, Op::ComputeLifecycle);
+    diff.lifecycles(&mut dc.storage_lifecycles, &desired.storage_lifecycles, &live_storage, &new_storage, &storage_renames, Op::StorageLifecycle);
+    let mut renames = compute_renames;
+    renames.extend(storage_renames);
+    renames.extend(diff.collection(&mut dc.interconnects, &desired.interconnects, Kind::InterConnect, Op::InterConnect, |_, d| d, |n| interconnects.contains(n))?);
+
+    // claims follow renamed resources
+    let claims: Vec<Allocation> = dc
+        .claims
+        .iter()
+        .filter(|a| {
+            let mut names = a.compute.iter().chain(a.gpus.keys()).chain(a.storage.keys()).chain(a.interconnect.keys());
+            names.any(|n| renames.contains_key(n))
+        })
+        .cloned()
+        .collect();
+    for mut a in claims {
+        let claim = a.clone();
+        let rename = |n: String| renames.get(&n).cloned().unwrap_or(n);
+        a.compute = a.compute.into_iter().map(rename).collect();
+        a.gpus = a.gpus.into_iter().map(|(n, g)| (rename(n), g)).collect();
+        a.storage = a.storage.into_iter().map(|(n, s)| (rename(n), s)).collect();
+        a.interconnect = a.interconnect.into_iter().map(|(n, i)| (rename(n), i)).collect();
+        admit(dc, &claim, &a).map_err(|error| ReconcileError::Claim {
+            data_centre: name.clone(),
+            name: a.name.clone(),
+            error,
+        })?;
+        if let Ok(op) = dc.claims.update(a, actor) {
+            dc.claims.apply(op.clone());
+            plan.ops.push(Op::Claim(name.clone(), op));
+        }
+    }
+    Ok(())
+}
+
+struct Diff<'a> {
+    plan: &'a mut Plan,
+    data_centre: &'a str,
+    actor: u64,
+}
+
+impl Diff<'_> {
+    /// Reconcile one collection, returning the renames it made.
+    ///
+    /// * `keep` - desired resource with the live state of the resource it replaces.
+    /// * `claimed` - whether a resource is held by a claim.
+    fn collection<T: Named + Clone + Debug + PartialEq + Serialize>(
+        &mut self,
+        live: &mut Collection<T>,
+        desired: &Collection<T>,
+        kind: Kind,
+        wrap: fn(String, Operation<T>) -> Op,
+        keep: impl Fn(&T, T) -> T,
+        claimed: impl Fn(&str) -> bool,
+    ) -> Result<BTreeMap<String, String>, ReconcileError> {
+        let mut removed: Vec<T> = live.iter().filter(|t| !desired.contains(t.name())).cloned().collect();
+        let mut added: Vec<T> = desired.iter().filter(|t| !live.contains(t.name())).cloned().collect();
+        let common: Vec<T> = desired.iter().filter(|t| live.contains(t.name())).cloned().collect();
+
+        let mut renames = BTreeMap::new();
+        removed.retain(|old| {
+            let contents = unnamed(old);
+            let Some(i) = added.iter().position(|new| contents.is_some() && unnamed(&keep(old, new.clone())) == contents) else {
+                return true;
+            };
+            renames.insert(old.name().to_string(), added.remove(i));
+            false
+        });
+
+        for old in removed.iter() {
+            if claimed(old.name()) {
+                return Err(ReconcileError::Claimed {
+                    data_centre: self.data_centre.to_string(),
+                    name: old.name().to_string(),
+                });
+            }
+        }
+        for old in removed {
+            self.push(live, live.remove(old.name()), wrap);
+            self.plan.changes.push(Change::Remove {
+                data_centre: self.data_centre.to_string(),
+                kind,
+                name: old.name().to_string(),
+            });
+        }
+        let mut renamed = BTreeMap::new();
+        for (from, new) in renames {
+            let old = live.get(&from).cloned();
+            self.push(live, live.remove(&from), wrap);
+            let to = new.name().to_string();
+            let new = match old {
+                Some(old) => keep(&old, new),
+                None => new,
+            };
+            self.push(live, live.add(new, self.actor), wrap);
+            self.plan.changes.push(Change::Rename {
+                data_centre: self.data_centre.to_string(),
+                kind,
+                from: from.clone(),
+                to: to.clone(),
+            });
+            renamed.insert(from, to);
+        }
+        for new in added {
+            let name = new.name().to_string();
+            self.push(live, live.add(new, self.actor), wrap);
+            self.plan.changes.push(Change::Add { data_centre: self.data_centre.to_string(), kind, name });
+        }
+        for new in common {
+            let Some(old) = live.get(new.name()) else { continue };
+            let new = keep(old, new);
+            if &new == old && live.conflicts(new.name()).len() == 1 {
+                continue;
+            }
+            let name = new.name().to_string();
+            self.push(live, live.update(new, self.actor), wrap);
+            self.plan.changes.push(Change::Update { data_centre: self.data_centre.to_string(), kind, name });
+        }
+        Ok(renamed)
+    }
+
+    /// Lifecycles follow their resource: a renamed resource keeps its live
+    /// lifecycle under the new name, a removed one loses it and one added by
+    /// the plan starts out in its desired state. The lifecycle of a resource
+    /// that stays is never reset.
+    fn lifecycles(
+        &mut self,
+        live: &mut Collection<ResourceLifecycle>,
+        desired: &Collection<ResourceLifecycle>,
+        resources: &BTreeSet<String>,
+        added: &BTreeSet<String>,
+        renames: &BTreeMap<String, String>,
+        wrap: fn(String, Operation<ResourceLifecycle>) -> Op,
+    ) {
+        for (from, to) in renames {
+            let Some(old) = live.get(from).cloned() else { continue };
+            self.push(live, live.remove(from), wrap);
+            self.push(live, live.add(ResourceLifecycle::new(to.clone(), old.lifecycle), self.actor), wrap);
+        }
+        let gone: Vec<String> = live.names().filter(|n| !resources.contains(*n)).map(|n| n.to_string()).collect();
+        for name in gone {
+            self.push(live, live.remove(&name), wrap);
+        }
+        for d in desired.iter().filter(|d| added.contains(&d.name) && !renames.values().any(|to| to == &d.name)) {
+            self.push(live, live.add(d.clone(), self.actor), wrap);
+        }
+    }
+
+    fn push<T: Named + Clone + Debug, E>(&mut self, live: &mut Collection<T>, op: Result<Operation<T>, E>, wrap: fn(String, Operation<T>) -> Op) {
+        if let Ok(op) = op {
+            live.apply(op.clone());
+            self.plan.ops.push(wrap(self.data_centre.to_string(), op));
+        }
+    }
+}
+
+/// Names of the resources `desired` has and `live` does not.
+fn added<T: Named + Clone + Debug>(live: &Collection<T>, desired: &Collection<T>) -> BTreeSet<String> {
+    desired.names().filter(|n| !live.contains(n)).map(|n| n.to_string()).collect()
+}
+
+/// Contents of a resource without its name, to recognise renames. Link
+/// peers are left out too, they change along with a peer that is renamed in
+/// the same plan.
+fn unnamed<T: Serialize>(t: &T) -> Option<serde_json::Value> {
+    let mut value = serde_json::to_value(t).ok()?;
+    let object = value.as_object_mut()?;
+    object.remove("name");
+    object.remove("peer");
+    if let Some(links) = object.get_mut("links").and_then(|l| l.as_array_mut()) {
+        for link in links.iter_mut().filter_map(|l| l.as_object_mut()) {
+            link.remove("peer");
+        }
+    }
+    Some(value)
+}
@@ -6,11 +6,14 @@
 //! - configuration file
 extern crate dirs;
 
-use config::{ Config, ConfigError, Environment, File };
+use config::{ Config, Environment, File };
 use serde::Deserialize;
 use std::path::PathBuf;
 use std::env;
 
+use crate::crdt::envelope::SecretKey;
+use crate::error::TheiaError;
+
 ///
 /// Standard search locations for configuration files
 ///
@@ -40,25 +43,40 @@ pub struct Log {
 #[allow(unused)]
 pub struct Settings {
     log: Log,
+    secret: Option<String>,
+    /// Actor this instance writes replicated state as, unique per instance.
+    replica: Option<u64>,
 }
 
 ///
 /// Settings loader
 ///
 impl Settings {
-    pub fn new() -> Result<Self, ConfigError> {
+    pub fn new() -> Result<Self, TheiaError> {
         let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "development".into());
         let mut b = Config::builder();
         // system configuration path
         b = b.add_source(File::with_name(&format!("{}/default", SYS_CONF_DIR)).required(false))
              .add_source(File::with_name(&format!("{}/{}", SYS_CONF_DIR, run_mode)).required(false));
         // user configuration path
-        if get_usr_conf_dir().is_some() {
-            b = b.add_source(File::with_name(&format!("{}/default", get_usr_conf_dir().unwrap().canonicalize().unwrap().to_str().unwrap())).required(false))
-                 .add_source(File::with_name(&format!("{}/{}", get_usr_conf_dir().unwrap().canonicalize().unwrap().to_str().unwrap(), run_mode)).required(false))
+        if let Some(dir) = get_usr_conf_dir().and_then(|d| d.canonicalize().ok()) {
+            let dir = dir.to_string_lossy();
+            b = b.add_source(File::with_name(&format!("{}/default", dir)).required(false))
+                 .add_source(File::with_name(&format!("{}/{}", dir, run_mode)).required(false))
         }
         // environment variables PREFIX 'THEIA_'
         b = b.add_source(Environment::with_prefix("THEIA"));
-        b.build()?.try_deserialize()
+        Ok(b.build()?.try_deserialize()?)
+    }
+
+    /// Key for sealing CRDT operations and snapshots, derived from the
+    /// configured secret.
+    pub fn secret_key(&self) -> Option<SecretKey> {
+        self.secret.as_deref().map(SecretKey::from_secret)
+    }
+
+    /// Replica ID of this instance, 0 is reserved for having none.
+    pub fn replica(&self) -> Option<u64> {
+        self.replica.filter(|r| *r != 0)
     }
 }
@@ -0,0 +1,448 @@
+//! Consistency validation of infrastructure.
+//!
+//! A `Validator` runs a set of rules over a `LogicalInfrastructure` and
+//! collects their findings. The default rules cover:
+//! - `duplicates`: data centres, concurrently defined resources and disks,
+//!   GPUs or links sharing a name within a resource;
+//! - `impossible-values`: zero or negative cores, clocks, memory, capacity,
+//!   speed or egress cost, CPU scores that are not finite and above zero,
+//!   negative latency and pools that cannot deliver their capacity;
+//! - `dangling-references`: peers, locations, GPU devices, claimed resources
+//!   and site link ends that do not exist;
+//! - `unit-mismatches`: values so small they were most likely meant in a
+//!   larger unit, a bare number is in the base unit, `ram: 512` reads as 512
+//!   bytes. Only the old field names (`ram_gb`, ...) take their old unit.
+//!
+//! Rules are pluggable, anything implementing `Rule` can be added and the
+//! default ones can be dropped by name.
+//!
+//! ```rust
+//! use libtheia::models::data_centre::{Compute, DataCentre};
+//! use libtheia::models::infrastructure::LogicalInfrastructure;
+//! use libtheia::models::units::{Bytes, Hertz};
+//! use libtheia::validate::{Severity, Validator};
+//!
+//! let mut dc = DataCentre::new("dc1".to_string(), 1);
+//! dc.add_compute(Compute::new("n1".to_string(), 0, Hertz::ghz(2), Bytes::gib(256))).unwrap();
+//! let mut infra = LogicalInfrastructure::new(1);
+//! infra.add_data_centre(dc).unwrap();
+//!
+//! let findings = Validator::new().validate(&infra);
+//! assert_eq!(findings[0].severity, Severity::Error);
+//! assert_eq!(findings[0].to_string(), "error [impossible-values] dc1/n1: 0 cores");
+//! ```
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display};
+
+use serde::Serialize;
+use crate::models::data_centre::{Compute, DataCentre, InterConnect, Storage};
+use crate::models::infrastructure::LogicalInfrastructure;
+use crate::models::pool::PoolError;
+use crate::models::units::{BitsPerSecond, Bytes, Hertz};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}", severity)
+    }
+}
+
+///
+/// Something a rule found, about a data centre or one of its resources
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub rule: String,
+    pub severity: Severity,
+    pub data_centre: String,
+    pub resource: Option<String>,
+    pub message: String,
+}
+
+impl Finding {
+    /// Finding instance
+    ///
+    /// * `r` - name of the rule.
+    /// * `s` - severity.
+    /// * `d` - data centre.
+    /// * `n` - resource, if the finding is about one.
+    /// * `m` - message.
+    pub fn new(r: &str, s: Severity, d: &str, n: Option<&str>, m: String) -> Finding {
+        Finding {
+            rule: r.to_string(),
+            severity: s,
+            data_centre: d.to_string(),
+            resource: n.map(str::to_string),
+            message: m,
+        }
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}] {}", self.severity, self.rule, self.data_centre)?;
+        if let Some(resource) = &self.resource {
+            write!(f, "/{}", resource)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// A consistency check over infrastructure.
+pub trait Rule {
+    /// Name findings are reported under, also used to drop the rule.
+    fn name(&self) -> &str;
+    fn check(&self, infra: &LogicalInfrastructure) -> Vec<Finding>;
+}
+
+///
+/// Set of rules to validate infrastructure with
+///
+pub struct Validator {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Default for Validator {
+    fn default() -> Self {
+        Validator::new()
+    }
+}
+
+impl Validator {
+    /// Validator with the default rules.
+    pub fn new() -> Validator {
+        Validator::empty()
+            .with_rule(Duplicates)
+            .with_rule(ImpossibleValues)
+            .with_rule(DanglingReferences)
+            .with_rule(UnitMismatches)
+    }
+
+    /// Validator without rules.
+    pub fn empty() -> Validator {
+        Validator { rules: Vec::new() }
+    }
+
+    pub fn with_rule(mut self, r: impl Rule + 'static) -> Validator {
+        self.rules.push(Box::new(r));
+        self
+    }
+
+    /// Drop the rules named `n`.
+    pub fn without_rule(mut self, n: &str) -> Validator {
+        self.rules.retain(|r| r.name() != n);
+        self
+    }
+
+    pub fn rules(&self) -> Vec<&str> {
+        self.rules.iter().map(|r| r.name()).collect()
+    }
+
+    /// Findings of all rules, most severe first.
+    pub fn validate(&self, infra: &LogicalInfrastructure) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = self.rules.iter().flat_map(|r| r.check(infra)).collect();
+        findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+        findings
+    }
+
+    /// Whether no rule finds an error.
+    pub fn is_valid(&self, infra: &LogicalInfrastructure) -> bool {
+        self.validate(infra).iter().all(|f| f.severity < Severity::Error)
+    }
+}
+
+/// Links of a data centre with the name of the resource they belong to.
+fn links(dc: &DataCentre) -> Vec<(String, &InterConnect)> {
+    let mut links = Vec::new();
+    for s in dc.switches.iter() {
+        links.extend(s.links.iter().map(|l| (s.name.clone(), l)));
+    }
+    for c in dc.compute.iter() {
+        links.extend(c.links.iter().map(|l| (c.name.clone(), l)));
+    }
+    for s in dc.storage.iter() {
+        links.extend(s.links.iter().map(|l| (s.name.clone(), l)));
+    }
+    links.extend(dc.interconnects.iter().map(|i| (i.name.clone(), i)));
+    links
+}
+
+/// Names that occur more than once.
+fn repeated<'a>(names: impl Iterator<Item = &'a str>) -> BTreeSet<&'a str> {
+    let mut seen = BTreeSet::new();
+    names.filter(|n| !seen.insert(*n)).collect()
+}
+
+/// Data centres, resources and parts of a resource that share a name.
+pub struct Duplicates;
+
+impl Rule for Duplicates {
+    fn name(&self) -> &str {
+        "duplicates"
+    }
+
+    fn check(&self, infra: &LogicalInfrastructure) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let error = |dc: &str, n: Option<&str>, m: String| Finding::new(self.name(), Severity::Error, dc, n, m);
+        for name in repeated(infra.data_centres.iter().map(|dc| dc.name.as_str())) {
+            findings.push(error(name, None, "data centre is defined more than once".to_string()));
+        }
+        for dc in infra.data_centres.iter() {
+            // concurrent adds under one name keep every value until resolved
+            let names = dc.compute.names().filter(|n| dc.compute.conflicts(n).len() > 1)
+                .chain(dc.storage.names().filter(|n| dc.storage.conflicts(n).len() > 1))
+                .chain(dc.interconnects.names().filter(|n| dc.interconnects.conflicts(n).len() > 1))
+                .chain(dc.switches.names().filter(|n| dc.switches.conflicts(n).len() > 1))
+                .chain(dc.rooms.names().filter(|n| dc.rooms.conflicts(n).len() > 1));
+            for name in names {
+                findings.push(error(&dc.name, Some(name), "concurrently defined more than once".to_string()));
+            }
+            for c in dc.compute.iter() {
+                for (part, names) in [
+                    ("disk", repeated(c.disks.iter().map(|d| d.name.as_str()))),
+                    ("gpu", repeated(c.gpus.iter().map(|g| g.name.as_str()))),
+                    ("link", repeated(c.links.iter().map(|l| l.name.as_str()))),
+                ] {
+                    for name in names {
+                        findings.push(error(&dc.name, Some(&c.name), format!("{} {} is defined more than once", part, name)));
+                    }
+                }
+            }
+            for s in dc.storage.iter() {
+                for (part, names) in [
+                    ("disk", repeated(s.disks.iter().flatten().map(|d| d.name.as_str()))),
+                    ("link", repeated(s.links.iter().map(|l| l.name.as_str()))),
+                ] {
+                    for name in names {
+                        findings.push(error(&dc.name, Some(&s.name), format!("{} {} is defined more than once", part, name)));
+                    }
+                }
+            }
+        }
+        findings
+    }
+}
+
+/// Values no hardware has.
+pub struct ImpossibleValues;
+
+impl ImpossibleValues {
+    fn compute(&self, c: &Compute) -> Vec<String> {
+        let mut problems = Vec::new();
+        if c.cores <= 0 {
+            problems.push(format!("{} cores", c.cores));
+        }
+        if c.core_clock == Hertz(0) {
+            problems.push("no core clock".to_string());
+        }
+        if c.ram == Bytes(0) {
+            problems.push("no ram".to_string());
+        }
+        if let Some(score) = c.cpu.score.filter(|s| !s.is_finite() || *s <= 0.0) {
+            problems.push(format!("cpu score of {}", score));
+        }
+        for d in c.disks.iter().filter(|d| d.capacity == Bytes(0)) {
+            problems.push(format!("disk {} has no capacity", d.name));
+        }
+        for g in c.gpus.iter() {
+            if g.count == 0 {
+                problems.push(format!("gpu {} has no devices", g.name));
+            }
+            if g.ram == Bytes(0) {
+                problems.push(format!("gpu {} has no ram", g.name));
+            }
+        }
+        problems
+    }
+
+    fn storage(&self, s: &Storage) -> Vec<String> {
+        let mut problems = Vec::new();
+        if s.capacity == Bytes(0) {
+            problems.push("no capacity".to_string());
+        }
+        for d in s.disks.iter().flatten().filter(|d| d.capacity == Bytes(0)) {
+            problems.push(format!("disk {} has no capacity", d.name));
+        }
+        match s.validate() {
+            Ok(()) => {}
+            Err(PoolError::OverDeclared { declared, effective }) => {
+                problems.push(format!("declares {} but its disks deliver {}", declared, effective))
+            }
+            Err(e) => problems.push(format!("pool cannot be formed: {}", e)),
+        }
+        problems
+    }
+
+    fn link(&self, l: &InterConnect) -> Vec<String> {
+        let mut problems = Vec::new();
+        if l.speed == BitsPerSecond(0) {
+            problems.push(format!("link {} has no speed", l.name));
+        }
+        if let Some(latency) = l.latency_us.filter(|l| *l < 0) {
+            problems.push(format!("link {} has a latency of {}us", l.name, latency));
+        }
+        problems
+    }
+}
+
+impl Rule for ImpossibleValues {
+    fn name(&self) -> &str {
+        "impossible-values"
+    }
+
+    fn check(&self, infra: &LogicalInfrastructure) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for dc in infra.data_centres.iter() {
+            let mut problems: Vec<(String, String)> = Vec::new();
+            for c in dc.compute.iter() {
+                problems.extend(self.compute(c).into_iter().map(|p| (c.name.clone(), p)));
+            }
+            for s in dc.storage.iter() {
+                problems.extend(self.storage(s).into_iter().map(|p| (s.name.clone(), p)));
+            }
+            for (owner, l) in links(dc) {
+                problems.extend(self.link(l).into_iter().map(|p| (owner.clone(), p)));
+            }
+            for (name, message) in problems {
+                findings.push(Finding::new(self.name(), Severity::Error, &dc.name, Some(&name), message));
+            }
+        }
+        for l in infra.site_links.iter() {
+            let mut problems = Vec::new();
+            if l.bandwidth == BitsPerSecond(0) {
+                problems.push("no bandwidth".to_string());
+            }
+            for us in [l.declared_latency_us, l.measured_latency_us].into_iter().flatten().filter(|us| *us < 0) {
+                problems.push(format!("latency of {}us", us));
+            }
+            if l.egress_cost < 0.0 {
+                problems.push(format!("egress cost of {}", l.egress_cost));
+            }
+            for message in problems {
+                findings.push(Finding::new(self.name(), Severity::Error, &l.a, Some(&l.name), message));
+            }
+        }
+        findings
+    }
+}
+
+/// Peers, locations, GPU devices, claimed resources and site link ends that
+/// do not exist.
+pub struct DanglingReferences;
+
+impl Rule for DanglingReferences {
+    fn name(&self) -> &str {
+        "dangling-references"
+    }
+
+    fn check(&self, infra: &LogicalInfrastructure) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let data_centres: BTreeSet<&str> = infra.data_centres.iter().map(|dc| dc.name.as_str()).collect();
+        for dc in infra.data_centres.iter() {
+            let mut found = |s: Severity, n: &str, m: String| findings.push(Finding::new(self.name(), s, &dc.name, Some(n), m));
+
+            let mut peers = data_centres.clone();
+            peers.extend(dc.switches.names());
+            peers.extend(dc.compute.names());
+            peers.extend(dc.storage.names());
+            peers.extend(dc.interconnects.names());
+            for (owner, l) in links(dc) {
+                if let Some(peer) = l.peer.as_ref().filter(|p| !peers.contains(p.as_str())) {
+                    found(Severity::Error, &owner, format!("link {} peers with unknown {}", l.name, peer));
+                }
+            }
+
+            let located = dc.switches.iter().map(|s| (&s.name, &s.location))
+                .chain(dc.compute.iter().map(|c| (&c.name, &c.location)))
+                .chain(dc.storage.iter().map(|s| (&s.name, &s.location)));
+            for (name, location) in located {
+                if let Some(location) = location.as_ref().filter(|l| dc.domains(l).is_none()) {
+                    found(Severity::Error, name, format!("placed at unknown location {}", location));
+                }
+            }
+
+            for c in dc.compute.iter() {
+                for device in c.gpu_links.iter().flat_map(|l| [l.a, l.b]).filter(|d| *d >= c.gpu_count()) {
+                    found(Severity::Error, &c.name, format!("gpu link to unknown device {}", device));
+                }
+            }
+
+            for a in dc.claims.iter() {
+                let compute = a.compute.iter().chain(a.gpus.keys()).filter(|n| !dc.compute.contains(n));
+                let storage = a.storage.keys().filter(|n| !dc.storage.contains(n));
+                let interconnects = a.interconnect.keys().filter(|n| !dc.interconnects.contains(n));
+                for name in compute.chain(storage).chain(interconnects) {
+                    found(Severity::Warning, &a.name, format!("claims unknown resource {}", name));
+                }
+            }
+        }
+        for l in infra.site_links.iter() {
+            for (end, other) in [(&l.a, &l.b), (&l.b, &l.a)] {
+                if !data_centres.contains(end.as_str()) {
+                    let m = format!("links to unknown data centre {}", end);
+                    findings.push(Finding::new(self.name(), Severity::Error, other, Some(&l.name), m));
+                }
+            }
+        }
+        findings
+    }
+}
+
+/// Values that read as a bare number in the base unit where a larger unit
+/// was meant.
+pub struct UnitMismatches;
+
+impl UnitMismatches {
+    const RAM: Bytes = Bytes(1 << 20);
+    const DISK: Bytes = Bytes(1_000_000_000);
+    const CLOCK: Hertz = Hertz(1_000_000);
+    const SPEED: BitsPerSecond = BitsPerSecond(1_000_000);
+}
+
+impl Rule for UnitMismatches {
+    fn name(&self) -> &str {
+        "unit-mismatches"
+    }
+
+    fn check(&self, infra: &LogicalInfrastructure) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for dc in infra.data_centres.iter() {
+            let mut found = |n: &str, m: String| findings.push(Finding::new(self.name(), Severity::Warning, &dc.name, Some(n), m));
+            for c in dc.compute.iter() {
+                if c.core_clock > Hertz(0) && c.core_clock < Self::CLOCK {
+                    found(&c.name, format!("core clock of {} is below 1MHz", c.core_clock));
+                }
+                if c.ram > Bytes(0) && c.ram < Self::RAM {
+                    found(&c.name, format!("ram of {} is below 1MiB", c.ram));
+                }
+                for g in c.gpus.iter().filter(|g| g.ram > Bytes(0) && g.ram < Self::RAM) {
+                    found(&c.name, format!("gpu {} ram of {} is below 1MiB", g.name, g.ram));
+                }
+            }
+            let disks = dc.compute.iter().flat_map(|c| c.disks.iter().map(move |d| (&c.name, d)))
+                .chain(dc.storage.iter().flat_map(|s| s.disks.iter().flatten().map(move |d| (&s.name, d))));
+            for (owner, d) in disks.filter(|(_, d)| d.capacity > Bytes(0) && d.capacity < Self::DISK) {
+                found(owner, format!("disk {} capacity of {} is below 1GB", d.name, d.capacity));
+            }
+            for (owner, l) in links(dc) {
+                if l.speed > BitsPerSecond(0) && l.speed < Self::SPEED {
+                    found(&owner, format!("link {} speed of {} is below 1Mbps", l.name, l.speed));
+                }
+            }
+        }
+        findings
+    }
+}
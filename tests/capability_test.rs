@@ -0,0 +1,142 @@
+mod common;
+
+use common::{allocation, data_centre, node};
+use libtheia::error::TheiaError;
+use libtheia::inventory::{self, Format};
+use libtheia::models::capability::{Capabilities, Capability, Capable, Feature, Requirement, RequirementError, Version};
+use libtheia::models::data_centre::{Compute, DataCentre, InterConnect};
+use libtheia::models::resource::{Allocation, ClaimError};
+use libtheia::models::units::BitsPerSecond;
+
+fn capabilities(cs: &[&str]) -> Capabilities {
+    cs.iter().map(|c| c.parse::<Capability>().unwrap()).collect()
+}
+
+fn capable_node(name: &str, cpu: &[&str], nic: &[&str]) -> Compute {
+    let mut c = node(name, 32);
+    for capability in cpu {
+        c.add_capability(capability.parse().unwrap());
+    }
+    let mut eth0 = InterConnect::new("eth0".to_string(), BitsPerSecond::gbps(100), true);
+    for capability in nic {
+        eth0.add_capability(capability.parse().unwrap());
+    }
+    c.add_link(eth0);
+    c
+}
+
+fn requiring(name: &str, requirement: &str) -> Allocation {
+    let mut a = allocation(name);
+    a.set_requirement(requirement.parse().unwrap());
+    a
+}
+
+fn capable_data_centre() -> DataCentre {
+    data_centre(
+        "dc1",
+        [
+            capable_node("n1", &["avx2"], &["sriov"]),
+            capable_node("n2", &["avx512", "tpm=2.0"], &["rdma", "roce=2"]),
+            capable_node("n3", &["avx512", "tpm=1.2"], &["rdma"]),
+        ],
+    )
+}
+
+#[test]
+fn test_parse_and_display() {
+    let r: Requirement = "rdma && (avx512 || sve) && !sev && tpm >= v2".parse().unwrap();
+    assert_eq!(r.to_string(), "rdma && (avx512 || sve) && !sev && tpm >= 2");
+    assert_eq!(r.to_string().parse::<Requirement>().unwrap(), r);
+    assert_eq!("nvidia/mig".parse::<Feature>().unwrap(), Feature::Vendor("nvidia/mig".to_string()));
+    assert_eq!(capabilities(&["tpm=2.0", "RDMA"]).iter().map(|c| c.to_string()).collect::<Vec<_>>(), vec!["rdma", "tpm=2.0"]);
+
+    assert_eq!("warp-drive".parse::<Requirement>(), Err(RequirementError::UnknownFeature("warp-drive".to_string())));
+    assert_eq!("tpm >= two".parse::<Requirement>(), Err(RequirementError::InvalidVersion("two".to_string())));
+    assert_eq!("rdma &&".parse::<Requirement>(), Err(RequirementError::UnexpectedEnd));
+    assert_eq!(
+        "rdma avx2".parse::<Requirement>(),
+        Err(RequirementError::Unexpected { position: 5, found: "avx2".to_string() })
+    );
+
+    // nesting is bounded, deeper expressions are refused instead of overflowing the stack
+    assert!(format!("{}rdma{}", "(".repeat(64), ")".repeat(64)).parse::<Requirement>().is_ok());
+    assert_eq!(
+        format!("{}rdma", "!".repeat(100_000)).parse::<Requirement>(),
+        Err(RequirementError::Unexpected { position: 64, found: "!".to_string() })
+    );
+}
+
+#[test]
+fn test_versions() {
+    assert_eq!("2".parse::<Version>().unwrap(), "2.0.0".parse::<Version>().unwrap());
+    assert!("1.10".parse::<Version>().unwrap() > "1.9".parse::<Version>().unwrap());
+
+    let have = capabilities(&["tpm=2.0", "roce=1"]);
+    assert!("tpm = 2 && tpm > 1.2 && tpm <= 2.0.0".parse::<Requirement>().unwrap().matches(&have));
+    assert!(!"tpm < 2 || tpm != 2".parse::<Requirement>().unwrap().matches(&have));
+    // a version comparison needs a version to compare
+    assert!(!"sev >= 1".parse::<Requirement>().unwrap().matches(&capabilities(&["sev"])));
+
+    let mut merged = have.clone();
+    merged.merge(&capabilities(&["tpm=1.2", "roce=2", "rdma"]));
+    assert_eq!(merged, capabilities(&["tpm=2.0", "roce=2", "rdma"]));
+}
+
+#[test]
+fn test_queries_include_links() {
+    let dc = capable_data_centre();
+    assert_eq!(dc.get_compute("n2").unwrap().capabilities(), capabilities(&["avx512", "tpm=2.0", "rdma", "roce=2"]));
+
+    let names = |r: &str| dc.compute_with(&r.parse().unwrap()).iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names("rdma && avx512"), vec!["n2", "n3"]);
+    assert_eq!(names("rdma && tpm >= 2"), vec!["n2"]);
+    assert_eq!(names("!rdma"), vec!["n1"]);
+
+    let mut dc = dc;
+    let mut ib = InterConnect::new("ib0".to_string(), BitsPerSecond::gbps(200), true);
+    ib.add_capability("infiniband".parse().unwrap());
+    dc.add_interconnect(ib).unwrap();
+    let found = dc.interconnects_with(&"infiniband".parse().unwrap());
+    assert_eq!(found.iter().map(|i| i.name.as_str()).collect::<Vec<_>>(), vec!["ib0"]);
+}
+
+#[test]
+fn test_claims_enforce_requirements() {
+    let mut dc = capable_data_centre();
+    let mut a = requiring("tenant-a", "rdma && avx512");
+    a.add_compute("n1");
+    assert_eq!(dc.add_claim(a), Err(TheiaError::Claim(ClaimError::Incapable("n1".to_string()))));
+
+    let mut b = requiring("tenant-b", "rdma && tpm >= 2");
+    b.set_compute_units(30.0);
+    dc.add_claim(b).unwrap();
+    // n1 and n3 have room, but lack the capabilities
+    assert_eq!(dc.get_claim("tenant-b").unwrap().compute.iter().collect::<Vec<_>>(), vec!["n2"]);
+
+    let mut c = requiring("tenant-c", "rdma && tpm >= 2");
+    c.set_compute_units(16.0);
+    assert_eq!(dc.add_claim(c), Err(TheiaError::Claim(ClaimError::InsufficientCapacity("16 compute units".to_string()))));
+}
+
+#[test]
+fn test_inventory() {
+    let yaml = r#"
+data_centres:
+  - name: dc1
+    compute:
+      - name: n1
+        cores: 64
+        core_clock: 2.3GHz
+        ram: 256GiB
+        capabilities: [avx512, "tpm=2.0"]
+        links:
+          - name: eth0
+            speed: 100Gbps
+            capabilities: [rdma, sriov]
+"#;
+    let infra = inventory::parse(yaml, Format::Yaml, 1).unwrap();
+    let dc = infra.get_data_centre("dc1").unwrap();
+    assert_eq!(dc.compute_with(&"rdma && avx512 && tpm >= 2".parse().unwrap()).len(), 1);
+
+    assert!(inventory::parse(&yaml.replace("sriov", "hyperdrive"), Format::Yaml, 1).is_err());
+}
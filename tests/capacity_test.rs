@@ -0,0 +1,180 @@
+mod common;
+
+use common::{allocation, inventory};
+use libtheia::error::TheiaError;
+use libtheia::models::capacity::Capacity;
+use libtheia::models::data_centre::{DataCentre, DiskType};
+use libtheia::models::hierarchy::{Level, Location};
+use libtheia::models::infrastructure::LogicalInfrastructure;
+use libtheia::models::power::Budget;
+use libtheia::models::resource::ClaimError;
+use libtheia::models::units::{BitsPerSecond, Bytes, Hertz, Watts};
+
+/// Two racks in different failure domains with a GPU node each, a storage
+/// resource of two disks and a WAN link.
+const RACKED: &str = r#"
+data_centres:
+  - name: dc1
+    rooms:
+      - name: hall1
+        rows:
+          - name: a
+            racks:
+              - {name: "01", domains: {failure_domains: [fd1]}}
+              - {name: "02", domains: {failure_domains: [fd2]}}
+    compute:
+      - name: n1
+        cores: 16
+        core_clock: 3GHz
+        ram: 128GiB
+        location: hall1/a/01
+        gpus: [{name: a100, clock: 1GHz, ram: 80GiB, bus_type: SXM}]
+        disks: [{name: d0, disk_type: NVMe, capacity: 2TB}]
+      - name: n2
+        cores: 16
+        core_clock: 3GHz
+        ram: 128GiB
+        location: hall1/a/02
+        gpus: [{name: a100, clock: 1GHz, ram: 80GiB, bus_type: SXM}]
+        disks: [{name: d0, disk_type: NVMe, capacity: 2TB}]
+    storage:
+      - name: s1
+        disks:
+          - {name: d0, disk_type: Spinning, capacity: 10TB}
+          - {name: d1, disk_type: Spinning, capacity: 10TB}
+    interconnects:
+      - {name: wan, speed: 100Gbps}
+"#;
+
+fn racked_data_centre(name: &str) -> DataCentre {
+    inventory(&RACKED.replace("dc1", name), name)
+}
+
+#[test]
+fn test_capacity_totals() {
+    let capacity = racked_data_centre("dc1").capacity();
+    assert_eq!(capacity.cores.total, 32);
+    assert_eq!(capacity.core_clock.total, Hertz::ghz(96));
+    assert_eq!(capacity.ram.total, Bytes::gib(256));
+    assert_eq!(capacity.gpus.total, 2);
+    assert_eq!(capacity.gpu_ram.total, Bytes::gib(160));
+    assert_eq!(capacity.disk[&DiskType::NVMe].total, Bytes::tb(4));
+    assert_eq!(capacity.disk[&DiskType::Spinning].total, Bytes::tb(20));
+    assert_eq!(capacity.storage.total, Bytes::tb(20));
+    assert_eq!(capacity.bandwidth.total, BitsPerSecond::gbps(100));
+    assert_eq!(capacity.cores.claimed, 0);
+}
+
+#[test]
+fn test_claims_reduce_free_capacity() {
+    let mut dc = racked_data_centre("dc1");
+    let mut a = allocation("tenant-a");
+    a.add_compute("n1");
+    a.add_storage("s1", Bytes::tb(5));
+    a.add_interconnect("wan", BitsPerSecond::gbps(40));
+    dc.add_claim(a).unwrap();
+
+    let capacity = dc.capacity();
+    assert_eq!((capacity.cores.claimed, capacity.cores.free()), (16, 16));
+    assert_eq!(capacity.gpus.free(), 1);
+    assert_eq!(capacity.disk[&DiskType::NVMe].claimed, Bytes::tb(2));
+    assert_eq!(capacity.storage.free(), Bytes::tb(15));
+    assert_eq!(capacity.disk[&DiskType::Spinning].claimed, Bytes::tb(5));
+    assert_eq!(capacity.bandwidth.free(), BitsPerSecond::gbps(60));
+
+    dc.release_claim("tenant-a").unwrap();
+    assert_eq!(dc.capacity().cores.claimed, 0);
+}
+
+#[test]
+fn test_claims_are_validated() {
+    let mut dc = racked_data_centre("dc1");
+    let mut a = allocation("tenant-a");
+    a.add_compute("n1");
+    dc.add_claim(a).unwrap();
+
+    let mut b = allocation("tenant-b");
+    b.add_compute("n1");
+    assert_eq!(dc.add_claim(b), Err(TheiaError::Claim(ClaimError::AlreadyClaimed("n1".to_string()))));
+
+    let mut c = allocation("tenant-c");
+    c.add_compute("n9");
+    assert_eq!(dc.add_claim(c), Err(TheiaError::Claim(ClaimError::UnknownResource("n9".to_string()))));
+
+    let mut d = allocation("tenant-d");
+    d.add_storage("s1", Bytes::gb(20001));
+    assert_eq!(dc.add_claim(d), Err(TheiaError::Claim(ClaimError::InsufficientCapacity("s1".to_string()))));
+
+    let mut e = allocation("tenant-e");
+    e.add_interconnect("wan", BitsPerSecond::gbps(101));
+    assert_eq!(dc.add_claim(e), Err(TheiaError::Claim(ClaimError::InsufficientCapacity("wan".to_string()))));
+
+    assert!(dc.get_claim("tenant-b").is_none());
+}
+
+#[test]
+fn test_capacity_breakdowns() {
+    let mut dc = racked_data_centre("dc1");
+    let mut a = allocation("tenant-a");
+    a.add_compute("n2");
+    dc.add_claim(a).unwrap();
+
+    let in_rack = dc.capacity_in(&Location::rack("hall1", "a", "01"));
+    assert_eq!((in_rack.cores.total, in_rack.cores.claimed), (16, 0));
+
+    let by_row = dc.capacity_by_location(Level::Row);
+    assert_eq!(by_row.len(), 2);
+    assert_eq!(by_row[&Some(Location::row("hall1", "a"))].cores.claimed, 16);
+    assert_eq!(by_row[&None].storage.total, Bytes::tb(20));
+
+    let by_domain = dc.capacity_by_failure_domain();
+    assert_eq!(by_domain["fd1"].cores.free(), 16);
+    assert_eq!(by_domain["fd2"].cores.free(), 0);
+}
+
+#[test]
+fn test_infrastructure_capacity() {
+    let mut infrastructure = LogicalInfrastructure::new(1);
+    infrastructure.add_data_centre(racked_data_centre("dc1")).unwrap();
+    infrastructure.add_data_centre(racked_data_centre("dc2")).unwrap();
+
+    assert_eq!(infrastructure.capacity().cores.total, 64);
+    assert_eq!(infrastructure.capacity_by_data_centre()["dc2"].ram.total, Bytes::gib(256));
+    assert_eq!(infrastructure.capacity_by_failure_domain()["fd1"].cores.total, 32);
+    let by_rack = infrastructure.capacity_by_location(Level::Rack);
+    assert_eq!(by_rack[&("dc1".to_string(), Some(Location::rack("hall1", "a", "02")))].gpus.total, 1);
+
+    // a data centre without a budget leaves the whole infrastructure unlimited
+    let limit = |kw| Budget::new(Some(Watts::kw(kw)), None);
+    infrastructure.get_data_centre_mut("dc1").unwrap().set_budget(limit(5)).unwrap();
+    assert_eq!(infrastructure.capacity().budget, Budget::default());
+    infrastructure.get_data_centre_mut("dc2").unwrap().set_budget(limit(4)).unwrap();
+    assert_eq!(infrastructure.capacity().budget.power, Some(Watts::kw(9)));
+    let mut unlimited = Capacity::new();
+    unlimited.merge(&Capacity::empty());
+    assert_eq!(unlimited.power_headroom(), None);
+}
+
+#[test]
+fn test_capacity_by_label() {
+    let mut dc = racked_data_centre("dc1");
+    let mut n1 = dc.get_compute("n1").unwrap().clone();
+    n1.set_label("tier", "gold");
+    dc.update_compute(n1).unwrap();
+    let mut s1 = dc.get_storage("s1").unwrap().clone();
+    s1.set_label("tier", "gold");
+    dc.update_storage(s1).unwrap();
+    let mut a = allocation("a");
+    a.add_compute("n1");
+    a.add_storage("s1", Bytes::tb(5));
+    dc.add_claim(a).unwrap();
+
+    let gold = dc.capacity_where(&"tier=gold".parse().unwrap());
+    assert_eq!((gold.cores.total, gold.cores.claimed), (16, 16));
+    assert_eq!(gold.storage.claimed, Bytes::tb(5));
+
+    let by_tier = dc.capacity_by_label("tier");
+    assert_eq!(by_tier.keys().collect::<Vec<_>>(), vec![&None, &Some("gold".to_string())]);
+    assert_eq!(by_tier[&None].cores.free(), 16);
+    assert_eq!(by_tier[&None].storage.total, Bytes(0));
+}
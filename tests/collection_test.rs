@@ -0,0 +1,165 @@
+mod common;
+
+use common::node;
+use libtheia::crdt::{CmRDT, CvRDT};
+use libtheia::error::TheiaError;
+use libtheia::models::collection::{Collection, CollectionError, Named};
+use libtheia::models::data_centre::{Compute, DataCentre, InterConnect, Storage};
+use libtheia::models::infrastructure::LogicalInfrastructure;
+use libtheia::models::units::{BitsPerSecond, Bytes};
+
+#[test]
+fn test_names_are_unique() {
+    let mut c: Collection<Compute> = Collection::new();
+    c.apply(c.add(node("n1", 8), 1).unwrap());
+    assert_eq!(c.add(node("n1", 16), 1), Err(CollectionError::Duplicate("n1".to_string())));
+    assert_eq!(c.update(node("n2", 16), 1), Err(CollectionError::NotFound("n2".to_string())));
+
+    c.apply(c.update(node("n1", 16), 1).unwrap());
+    assert_eq!(c.get("n1").unwrap().cores, 16);
+    assert_eq!(c.len(), 1);
+}
+
+#[test]
+fn test_remove_by_name() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_compute(node("n1", 8)).unwrap();
+    dc.add_compute(node("n2", 8)).unwrap();
+    dc.add_storage(Storage::new("s1".to_string(), None, Bytes::gb(100))).unwrap();
+    dc.add_interconnect(InterConnect::new("ib".to_string(), BitsPerSecond::gbps(200), true)).unwrap();
+
+    dc.remove_compute("n1").unwrap();
+    dc.remove_storage("s1").unwrap();
+    dc.remove_interconnect("ib").unwrap();
+
+    assert_eq!(dc.compute.names().collect::<Vec<_>>(), vec!["n2"]);
+    assert!(dc.storage.is_empty());
+    assert!(dc.interconnects.is_empty());
+    assert_eq!(dc.remove_storage("s1"), Err(TheiaError::Collection(CollectionError::NotFound("s1".to_string()))));
+
+    // the name is free again
+    dc.add_compute(node("n1", 4)).unwrap();
+    assert_eq!(dc.get_compute("n1").unwrap().cores, 4);
+}
+
+#[test]
+fn test_batch_add_is_all_or_nothing() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_compute(node("n3", 8)).unwrap();
+    let batch = vec![node("n1", 8), node("n2", 8), node("n1", 8)];
+    assert_eq!(dc.add_computes(batch), Err(TheiaError::Collection(CollectionError::Duplicate("n1".to_string()))));
+    assert_eq!(dc.add_computes(vec![node("n4", 8), node("n3", 8)]), Err(TheiaError::Collection(CollectionError::Duplicate("n3".to_string()))));
+    assert_eq!(dc.compute.len(), 1);
+}
+
+#[test]
+fn test_concurrent_add_wins_over_remove() {
+    let mut r1: Collection<Compute> = Collection::new();
+    r1.apply(r1.add(node("n1", 8), 1).unwrap());
+    let mut r2 = r1.clone();
+
+    let remove = r1.remove("n1").unwrap();
+    r1.apply(remove.clone());
+    let update = r2.update(node("n1", 16), 2).unwrap();
+    r2.apply(update.clone());
+
+    r1.apply(update);
+    r2.apply(remove);
+    assert_eq!(r1.get("n1").unwrap().cores, 16);
+    assert_eq!(r2.get("n1").unwrap().cores, 16);
+}
+
+#[test]
+fn test_concurrent_adds_resolve_identically() {
+    let mut r1: Collection<Compute> = Collection::new();
+    let mut r2: Collection<Compute> = Collection::new();
+
+    let a1 = r1.add(node("n1", 8), 1).unwrap();
+    let a2 = r2.add(node("n1", 16), 2).unwrap();
+    r1.apply(a1.clone());
+    r2.apply(a2.clone());
+
+    let mut r3 = r2.clone();
+    r1.apply(a2);
+    r2.apply(a1);
+    r3.merge(r1.clone());
+
+    assert_eq!(r1.conflicts("n1").len(), 2);
+    for r in [&r1, &r2, &r3] {
+        assert_eq!(r.get("n1").map(|c| c.cores), Some(16));
+        assert_eq!(r.iter().count(), 1);
+    }
+
+    // an update that observed both resolves the conflict
+    r1.apply(r1.update(node("n1", 32), 1).unwrap());
+    assert_eq!(r1.conflicts("n1").iter().map(|c| c.cores).collect::<Vec<_>>(), vec![32]);
+}
+
+#[test]
+fn test_serde_round_trip() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_computes(vec![node("n1", 8), node("n2", 16)]).unwrap();
+    dc.remove_compute("n1").unwrap();
+
+    let json = serde_json::to_string(&dc).unwrap();
+    let back: DataCentre = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.compute, dc.compute);
+    assert_eq!(back.get_compute("n2").map(Named::name), Some("n2"));
+}
+
+#[test]
+fn test_indexes_follow_merges_and_serde() {
+    let mut a: Collection<Compute> = Collection::new();
+    let mut gpu = node("n1", 8);
+    gpu.set_label("accelerator", "h100");
+    a.apply(a.add(gpu, 1).unwrap());
+    let mut b = a.clone();
+    let mut other = node("n2", 8);
+    other.set_label("accelerator", "h100");
+    b.apply(b.add(other, 2).unwrap());
+    a.merge(b);
+
+    let names = |c: &Collection<Compute>| c.lookup("labels", "accelerator=h100").iter().map(|n| n.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names(&a), vec!["n1", "n2"]);
+    assert_eq!(a.lookup("labels", "accelerator").len(), 2);
+
+    // indexes are not serialized, deserializing declares them again
+    let back: Collection<Compute> = serde_json::from_str(&serde_json::to_string(&a).unwrap()).unwrap();
+    assert_eq!(names(&back), vec!["n1", "n2"]);
+    assert!(back.attributes("labels").contains(&&"accelerator".into()));
+}
+
+#[test]
+fn test_writes_need_an_actor() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_compute(node("n1", 8)).unwrap();
+    let json = serde_json::to_string(&dc).unwrap();
+
+    // the actor is local to a replica, a deserialized one has to be given one
+    let mut replica: DataCentre = serde_json::from_str(&json).unwrap();
+    assert_eq!(replica.add_compute(node("n2", 8)), Err(TheiaError::Collection(CollectionError::NoActor)));
+    assert!(replica.get_compute("n2").is_none());
+    let mut replica = replica.with_actor(2);
+    replica.add_compute(node("n2", 8)).unwrap();
+    assert_eq!(DataCentre::new("dc2".to_string(), 0).add_compute(node("n1", 8)), Err(TheiaError::Collection(CollectionError::NoActor)));
+}
+
+#[test]
+fn test_data_centres_are_written_as_the_replica() {
+    assert_eq!(
+        LogicalInfrastructure::new(0).add_data_centre(DataCentre::new("dc1".to_string(), 0)),
+        Err(TheiaError::Collection(CollectionError::NoActor))
+    );
+
+    // two replicas adding a data centre each at the same time keep both
+    let mut r1 = LogicalInfrastructure::new(1);
+    r1.add_data_centre(DataCentre::new("dc1".to_string(), 1)).unwrap();
+    let mut r2 = r1.clone().with_actor(2);
+    r1.add_data_centre(DataCentre::new("dc2".to_string(), 1)).unwrap();
+    r2.add_data_centre(DataCentre::new("dc3".to_string(), 2)).unwrap();
+    r2.remove_data_centre("dc1").unwrap();
+    r1.merge(r2);
+    let mut names: Vec<_> = r1.data_centres.iter().map(|dc| dc.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["dc2", "dc3"]);
+}
@@ -0,0 +1,195 @@
+use proptest::prelude::*;
+use rand::rngs::StdRng;
+use rand::Rng;
+use libtheia::crdt::{CmRDT, List, Map, VectorClock};
+use libtheia::crdt::index::Attribute;
+use libtheia::crdt::map::Operation;
+use libtheia::crdt::multi_value::MultiValue;
+use libtheia::crdt::testing::{Config, Simulation};
+
+type Actor = u8;
+
+fn config() -> impl Strategy<Value = Config> {
+    (any::<u64>(), 50..300usize, 0.0..0.5f64, 0.0..0.2f64, 0.0..0.3f64).prop_map(
+        |(seed, steps, duplicate_rate, partition_rate, merge_rate)| Config {
+            seed,
+            steps,
+            duplicate_rate,
+            partition_rate,
+            merge_rate,
+            snapshots: 6,
+        },
+    )
+}
+
+fn actors() -> impl Strategy<Value = Vec<Actor>> {
+    (1..6u8).prop_map(|n| (0..n).collect())
+}
+
+type Inner = MultiValue<u8, Actor>;
+
+/// Writes a random value to one of eight keys, or removes a key it sees.
+fn map_operation(map: &Map<u8, Inner, Actor>, actor: &Actor, rng: &mut StdRng) -> Option<Operation<u8, Inner, Actor>> {
+    let key = rng.gen_range(0..8);
+    let read = map.get(&key);
+    if read.value.is_some() && rng.gen_bool(0.3) {
+        Some(map.remove(key, read.derive_remove()))
+    } else {
+        let value = rng.gen();
+        Some(map.update(key, read.derive_add(*actor), |mv, a| mv.write(value, a)))
+    }
+}
+
+fn observe(map: &Map<u8, Inner, Actor>) -> Vec<(u8, Vec<u8>)> {
+    map.iterator()
+        .map(|item| {
+            let mut values = item.value.1.read().value;
+            values.sort();
+            (*item.value.0, values)
+        })
+        .collect()
+}
+
+/// Every key of the index points at an entry holding that attribute and the
+/// other way around.
+fn index_consistent(map: &Map<u8, Inner, Actor>) -> bool {
+    (0..4u8).all(|bucket| {
+        let expected: Vec<u8> = observe(map)
+            .into_iter()
+            .filter(|(_, values)| values.iter().any(|v| v % 4 == bucket))
+            .map(|(k, _)| k)
+            .collect();
+        map.lookup("bucket", Attribute::Int(bucket.into())).into_iter().copied().collect::<Vec<_>>() == expected
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn vector_clock_converges(config in config(), actors in actors()) {
+        let generator = |clock: &VectorClock<Actor>, actor: &Actor, _: &mut StdRng| {
+            Some(clock.increment(*actor))
+        };
+        let result = Simulation::new(VectorClock::new(), actors, generator)
+            .with_config(config)
+            .run();
+        prop_assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn multi_value_converges(config in config(), actors in actors()) {
+        let generator = |mv: &MultiValue<u8, Actor>, actor: &Actor, rng: &mut StdRng| {
+            Some(mv.write(rng.gen(), mv.read().derive_add(*actor)))
+        };
+        let result = Simulation::new(MultiValue::new(), actors, generator)
+            .with_config(config)
+            .run();
+        prop_assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn map_converges(config in config(), actors in actors()) {
+        // removes that were deferred on one replica and applied on another
+        // leave different causal context behind, only the observable
+        // contents have to match
+        let result = Simulation::new(Map::new(), actors, map_operation)
+            .with_config(config)
+            .with_equivalence(|a, b| observe(a) == observe(b))
+            .run();
+        prop_assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn map_converges_with_state_merges(config in config(), actors in actors()) {
+        let result = Simulation::new(Map::new(), actors, map_operation)
+            .with_config(Config { merge_rate: config.merge_rate.max(0.1), ..config })
+            .with_equivalence(|a, b| observe(a) == observe(b))
+            .run();
+        prop_assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn map_index_converges(config in config(), actors in actors()) {
+        let mut initial: Map<u8, Inner, Actor> = Map::new();
+        initial.declare_index("bucket", |mv| {
+            mv.read().value.into_iter().map(|v| Attribute::Int((v % 4).into())).collect()
+        });
+        let result = Simulation::new(initial, actors, map_operation)
+            .with_config(config)
+            .with_equivalence(|a, b| observe(a) == observe(b) && index_consistent(a) && index_consistent(b))
+            .run();
+        prop_assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn map_index_follows_merges(config in config(), actors in actors()) {
+        let mut initial: Map<u8, Inner, Actor> = Map::new();
+        initial.declare_index("bucket", |mv| {
+            mv.read().value.into_iter().map(|v| Attribute::Int((v % 4).into())).collect()
+        });
+        let result = Simulation::new(initial, actors, map_operation)
+            .with_config(Config { merge_rate: config.merge_rate.max(0.1), ..config })
+            .with_equivalence(|a, b| observe(a) == observe(b) && index_consistent(a) && index_consistent(b))
+            .run();
+        prop_assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn list_converges(config in config(), actors in actors()) {
+        let generator = |list: &List<u8, Actor>, actor: &Actor, rng: &mut StdRng| {
+            let index = rng.gen_range(0..list.len() + 1);
+            match rng.gen_range(0..10) {
+                0..=1 if !list.is_empty() => list.delete_index(index.min(list.len() - 1), *actor),
+                2 => list.delete_range(index..index + rng.gen_range(0..4), *actor),
+                3 => {
+                    let run: Vec<u8> = (0..rng.gen_range(0..4)).map(|_| rng.gen()).collect();
+                    Some(list.insert_run(index, run, *actor))
+                }
+                _ => Some(list.insert_index(index, rng.gen(), *actor)),
+            }
+        };
+        let result = Simulation::new(List::new(), actors, generator)
+            .with_config(config)
+            .run();
+        prop_assert!(result.is_ok(), "{:?}", result);
+    }
+}
+
+#[test]
+fn test_harness_detects_divergence() {
+    // a "last writer wins by arrival" register is not a CRDT, the harness must notice
+    #[derive(Debug, Clone, Default, PartialEq)]
+    struct Arrival(u8);
+
+    impl CmRDT for Arrival {
+        type Operation = u8;
+        type Validation = std::convert::Infallible;
+
+        fn validate_apply(&self, _op: &u8) -> Result<(), Self::Validation> {
+            Ok(())
+        }
+
+        fn apply(&mut self, op: u8) {
+            self.0 = op;
+        }
+    }
+
+    impl libtheia::crdt::CvRDT for Arrival {
+        type Validation = std::convert::Infallible;
+
+        fn validate_merge(&self, _other: &Self) -> Result<(), Self::Validation> {
+            Ok(())
+        }
+
+        fn merge(&mut self, other: Self) {
+            self.0 = other.0;
+        }
+    }
+
+    let generator = |_: &Arrival, actor: &Actor, rng: &mut StdRng| Some(actor * 64 + rng.gen_range(0..64));
+    let result = Simulation::new(Arrival::default(), vec![0, 1, 2], generator)
+        .with_config(Config { steps: 500, ..Config::default() })
+        .run();
+    assert!(result.is_err());
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::{allocation, data_centre, node};
+use libtheia::error::TheiaError;
+use libtheia::inventory::{self, Format};
+use libtheia::models::cpu::{Architecture, CPUVendor, Cpu, EquivalenceTable, ScoreError};
+use libtheia::models::data_centre::{Compute, DataCentre};
+use libtheia::models::labels::{Effect, Taint};
+use libtheia::models::lifecycle::State;
+use libtheia::models::resource::ClaimError;
+use libtheia::validate::Validator;
+
+fn table() -> EquivalenceTable {
+    let mut table = EquivalenceTable::new();
+    table.set("haswell", 0.5).unwrap();
+    table.set("icelake", 1.0).unwrap();
+    table.set("zen4", 1.5).unwrap();
+    table
+}
+
+fn cpu_node(name: &str, cores: i32, generation: &str) -> Compute {
+    let mut c = node(name, cores);
+    let vendor = if generation == "zen4" { CPUVendor::Amd } else { CPUVendor::Intel };
+    c.set_cpu(table().resolve(Cpu::new(vendor, Architecture::X86_64, generation.to_string())));
+    c
+}
+
+fn mixed_data_centre() -> DataCentre {
+    // 32, 32, 48 and 96 compute units
+    data_centre(
+        "dc1",
+        [cpu_node("old1", 64, "haswell"), cpu_node("old2", 64, "haswell"), cpu_node("new1", 32, "zen4"), cpu_node("new2", 64, "zen4")],
+    )
+}
+
+#[test]
+fn test_scores_and_conversion() {
+    let table = table();
+    assert_eq!(table.convert(64.0, "haswell", "zen4").map(|c| c.round()), Some(21.0));
+    assert_eq!(table.convert(64.0, "haswell", "skylake"), None);
+
+    let mut own = Cpu::new(CPUVendor::Intel, Architecture::X86_64, "haswell".to_string());
+    own.set_score(0.45).unwrap();
+    assert_eq!(table.resolve(own.clone()).score, Some(0.45));
+    for score in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        assert_eq!(own.set_score(score), Err(ScoreError::Invalid(score.to_string())));
+        assert_eq!(table.clone().set("haswell", score), Err(ScoreError::Invalid(score.to_string())));
+    }
+    assert_eq!(own.score, Some(0.45));
+    assert_eq!(cpu_node("n1", 64, "haswell").compute_units(), 32.0);
+    assert_eq!(cpu_node("n1", 64, "zen4").compute_units(), 96.0);
+    // unknown generations count as reference cores
+    assert_eq!(cpu_node("n1", 64, "power9").compute_units(), 64.0);
+}
+
+#[test]
+fn test_claims_in_compute_units() {
+    let mut dc = mixed_data_centre();
+    let mut a = allocation("tenant-a");
+    a.set_compute_units(40.0);
+    dc.add_claim(a).unwrap();
+    // the smallest resource covering 40 units
+    assert_eq!(dc.get_claim("tenant-a").unwrap().compute.iter().collect::<Vec<_>>(), vec!["new1"]);
+
+    let mut b = allocation("tenant-b");
+    b.set_compute_units(120.0);
+    dc.add_claim(b).unwrap();
+    // the largest first, then the smallest covering the rest
+    assert_eq!(dc.get_claim("tenant-b").unwrap().compute.iter().collect::<Vec<_>>(), vec!["new2", "old1"]);
+
+    let mut c = allocation("tenant-c");
+    c.set_compute_units(40.0);
+    assert_eq!(dc.add_claim(c), Err(TheiaError::Claim(ClaimError::InsufficientCapacity("40 compute units".to_string()))));
+
+    for (name, units) in [("nan", f64::NAN), ("inf", f64::INFINITY), ("zero", 0.0), ("negative", -8.0)] {
+        let mut d = allocation(name);
+        d.set_compute_units(units);
+        assert_eq!(dc.add_claim(d), Err(TheiaError::Claim(ClaimError::InvalidUnits(units.to_string()))));
+    }
+}
+
+#[test]
+fn test_named_resources_count_towards_units() {
+    let mut dc = mixed_data_centre();
+    let mut old = cpu_node("old3", 64, "haswell");
+    old.add_taint(Taint::new("legacy".to_string(), None, Effect::PreferNoSchedule));
+    dc.add_compute(old).unwrap();
+
+    let mut a = allocation("tenant-a");
+    a.add_compute("new1");
+    a.set_compute_units(80.0);
+    dc.add_claim(a).unwrap();
+    // old3 fits as well, but is only taken when nothing else does
+    assert_eq!(dc.get_claim("tenant-a").unwrap().compute.iter().collect::<Vec<_>>(), vec!["new1", "old1"]);
+}
+
+#[test]
+fn test_evacuation_across_generations() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_computes(vec![cpu_node("old1", 64, "haswell"), cpu_node("new1", 24, "zen4"), cpu_node("new2", 16, "zen4")]).unwrap();
+    let mut a = allocation("tenant-a");
+    a.add_compute("old1");
+    dc.add_claim(a).unwrap();
+
+    // 24 zen4 cores (36 units) do the work of 64 haswell cores (32 units)
+    let evacuation = dc.transition_compute("old1", State::Draining, "end of life", Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap()).unwrap();
+    assert_eq!(evacuation.moved, vec![("tenant-a".to_string(), "old1".to_string(), "new1".to_string())]);
+}
+
+#[test]
+fn test_inventory_and_validation() {
+    let yaml = r#"
+generations:
+  haswell: 0.5
+  zen4: 1.5
+data_centres:
+  - name: dc1
+    compute:
+      - name: n1
+        cores: 64
+        core_clock: 2.3GHz
+        ram: 256GiB
+        cpu: {vendor: Intel, architecture: X86_64, generation: haswell}
+      - name: n2
+        cores: 64
+        core_clock: 2.4GHz
+        ram: 256GiB
+        cpu: {vendor: Amd, architecture: X86_64, generation: zen4, score: 1.6}
+"#;
+    let infra = inventory::parse(yaml, Format::Yaml, 1).unwrap();
+    let dc = infra.get_data_centre("dc1").unwrap();
+    assert_eq!(dc.get_compute("n1").unwrap().cpu.score, Some(0.5));
+    assert_eq!(dc.get_compute("n2").unwrap().compute_units(), 102.4);
+
+    let broken = inventory::parse(&yaml.replace("score: 1.6", "score: 0"), Format::Yaml, 1).unwrap();
+    let findings: Vec<String> = Validator::new().validate(&broken).iter().map(|f| f.to_string()).collect();
+    assert_eq!(findings, vec!["error [impossible-values] dc1/n2: cpu score of 0"]);
+    let broken = inventory::parse(&yaml.replace("score: 1.6", "score: .nan"), Format::Yaml, 1).unwrap();
+    let findings: Vec<String> = Validator::new().validate(&broken).iter().map(|f| f.to_string()).collect();
+    assert_eq!(findings, vec!["error [impossible-values] dc1/n2: cpu score of NaN"]);
+}
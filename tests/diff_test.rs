@@ -0,0 +1,114 @@
+use libtheia::diff::{self, Change, Field};
+use libtheia::inventory::{self, Format};
+use libtheia::models::infrastructure::LogicalInfrastructure;
+use libtheia::reconcile::Kind;
+use serde_json::{json, Value};
+
+const BEFORE: &str = r#"
+data_centres:
+  - name: dc1
+    compute:
+      - name: n1
+        cores: 64
+        core_clock: 2.4GHz
+        ram: 512GiB
+        disks:
+          - {name: d0, disk_type: NVMe, capacity: 4TB}
+          - {name: d1, disk_type: NVMe, capacity: 4TB}
+        gpus:
+          - {name: h100, clock: 1980MHz, ram: 80GiB, bus_type: SXM, count: 2}
+        links:
+          - {name: eth0, speed: 100Gbps}
+      - {name: n2, cores: 32, core_clock: 3GHz, ram: 256GiB}
+    storage:
+      - {name: s1, capacity: 10TB}
+    interconnects:
+      - {name: wan, speed: 10Gbps}
+  - name: dc2
+"#;
+
+fn infra(source: &str) -> LogicalInfrastructure {
+    inventory::parse(source, Format::Yaml, 1).unwrap()
+}
+
+fn modified(old: &str, new: &str) -> Vec<Field> {
+    let d = diff::infrastructures(&infra(BEFORE), &infra(&BEFORE.replace(old, new)));
+    match d.changes.as_slice() {
+        [Change::Modified { fields, .. }] => fields.clone(),
+        changes => panic!("{:?}", changes),
+    }
+}
+
+fn field(path: &str, old: Option<Value>, new: Option<Value>) -> Field {
+    Field { path: path.to_string(), old, new }
+}
+
+#[test]
+fn test_identical_snapshots() {
+    assert!(diff::infrastructures(&infra(BEFORE), &infra(BEFORE)).is_empty());
+    let dc = infra(BEFORE).get_data_centre("dc1").cloned().unwrap();
+    assert_eq!(diff::data_centres(&dc, &dc).to_string(), "");
+}
+
+#[test]
+fn test_added_and_removed() {
+    let after = BEFORE
+        .replace("      - {name: s1, capacity: 10TB}\n", "      - {name: s2, capacity: 10TB}\n")
+        .replace("  - name: dc2\n", "  - name: dc3\n");
+    let d = diff::infrastructures(&infra(BEFORE), &infra(&after));
+    assert_eq!(
+        d.changes,
+        vec![
+            Change::DataCentreRemoved { data_centre: "dc2".to_string() },
+            Change::Removed { data_centre: "dc1".to_string(), kind: Kind::Storage, name: "s1".to_string() },
+            Change::Added { data_centre: "dc1".to_string(), kind: Kind::Storage, name: "s2".to_string() },
+            Change::DataCentreAdded { data_centre: "dc3".to_string() },
+        ]
+    );
+    assert_eq!(d.to_string(), "- dc2\n- dc1/storage/s1\n+ dc1/storage/s2\n+ dc3\n");
+}
+
+#[test]
+fn test_nested_resources_by_name() {
+    assert_eq!(
+        modified("{name: d1, disk_type: NVMe, capacity: 4TB}", "{name: d1, disk_type: NVMe, capacity: 8TB}"),
+        vec![field("disks[d1].capacity", Some(json!("4TB")), Some(json!("8TB")))]
+    );
+    assert_eq!(modified("count: 2", "count: 4"), vec![field("gpus[h100].count", Some(json!(2)), Some(json!(4)))]);
+    // a renamed link is a different link
+    let link = |name: &str| json!({"name": name, "speed": "100Gbps", "low_latency": false, "peer": null, "latency_us": null, "labels": {}, "taints": [], "capabilities": []});
+    assert_eq!(
+        modified("{name: eth0, speed: 100Gbps}", "{name: eth1, speed: 100Gbps}"),
+        vec![field("links[eth0]", Some(link("eth0")), None), field("links[eth1]", None, Some(link("eth1")))]
+    );
+}
+
+#[test]
+fn test_human_renderer() {
+    let after = BEFORE
+        .replace("cores: 64", "cores: 96")
+        .replace("ram: 512GiB", "ram: 1TiB")
+        .replace("          - {name: d1, disk_type: NVMe, capacity: 4TB}\n", "")
+        .replace("{name: wan, speed: 10Gbps}", "{name: wan, speed: 10Gbps, peer: dc2}");
+    let d = diff::infrastructures(&infra(BEFORE), &infra(&after));
+    assert_eq!(
+        d.to_string(),
+        "~ dc1/compute/n1\n    ~ cores: 64 -> 96\n    - disks[d1]: {\"capacity\":\"4TB\",\"disk_type\":\"NVMe\",\"iops\":null,\"name\":\"d1\",\"read_speed\":null,\"write_speed\":null}\n    ~ ram: 512GiB -> 1TiB\n~ dc1/interconnect/wan\n    ~ peer: null -> dc2\n"
+    );
+}
+
+#[test]
+fn test_json_renderer() {
+    let d = diff::infrastructures(&infra(BEFORE), &infra(&BEFORE.replace("ram: 256GiB", "ram: 384GiB")));
+    let json: Value = serde_json::from_str(&d.to_json()).unwrap();
+    assert_eq!(
+        json,
+        json!({"changes": [{
+            "change": "modified",
+            "data_centre": "dc1",
+            "kind": "compute",
+            "name": "n2",
+            "fields": [{"path": "ram", "old": "256GiB", "new": "384GiB"}],
+        }]})
+    );
+}
@@ -0,0 +1,154 @@
+use libtheia::crdt::envelope::{Envelope, EnvelopeError, Kind, Opener, Sealer, SecretKey, SigningKey};
+use libtheia::crdt::map::{CmRDTValidation, Operation as MapOperation};
+use libtheia::crdt::multi_value::MultiValue;
+use libtheia::crdt::{CmRDT, List, Map, VersionRange};
+
+const SECRET: &str = "6b86b273ff34fce19d6b804eff5a3f5747ada4eaa22f1d49c01e52ddb7875b4b";
+
+fn pair(actor: u64) -> (Sealer<u64>, Opener<u64>) {
+    let key = SecretKey::from_secret(SECRET);
+    let sealer = Sealer::new(key.clone(), actor, SigningKey::generate());
+    let mut opener = Opener::new(key);
+    opener.trust(actor, sealer.verifying_key());
+    (sealer, opener)
+}
+
+fn tamper(envelope: &Envelope<u64>, f: impl FnOnce(&mut serde_json::Value)) -> Envelope<u64> {
+    let mut json = serde_json::to_value(envelope).unwrap();
+    f(&mut json);
+    serde_json::from_value(json).unwrap()
+}
+
+#[test]
+fn test_list_operations_round_trip() {
+    let (sealer, opener) = pair(1);
+    let mut source: List<String, u64> = List::new();
+    let mut replica = List::new();
+
+    for name in ["node1", "node2"] {
+        let op = source.append(name.to_string(), 1);
+        source.apply(op.clone());
+        opener.apply(&mut replica, &sealer.seal_operation(&op).unwrap()).unwrap();
+    }
+    assert_eq!(replica, source);
+}
+
+#[test]
+fn test_ciphertext_hides_payload() {
+    let (sealer, _) = pair(1);
+    let list: List<String, u64> = List::new();
+    let envelope = sealer.seal_operation(&list.append("very-secret-node".to_string(), 1)).unwrap();
+    assert!(!serde_json::to_string(&envelope).unwrap().contains("very-secret-node"));
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let (sealer, opener) = pair(1);
+    let mut map: Map<String, MultiValue<u8, u64>, u64> = Map::new();
+    map.apply(map.update("dc1".to_string(), map.get(&"dc1".to_string()).derive_add(1), |mv, a| mv.write(4, a)));
+
+    let envelope = sealer.seal_snapshot(&map).unwrap();
+    assert_eq!(envelope.kind, Kind::Snapshot);
+    let opened: Map<String, MultiValue<u8, u64>, u64> = opener.open_snapshot(&envelope).unwrap();
+    assert_eq!(opened, map);
+
+    let mut list: List<u8, u64> = List::new();
+    assert_eq!(opener.apply(&mut list, &envelope), Err(EnvelopeError::WrongKind(Kind::Snapshot)));
+}
+
+#[test]
+fn test_reject_tampered() {
+    let (sealer, opener) = pair(1);
+    let list: List<u8, u64> = List::new();
+    let envelope = sealer.seal_operation(&list.append(1, 1)).unwrap();
+
+    let flipped = tamper(&envelope, |json| {
+        let byte = &mut json["ciphertext"][0];
+        *byte = (byte.as_u64().unwrap() ^ 1).into();
+    });
+    let mut replica: List<u8, u64> = List::new();
+    assert_eq!(opener.apply(&mut replica, &flipped), Err(EnvelopeError::BadSignature));
+
+    let relabeled = tamper(&envelope, |json| json["kind"] = "Snapshot".into());
+    assert_eq!(opener.open_snapshot::<List<u8, u64>>(&relabeled), Err(EnvelopeError::BadSignature));
+    assert!(replica.is_empty());
+}
+
+#[test]
+fn test_reject_forged() {
+    let (_, mut opener) = pair(1);
+    let (other, _) = pair(2);
+    let list: List<u8, u64> = List::new();
+    let mut replica: List<u8, u64> = List::new();
+
+    // unknown replica
+    let envelope = other.seal_operation(&list.append(1, 2)).unwrap();
+    assert_eq!(opener.apply(&mut replica, &envelope), Err(EnvelopeError::UnknownSigner));
+
+    // trusted replica writing as someone else
+    opener.trust(2, other.verifying_key());
+    let envelope = other.seal_operation(&list.append(1, 1)).unwrap();
+    assert_eq!(opener.apply(&mut replica, &envelope), Err(EnvelopeError::AuthorMismatch));
+
+    // replica 2 claiming to be replica 1
+    let envelope = tamper(&other.seal_operation(&list.append(1, 2)).unwrap(), |json| json["actor"] = 1.into());
+    assert_eq!(opener.apply(&mut replica, &envelope), Err(EnvelopeError::BadSignature));
+
+    // sealed under another secret by a trusted signing key
+    let stranger = Sealer::new(SecretKey::from_secret("another secret"), 1, SigningKey::from_bytes(&[7; 32]));
+    opener.trust(1, stranger.verifying_key());
+    let envelope = stranger.seal_operation(&list.append(1, 1)).unwrap();
+    assert_eq!(opener.apply(&mut replica, &envelope), Err(EnvelopeError::Decryption));
+
+    assert!(opener.revoke(&2));
+    assert!(replica.is_empty());
+}
+
+#[test]
+fn test_reject_foreign_batch_updates() {
+    type Op = MapOperation<u8, MultiValue<u8, u64>, u64>;
+    let (sealer, opener) = pair(1);
+    let map: Map<u8, MultiValue<u8, u64>, u64> = Map::new();
+    let batch = |actor: u64| match map.update_all(actor, [(actor as u8, |mv: &MultiValue<u8, u64>, a| mv.write(1, a))]) {
+        Op::Batch { updates } => updates,
+        _ => unreachable!(),
+    };
+
+    let own = Op::Batch { updates: batch(1) };
+    assert!(opener.open_operation::<Op>(&sealer.seal_operation(&own).unwrap()).is_ok());
+
+    // later updates of another actor under the signature of the first
+    let mixed = Op::Batch { updates: [batch(1), batch(2)].concat() };
+    let envelope = sealer.seal_operation(&mixed).unwrap();
+    assert_eq!(
+        opener.open_operation::<Op>(&envelope).err(),
+        Some(EnvelopeError::AuthorMismatch)
+    );
+
+    let empty = Op::Batch { updates: Vec::new() };
+    let envelope = sealer.seal_operation(&empty).unwrap();
+    assert_eq!(
+        opener.open_operation::<Op>(&envelope).err(),
+        Some(EnvelopeError::AuthorMismatch)
+    );
+}
+
+#[test]
+fn test_reject_invalid_operation() {
+    let (sealer, opener) = pair(1);
+    let mut source: Map<u8, MultiValue<u8, u64>, u64> = Map::new();
+    let first = source.update(1, source.get(&1).derive_add(1), |mv, a| mv.write(1, a));
+    source.apply(first);
+    let second = source.update(2, source.get(&2).derive_add(1), |mv, a| mv.write(2, a));
+
+    let mut replica: Map<u8, MultiValue<u8, u64>, u64> = Map::new();
+    let result = opener.apply(&mut replica, &sealer.seal_operation(&second).unwrap());
+    assert_eq!(
+        result,
+        Err(EnvelopeError::Rejected(CmRDTValidation::SourceOrder(VersionRange {
+            actor: 1,
+            counter_range: 1..2
+        })))
+    );
+    assert_eq!(replica, Map::new());
+}
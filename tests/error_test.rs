@@ -0,0 +1,98 @@
+use chrono::{NaiveDate, Utc};
+use libtheia::error::TheiaError;
+use libtheia::inventory::{self, Format, InventoryError};
+use libtheia::models::collection::CollectionError;
+use libtheia::models::data_centre::{Compute, DataCentre};
+use libtheia::models::infrastructure::LogicalInfrastructure;
+use libtheia::models::lifecycle::State;
+use libtheia::models::resource::{Allocation, ClaimError};
+use libtheia::models::tenant::Tenant;
+use libtheia::models::units::{Bytes, Hertz, UnitError};
+
+/// Claim `node` in a data centre loaded from `source`, every failure on the
+/// way surfaces as a `TheiaError`.
+fn claim(source: &str, node: &str) -> Result<DataCentre, TheiaError> {
+    let infra = inventory::parse(source, Format::Yaml, 1)?;
+    let mut dc = infra.get_data_centre("dc1").cloned().ok_or_else(|| CollectionError::NotFound("dc1".to_string()))?;
+    let mut a = Allocation::new("tenant-a".to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), None);
+    a.add_compute(node);
+    dc.add_claim(a)?;
+    Ok(dc)
+}
+
+const YAML: &str = r#"
+data_centres:
+  - name: dc1
+    compute:
+      - {name: n1, cores: 32, core_clock: 2GHz, ram: 256GiB}
+"#;
+
+#[test]
+fn test_module_errors_convert() {
+    assert!(claim(YAML, "n1").is_ok());
+    assert_eq!(claim(YAML, "n9").err(), Some(TheiaError::Claim(ClaimError::UnknownResource("n9".to_string()))));
+    assert!(matches!(claim("data_centres: [", "n1"), Err(TheiaError::Inventory(InventoryError::Parse { .. }))));
+    assert_eq!(
+        claim("data_centres: []", "n1").err(),
+        Some(TheiaError::Collection(CollectionError::NotFound("dc1".to_string())))
+    );
+}
+
+#[test]
+fn test_unit_and_serialization_errors() {
+    let unit: Result<Bytes, TheiaError> = "12 parsecs".parse::<Bytes>().map_err(TheiaError::from);
+    assert!(matches!(unit, Err(TheiaError::Unit(UnitError::UnknownUnit(_)))));
+    let json: Result<DataCentre, TheiaError> = serde_json::from_str("{\"name\": 1}").map_err(TheiaError::from);
+    assert!(matches!(json, Err(TheiaError::Serialization(_))));
+}
+
+#[test]
+fn test_remove_data_centre() {
+    let mut infra = LogicalInfrastructure::new(1);
+    infra.add_data_centre(DataCentre::new("dc1".to_string(), 1)).unwrap();
+    infra.add_data_centre(DataCentre::new("dc2".to_string(), 1)).unwrap();
+    assert_eq!(infra.remove_data_centre("dc1"), Ok(()));
+    assert_eq!(
+        infra.remove_data_centre("dc1"),
+        Err(TheiaError::Collection(CollectionError::NotFound("dc1".to_string())))
+    );
+    assert_eq!(infra.data_centres.iter().map(|dc| dc.name.as_str()).collect::<Vec<_>>(), vec!["dc2"]);
+}
+
+#[test]
+fn test_writes_return_theia_errors() {
+    let mut infra = LogicalInfrastructure::new(1);
+    infra.add_tenant(Tenant::new("acme".to_string())).unwrap();
+    assert_eq!(
+        infra.add_tenant(Tenant::new("acme".to_string())),
+        Err(TheiaError::Collection(CollectionError::Duplicate("acme".to_string())))
+    );
+    assert!(matches!(infra.record_latency("dc1", "dc2", 900), Err(TheiaError::Collection(CollectionError::NotFound(_)))));
+
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_compute(Compute::new("n1".to_string(), 32, Hertz::ghz(2), Bytes::gib(256))).unwrap();
+    let mut a = Allocation::new("tenant-a".to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), None);
+    a.add_compute("n1");
+    dc.add_claim(a).unwrap();
+    let e = dc.transition_compute("n1", State::Decommissioned, "retired", Utc::now()).unwrap_err();
+    assert!(matches!(e, TheiaError::Lifecycle(_)));
+}
+
+#[test]
+fn test_display_and_source() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_compute(Compute::new("n1".to_string(), 32, Hertz::ghz(2), Bytes::gib(256))).unwrap();
+    let e = dc.add_compute(Compute::new("n1".to_string(), 32, Hertz::ghz(2), Bytes::gib(256))).unwrap_err();
+    assert_eq!(e.to_string(), "Collection(Duplicate(\"n1\"))");
+    let boxed: Box<dyn std::error::Error> = Box::new(e);
+    assert!(boxed.to_string().contains("n1"));
+}
+
+#[test]
+fn test_config_does_not_panic() {
+    // without configuration files loading fails, but only through the result
+    if let Err(e) = libtheia::config() {
+        assert!(matches!(e, TheiaError::Config(_)));
+        assert_eq!(libtheia::config().err(), Some(e));
+    }
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use common::{allocation, data_centre, node};
+use libtheia::error::TheiaError;
+use libtheia::models::data_centre::{DataCentre, GPUBusType, GPU};
+use libtheia::models::gpu::{GPULink, GPULinkType, GPUStack, GPUVendor, PartitionProfile};
+use libtheia::models::resource::ClaimError;
+use libtheia::models::units::{BitsPerSecond, Bytes, Hertz};
+
+fn a100() -> GPU {
+    let mut g = GPU::new("a100".to_string(), Hertz::mhz(1410), Bytes::gib(80), GPUBusType::SXM);
+    g.set_model(GPUVendor::Nvidia, "A100-SXM4-80GB".to_string());
+    g.set_stack(GPUStack::CUDA);
+    g.set_count(4);
+    g.set_partitioning(
+        7,
+        vec![
+            PartitionProfile::new("1g.10gb".to_string(), 1, Bytes::gib(10)),
+            PartitionProfile::new("3g.40gb".to_string(), 3, Bytes::gib(40)),
+        ],
+    );
+    g
+}
+
+/// Devices 0-1 and 2-3 are NVLink pairs, all devices share PCIe.
+fn gpu_data_centre() -> DataCentre {
+    let mut c = node("n1", 64);
+    c.add_gpu(a100());
+    for (a, b) in [(0, 1), (2, 3)] {
+        c.add_gpu_link(GPULink::new(a, b, GPULinkType::NVLink, BitsPerSecond::gbps(600)));
+    }
+    for (a, b) in [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)] {
+        c.add_gpu_link(GPULink::new(a, b, GPULinkType::PCIe, BitsPerSecond::gbps(256)));
+    }
+    data_centre("dc1", [c, node("n2", 64)])
+}
+
+#[test]
+fn test_devices_and_links() {
+    let dc = gpu_data_centre();
+    let c = dc.get_compute("n1").unwrap();
+    assert_eq!(c.gpu_count(), 4);
+    assert_eq!(c.gpu_device(3).map(|g| g.model.as_str()), Some("A100-SXM4-80GB"));
+    assert!(c.gpu_device(4).is_none());
+    assert!(c.gpus_connected(&[0, 1], Some(GPULinkType::NVLink)));
+    assert!(!c.gpus_connected(&[0, 1, 2], Some(GPULinkType::NVLink)));
+    assert!(c.gpus_connected(&[0, 1, 2, 3], None));
+
+    assert_eq!(dc.find_gpus("n1", 2, Some(GPULinkType::NVLink)), Some(vec![0, 1]));
+    assert_eq!(dc.find_gpus("n1", 3, Some(GPULinkType::NVLink)), None);
+    assert_eq!(dc.find_gpus("n2", 1, None), None);
+}
+
+#[test]
+fn test_claim_whole_devices() {
+    let mut dc = gpu_data_centre();
+    let mut a = allocation("tenant-a");
+    a.add_gpus("n1", dc.find_gpus("n1", 2, Some(GPULinkType::NVLink)).unwrap());
+    dc.add_claim(a).unwrap();
+
+    assert_eq!(dc.free_gpus("n1"), vec![2, 3]);
+    assert_eq!(dc.find_gpus("n1", 2, Some(GPULinkType::NVLink)), Some(vec![2, 3]));
+
+    let mut b = allocation("tenant-b");
+    b.add_gpus("n1", [1]);
+    assert_eq!(dc.add_claim(b), Err(TheiaError::Claim(ClaimError::AlreadyClaimed("n1/gpu1".to_string()))));
+    let mut b = allocation("tenant-b");
+    b.add_gpus("n1", [7]);
+    assert_eq!(dc.add_claim(b), Err(TheiaError::Claim(ClaimError::UnknownResource("n1/gpu7".to_string()))));
+    let mut b = allocation("tenant-b");
+    b.add_compute("n1");
+    assert_eq!(dc.add_claim(b), Err(TheiaError::Claim(ClaimError::AlreadyClaimed("n1".to_string()))));
+
+    let capacity = dc.capacity();
+    assert_eq!((capacity.gpus.total, capacity.gpus.claimed), (4, 2));
+    assert_eq!(capacity.gpu_ram.free(), Bytes::gib(160));
+    assert_eq!(capacity.cores.claimed, 0);
+}
+
+#[test]
+fn test_claim_partitions() {
+    let mut dc = gpu_data_centre();
+    let mut a = allocation("tenant-a");
+    a.add_partition("n1", 0, "3g.40gb");
+    a.add_partition("n1", 0, "1g.10gb");
+    a.add_partition("n1", 0, "1g.10gb");
+    dc.add_claim(a).unwrap();
+
+    // 5 of 7 slices are taken, two small slices still fit
+    let mut b = allocation("tenant-b");
+    b.add_partition("n1", 0, "3g.40gb");
+    assert_eq!(dc.add_claim(b), Err(TheiaError::Claim(ClaimError::InsufficientCapacity("n1/gpu0".to_string()))));
+    let mut b = allocation("tenant-b");
+    b.add_partition("n1", 0, "1g.10gb");
+    b.add_partition("n1", 0, "1g.10gb");
+    dc.add_claim(b).unwrap();
+
+    let mut c = allocation("tenant-c");
+    c.add_partition("n1", 1, "7g.80gb");
+    assert_eq!(dc.add_claim(c), Err(TheiaError::Claim(ClaimError::UnknownResource("n1/gpu1/7g.80gb".to_string()))));
+    let mut c = allocation("tenant-c");
+    c.add_gpus("n1", [0]);
+    assert_eq!(dc.add_claim(c), Err(TheiaError::Claim(ClaimError::AlreadyClaimed("n1/gpu0".to_string()))));
+
+    assert_eq!(dc.free_gpus("n1"), vec![1, 2, 3]);
+    let capacity = dc.capacity();
+    assert_eq!(capacity.gpus.claimed, 1);
+    assert_eq!(capacity.gpu_ram.claimed, Bytes::gib(80));
+}
+
+#[test]
+fn test_legacy_gpu() {
+    let gpu: GPU = serde_json::from_str(r#"{"name": "mi250", "clock_ghz": 2, "ram_gb": 128, "bus_type": "InfinityFabric"}"#).unwrap();
+    assert_eq!(gpu.bus_type, GPUBusType::OAM);
+    assert_eq!(gpu.vendor, GPUVendor::Unknown);
+    assert_eq!((gpu.count, gpu.compute_slices), (1, 1));
+    assert_eq!(gpu.ram, Bytes::gib(128));
+
+    let gpu: GPU = serde_json::from_str(r#"{"name": "mi100", "clock_ghz": 1, "ram_gb": 32, "bus_type": "ROCm"}"#).unwrap();
+    assert_eq!(gpu.bus_type, GPUBusType::PCIe);
+}
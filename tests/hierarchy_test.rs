@@ -0,0 +1,107 @@
+mod common;
+
+use common::{data_centre, node};
+use libtheia::models::data_centre::{Compute, DataCentre, Storage};
+use libtheia::models::hierarchy::{Chassis, Domains, Level, Location, LocationError, Rack, Room, Row};
+use libtheia::models::units::Bytes;
+
+fn hall() -> Room {
+    let mut room = Room::new("hall1".to_string(), Domains::new(&[], &["building-a"]));
+    for (row_name, feed) in [("a", "feed-a"), ("b", "feed-b")] {
+        let mut row = Row::new(row_name.to_string(), Domains::new(&[feed], &[]));
+        for rack_name in ["01", "02"] {
+            let mut rack = Rack::new(rack_name.to_string(), Domains::new(&[], &[&format!("{}{}", row_name, rack_name)]));
+            rack.add_chassis(Chassis::new("c1".to_string(), Domains::default()));
+            row.add_rack(rack);
+        }
+        room.add_row(row);
+    }
+    room
+}
+
+fn located_data_centre() -> DataCentre {
+    let mut dc = data_centre("dc1", []);
+    dc.add_room(hall()).unwrap();
+    for (name, location) in [
+        ("n1", "hall1/a/01/c1"),
+        ("n2", "hall1/a/02"),
+        ("n3", "hall1/b/01"),
+        ("n4", "hall2/a/01"),
+    ] {
+        let mut c = node(name, 8);
+        c.set_location(location.parse().unwrap());
+        dc.add_compute(c).unwrap();
+    }
+    dc.add_compute(node("n5", 8)).unwrap();
+    let mut s = Storage::new("s1".to_string(), None, Bytes::gb(100));
+    s.set_location(Location::row("hall1", "b"));
+    dc.add_storage(s).unwrap();
+    dc
+}
+
+fn names<'a>(items: impl IntoIterator<Item = &'a Compute>) -> Vec<&'a str> {
+    items.into_iter().map(|c| c.name.as_str()).collect()
+}
+
+#[test]
+fn test_parse_location() {
+    let location: Location = "hall1/a/01".parse().unwrap();
+    assert_eq!(location, Location::rack("hall1", "a", "01"));
+    assert_eq!(location.level(), Level::Rack);
+    assert_eq!(location.get(Level::Row), Some("a"));
+    assert_eq!(location.get(Level::Chassis), None);
+    assert_eq!(location.parent(), Some(Location::row("hall1", "a")));
+    assert_eq!(Location::room("hall1").parent(), None);
+
+    assert_eq!("".parse::<Location>(), Err(LocationError::Empty));
+    assert_eq!("hall1//01".parse::<Location>(), Err(LocationError::EmptySegment(1)));
+    assert_eq!("a/b/c/d/e".parse::<Location>(), Err(LocationError::TooDeep(5)));
+
+    assert_eq!(serde_json::to_string(&location).unwrap(), "\"hall1/a/01\"");
+    assert!(serde_json::from_str::<Location>("\"hall1//01\"").is_err());
+}
+
+#[test]
+fn test_subtree_queries() {
+    let dc = located_data_centre();
+    assert_eq!(names(dc.compute_in(&Location::room("hall1"))), vec!["n1", "n2", "n3"]);
+    assert_eq!(names(dc.compute_in(&Location::row("hall1", "a"))), vec!["n1", "n2"]);
+    assert_eq!(names(dc.compute_in(&Location::chassis("hall1", "a", "01", "c1"))), vec!["n1"]);
+    assert_eq!(dc.storage_in(&Location::room("hall1")).len(), 1);
+    assert!(dc.storage_in(&Location::row("hall1", "a")).is_empty());
+}
+
+#[test]
+fn test_inherited_domains() {
+    let dc = located_data_centre();
+    let domains = dc.domains(&Location::chassis("hall1", "a", "01", "c1")).unwrap();
+    assert_eq!(domains, Domains::new(&["feed-a"], &["building-a", "a01"]));
+    assert_eq!(dc.domains(&Location::rack("hall1", "c", "01")), None);
+
+    assert_eq!(names(dc.compute_in_failure_domain("a01")), vec!["n1"]);
+    assert_eq!(names(dc.compute_in_failure_domain("building-a")), vec!["n1", "n2", "n3"]);
+    assert_eq!(names(dc.compute_on_power_feed("feed-b")), vec!["n3"]);
+    assert_eq!(dc.storage_on_power_feed("feed-b").len(), 1);
+    assert!(dc.storage_in_failure_domain("a01").is_empty());
+}
+
+#[test]
+fn test_unplaced() {
+    let mut dc = located_data_centre();
+    assert_eq!(dc.unplaced(), vec!["n4", "n5"]);
+
+    // removing a rack orphans what was placed in it
+    let mut room = dc.get_room("hall1").unwrap().clone();
+    room.rows[1].racks.retain(|r| r.name != "01");
+    dc.update_room(room).unwrap();
+    assert_eq!(dc.unplaced(), vec!["n3", "n4", "n5"]);
+    assert_eq!(names(dc.compute_in(&Location::row("hall1", "b"))), vec!["n3"]);
+}
+
+#[test]
+fn test_serde_round_trip() {
+    let dc = located_data_centre();
+    let back: DataCentre = serde_json::from_str(&serde_json::to_string(&dc).unwrap()).unwrap();
+    assert_eq!(back.get_room("hall1"), Some(&hall()));
+    assert_eq!(back.get_compute("n1").unwrap().location, Some("hall1/a/01/c1".parse().unwrap()));
+}
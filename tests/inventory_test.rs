@@ -0,0 +1,246 @@
+use std::fs;
+
+use libtheia::inventory::{self, Format, InventoryError};
+use libtheia::models::data_centre::DiskType;
+use libtheia::models::hierarchy::Location;
+use libtheia::models::lifecycle::State;
+use libtheia::models::pool::{PoolError, Redundancy};
+use libtheia::models::units::{BitsPerSecond, Bytes, Hertz};
+
+const YAML: &str = r#"
+data_centres:
+  - name: dc1
+    labels:
+      region: eu-west
+    rooms:
+      - name: hall1
+        domains: {power_feeds: [feed-a], failure_domains: [fd1]}
+        rows:
+          - name: a
+            racks: [{name: "01"}, {name: "02"}]
+    switches:
+      - name: spine
+        location: hall1/a/01
+    compute:
+      - name: n1
+        cores: 64
+        core_clock: 2.4GHz
+        ram: 512GiB
+        location: hall1/a/02
+        labels: {purpose: gpu-training}
+        disks:
+          - {name: d0, disk_type: NVMe, capacity: 4TB}
+        gpus:
+          - {name: h100, clock: 1980MHz, ram: 80GiB, bus_type: SXM, count: 2}
+        gpu_links:
+          - {a: 0, b: 1, link_type: NVLink, bandwidth: 900Gbps}
+        links:
+          - {name: eth0, speed: 100Gbps, peer: spine}
+      - name: n2
+        cores: 32
+        core_clock: 3GHz
+        ram: 256GiB
+        state: Provisioning
+    storage:
+      - name: s1
+        redundancy: !ErasureCoding {data: 4, parity: 2}
+        disks:
+          - {name: d0, disk_type: Spinning, capacity: 12TB}
+          - {name: d1, disk_type: Spinning, capacity: 12TB}
+          - {name: d2, disk_type: Spinning, capacity: 12TB}
+          - {name: d3, disk_type: Spinning, capacity: 12TB}
+          - {name: d4, disk_type: Spinning, capacity: 12TB}
+          - {name: d5, disk_type: Spinning, capacity: 12TB}
+    interconnects:
+      - name: wan
+        speed: 10Gbps
+        peer: dc2
+  - name: dc2
+"#;
+
+const TOML: &str = r#"
+[[data_centres]]
+name = "dc1"
+
+[[data_centres.compute]]
+name = "n1"
+cores = 64
+core_clock = "2.4GHz"
+ram = "512GiB"
+
+[[data_centres.compute.links]]
+name = "eth0"
+speed = "100Gbps"
+peer = "wan"
+
+[[data_centres.interconnects]]
+name = "wan"
+speed = "10Gbps"
+"#;
+
+const JSON: &str = r#"{
+  "data_centres": [
+    {
+      "name": "dc1",
+      "compute": [
+        {
+          "name": "n1",
+          "cores": 64,
+          "core_clock": "2.4GHz",
+          "ram": "512GiB",
+          "links": [{"name": "eth0", "speed": "100Gbps", "peer": "wan"}]
+        }
+      ],
+      "interconnects": [{"name": "wan", "speed": "10Gbps"}]
+    }
+  ]
+}"#;
+
+fn error(source: &str) -> InventoryError {
+    inventory::parse(source, Format::Yaml, 1).err().unwrap()
+}
+
+#[test]
+fn test_yaml_inventory() {
+    let infra = inventory::parse(YAML, Format::Yaml, 1).unwrap();
+    assert_eq!(infra.data_centres.len(), 2);
+    let dc = infra.get_data_centre("dc1").unwrap();
+    assert_eq!(dc.labels.get("region").map(|l| l.value.as_str()), Some("eu-west"));
+
+    let n1 = dc.get_compute("n1").unwrap();
+    assert_eq!((n1.cores, n1.core_clock, n1.ram), (64, Hertz::mhz(2400), Bytes::gib(512)));
+    assert_eq!(n1.gpu_count(), 2);
+    assert_eq!(n1.disks[0].disk_type, DiskType::NVMe);
+    assert_eq!(n1.links[0].speed, BitsPerSecond::gbps(100));
+    assert_eq!(n1.location, Some(Location::rack("hall1", "a", "02")));
+    assert_eq!(n1.labels.get("purpose").map(|v| v.as_str()), Some("gpu-training"));
+    assert_eq!(dc.compute_lifecycle("n2").unwrap().state, State::Provisioning);
+
+    let s1 = dc.get_storage("s1").unwrap();
+    assert_eq!(s1.redundancy, Redundancy::ErasureCoding { data: 4, parity: 2 });
+    assert_eq!(s1.capacity, Bytes::tb(48));
+    assert_eq!(dc.capacity_by_failure_domain()["fd1"].cores.total, 64);
+}
+
+#[test]
+fn test_formats_agree() {
+    let yaml = r#"
+data_centres:
+  - name: dc1
+    compute:
+      - name: n1
+        cores: 64
+        core_clock: 2.4GHz
+        ram: 512GiB
+        links: [{name: eth0, speed: 100Gbps, peer: wan}]
+    interconnects:
+      - {name: wan, speed: 10Gbps}
+"#;
+    let expected = inventory::parse(yaml, Format::Yaml, 1).unwrap();
+    let expected = expected.get_data_centre("dc1").unwrap();
+    for (source, format) in [(TOML, Format::Toml), (JSON, Format::Json)] {
+        let infra = inventory::parse(source, format, 1).unwrap();
+        let dc = infra.get_data_centre("dc1").unwrap();
+        assert_eq!(dc.get_compute("n1"), expected.get_compute("n1"), "{:?}", format);
+        assert_eq!(dc.get_interconnect("wan"), expected.get_interconnect("wan"), "{:?}", format);
+    }
+}
+
+#[test]
+fn test_parse_errors_have_positions() {
+    // errors in a value are reported at the mapping that holds it
+    let bad_unit = YAML.replace("ram: 256GiB", "ram: 256 parsecs");
+    let e = error(&bad_unit);
+    assert!(matches!(e, InventoryError::Parse { .. }), "{:?}", e);
+    assert_eq!((e.line(), e.column()), (Some(30), Some(9)));
+
+    let unknown_field = YAML.replace("cores: 32", "cpus: 32");
+    assert_eq!(error(&unknown_field).line(), Some(31));
+
+    let bad_toml = TOML.replace("cores = 64", "cores = \"many\"");
+    let e = inventory::parse(&bad_toml, Format::Toml, 1).err().unwrap();
+    assert_eq!((e.line(), e.column()), (Some(7), Some(9)));
+
+    let bad_json = JSON.replace("\"10Gbps\"", "\"10Gbps\",");
+    let e = inventory::parse(&bad_json, Format::Json, 1).err().unwrap();
+    assert_eq!((e.line(), e.column()), (Some(14), Some(59)));
+}
+
+#[test]
+fn test_quantities_need_units() {
+    for (from, to) in [
+        ("ram: 256GiB", "ram: 256"),
+        ("ram: 256GiB", "ram: \"256\""),
+        ("capacity: 4TB", "capacity: 4"),
+        ("bandwidth: 900Gbps", "bandwidth: 900"),
+    ] {
+        let e = error(&YAML.replace(from, to));
+        assert!(matches!(&e, InventoryError::Parse { message, .. } if message.contains("MissingUnit")), "{}: {:?}", to, e);
+    }
+}
+
+#[test]
+fn test_nested_entries_refuse_unknown_fields() {
+    for (from, to) in [
+        ("disk_type: NVMe", "disk_type: NVMe, colour: red"),
+        ("bus_type: SXM", "bus_type: SXM, speed: 1Gbps"),
+        ("link_type: NVLink", "link_type: NVLink, lanes: 18"),
+        ("{name: \"02\"}", "{name: \"02\", height: 42}"),
+        ("failure_domains: [fd1]}", "failure_domains: [fd1], cooling: [crac-1]}"),
+    ] {
+        let e = error(&YAML.replace(from, to));
+        assert!(matches!(&e, InventoryError::Parse { message, .. } if message.contains("unknown field")), "{}: {:?}", to, e);
+    }
+}
+
+#[test]
+fn test_reference_errors_name_the_entry() {
+    let unknown_peer = YAML.replace("peer: dc2", "peer: dc3");
+    assert_eq!(
+        error(&unknown_peer),
+        InventoryError::UnknownPeer { name: "wan".to_string(), peer: "dc3".to_string() }
+    );
+
+    let duplicate = YAML.replace("name: n2", "name: n1");
+    assert_eq!(
+        error(&duplicate),
+        InventoryError::Duplicate { name: "n1".to_string() }
+    );
+    let duplicate = YAML.replace("dc2", "dc1");
+    assert_eq!(error(&duplicate), InventoryError::Duplicate { name: "dc1".to_string() });
+
+    let unplaced = YAML.replace("location: hall1/a/02", "location: hall1/b/02");
+    assert_eq!(
+        error(&unplaced),
+        InventoryError::Unplaced { name: "n1".to_string(), location: Location::rack("hall1", "b", "02") }
+    );
+
+    let unknown_device = YAML.replace("{a: 0, b: 1,", "{a: 0, b: 2,");
+    assert_eq!(
+        error(&unknown_device),
+        InventoryError::UnknownDevice { name: "n1".to_string(), device: 2 }
+    );
+
+    let too_few_disks = YAML.replace("data: 4, parity: 2", "data: 6, parity: 2");
+    assert_eq!(
+        error(&too_few_disks),
+        InventoryError::Pool {
+            name: "s1".to_string(),
+            error: PoolError::TooFewDisks { disk_type: DiskType::Spinning, required: 8, found: 6 },
+        }
+    );
+}
+
+#[test]
+fn test_load() {
+    let dir = std::env::temp_dir().join(format!("theia-inventory-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (file, source) in [("dcs.yml", YAML), ("dcs.toml", TOML), ("dcs.json", JSON)] {
+        fs::write(dir.join(file), source).unwrap();
+        assert!(inventory::load(&dir.join(file), 1).unwrap().get_data_centre("dc1").is_some(), "{}", file);
+    }
+    fs::write(dir.join("dcs.ini"), "").unwrap();
+    assert!(matches!(inventory::load(&dir.join("dcs.ini"), 1), Err(InventoryError::UnknownFormat(_))));
+    assert!(matches!(inventory::load(&dir.join("missing.yaml"), 1), Err(InventoryError::Io(_))));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,201 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use common::{allocation, node};
+use libtheia::crdt::CvRDT;
+use libtheia::error::TheiaError;
+use libtheia::models::data_centre::{Compute, DataCentre, InterConnect, Storage};
+use libtheia::models::infrastructure::LogicalInfrastructure;
+use libtheia::models::labels::{Effect, Selector, SelectorError, Taint, Toleration};
+use libtheia::models::lifecycle::State;
+use libtheia::models::resource::ClaimError;
+use libtheia::models::units::{BitsPerSecond, Bytes};
+
+fn labels(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+fn selector(s: &str) -> Selector {
+    s.parse().unwrap()
+}
+
+fn labelled_node(name: &str, pairs: &[(&str, &str)]) -> Compute {
+    let mut c = node(name, 32);
+    for (k, v) in pairs {
+        c.set_label(k, v);
+    }
+    c
+}
+
+fn pci_dss() -> Taint {
+    Taint::new("compliance".to_string(), Some("pci-dss".to_string()), Effect::NoSchedule)
+}
+
+#[test]
+fn test_selector_evaluation() {
+    let l = labels(&[("tier", "gold"), ("zone", "eu-1"), ("gpu-training", "")]);
+    let cases = [
+        ("tier=gold", true),
+        ("tier == silver", false),
+        ("tier != silver", true),
+        ("owner != customer-x", true),
+        ("zone in (eu-1, eu-2)", true),
+        ("zone notin (eu-1)", false),
+        ("owner notin (customer-x)", true),
+        ("gpu-training", true),
+        ("!customer-x-only", true),
+        ("!(tier=gold)", false),
+        ("tier=gold, zone=us-1", false),
+        ("tier=bronze || zone=eu-1 && gpu-training", true),
+        ("(tier=bronze || zone=eu-1) && !gpu-training", false),
+        ("\"tier\" = \"gold\"", true),
+    ];
+    for (s, expected) in cases {
+        assert_eq!(selector(s).matches(&l), expected, "{}", s);
+    }
+}
+
+#[test]
+fn test_selector_parsing() {
+    assert_eq!(
+        selector("a=1 && b in (x, y)"),
+        Selector::And(vec![
+            Selector::Eq("a".to_string(), "1".to_string()),
+            Selector::In("b".to_string(), ["x".to_string(), "y".to_string()].into()),
+        ])
+    );
+    for s in ["a = 1 && (b || !c)", "a notin (\"x y\", z) || b != 2", "!(a = 1 || b)"] {
+        assert_eq!(selector(&selector(s).to_string()), selector(s), "{}", s);
+    }
+
+    assert_eq!("a = ".parse::<Selector>(), Err(SelectorError::UnexpectedEnd));
+    assert_eq!(
+        "a = 1 b".parse::<Selector>(),
+        Err(SelectorError::Unexpected { position: 6, found: "b".to_string() })
+    );
+    assert_eq!(
+        "a in (x y)".parse::<Selector>(),
+        Err(SelectorError::Unexpected { position: 8, found: "y".to_string() })
+    );
+    assert_eq!("a = \"x".parse::<Selector>(), Err(SelectorError::UnterminatedString(4)));
+    assert_eq!(
+        "a & b".parse::<Selector>(),
+        Err(SelectorError::Unexpected { position: 2, found: "&".to_string() })
+    );
+    assert!(format!("{}a{}", "(".repeat(64), ")".repeat(64)).parse::<Selector>().is_ok());
+    assert_eq!(
+        format!("{}a", "(!".repeat(50_000)).parse::<Selector>(),
+        Err(SelectorError::Unexpected { position: 64, found: "(".to_string() })
+    );
+
+    let json = serde_json::to_string(&selector("tier in (gold)")).unwrap();
+    assert_eq!(json, "\"tier in (gold)\"");
+    assert_eq!(serde_json::from_str::<Selector>(&json).unwrap(), selector("tier in (gold)"));
+}
+
+#[test]
+fn test_queries() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_compute(labelled_node("n1", &[("purpose", "gpu-training")])).unwrap();
+    dc.add_compute(labelled_node("n2", &[("purpose", "inference"), ("customer-x-only", "true")])).unwrap();
+    dc.add_compute(labelled_node("n3", &[])).unwrap();
+    let mut s = Storage::new("s1".to_string(), None, Bytes::tb(10));
+    s.set_label("tier", "gold");
+    dc.add_storage(s).unwrap();
+    let mut i = InterConnect::new("wan".to_string(), BitsPerSecond::gbps(100), false);
+    i.set_label("provider", "carrier-a");
+    dc.add_interconnect(i).unwrap();
+
+    let names = |c: Vec<&Compute>| c.into_iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names(dc.select_compute(&selector("purpose"))), vec!["n1", "n2"]);
+    assert_eq!(names(dc.select_compute(&selector("!customer-x-only"))), vec!["n1", "n3"]);
+    assert_eq!(dc.select_storage(&selector("tier=gold")).len(), 1);
+    assert_eq!(dc.select_interconnects(&selector("provider=carrier-b")).len(), 0);
+
+    dc.set_label("region", "eu").unwrap();
+    dc.set_label("region", "eu-west").unwrap();
+    let mut infra = LogicalInfrastructure::new(1);
+    infra.add_data_centre(dc).unwrap();
+    infra.add_data_centre(DataCentre::new("dc2".to_string(), 1)).unwrap();
+    let selected: Vec<_> = infra.select_data_centres(&selector("region in (eu-west)")).into_iter().map(|dc| dc.name.clone()).collect();
+    assert_eq!(selected, vec!["dc1"]);
+}
+
+#[test]
+fn test_claims_respect_selectors_and_taints() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    let mut restricted = labelled_node("n1", &[("purpose", "payments")]);
+    restricted.add_taint(pci_dss());
+    dc.add_compute(restricted).unwrap();
+    dc.add_compute(labelled_node("n2", &[("purpose", "batch")])).unwrap();
+
+    let mut a = allocation("tenant-a");
+    a.add_compute("n1");
+    assert_eq!(dc.add_claim(a.clone()), Err(TheiaError::Claim(ClaimError::Tainted("n1".to_string()))));
+    a.add_toleration(Toleration::new("compliance".to_string(), None));
+    a.set_selector(selector("purpose=batch"));
+    assert_eq!(dc.add_claim(a.clone()), Err(TheiaError::Claim(ClaimError::Unselected("n1".to_string()))));
+    a.set_selector(selector("purpose in (payments, batch)"));
+    dc.add_claim(a).unwrap();
+
+    // a taint on the data centre applies to every claim on it
+    dc.add_taint(Taint::new("maintenance-window".to_string(), None, Effect::NoSchedule)).unwrap();
+    let mut b = allocation("tenant-b");
+    b.add_compute("n2");
+    assert_eq!(dc.add_claim(b.clone()), Err(TheiaError::Claim(ClaimError::Tainted("dc1".to_string()))));
+    dc.remove_taint("maintenance-window").unwrap();
+    dc.add_claim(b).unwrap();
+}
+
+#[test]
+fn test_evacuation_respects_selectors_and_taints() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_compute(labelled_node("n1", &[("zone", "a")])).unwrap();
+    let mut tainted = labelled_node("n2", &[("zone", "a")]);
+    tainted.add_taint(pci_dss());
+    dc.add_compute(tainted).unwrap();
+    let mut avoided = labelled_node("n3", &[("zone", "a")]);
+    avoided.add_taint(Taint::new("spot".to_string(), None, Effect::PreferNoSchedule));
+    dc.add_compute(avoided).unwrap();
+    dc.add_compute(labelled_node("n4", &[("zone", "b")])).unwrap();
+    dc.add_compute(labelled_node("n5", &[("zone", "a")])).unwrap();
+
+    let mut a = allocation("tenant-a");
+    a.add_compute("n1");
+    a.set_selector(selector("zone=a"));
+    dc.add_claim(a).unwrap();
+
+    // n2 is not tolerated, n4 not selected and n3 avoided while n5 is free
+    let evacuation = dc.transition_compute("n1", State::Draining, "rack move", Utc::now()).unwrap();
+    assert_eq!(evacuation.moved, vec![("tenant-a".to_string(), "n1".to_string(), "n5".to_string())]);
+    let evacuation = dc.transition_compute("n5", State::Draining, "rack move", Utc::now()).unwrap();
+    assert_eq!(evacuation.moved, vec![("tenant-a".to_string(), "n5".to_string(), "n3".to_string())]);
+}
+
+#[test]
+fn test_labels_replicate() {
+    let mut a = DataCentre::new("dc1".to_string(), 1).with_actor(1);
+    a.add_compute(labelled_node("n1", &[])).unwrap();
+    let mut b = a.clone().with_actor(2);
+
+    let mut n1 = a.get_compute("n1").cloned().unwrap();
+    n1.set_label("owner", "customer-x");
+    a.update_compute(n1).unwrap();
+    a.set_label("region", "eu").unwrap();
+    b.add_taint(pci_dss()).unwrap();
+
+    a.compute.merge(b.compute.clone());
+    a.labels.merge(b.labels.clone());
+    a.taints.merge(b.taints.clone());
+    b.compute.merge(a.compute.clone());
+    b.labels.merge(a.labels.clone());
+    b.taints.merge(a.taints.clone());
+
+    for dc in [&a, &b] {
+        assert_eq!(dc.get_compute("n1").unwrap().labels, labels(&[("owner", "customer-x")]));
+        assert_eq!(dc.label_map(), labels(&[("region", "eu")]));
+        assert_eq!(dc.taints.get("compliance"), Some(&pci_dss()));
+    }
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use chrono::{DateTime, TimeZone, Utc};
+use common::{allocation, data_centre, node};
+use libtheia::crdt::CvRDT;
+use libtheia::error::TheiaError;
+use libtheia::models::data_centre::{Compute, DataCentre, GPUBusType, Storage, GPU};
+use libtheia::models::gpu::PartitionProfile;
+use libtheia::models::lifecycle::{Lifecycle, LifecycleError, State};
+use libtheia::models::resource::ClaimError;
+use libtheia::models::units::{Bytes, Hertz};
+
+fn at(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 1, hour, 0, 0).unwrap()
+}
+
+fn stocked_data_centre() -> DataCentre {
+    let mut dc = data_centre("dc1", [node("n1", 16), node("n2", 8), node("n3", 32), node("n4", 16)]);
+    for (name, tb) in [("s1", 10), ("s2", 4), ("s3", 20)] {
+        dc.add_storage(Storage::new(name.to_string(), None, Bytes::tb(tb))).unwrap();
+    }
+    dc
+}
+
+#[test]
+fn test_transitions_are_validated() {
+    let mut lifecycle = Lifecycle::default();
+    assert_eq!(lifecycle.state, State::Available);
+
+    lifecycle.transition(State::Draining, "rack move", at(1)).unwrap();
+    lifecycle.transition(State::Maintenance, "rack move", at(2)).unwrap();
+    assert_eq!(
+        lifecycle.transition(State::Reserved, "tenant-a", at(3)),
+        Err(LifecycleError::Invalid { from: State::Maintenance, to: State::Reserved })
+    );
+    lifecycle.transition(State::Decommissioned, "end of life", at(4)).unwrap();
+    assert!(State::Decommissioned.successors().is_empty());
+
+    let history: Vec<_> = lifecycle.history.iter().map(|t| (t.from, t.to, t.at)).collect();
+    assert_eq!(
+        history,
+        vec![
+            (State::Available, State::Draining, at(1)),
+            (State::Draining, State::Maintenance, at(2)),
+            (State::Maintenance, State::Decommissioned, at(4)),
+        ]
+    );
+    assert_eq!(lifecycle.history[2].reason, "end of life");
+}
+
+#[test]
+fn test_only_schedulable_resources_are_claimed() {
+    let mut dc = stocked_data_centre();
+    dc.transition_compute("n1", State::Reserved, "tenant-b", at(1)).unwrap();
+    dc.transition_storage("s1", State::Failed, "controller fault", at(1)).unwrap();
+
+    let mut a = allocation("tenant-a");
+    a.add_compute("n1");
+    assert_eq!(dc.add_claim(a), Err(TheiaError::Claim(ClaimError::Ineligible("n1".to_string()))));
+    let mut a = allocation("tenant-a");
+    a.add_storage("s1", Bytes::tb(1));
+    assert_eq!(dc.add_claim(a), Err(TheiaError::Claim(ClaimError::Ineligible("s1".to_string()))));
+
+    let names: Vec<_> = dc.schedulable_compute().iter().map(|c| c.name.clone()).collect();
+    assert_eq!(names, vec!["n2", "n3", "n4"]);
+    assert_eq!(dc.schedulable_storage().len(), 2);
+}
+
+#[test]
+fn test_draining_evacuates_compute() {
+    let mut dc = stocked_data_centre();
+    for (claim, compute) in [("tenant-a", "n1"), ("tenant-b", "n3")] {
+        let mut a = allocation(claim);
+        a.add_compute(compute);
+        dc.add_claim(a).unwrap();
+    }
+
+    // n4 is the only unclaimed node at least as large as n1
+    let evacuation = dc.transition_compute("n1", State::Draining, "firmware", at(1)).unwrap();
+    assert_eq!(evacuation.moved, vec![("tenant-a".to_string(), "n1".to_string(), "n4".to_string())]);
+    assert!(dc.get_claim("tenant-a").unwrap().compute.contains("n4"));
+
+    // nothing left that fits n3, the claim stays until released
+    let evacuation = dc.transition_compute("n3", State::Draining, "firmware", at(2)).unwrap();
+    assert_eq!(evacuation.stranded, vec![("tenant-b".to_string(), "n3".to_string())]);
+    assert_eq!(
+        dc.transition_compute("n3", State::Maintenance, "firmware", at(3)),
+        Err(TheiaError::Lifecycle(LifecycleError::Claimed("n3".to_string())))
+    );
+    dc.release_claim("tenant-b").unwrap();
+    dc.transition_compute("n3", State::Maintenance, "firmware", at(3)).unwrap();
+    dc.transition_compute("n1", State::Maintenance, "firmware", at(3)).unwrap();
+    assert_eq!(dc.compute_lifecycle("n1").unwrap().history.len(), 2);
+}
+
+#[test]
+fn test_draining_skips_partitioned_gpus() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    for (name, cores) in [("g1", 16), ("g2", 16), ("g3", 32)] {
+        let mut c = node(name, cores);
+        let mut g = GPU::new("a100".to_string(), Hertz::mhz(1410), Bytes::gib(80), GPUBusType::SXM);
+        g.set_partitioning(7, vec![PartitionProfile::new("1g.10gb".to_string(), 1, Bytes::gib(10))]);
+        c.add_gpu(g);
+        dc.add_compute(c).unwrap();
+    }
+    let mut a = allocation("tenant-a");
+    a.add_compute("g1");
+    dc.add_claim(a).unwrap();
+    let mut b = allocation("tenant-b");
+    b.add_partition("g2", 0, "1g.10gb");
+    dc.add_claim(b).unwrap();
+
+    // g2 is the closest fit but a partition of its GPU is claimed
+    let evacuation = dc.transition_compute("g1", State::Draining, "firmware", at(1)).unwrap();
+    assert_eq!(evacuation.moved, vec![("tenant-a".to_string(), "g1".to_string(), "g3".to_string())]);
+    assert_eq!(dc.get_claim("tenant-b").unwrap().gpus.keys().collect::<Vec<_>>(), vec!["g2"]);
+}
+
+#[test]
+fn test_draining_evacuates_storage() {
+    let mut dc = stocked_data_centre();
+    let mut a = allocation("tenant-a");
+    a.add_storage("s1", Bytes::tb(3));
+    dc.add_claim(a).unwrap();
+
+    // s2 is the tightest fit
+    let evacuation = dc.transition_storage("s1", State::Draining, "disk swap", at(1)).unwrap();
+    assert_eq!(evacuation.moved, vec![("tenant-a".to_string(), "s1".to_string(), "s2".to_string())]);
+    assert_eq!(dc.claimed_storage("s1"), Bytes(0));
+    assert_eq!(dc.claimed_storage("s2"), Bytes::tb(3));
+    dc.transition_storage("s1", State::Decommissioned, "disk swap", at(2)).unwrap();
+}
+
+#[test]
+fn test_concurrent_transitions_converge() {
+    let mut r1 = stocked_data_centre().with_actor(1);
+    let mut r2 = r1.clone().with_actor(2);
+
+    r1.transition_compute("n2", State::Maintenance, "bios", at(1)).unwrap();
+    r2.transition_compute("n2", State::Failed, "psu", at(1)).unwrap();
+    // a concurrent spec update does not touch the lifecycle
+    let mut n2 = r2.get_compute("n2").unwrap().clone();
+    n2.set_label("rack", "r7");
+    r2.update_compute(n2).unwrap();
+
+    let (c1, c2) = (r1.compute.clone(), r2.compute.clone());
+    let (l1, l2) = (r1.compute_lifecycles.clone(), r2.compute_lifecycles.clone());
+    r1.compute.merge(c2);
+    r2.compute.merge(c1);
+    r1.compute_lifecycles.merge(l2);
+    r2.compute_lifecycles.merge(l1);
+
+    let state = r1.compute_lifecycle("n2").unwrap().state;
+    assert_eq!(r2.compute_lifecycle("n2").unwrap().state, state);
+    assert_eq!(r1.compute_lifecycles.conflicts("n2").len(), 2);
+    assert_eq!(r1.compute.conflicts("n2").len(), 1);
+    assert_eq!(r1.get_compute("n2").unwrap().labels["rack"], "r7");
+
+    // a transition that observed both resolves the conflict
+    r1.transition_compute("n2", State::Decommissioned, "replaced", at(2)).unwrap();
+    assert_eq!(r1.compute_lifecycles.conflicts("n2").len(), 1);
+}
+
+#[test]
+fn test_lifecycle_defaults_for_existing_inventories() {
+    let compute: Compute = serde_json::from_str(
+        r#"{"name": "n1", "cores": 8, "core_clock": "3GHz", "ram": "64GiB", "disks": [], "links": [], "gpus": []}"#,
+    )
+    .unwrap();
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_compute(compute).unwrap();
+    assert_eq!(dc.compute_lifecycle("n1"), Some(Lifecycle::new(State::Available)));
+    assert_eq!(dc.compute_lifecycle("n9"), None);
+
+    // data centres stored before lifecycles had their own collection
+    let mut stored = serde_json::to_value(&dc).unwrap();
+    stored.as_object_mut().unwrap().remove("compute_lifecycles");
+    let dc: DataCentre = serde_json::from_value(stored).unwrap();
+    assert_eq!(dc.schedulable_compute().len(), 1);
+}
@@ -1,6 +1,6 @@
 use rand::distributions::Alphanumeric;
 use rand::Rng;
-use libtheia::crdt::{ List, CmRDT };
+use libtheia::crdt::{ List, CmRDT, CvRDT, Version };
 
 type SiteId = u32;
 
@@ -54,7 +54,7 @@ fn test_out_of_order_inserts() {
     while let Some(op) = operations.pop() {
         assert!(iterations < (3 * (3 + 1)) / 2);
         iterations += 1;
-        if list2.validate(&op).is_ok() {
+        if list2.validate_apply(&op).is_ok() {
             list2.apply(op)
         } else {
             operations.insert(0, op);
@@ -262,3 +262,100 @@ fn test_deep_inserts() {
     assert_eq!(list.len(), n);
     assert_eq!(Vec::from_iter(list), vec);
 }
+
+#[test]
+fn test_insert_run() {
+    let mut list1 = List::new();
+    let mut list2 = List::new();
+    let o1 = list1.append_run("ad".chars(), 0);
+    list1.apply(o1.clone());
+    let o2 = list1.insert_run(1, "bc".chars(), 0);
+    list1.apply(o2.clone());
+    assert_eq!(list1.read::<String>(), "abcd");
+    assert_eq!(o2.version(), Version::new(0, 2));
+    assert_eq!(o2.ids().iter().map(|id| list1.pos_entry(id)).collect::<Vec<_>>(), vec![Some(1), Some(2)]);
+
+    list2.apply(o1);
+    list2.apply(o2.clone());
+    list2.apply(o2);
+    assert_eq!(list2, list1);
+
+    // a plain insert into the middle of a run
+    list1.apply(list1.insert_index(2, 'x', 1));
+    assert_eq!(list1.read::<String>(), "abxcd");
+}
+
+#[test]
+fn test_concurrent_insert_runs() {
+    let mut list1 = List::new();
+    list1.apply(list1.append_run("ab".chars(), 'A'));
+    let mut list2 = list1.clone();
+
+    let o1 = list1.insert_run(1, "123".chars(), 'A');
+    let o2 = list2.insert_run(1, "xyz".chars(), 'B');
+    list1.apply(o1.clone());
+    list1.apply(o2.clone());
+    list2.apply(o2);
+    list2.apply(o1);
+
+    let read = list1.read::<String>();
+    assert!(read == "a123xyzb" || read == "axyz123b", "runs interleaved: {}", read);
+    assert_eq!(read, list2.read::<String>());
+}
+
+#[test]
+fn test_delete_range() {
+    let mut list = List::new();
+    list.apply(list.append_run("abcdef".chars(), 0));
+
+    assert_eq!(list.delete_range(6.., 0), None);
+    assert_eq!(list.delete_range(2..2, 0), None);
+
+    let op = list.delete_range(1..=2, 0).unwrap();
+    list.apply(op.clone());
+    list.apply(op);
+    assert_eq!(list.read::<String>(), "adef");
+
+    list.apply(list.delete_range(.., 0).unwrap());
+    assert!(list.is_empty());
+}
+
+#[test]
+fn test_delete_range_keeps_concurrent_inserts() {
+    let mut list1 = List::new();
+    list1.apply(list1.append_run("abcd".chars(), 'A'));
+    let mut list2 = list1.clone();
+
+    let insert = list2.insert_index(2, 'x', 'B');
+    list2.apply(insert.clone());
+    let delete = list1.delete_range(0..4, 'A').unwrap();
+    list1.apply(delete.clone());
+
+    list1.apply(insert);
+    list2.apply(delete.clone());
+    assert_eq!(list1.read::<String>(), "x");
+    assert_eq!(list2.read::<String>(), "x");
+
+    let mut list3 = List::new();
+    list3.apply(list3.append_run("abcd".chars(), 'A'));
+    list3.apply(delete);
+    list3.merge(list2.clone());
+    assert_eq!(list3.read::<String>(), "x");
+}
+
+#[test]
+fn test_run_is_compact() {
+    let list: List<u64, u8> = List::new();
+    let values: Vec<u64> = (0..40).collect();
+    let run = serde_json::to_string(&list.append_run(values.clone(), 0)).unwrap();
+
+    let mut single = list.clone();
+    let mut ops = Vec::new();
+    for v in values {
+        let op = single.append(v, 0);
+        single.apply(op.clone());
+        ops.push(op);
+    }
+    let singles = serde_json::to_string(&ops).unwrap();
+    assert!(run.len() * 4 < singles.len(), "{} vs {}", run.len(), singles.len());
+}
@@ -1,6 +1,11 @@
-use libtheia::crdt::{CmRDT, CvRDT, Map, Version};
-use libtheia::crdt::map::Operation as MapOperation;
+use std::collections::BTreeSet;
+use std::ops::Bound;
+use libtheia::crdt::{CmRDT, CvRDT, Map, Reset, VectorClock, Version, VersionRange};
+use libtheia::crdt::base::Remove;
+use libtheia::crdt::index::Attribute;
+use libtheia::crdt::map::{CmRDTValidation, Operation as MapOperation};
 use libtheia::crdt::multi_value::Operation as MultiValueOperation;
+use libtheia::crdt::multi_value::Validation as MultiValueValidation;
 use libtheia::crdt::multi_value::MultiValue;
 
 #[test]
@@ -127,3 +132,202 @@ fn test_reset_remove_semantics() {
     assert_eq!(inner_map.get(&2).value, None);
     assert_eq!(inner_map.len().value, 1);
 }
+
+#[test]
+fn test_remove_covers_context_of_other_keys() {
+    let mut m0: Map<u8, MultiValue<u8, u8>, u8> = Map::new();
+    let mut m1 = m0.clone();
+    let mut m2 = m0.clone();
+
+    let o1 = m2.update(0, m2.get(&0).derive_add(2), |mv, a| mv.write(17, a));
+    m2.apply(o1.clone());
+    m1.apply(o1.clone());
+
+    let o2 = m1.remove(0, m1.get(&0).derive_remove());
+    m1.apply(o2.clone());
+    let o3 = m1.update(0, m1.get(&0).derive_add(1), |mv, a| mv.write(77, a));
+    m1.apply(o3.clone());
+
+    let o4 = m2.update(0, m2.get(&0).derive_add(2), |mv, a| mv.write(39, a));
+    m2.apply(o4.clone());
+
+    let o5 = m1.remove(0, m1.get(&0).derive_remove());
+    m1.apply(o5.clone());
+    m1.apply(o4.clone());
+
+    for op in [o1, o2, o3, o4, o5] {
+        m0.apply(op.clone());
+        m2.apply(op);
+    }
+
+    for m in [&m0, &m1, &m2] {
+        assert_eq!(m.get(&0).value.map(|mv| mv.read().value), Some(vec![39]));
+    }
+}
+
+#[test]
+fn test_validate_remove() {
+    let mut m: Map<u8, MultiValue<u8, u8>, u8> = Map::new();
+    m.apply(m.update(1, m.get(&1).derive_add(1), |mv, a| mv.write(1, a)));
+
+    let remove = m.remove(1, m.get(&1).derive_remove());
+    assert_eq!(m.validate_apply(&remove), Ok(()));
+
+    let empty_keys = MapOperation::Remove { clock: m.read().remove_clock, key_set: BTreeSet::new() };
+    assert_eq!(m.validate_apply(&empty_keys), Err(CmRDTValidation::EmptyKeySet));
+
+    let empty_clock = m.remove(1, m.get(&2).derive_remove());
+    assert_eq!(m.validate_apply(&empty_clock), Err(CmRDTValidation::EmptyClock));
+
+    let unseen = m.remove(1, Remove { clock: Version::new(9, 2).into() });
+    assert_eq!(
+        m.validate_apply(&unseen),
+        Err(CmRDTValidation::RemoveAhead(VersionRange { actor: 9, counter_range: 1..3 }))
+    );
+
+    let mut invalid = VectorClock::new();
+    invalid.versions.insert(9, 0);
+    let zero = m.remove(1, Remove { clock: invalid });
+    assert_eq!(m.validate_apply(&zero), Err(CmRDTValidation::ZeroCounter(9)));
+
+    let ahead = m.remove(1, Remove { clock: Version::new(1, 4).into() });
+    assert_eq!(
+        m.validate_apply(&ahead),
+        Err(CmRDTValidation::RemoveAhead(VersionRange { actor: 1, counter_range: 2..5 }))
+    );
+}
+
+#[test]
+fn test_validate_update_after_other_keys() {
+    let mut m: Map<u8, MultiValue<u8, u8>, u8> = Map::new();
+    let o1 = m.update(1, m.get(&1).derive_add(1), |mv, a| mv.write(1, a));
+    m.apply(o1.clone());
+    let o2 = m.update(2, m.get(&2).derive_add(1), |mv, a| mv.write(2, a));
+    m.apply(o2.clone());
+    let o3 = m.update(1, m.get(&1).derive_add(1), |mv, a| mv.write(3, a));
+
+    let mut replica: Map<u8, MultiValue<u8, u8>, u8> = Map::new();
+    assert_eq!(replica.validate_apply(&o1), Ok(()));
+    replica.apply(o1);
+    assert!(matches!(replica.validate_apply(&o3), Err(CmRDTValidation::SourceOrder(_))));
+    replica.apply(o2);
+    assert_eq!(replica.validate_apply(&o3), Ok(()));
+}
+
+#[test]
+fn test_validate_empty_put() {
+    let m: Map<u8, MultiValue<u8, u8>, u8> = Map::new();
+    let op = MapOperation::Update {
+        version: Version::new(1, 1),
+        key: 1,
+        operation: MultiValueOperation::Put { clock: VectorClock::new(), value: 1 },
+    };
+    assert_eq!(m.validate_apply(&op), Err(CmRDTValidation::Value(MultiValueValidation::EmptyClock)));
+}
+
+fn parity_index(m: &mut Map<u8, MultiValue<u8, u8>, u8>) {
+    m.declare_index("parity", |mv| {
+        mv.read().value.into_iter().map(|v| Attribute::from(v % 2 == 0)).collect()
+    });
+}
+
+#[test]
+fn test_index_follows_apply() {
+    let mut m: Map<u8, MultiValue<u8, u8>, u8> = Map::new();
+    m.apply(m.update(1, m.get(&1).derive_add(1), |mv, a| mv.write(10, a)));
+    parity_index(&mut m);
+    assert_eq!(m.lookup("parity", true), vec![&1]);
+
+    m.apply(m.update(2, m.get(&2).derive_add(1), |mv, a| mv.write(3, a)));
+    m.apply(m.update(1, m.get(&1).derive_add(1), |mv, a| mv.write(11, a)));
+    assert!(m.lookup("parity", true).is_empty());
+    assert_eq!(m.lookup("parity", false), vec![&1, &2]);
+
+    m.apply(m.remove(2, m.get(&2).derive_remove()));
+    assert_eq!(m.lookup("parity", false), vec![&1]);
+    assert!(m.lookup("unknown", false).is_empty());
+}
+
+#[test]
+fn test_index_follows_merge_and_reset() {
+    let mut m1: Map<u8, MultiValue<u8, u8>, u8> = Map::new();
+    let mut m2 = m1.clone();
+    parity_index(&mut m1);
+
+    m2.apply(m2.update(1, m2.get(&1).derive_add(2), |mv, a| mv.write(4, a)));
+    m2.apply(m2.update(2, m2.get(&2).derive_add(2), |mv, a| mv.write(6, a)));
+    m1.merge(m2.clone());
+    assert_eq!(m1.lookup("parity", true), vec![&1, &2]);
+
+    m2.apply(m2.remove(1, m2.get(&1).derive_remove()));
+    m1.merge(m2.clone());
+    assert_eq!(m1.lookup("parity", true), vec![&2]);
+
+    let clock = m1.read().add_clock;
+    m1.reset(&clock);
+    assert!(m1.lookup("parity", true).is_empty());
+}
+
+#[test]
+fn test_index_range() {
+    let mut m: Map<u8, MultiValue<u8, u8>, u8> = Map::new();
+    m.declare_index("value", |mv| mv.read().value.into_iter().map(|v| Attribute::Int(v.into())).collect());
+    m.declare_index("name", |mv| mv.read().value.into_iter().map(|v| Attribute::from(v.to_string())).collect());
+    for (k, v) in [(1, 5), (2, 50), (3, 150), (4, 250)] {
+        m.apply(m.update(k, m.get(&k).derive_add(1), |mv, a| mv.write(v, a)));
+    }
+
+    assert_eq!(m.lookup_range("value", Attribute::Int(50)..Attribute::Int(250)), vec![&2, &3]);
+    assert_eq!(m.lookup_range("value", Attribute::Int(100)..), vec![&3, &4]);
+    assert_eq!(m.lookup_range("value", ..=Attribute::Int(50)), vec![&1, &2]);
+    assert!(m.lookup_range("value", Attribute::Int(250)..Attribute::Int(50)).is_empty());
+    assert!(m.lookup_range("value", Attribute::Int(50)..Attribute::Int(50)).is_empty());
+    assert!(m.lookup_range("value", (Bound::Excluded(Attribute::Int(50)), Bound::Excluded(Attribute::Int(50)))).is_empty());
+    assert_eq!(m.lookup_range("value", Attribute::Int(50)..=Attribute::Int(50)), vec![&2]);
+    assert_eq!(m.lookup("name", "150"), vec![&3]);
+
+    assert!(m.drop_index("name"));
+    assert_eq!(m.index_names().collect::<Vec<_>>(), vec!["value"]);
+}
+
+#[test]
+fn test_index_is_not_replicated() {
+    let mut m: Map<u8, MultiValue<u8, u8>, u8> = Map::new();
+    parity_index(&mut m);
+    m.apply(m.update(1, m.get(&1).derive_add(1), |mv, a| mv.write(2, a)));
+
+    let copy = m.clone();
+    assert_eq!(copy.lookup("parity", true), vec![&1]);
+
+    let decoded: Map<u8, MultiValue<u8, u8>, u8> =
+        serde_json::from_str(&serde_json::to_string(&m).unwrap()).unwrap();
+    assert_eq!(decoded, m);
+    assert_eq!(decoded.index_names().count(), 0);
+}
+
+#[test]
+fn test_batch_updates_one_operation() {
+    let mut m: Map<u8, MultiValue<u8, u8>, u8> = Map::new();
+    m.apply(m.update(0, m.get(&0).derive_add(2), |mv, a| mv.write(0, a)));
+    let batch = m.update_all(1, (1..4u8).map(|k| (k, move |mv: &MultiValue<u8, u8>, a| mv.write(k * 10, a))));
+    assert_eq!(m.validate_apply(&batch), Ok(()));
+
+    let mut replica = m.clone();
+    m.apply(batch.clone());
+    assert_eq!(m.get_value(&3).unwrap().read().value, vec![30]);
+    assert_eq!(m.read().add_clock.get(&1), 3);
+    // applying it again changes nothing
+    replica.apply(batch.clone());
+    replica.apply(batch);
+    assert_eq!(replica, m);
+
+    // a batch is written by a single actor
+    let mut mixed = Vec::new();
+    for actor in [1, 2] {
+        if let MapOperation::Batch { updates } = m.update_all(actor, [(5, |mv: &MultiValue<u8, u8>, a| mv.write(5, a))]) {
+            mixed.extend(updates);
+        }
+    }
+    assert_eq!(m.validate_apply(&MapOperation::Batch { updates: mixed }), Err(CmRDTValidation::InvalidBatch));
+    assert_eq!(m.validate_apply(&MapOperation::Batch { updates: Vec::new() }), Err(CmRDTValidation::InvalidBatch));
+}
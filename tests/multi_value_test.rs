@@ -0,0 +1,20 @@
+use libtheia::crdt::multi_value::{MultiValue, Operation};
+use libtheia::crdt::{CmRDT, CvRDT, Reset, VectorClock, Version};
+
+fn clock(versions: &[(u8, u64)]) -> VectorClock<u8> {
+    versions.iter().map(|(a, c)| Version::new(*a, *c)).collect()
+}
+
+#[test]
+fn test_merge_with_itself_after_reset() {
+    let mut mv: MultiValue<&str, u8> = MultiValue::new();
+    mv.apply(Operation::Put { clock: clock(&[(1, 2), (2, 1)]), value: "x" });
+    mv.apply(Operation::Put { clock: clock(&[(1, 1), (3, 1)]), value: "y" });
+    // neither value is covered by the reset clock, both are held
+    mv.reset(&clock(&[(2, 1), (3, 1)]));
+    assert_eq!(mv.read().value.len(), 2);
+
+    let mut merged = mv.clone();
+    merged.merge(mv.clone());
+    assert_eq!(merged, mv);
+}
@@ -1,5 +1,5 @@
-use redis::{Commands, RedisError};
-use libtheia::models::data_centre::{Compute, DataCentre, InterConnect};
+// use redis::{Commands, RedisError};
+// use libtheia::models::data_centre::{Compute, DataCentre, InterConnect};
 // use crate::common::{setup, teardown};
 
 mod common;
@@ -13,7 +13,7 @@ async fn dc_simple_claim_works() {
     // let redis_instance = s.1;
     // /// test
     // let dc_name = "DC";
-    // let dc = DataCentre::new(dc_name.to_string());
+    // let dc = DataCentre::new(dc_name.to_string(), 1);
     // let connection_string = format!("redis://{}:{}/", redis_instance.host, redis_instance.port);
     // let mut rdc = redis::Client::open(connection_string).unwrap().get_connection().unwrap();
     // let write: Result<String, RedisError> = rdc.set(dc.name.to_string(), serde_json::to_string(&dc).unwrap());
@@ -0,0 +1,113 @@
+use libtheia::models::data_centre::{Disk, DiskType, Storage};
+use libtheia::models::pool::{PoolError, RaidLevel, Redundancy};
+use libtheia::models::units::{Bytes, Iops};
+
+fn disks(t: DiskType, n: usize, tb: u64, iops: Option<u64>) -> Vec<Disk> {
+    (0..n)
+        .map(|i| Disk::new(format!("{:?}{}", t, i), t.clone(), Bytes::tb(tb), None, None, iops.map(Iops)))
+        .collect()
+}
+
+fn pool(d: Vec<Disk>, r: Redundancy) -> Storage {
+    let mut s = Storage::new("s1".to_string(), Some(d), Bytes(0));
+    s.set_redundancy(r);
+    s
+}
+
+#[test]
+fn test_effective_capacity() {
+    let cases = [
+        (Redundancy::None, Bytes::tb(40)),
+        (Redundancy::Replication { factor: 3 }, Bytes(13_333_333_333_333)),
+        (Redundancy::ErasureCoding { data: 8, parity: 2 }, Bytes::tb(32)),
+        (Redundancy::Raid(RaidLevel::Raid0), Bytes::tb(40)),
+        (Redundancy::Raid(RaidLevel::Raid1), Bytes::tb(4)),
+        (Redundancy::Raid(RaidLevel::Raid5), Bytes::tb(36)),
+        (Redundancy::Raid(RaidLevel::Raid6), Bytes::tb(32)),
+        (Redundancy::Raid(RaidLevel::Raid10), Bytes::tb(20)),
+    ];
+    for (r, expected) in cases {
+        let effective = pool(disks(DiskType::Spinning, 10, 4, None), r).effective_capacity().unwrap();
+        assert_eq!(effective, expected, "{:?}", r);
+    }
+
+    // RAID members only contribute the smallest size
+    let mut mixed = disks(DiskType::Spinning, 3, 4, None);
+    mixed.push(Disk::new("big".to_string(), DiskType::Spinning, Bytes::tb(8), None, None, None));
+    assert_eq!(pool(mixed, Redundancy::Raid(RaidLevel::Raid5)).effective_capacity(), Ok(Bytes::tb(12)));
+
+    // without disks the declared capacity stands
+    assert_eq!(Storage::new("s2".to_string(), None, Bytes::tb(5)).effective_capacity(), Ok(Bytes::tb(5)));
+}
+
+#[test]
+fn test_tiers() {
+    let mut d = disks(DiskType::NVMe, 4, 2, Some(500_000));
+    d.extend(disks(DiskType::Spinning, 12, 16, Some(200)));
+    let mut s = pool(d, Redundancy::ErasureCoding { data: 8, parity: 4 });
+    s.set_tier_redundancy(DiskType::NVMe, Redundancy::Replication { factor: 2 });
+
+    let tiers = s.tiers().unwrap();
+    assert_eq!(tiers.len(), 2);
+    assert_eq!(tiers[0].disk_type, DiskType::Spinning);
+    assert_eq!((tiers[0].raw, tiers[0].effective), (Bytes::tb(192), Bytes::tb(128)));
+    assert_eq!(tiers[1].disk_type, DiskType::NVMe);
+    assert_eq!((tiers[1].raw, tiers[1].effective), (Bytes::tb(8), Bytes::tb(4)));
+    assert_eq!(tiers[1].read_iops, Some(Iops(2_000_000)));
+    assert_eq!(tiers[1].write_iops, Some(Iops(1_000_000)));
+
+    assert_eq!(s.effective_capacity(), Ok(Bytes::tb(132)));
+    assert_eq!(s.read_iops(), Some(Iops(2_002_400)));
+    assert_eq!(s.write_iops(), Some(Iops(1_001_600)));
+
+    // one disk without a rating makes IOPS unknown
+    let mut d = disks(DiskType::SolidState, 2, 1, Some(1000));
+    d.extend(disks(DiskType::SolidState, 1, 1, None));
+    assert_eq!(pool(d, Redundancy::None).read_iops(), None);
+}
+
+#[test]
+fn test_write_penalty() {
+    let d = disks(DiskType::SolidState, 6, 1, Some(10_000));
+    let write = |r| pool(d.clone(), r).write_iops();
+    assert_eq!(write(Redundancy::Raid(RaidLevel::Raid0)), Some(Iops(60_000)));
+    assert_eq!(write(Redundancy::Raid(RaidLevel::Raid5)), Some(Iops(15_000)));
+    assert_eq!(write(Redundancy::Raid(RaidLevel::Raid6)), Some(Iops(10_000)));
+    assert_eq!(write(Redundancy::Raid(RaidLevel::Raid10)), Some(Iops(30_000)));
+    assert_eq!(write(Redundancy::Replication { factor: 3 }), Some(Iops(20_000)));
+    assert_eq!(Redundancy::Raid(RaidLevel::Raid6).fault_tolerance(6), 2);
+    assert_eq!(Redundancy::Raid(RaidLevel::Raid1).fault_tolerance(3), 2);
+}
+
+#[test]
+fn test_validation() {
+    let s = pool(disks(DiskType::NVMe, 2, 4, None), Redundancy::Raid(RaidLevel::Raid5));
+    assert_eq!(
+        s.validate(),
+        Err(PoolError::TooFewDisks { disk_type: DiskType::NVMe, required: 3, found: 2 })
+    );
+    let s = pool(disks(DiskType::NVMe, 5, 4, None), Redundancy::Raid(RaidLevel::Raid10));
+    assert_eq!(s.validate(), Err(PoolError::UnpairedDisks { disk_type: DiskType::NVMe, found: 5 }));
+    let s = pool(disks(DiskType::NVMe, 5, 4, None), Redundancy::Replication { factor: 0 });
+    assert_eq!(s.validate(), Err(PoolError::InvalidRedundancy(Redundancy::Replication { factor: 0 })));
+
+    let mut s = pool(disks(DiskType::NVMe, 4, 4, None), Redundancy::Raid(RaidLevel::Raid10));
+    s.capacity = Bytes::tb(8);
+    assert_eq!(s.validate(), Ok(()));
+    s.capacity = Bytes::tb(10);
+    assert_eq!(
+        s.validate(),
+        Err(PoolError::OverDeclared { declared: Bytes::tb(10), effective: Bytes::tb(8) })
+    );
+
+    let s = Storage::from_disks("s3".to_string(), disks(DiskType::Spinning, 3, 10, None), Redundancy::Replication { factor: 3 });
+    assert_eq!(s.map(|s| s.capacity), Ok(Bytes::tb(10)));
+}
+
+#[test]
+fn test_legacy_storage_has_no_redundancy() {
+    let s: Storage = serde_json::from_str(r#"{"name": "s1", "disks": null, "capacity_gb": 100, "links": []}"#).unwrap();
+    assert_eq!(s.redundancy, Redundancy::None);
+    assert!(s.tiers().unwrap().is_empty());
+    assert_eq!(s.validate(), Ok(()));
+}
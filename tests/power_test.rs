@@ -0,0 +1,141 @@
+mod common;
+
+use common::{allocation, inventory};
+use libtheia::error::TheiaError;
+use libtheia::inventory::{self, Format};
+use libtheia::models::data_centre::{DataCentre, Storage};
+use libtheia::models::hierarchy::{Level, Location};
+use libtheia::models::power::{Budget, Power};
+use libtheia::models::resource::{Allocation, ClaimError};
+use libtheia::models::units::{Bytes, Watts};
+
+fn claim(name: &str, compute: &[&str]) -> Allocation {
+    let mut a = allocation(name);
+    for c in compute {
+        a.add_compute(c);
+    }
+    a
+}
+
+/// Two racks of two nodes each drawing 200W idle and 800W at peak, rack 01
+/// can deliver 1.5kW.
+const POWERED: &str = r#"
+data_centres:
+  - name: dc1
+    rooms:
+      - name: hall1
+        rows:
+          - name: a
+            racks: [{name: "01", budget: {power: 1.5kW}}, {name: "02"}]
+    compute:
+      - {name: n1, cores: 32, core_clock: 2GHz, ram: 256GiB, location: hall1/a/01, power: {nominal: 200W, peak: 800W}}
+      - {name: n2, cores: 32, core_clock: 2GHz, ram: 256GiB, location: hall1/a/01, power: {nominal: 200W, peak: 800W}}
+      - {name: n3, cores: 32, core_clock: 2GHz, ram: 256GiB, location: hall1/a/02, power: {nominal: 200W, peak: 800W}}
+      - {name: n4, cores: 32, core_clock: 2GHz, ram: 256GiB, location: hall1/a/02, power: {nominal: 200W, peak: 800W}}
+"#;
+
+fn powered_data_centre() -> DataCentre {
+    inventory(POWERED, "dc1")
+}
+
+#[test]
+fn test_watts() {
+    assert_eq!("12.5kW".parse::<Watts>(), Ok(Watts(12_500)));
+    assert_eq!("750W".parse::<Watts>(), Ok(Watts(750)));
+    assert_eq!(Watts::kw(1_500).to_string(), "1.5MW");
+    assert!(Budget::new(Some(Watts(100)), Some(Watts(90))).allows(Watts(90)));
+    assert!(!Budget::new(Some(Watts(100)), Some(Watts(90))).allows(Watts(95)));
+    assert!(Budget::default().allows(Watts::kw(1_000_000)));
+}
+
+#[test]
+fn test_draw_and_headroom() {
+    let mut dc = powered_data_centre();
+    dc.set_budget(Budget::new(Some(Watts::kw(5)), Some(Watts::kw(3)))).unwrap();
+    let capacity = dc.capacity();
+    assert_eq!(capacity.power_draw, Watts(800));
+    assert_eq!((capacity.power_headroom(), capacity.cooling_headroom()), (Some(Watts(4_200)), Some(Watts(2_200))));
+
+    dc.add_claim(claim("tenant-a", &["n3"])).unwrap();
+    let capacity = dc.capacity();
+    assert_eq!(capacity.power_draw, Watts(1_400));
+    assert_eq!(capacity.cooling_headroom(), Some(Watts(1_600)));
+    assert!(!capacity.over_budget());
+
+    let racks = dc.capacity_by_location(Level::Rack);
+    let rack = &racks[&Some(Location::rack("hall1", "a", "01"))];
+    assert_eq!((rack.power_draw, rack.power_headroom()), (Watts(400), Some(Watts(1_100))));
+    assert_eq!(racks[&Some(Location::rack("hall1", "a", "02"))].power_headroom(), None);
+}
+
+#[test]
+fn test_rack_budget_refuses_claims() {
+    let mut dc = powered_data_centre();
+    dc.add_claim(claim("tenant-a", &["n1"])).unwrap();
+    // n2 at peak would take rack 01 to 1.6kW
+    assert_eq!(
+        dc.add_claim(claim("tenant-b", &["n2"])),
+        Err(TheiaError::Claim(ClaimError::OverBudget("hall1/a/01".to_string())))
+    );
+    // rack 02 has no budget
+    dc.add_claim(claim("tenant-b", &["n3", "n4"])).unwrap();
+
+    let rack = dc.capacity_in(&Location::rack("hall1", "a", "01"));
+    assert_eq!((rack.power_draw, rack.power_headroom()), (Watts(1_000), Some(Watts(500))));
+    dc.release_claim("tenant-a").unwrap();
+    dc.add_claim(claim("tenant-c", &["n2"])).unwrap();
+}
+
+#[test]
+fn test_data_centre_cooling_budget() {
+    let mut dc = powered_data_centre();
+    let mut s = Storage::new("s1".to_string(), None, Bytes::tb(100));
+    s.set_power(Power::new(Watts(300), Watts(500)));
+    dc.add_storage(s).unwrap();
+    dc.set_budget(Budget::new(None, Some(Watts(2_200)))).unwrap();
+
+    dc.add_claim(claim("tenant-a", &["n3"])).unwrap();
+    let mut a = claim("tenant-b", &[]);
+    a.add_storage("s1", Bytes::tb(10));
+    dc.add_claim(a).unwrap();
+    assert_eq!(dc.capacity().power_draw, Watts(1_900));
+
+    // storage already drawing peak does not add to it
+    let mut a = claim("tenant-c", &[]);
+    a.add_storage("s1", Bytes::tb(10));
+    dc.add_claim(a).unwrap();
+    assert_eq!(dc.add_claim(claim("tenant-d", &["n4"])), Err(TheiaError::Claim(ClaimError::OverBudget("dc1".to_string()))));
+}
+
+#[test]
+fn test_inventory() {
+    let yaml = r#"
+data_centres:
+  - name: dc1
+    budget: {power: 20kW, cooling: 18kW}
+    rooms:
+      - name: hall1
+        rows:
+          - name: a
+            racks: [{name: "01", budget: {power: 12kW}}]
+    compute:
+      - name: n1
+        cores: 64
+        core_clock: 2.4GHz
+        ram: 512GiB
+        location: hall1/a/01
+        power: {nominal: 350W, peak: 1.2kW}
+    storage:
+      - name: s1
+        capacity: 100TB
+        power: {nominal: 400W, peak: 600W}
+"#;
+    let infra = inventory::parse(yaml, Format::Yaml, 1).unwrap();
+    let dc = infra.get_data_centre("dc1").unwrap();
+    assert_eq!(dc.budget(), Budget::new(Some(Watts::kw(20)), Some(Watts::kw(18))));
+    assert_eq!(dc.get_compute("n1").unwrap().power, Power::new(Watts(350), Watts(1_200)));
+    assert_eq!(dc.capacity().power_draw, Watts(750));
+    let rack = dc.capacity_in(&Location::rack("hall1", "a", "01"));
+    assert_eq!(rack.budget, Budget::new(Some(Watts::kw(12)), None));
+    assert_eq!(rack.power_headroom(), Some(Watts(11_650)));
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use chrono::NaiveDate;
+use common::inventory;
+use libtheia::models::data_centre::{DataCentre, DiskType, InterConnect};
+use libtheia::models::infrastructure::LogicalInfrastructure;
+use libtheia::models::resource::{Allocation, Claim, UNOWNED};
+use libtheia::models::tenant::Tenant;
+use libtheia::models::units::{BitsPerSecond, Bytes};
+use libtheia::pricing::{PriceList, Rates};
+
+fn date(m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, m, d).unwrap()
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+/// Two nodes with two partitionable H100s each, a storage resource with a
+/// NVMe and a spinning tier and an uplink.
+const PRICED: &str = r#"
+data_centres:
+  - name: dc1
+    compute:
+      - name: n1
+        cores: 16
+        core_clock: 3GHz
+        ram: 128GB
+        gpus:
+          - {name: h100-sxm, clock: 1980MHz, ram: 80GiB, bus_type: SXM, vendor: Nvidia, model: H100, count: 2, compute_slices: 7, profiles: [{name: 1g.10gb, compute_slices: 1, memory: 10GiB}]}
+      - name: n2
+        cores: 16
+        core_clock: 3GHz
+        ram: 128GB
+        gpus:
+          - {name: h100-sxm, clock: 1980MHz, ram: 80GiB, bus_type: SXM, vendor: Nvidia, model: H100, count: 2, compute_slices: 7, profiles: [{name: 1g.10gb, compute_slices: 1, memory: 10GiB}]}
+    storage:
+      - name: s1
+        disks:
+          - {name: d0, disk_type: NVMe, capacity: 1TB}
+          - {name: d1, disk_type: NVMe, capacity: 1TB}
+          - {name: d2, disk_type: Spinning, capacity: 3TB}
+          - {name: d3, disk_type: Spinning, capacity: 3TB}
+    interconnects:
+      - {name: uplink, speed: 400Gbps}
+"#;
+
+fn priced_data_centre(name: &str) -> DataCentre {
+    inventory(&PRICED.replace("dc1", name), name)
+}
+
+fn prices() -> PriceList {
+    let mut rates = Rates::default();
+    rates.set_core(0.02);
+    rates.set_ram_gb(0.005);
+    rates.set_gpu("H100", 2.0);
+    rates.set_disk_tb(DiskType::NVMe, 0.1);
+    rates.set_disk_tb(DiskType::Spinning, 0.02);
+    rates.set_interconnect_gbps(0.5);
+    PriceList::new(rates)
+}
+
+fn between(name: &str, from: NaiveDate, till: Option<NaiveDate>) -> Allocation {
+    Allocation::new(name.to_string(), from, till)
+}
+
+#[test]
+fn test_whole_compute() {
+    let dc = priced_data_centre("dc1");
+    let mut a = between("job-1", date(1, 1), None);
+    a.add_compute("n1");
+    let cost = prices().cost(&dc, &a, date(3, 1), date(3, 2));
+    assert_eq!(cost.hours, 24);
+    assert!(close(cost.cores, 16.0 * 0.02 * 24.0));
+    assert!(close(cost.ram, 128.0 * 0.005 * 24.0));
+    assert!(close(cost.gpus, 2.0 * 2.0 * 24.0));
+    assert!(close(cost.total(), 4.96 * 24.0));
+}
+
+#[test]
+fn test_gpus_storage_and_interconnect() {
+    let dc = priced_data_centre("dc1");
+    let mut a = between("job-1", date(1, 1), None);
+    a.add_gpus("n1", [1]);
+    a.add_partition("n2", 0, "1g.10gb");
+    a.add_storage("s1", Bytes::tb(4));
+    a.add_interconnect("uplink", BitsPerSecond::gbps(10));
+    let cost = prices().cost(&dc, &a, date(1, 1), date(1, 2));
+    assert!(close(cost.gpus, (2.0 + 2.0 / 7.0) * 24.0));
+    // 2TB of NVMe and 6TB of spinning disks average to 0.04 per TB
+    assert!(close(cost.storage, 4.0 * 0.04 * 24.0));
+    assert!(close(cost.interconnect, 5.0 * 24.0));
+    assert_eq!(cost.cores, 0.0);
+}
+
+#[test]
+fn test_data_centre_overrides() {
+    let mut prices = prices();
+    let mut cheaper = Rates::default();
+    cheaper.set_core(0.01);
+    cheaper.set_gpu("H100", 1.5);
+    prices.set_override("dc2", cheaper);
+    let rates = prices.rates("dc2");
+    assert_eq!((rates.core, rates.ram_gb, rates.gpu["H100"]), (Some(0.01), Some(0.005), 1.5));
+    assert_eq!(prices.rates("dc1"), prices.rates);
+
+    let mut a = between("job-1", date(1, 1), None);
+    a.add_compute("n1");
+    let (from, till) = (date(1, 1), date(1, 2));
+    let dc1 = prices.cost(&priced_data_centre("dc1"), &a, from, till);
+    let dc2 = prices.cost(&priced_data_centre("dc2"), &a, from, till);
+    assert!(close(dc1.total() - dc2.total(), (16.0 * 0.01 + 2.0 * 0.5) * 24.0));
+
+    let claim = Claim {
+        compute: vec![priced_data_centre("dc2").get_compute("n1").unwrap().clone()],
+        storage: vec![],
+        network: InterConnect::new("eth0".to_string(), BitsPerSecond::gbps(100), false),
+        from: date(1, 1),
+        till: Some(date(1, 3)),
+    };
+    let cost = prices.claim_cost("dc2", &claim, date(1, 1), date(2, 1));
+    assert_eq!(cost.hours, 48);
+    assert!(close(cost.total(), (16.0 * 0.01 + 128.0 * 0.005 + 2.0 * 1.5 + 100.0 * 0.5) * 48.0));
+}
+
+#[test]
+fn test_windows() {
+    let dc = priced_data_centre("dc1");
+    let mut a = between("job-1", date(1, 20), Some(date(2, 10)));
+    a.add_compute("n1");
+    let prices = prices();
+    assert_eq!(prices.cost(&dc, &a, date(1, 1), date(2, 1)).hours, 12 * 24);
+    assert_eq!(prices.cost(&dc, &a, date(2, 1), date(3, 1)).hours, 9 * 24);
+    assert_eq!(prices.cost(&dc, &a, date(3, 1), date(4, 1)), Default::default());
+
+    // open ended claims run to the end of the period
+    let mut b = between("job-2", date(2, 15), None);
+    b.add_compute("n2");
+    assert_eq!(prices.cost(&dc, &b, date(2, 1), date(3, 1)).hours, 15 * 24);
+}
+
+#[test]
+fn test_monthly_chargeback() {
+    let mut infra = LogicalInfrastructure::new(1);
+    infra.add_data_centre(priced_data_centre("dc1")).unwrap();
+    infra.add_tenant(Tenant::new("acme".to_string())).unwrap();
+    let mut a = between("training", date(1, 1), None);
+    a.set_tenant("acme");
+    a.add_compute("n1");
+    infra.add_claim("dc1", a).unwrap();
+    let mut b = between("scratch", date(2, 28), None);
+    b.set_tenant("acme");
+    b.add_storage("s1", Bytes::tb(5));
+    infra.add_claim("dc1", b).unwrap();
+    let mut c = between("globex, inc", date(1, 1), Some(date(2, 1)));
+    c.add_compute("n2");
+    infra.add_claim("dc1", c).unwrap();
+
+    let chargeback = prices().chargeback(&infra, date(2, 14));
+    assert_eq!(chargeback.month, "2024-02");
+    assert_eq!(chargeback.statements.len(), 1);
+    let acme = chargeback.statement("acme").unwrap();
+    assert!(close(acme.total, 4.96 * 24.0 * 29.0 + 5.0 * 0.04 * 48.0));
+    assert_eq!(
+        chargeback.to_csv(),
+        "month,tenant,data_centre,claim,hours,cores,ram,gpus,storage,interconnect,total\n\
+         2024-02,acme,dc1,scratch,48,0.00,0.00,0.00,9.60,0.00,9.60\n\
+         2024-02,acme,dc1,training,696,222.72,445.44,2784.00,0.00,0.00,3452.16\n"
+    );
+    let json: serde_json::Value = serde_json::from_str(&acme.to_json()).unwrap();
+    assert_eq!(json["lines"][1]["cost"]["hours"], 696);
+
+    let january = prices().chargeback(&infra, date(1, 31));
+    assert_eq!(january.statements.iter().map(|s| s.tenant.as_str()).collect::<Vec<_>>(), vec!["acme", UNOWNED]);
+    assert!(january.to_csv().ends_with("2024-01,unowned,dc1,\"globex, inc\",744,238.08,476.16,2976.00,0.00,0.00,3690.24\n"));
+}
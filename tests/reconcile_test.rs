@@ -0,0 +1,206 @@
+use chrono::{NaiveDate, Utc};
+use libtheia::inventory::{self, Format};
+use libtheia::models::data_centre::Storage;
+use libtheia::models::infrastructure::LogicalInfrastructure;
+use libtheia::models::lifecycle::State;
+use libtheia::models::resource::{Allocation, ClaimError};
+use libtheia::models::tenant::{Quota, Tenant};
+use libtheia::models::units::{BitsPerSecond, Bytes, Watts};
+use libtheia::reconcile::{self, Change, Kind, Plan, ReconcileError};
+
+const LIVE: &str = r#"
+data_centres:
+  - name: dc1
+    labels: {region: eu}
+    compute:
+      - {name: n1, cores: 64, core_clock: 2.4GHz, ram: 512GiB, state: Provisioning}
+      - {name: n2, cores: 32, core_clock: 3GHz, ram: 256GiB}
+    storage:
+      - {name: s1, capacity: 10TB}
+    interconnects:
+      - {name: wan, speed: 10Gbps}
+  - name: dc2
+"#;
+
+fn infra(source: &str) -> LogicalInfrastructure {
+    inventory::parse(source, Format::Yaml, 1).unwrap()
+}
+
+fn claim(infra: &mut LogicalInfrastructure, compute: &str) {
+    let mut a = Allocation::new("tenant-a".to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), None);
+    a.add_compute(compute);
+    infra.get_data_centre_mut("dc1").unwrap().add_claim(a).unwrap();
+}
+
+#[test]
+fn test_plan_diff() {
+    let live = infra(LIVE);
+    let desired = infra(
+        &LIVE
+            .replace("{region: eu}", "{region: eu-west}")
+            .replace("ram: 256GiB", "ram: 512GiB")
+            .replace("      - {name: s1, capacity: 10TB}\n", "      - {name: s2, capacity: 20TB}\n"),
+    );
+    let plan = reconcile::plan(&live, &desired, 1).unwrap();
+    assert_eq!(plan.to_string(), "~ dc1/label/region\n~ dc1/compute/n2\n- dc1/storage/s1\n+ dc1/storage/s2\n");
+    assert_eq!(
+        plan.changes[1],
+        Change::Update { data_centre: "dc1".to_string(), kind: Kind::Compute, name: "n2".to_string() }
+    );
+}
+
+#[test]
+fn test_apply_converges() {
+    let mut live = infra(LIVE);
+    let desired = LIVE.replace("speed: 10Gbps", "speed: 100Gbps").replace(", state: Provisioning", "");
+    let desired = infra(&(desired + "    interconnects: [{name: wan, speed: 10Gbps}]\n"));
+    let plan = reconcile::plan(&live, &desired, 1).unwrap();
+    assert_eq!(plan.to_string(), "~ dc1/interconnect/wan\n+ dc2/interconnect/wan\n");
+    plan.apply(&mut live);
+
+    let dc1 = live.get_data_centre("dc1").unwrap();
+    assert_eq!(dc1.get_interconnect("wan").unwrap().speed, BitsPerSecond::gbps(100));
+    // lifecycle is live state and is not reset by the inventory
+    assert_eq!(dc1.compute_lifecycle("n1").unwrap().state, State::Provisioning);
+    assert!(live.get_data_centre("dc2").unwrap().get_interconnect("wan").is_some());
+    assert!(reconcile::plan(&live, &desired, 1).unwrap().is_empty());
+}
+
+#[test]
+fn test_data_centres() {
+    let mut live = infra(LIVE);
+    let desired = infra(&LIVE.replace("  - name: dc2\n", "  - name: dc3\n"));
+    let plan = reconcile::plan(&live, &desired, 1).unwrap();
+    assert_eq!(plan.changes, vec![Change::RemoveDataCentre("dc2".to_string()), Change::AddDataCentre("dc3".to_string())]);
+    plan.apply(&mut live);
+    let names: Vec<_> = live.data_centres.iter().map(|dc| dc.name.clone()).collect();
+    assert_eq!(names, vec!["dc1", "dc3"]);
+}
+
+#[test]
+fn test_plans_replicate() {
+    let mut r1 = infra(LIVE).with_actor(1);
+    let mut r2 = r1.clone().with_actor(2);
+    let desired = infra(&(LIVE.to_string() + "  - name: dc3\n    compute: [{name: n9, cores: 8, core_clock: 2GHz, ram: 64GiB}]\n"));
+
+    let plan = reconcile::plan(&r1, &desired, 1).unwrap();
+    assert_eq!(plan.to_string(), "+ dc3\n+ dc3/compute/n9\n");
+    let sent: Plan = serde_json::from_str(&serde_json::to_string(&plan).unwrap()).unwrap();
+    plan.apply(&mut r1);
+    sent.apply(&mut r2);
+    for r in [&r1, &r2] {
+        assert!(r.get_data_centre("dc3").unwrap().get_compute("n9").is_some());
+    }
+
+    // the contents of a data centre replicate through merges as well
+    r2.get_data_centre_mut("dc3").unwrap().add_storage(Storage::new("s9".to_string(), None, Bytes::tb(1))).unwrap();
+    r1.merge(r2);
+    assert!(r1.get_data_centre("dc3").unwrap().get_storage("s9").is_some());
+    assert_eq!(reconcile::plan(&r1, &desired, 1).unwrap().to_string(), "- dc3/storage/s9\n");
+}
+
+#[test]
+fn test_renames_carry_claims() {
+    let mut live = infra(LIVE);
+    claim(&mut live, "n2");
+    live.get_data_centre_mut("dc1").unwrap().transition_compute("n2", State::Reserved, "tenant-b", Utc::now()).unwrap();
+    let desired = infra(&LIVE.replace("name: n2", "name: n3").replace("name: s1", "name: s-1"));
+    let plan = reconcile::plan(&live, &desired, 1).unwrap();
+    assert_eq!(plan.to_string(), "> dc1/compute/n2 -> n3\n> dc1/storage/s1 -> s-1\n");
+    plan.apply(&mut live);
+
+    let dc1 = live.get_data_centre("dc1").unwrap();
+    assert!(dc1.get_compute("n2").is_none());
+    assert!(dc1.claims.get("tenant-a").unwrap().compute.contains("n3"));
+    assert_eq!(dc1.compute_lifecycle("n3").unwrap().state, State::Reserved);
+    assert!(dc1.compute_lifecycles.get("n2").is_none());
+    assert_eq!(dc1.get_storage("s-1").unwrap().capacity, Bytes::tb(10));
+    assert!(reconcile::plan(&live, &desired, 1).unwrap().is_empty());
+}
+
+#[test]
+fn test_renamed_claims_keep_quotas() {
+    let mut live = infra(LIVE);
+    let mut acme = Tenant::new("acme".to_string());
+    let mut quota = Quota::new();
+    quota.set_cores(32);
+    acme.quotas.set_total(quota);
+    live.add_tenant(acme.clone()).unwrap();
+    let mut a = Allocation::new("a".to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), None);
+    a.set_tenant("acme");
+    a.add_compute("n2");
+    live.add_claim("dc1", a).unwrap();
+    let desired = infra(&LIVE.replace("name: n2", "name: n3"));
+    assert!(reconcile::plan(&live, &desired, 1).is_ok());
+
+    // a quota lowered below what the claim holds refuses the rewritten claim
+    quota.set_cores(16);
+    acme.quotas.set_total(quota);
+    live.update_tenant(acme).unwrap();
+    assert!(matches!(
+        reconcile::plan(&live, &desired, 1).err(),
+        Some(ReconcileError::Claim { data_centre, name, error: ClaimError::OverQuota(_) }) if data_centre == "dc1" && name == "a"
+    ));
+}
+
+#[test]
+fn test_settings_site_links_and_linked_renames() {
+    const SITES: &str = r#"
+data_centres:
+  - name: dc1
+    coordinates: {latitude: 47.4, longitude: 8.5}
+    budget: {power: 20kW}
+    compute:
+      - {name: n1, cores: 64, core_clock: 2.4GHz, ram: 512GiB, links: [{name: eth0, speed: 10Gbps, peer: wan}]}
+    interconnects:
+      - {name: wan, speed: 10Gbps}
+  - name: dc2
+site_links:
+  - {a: dc1, b: dc2, bandwidth: 10Gbps}
+"#;
+    let mut live = infra(SITES);
+    claim(&mut live, "n1");
+    live.record_latency("dc1", "dc2", 900).unwrap();
+    let desired = infra(
+        &SITES
+            .replace("power: 20kW", "power: 25kW")
+            .replace("longitude: 8.5", "longitude: 8.6")
+            .replace("bandwidth: 10Gbps", "bandwidth: 40Gbps")
+            .replace("name: n1", "name: n2")
+            .replace("wan", "wan2"),
+    );
+    let plan = reconcile::plan(&live, &desired, 1).unwrap();
+    assert_eq!(
+        plan.to_string(),
+        "~ dc1/setting/budget\n~ dc1/setting/coordinates\n> dc1/compute/n1 -> n2\n> dc1/interconnect/wan -> wan2\n~ site_link/dc1<->dc2\n"
+    );
+    plan.apply(&mut live);
+
+    let dc1 = live.get_data_centre("dc1").unwrap();
+    assert_eq!(dc1.budget().power, Some(Watts::kw(25)));
+    assert_eq!(dc1.coordinates().map(|c| c.longitude), Some(8.6));
+    assert_eq!(dc1.get_compute("n2").unwrap().links[0].peer.as_deref(), Some("wan2"));
+    let link = live.get_site_link("dc1", "dc2").unwrap();
+    assert_eq!((link.bandwidth, link.measured_latency_us), (BitsPerSecond::gbps(40), Some(900)));
+    assert!(reconcile::plan(&live, &desired, 1).unwrap().is_empty());
+}
+
+#[test]
+fn test_claimed_removals_refused() {
+    let mut live = infra(LIVE);
+    claim(&mut live, "n2");
+    let without_n2 = infra(&LIVE.replace("      - {name: n2, cores: 32, core_clock: 3GHz, ram: 256GiB}\n", ""));
+    assert_eq!(
+        reconcile::plan(&live, &without_n2, 1).err(),
+        Some(ReconcileError::Claimed { data_centre: "dc1".to_string(), name: "n2".to_string() })
+    );
+
+    let without_dc1 = infra("data_centres: [{name: dc2}]");
+    assert_eq!(
+        reconcile::plan(&live, &without_dc1, 1).err(),
+        Some(ReconcileError::Claimed { data_centre: "dc1".to_string(), name: "dc1".to_string() })
+    );
+    // an unclaimed resource can go
+    let without_n1 = infra(&LIVE.replace("      - {name: n1, cores: 64, core_clock: 2.4GHz, ram: 512GiB, state: Provisioning}\n", ""));
+    assert_eq!(reconcile::plan(&live, &without_n1, 1).unwrap().to_string(), "- dc1/compute/n1\n");
+}
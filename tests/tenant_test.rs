@@ -0,0 +1,249 @@
+mod common;
+
+use chrono::{NaiveDate, Utc};
+use common::{allocation, inventory};
+use libtheia::crdt::CvRDT;
+use libtheia::error::TheiaError;
+use libtheia::models::collection::CollectionError;
+use libtheia::models::data_centre::DataCentre;
+use libtheia::models::infrastructure::LogicalInfrastructure;
+use libtheia::models::lifecycle::State;
+use libtheia::models::resource::{Allocation, ClaimError, UNOWNED};
+use libtheia::models::tenant::{Contact, Project, Quota, ResourceClass, Tenant, Usage};
+use libtheia::models::units::Bytes;
+
+/// Three 32 core nodes, a 64 core node with four H100s and 100TB of storage.
+const SHARED: &str = r#"
+data_centres:
+  - name: dc1
+    compute:
+      - {name: n1, cores: 32, core_clock: 2GHz, ram: 256GiB}
+      - {name: n2, cores: 32, core_clock: 2GHz, ram: 256GiB}
+      - {name: n3, cores: 32, core_clock: 2GHz, ram: 256GiB}
+      - name: g1
+        cores: 64
+        core_clock: 2GHz
+        ram: 1TiB
+        gpus: [{name: h100, clock: 1980MHz, ram: 80GiB, bus_type: SXM, count: 4}]
+    storage:
+      - {name: s1, capacity: 100TB}
+"#;
+
+fn shared_data_centre(name: &str) -> DataCentre {
+    inventory(&SHARED.replace("dc1", name), name)
+}
+
+fn cores(n: u64) -> Quota {
+    let mut q = Quota::new();
+    q.set_cores(n);
+    q
+}
+
+fn tenant() -> Tenant {
+    let mut t = Tenant::new("acme".to_string());
+    let mut owner = Contact::new("Ops".to_string(), "ops@acme.example".to_string());
+    owner.set_role("owner");
+    t.add_contact(owner);
+    t.set_metadata("cost-centre", "4711");
+    let mut ml = Project::new("ml".to_string());
+    ml.quotas.set_data_centre("dc1", cores(64));
+    t.add_project(ml);
+    t.add_project(Project::new("web".to_string()));
+    t.quotas.set_total(cores(128));
+    t
+}
+
+fn infra() -> LogicalInfrastructure {
+    let mut infra = LogicalInfrastructure::new(1);
+    infra.add_data_centre(shared_data_centre("dc1")).unwrap();
+    infra.add_data_centre(shared_data_centre("dc2")).unwrap();
+    infra.add_tenant(tenant()).unwrap();
+    infra
+}
+
+fn claim(name: &str, project: Option<&str>, nodes: &[&str]) -> Allocation {
+    let mut a = allocation(name);
+    a.set_tenant("acme");
+    if let Some(p) = project {
+        a.set_project(p);
+    }
+    for n in nodes {
+        a.add_compute(n);
+    }
+    a
+}
+
+#[test]
+fn test_tenants_replicate() {
+    let mut a = infra().with_actor(1);
+    let mut b = a.clone().with_actor(2);
+    let mut t = tenant();
+    t.set_metadata("cost-centre", "4712");
+    a.update_tenant(t).unwrap();
+    b.add_tenant(Tenant::new("globex".to_string())).unwrap();
+    a.tenants.merge(b.tenants.clone());
+    b.tenants.merge(a.tenants.clone());
+
+    for infra in [&a, &b] {
+        let acme = infra.get_tenant("acme").unwrap();
+        assert_eq!(acme.metadata["cost-centre"], "4712");
+        assert_eq!(acme.contacts[0].role.as_deref(), Some("owner"));
+        assert_eq!(acme.projects.keys().collect::<Vec<_>>(), vec!["ml", "web"]);
+        assert!(infra.get_tenant("globex").is_some());
+    }
+
+    let json = serde_json::to_string(&a).unwrap();
+    let back: LogicalInfrastructure = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.get_tenant("acme"), a.get_tenant("acme"));
+    a.remove_tenant("globex").unwrap();
+    assert_eq!(a.remove_tenant("globex"), Err(TheiaError::Collection(CollectionError::NotFound("globex".to_string()))));
+}
+
+#[test]
+fn test_tenant_quota_over_data_centres() {
+    let mut infra = infra();
+    infra.add_claim("dc1", claim("a", None, &["g1"])).unwrap();
+    infra.add_claim("dc2", claim("b", None, &["n1", "n2"])).unwrap();
+    assert_eq!(
+        infra.add_claim("dc2", claim("c", None, &["n3"])),
+        Err(TheiaError::Claim(ClaimError::OverQuota("acme: cores".to_string())))
+    );
+    // refused claims are not made
+    assert!(infra.get_data_centre("dc2").unwrap().get_claim("c").is_none());
+
+    // once released there is room again
+    infra.get_data_centre_mut("dc2").unwrap().release_claim("b").unwrap();
+    infra.add_claim("dc2", claim("c", None, &["n3"])).unwrap();
+}
+
+#[test]
+fn test_project_quota_per_data_centre() {
+    let mut infra = infra();
+    infra.add_claim("dc1", claim("a", Some("ml"), &["n1", "n2"])).unwrap();
+    assert_eq!(
+        infra.add_claim("dc1", claim("b", Some("ml"), &["n3"])),
+        Err(TheiaError::Claim(ClaimError::OverQuota("acme/ml in dc1: cores".to_string())))
+    );
+    // the quota of ml applies in dc1 only, and not to other projects
+    infra.add_claim("dc2", claim("b", Some("ml"), &["n3"])).unwrap();
+    infra.add_claim("dc1", claim("c", Some("web"), &["n3"])).unwrap();
+
+    assert_eq!(
+        infra.add_claim("dc1", claim("d", Some("hpc"), &[])),
+        Err(TheiaError::Claim(ClaimError::UnknownOwner("acme/hpc".to_string())))
+    );
+    let mut stranger = claim("e", None, &["n1"]);
+    stranger.set_tenant("initech");
+    assert_eq!(infra.add_claim("dc2", stranger), Err(TheiaError::Claim(ClaimError::UnknownOwner("initech".to_string()))));
+    // claims without a tenant of their own are not limited
+    let unowned = Allocation::new("f".to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), None);
+    infra.add_claim("dc2", unowned).unwrap();
+    assert_eq!(infra.add_claim("dc3", claim("g", None, &[])), Err(TheiaError::Collection(CollectionError::NotFound("dc3".to_string()))));
+}
+
+#[test]
+fn test_usage_per_resource_class() {
+    let mut infra = infra();
+    let mut a = claim("a", Some("ml"), &[]);
+    a.add_gpus("g1", [0, 1]);
+    a.add_storage("s1", Bytes::tb(10));
+    infra.add_claim("dc1", a).unwrap();
+    infra.add_claim("dc2", claim("b", Some("web"), &["g1"])).unwrap();
+
+    let dc1 = infra.get_data_centre("dc1").unwrap();
+    assert_eq!(Usage::of(dc1, dc1.get_claim("a").unwrap()).gpus, 2);
+    let usage = infra.usage("acme", None, None);
+    assert_eq!((usage.cores, usage.ram, usage.gpus, usage.storage), (64, Bytes::gib(1024), 6, Bytes::tb(10)));
+    assert_eq!(infra.usage("acme", Some("ml"), None).gpus, 2);
+    assert_eq!(infra.usage("acme", None, Some("dc2")).gpus, 4);
+    assert_eq!(infra.usage("globex", None, None), Usage::new());
+
+    let mut t = tenant();
+    let mut gpus = Quota::new();
+    gpus.set_gpus(3);
+    t.quotas.set_data_centre("dc1", gpus);
+    infra.update_tenant(t).unwrap();
+    let mut more = claim("c", Some("ml"), &[]);
+    more.add_gpus("g1", [2]);
+    infra.add_claim("dc1", more).unwrap();
+    let mut most = claim("d", Some("web"), &[]);
+    most.add_gpus("g1", [3]);
+    assert_eq!(infra.add_claim("dc1", most), Err(TheiaError::Claim(ClaimError::OverQuota("acme in dc1: gpus".to_string()))));
+}
+
+#[test]
+fn test_quota_usage_queries() {
+    let mut infra = infra();
+    infra.add_claim("dc1", claim("a", Some("ml"), &["n1"])).unwrap();
+    infra.add_claim("dc2", claim("b", None, &["g1"])).unwrap();
+
+    let quotas = infra.quota_usage("acme");
+    let scopes: Vec<(&str, Option<&str>)> = quotas.iter().map(|q| (q.owner.as_str(), q.data_centre.as_deref())).collect();
+    assert_eq!(scopes, vec![("acme", None), ("acme/ml", None), ("acme/ml", Some("dc1")), ("acme/web", None)]);
+    assert_eq!(quotas[0].used.cores, 96);
+    assert_eq!(quotas[0].remaining().cores, Some(32));
+    assert_eq!(quotas[2].remaining(), cores(32));
+    assert!(quotas.iter().all(|q| q.exceeded().is_empty()));
+
+    // lowering a quota below what is held shows up as exceeded
+    let mut t = tenant();
+    t.quotas.set_total(cores(64));
+    infra.update_tenant(t).unwrap();
+    let quotas = infra.quota_usage("acme");
+    assert_eq!(quotas[0].exceeded(), vec![ResourceClass::Cores]);
+    assert_eq!(quotas[0].remaining().cores, Some(0));
+    assert!(infra.quota_usage("globex").is_empty());
+}
+
+#[test]
+fn test_moves_are_admitted_against_quotas() {
+    let mut infra = infra();
+    infra.add_claim("dc1", claim("a", Some("ml"), &["n1"])).unwrap();
+    let evacuation = infra.transition_compute("dc1", "n1", State::Draining, "firmware update", Utc::now()).unwrap();
+    assert_eq!(evacuation.moved, vec![("a".to_string(), "n1".to_string(), "n2".to_string())]);
+    assert_eq!(infra.usage("acme", Some("ml"), Some("dc1")).cores, 32);
+
+    // below what the claim holds in dc1 it moves nowhere, even to a resource of the same size
+    let mut t = tenant();
+    let mut ml = Project::new("ml".to_string());
+    ml.quotas.set_data_centre("dc1", cores(16));
+    t.add_project(ml);
+    infra.update_tenant(t).unwrap();
+    let evacuation = infra.transition_compute("dc1", "n2", State::Draining, "firmware update", Utc::now()).unwrap();
+    assert_eq!(evacuation.stranded, vec![("a".to_string(), "n2".to_string())]);
+    assert!(infra.get_data_centre("dc1").unwrap().get_claim("a").unwrap().compute.contains("n2"));
+}
+
+#[test]
+fn test_unowned_is_reserved() {
+    let mut infra = infra();
+    assert_eq!(
+        infra.add_tenant(Tenant::new(UNOWNED.to_string())),
+        Err(TheiaError::Collection(CollectionError::Reserved(UNOWNED.to_string())))
+    );
+}
+
+#[test]
+fn test_every_claim_write_keeps_quotas() {
+    // a data centre on its own does not know the quotas of tenants
+    let mut dc = shared_data_centre("dc3");
+    assert_eq!(dc.add_claim(claim("a", None, &["n1"])), Err(TheiaError::Claim(ClaimError::UnknownOwner("acme".to_string()))));
+
+    let mut infra = infra();
+    infra.add_claim("dc1", claim("a", None, &["n1"])).unwrap();
+    for (name, node) in [("b", "n2"), ("c", "n3")] {
+        let mut a = allocation(name);
+        a.add_compute(node);
+        infra.add_claim("dc1", a).unwrap();
+    }
+    let dc1 = infra.get_data_centre_mut("dc1").unwrap();
+    assert_eq!(dc1.get_claim("b").unwrap().tenant(), UNOWNED);
+
+    // only the larger g1 is free, a claim of a tenant does not grow by moving
+    let evacuation = dc1.transition_compute("n1", State::Draining, "firmware update", Utc::now()).unwrap();
+    assert_eq!(evacuation.stranded, vec![("a".to_string(), "n1".to_string())]);
+    let evacuation = dc1.transition_compute("n2", State::Draining, "firmware update", Utc::now()).unwrap();
+    assert_eq!(evacuation.moved, vec![("b".to_string(), "n2".to_string(), "g1".to_string())]);
+    assert_eq!(infra.usage("acme", None, None).cores, 32);
+    assert_eq!(infra.usage(UNOWNED, None, None), Usage::new());
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::{data_centre, node};
+use libtheia::models::data_centre::{DataCentre, InterConnect, Storage, Switch};
+use libtheia::models::infrastructure::LogicalInfrastructure;
+use libtheia::models::topology::{Node, Topology};
+use libtheia::models::units::{BitsPerSecond, Bytes};
+use libtheia::models::wan::SiteLink;
+
+fn link(name: &str, speed: u64, peer: &str, latency: Option<i32>) -> InterConnect {
+    let mut l = InterConnect::new(name.to_string(), BitsPerSecond::gbps(speed), false);
+    l.set_peer(peer.to_string());
+    if let Some(us) = latency {
+        l.set_latency(us);
+    }
+    l
+}
+
+/// two racks behind a spine, the spine uplinks to the DC gateway
+fn switched_data_centre(name: &str) -> DataCentre {
+    let mut dc = data_centre(name, []);
+    let mut spine = Switch::new("spine".to_string());
+    spine.add_link(link("up", 400, name, Some(5)));
+    dc.add_switch(spine).unwrap();
+    for (tor, uplink) in [("tor1", 100), ("tor2", 40)] {
+        let mut s = Switch::new(tor.to_string());
+        s.add_link(link("up", uplink, "spine", Some(2)));
+        dc.add_switch(s).unwrap();
+    }
+    for (name, tor) in [("n1", "tor1"), ("n2", "tor1"), ("n3", "tor2")] {
+        let mut c = node(name, 8);
+        c.add_link(link("eth0", 25, tor, Some(1)));
+        dc.add_compute(c).unwrap();
+    }
+    let mut s = Storage::new("s1".to_string(), None, Bytes::gb(100));
+    s.add_link(link("eth0", 100, "tor2", Some(1)));
+    dc.add_storage(s).unwrap();
+    dc
+}
+
+#[test]
+fn test_paths_within_data_centre() {
+    let t = Topology::from_data_centre(&switched_data_centre("dc1"));
+    let (n1, n2, n3) = (Node::compute("dc1", "n1"), Node::compute("dc1", "n2"), Node::compute("dc1", "n3"));
+    let s1 = Node::storage("dc1", "s1");
+
+    assert_eq!(t.hops(&n1, &n2), Some(2));
+    assert_eq!(t.hops(&n1, &n3), Some(4));
+    assert_eq!(t.hops(&n1, &n1), Some(0));
+    assert_eq!(t.bottleneck(&n1, &s1), Some(BitsPerSecond::gbps(25)));
+    assert_eq!(t.bottleneck(&Node::switch("dc1", "tor1"), &s1), Some(BitsPerSecond::gbps(40)));
+    assert_eq!(t.bottleneck(&n1, &n1), None);
+    assert_eq!(t.latency_us(&n1, &s1), Some(6));
+    assert!(t.supports(&n1, &n1, BitsPerSecond::gbps(1000)));
+    assert!(t.supports(&n1, &n3, BitsPerSecond::gbps(25)));
+    assert!(!t.supports(&n1, &s1, BitsPerSecond::gbps(40)));
+    assert!(t.dangling().is_empty());
+}
+
+#[test]
+fn test_widest_path_prefers_capacity_over_hops() {
+    let mut dc = switched_data_centre("dc1");
+    // a slow direct link between the racks next to the fast path via the spine
+    let mut tor1 = dc.get_switch("tor1").unwrap().clone();
+    tor1.add_link(link("cross", 10, "tor2", None));
+    dc.update_switch(tor1).unwrap();
+
+    let t = Topology::from_data_centre(&dc);
+    let (tor1, s1) = (Node::switch("dc1", "tor1"), Node::storage("dc1", "s1"));
+    assert_eq!(t.hops(&tor1, &s1), Some(2));
+    assert_eq!(t.bottleneck(&tor1, &s1), Some(BitsPerSecond::gbps(40)));
+}
+
+#[test]
+fn test_links_declared_twice_count_once() {
+    let mut dc = switched_data_centre("dc1");
+    let mut tor2 = dc.get_switch("tor2").unwrap().clone();
+    tor2.add_link(link("n3", 10, "n3", Some(3)));
+    dc.update_switch(tor2).unwrap();
+
+    let t = Topology::from_data_centre(&dc);
+    let edges: Vec<_> = t.neighbours(&Node::compute("dc1", "n3")).collect();
+    assert_eq!(edges.len(), 1);
+    assert_eq!(edges[0].1.speed, BitsPerSecond::gbps(25));
+    assert_eq!(edges[0].1.latency_us, Some(1));
+}
+
+#[test]
+fn test_dangling_links() {
+    let mut dc = switched_data_centre("dc1");
+    let mut c = node("n9", 8);
+    c.add_link(link("eth0", 25, "tor9", None));
+    c.add_link(InterConnect::new("ib0".to_string(), BitsPerSecond::gbps(200), true));
+    dc.add_compute(c).unwrap();
+
+    let t = Topology::from_data_centre(&dc);
+    let n9 = Node::compute("dc1", "n9");
+    assert_eq!(t.dangling(), &[(n9.clone(), "eth0".to_string()), (n9.clone(), "ib0".to_string())]);
+    assert_eq!(t.hops(&n9, &Node::compute("dc1", "n1")), None);
+    assert_eq!(t.hops(&Node::compute("dc1", "unknown"), &n9), None);
+}
+
+#[test]
+fn test_paths_between_data_centres() {
+    let mut infra = LogicalInfrastructure::new(1);
+    let mut dc1 = switched_data_centre("dc1");
+    dc1.add_interconnect(link("wan", 10, "dc2", Some(2000))).unwrap();
+    infra.add_data_centre(dc1).unwrap();
+    infra.add_data_centre(switched_data_centre("dc2")).unwrap();
+    infra.add_data_centre(switched_data_centre("dc3")).unwrap();
+
+    let t = Topology::from_infrastructure(&infra);
+    let (dc1, dc2, dc3) = (Node::data_centre("dc1"), Node::data_centre("dc2"), Node::data_centre("dc3"));
+    assert_eq!(t.hops(&dc1, &dc2), Some(1));
+    assert_eq!(t.bottleneck(&dc2, &dc1), Some(BitsPerSecond::gbps(10)));
+    assert_eq!(t.hops(&dc1, &dc3), None);
+
+    let (a, b) = (Node::compute("dc1", "n1"), Node::compute("dc2", "n3"));
+    assert_eq!(t.hops(&a, &b), Some(7));
+    assert_eq!(t.bottleneck(&a, &b), Some(BitsPerSecond::gbps(10)));
+    assert_eq!(t.latency_us(&a, &b), Some(1 + 2 + 5 + 2000 + 5 + 2 + 1));
+
+    // site links join gateways as well
+    let mut l = SiteLink::new("dc2".to_string(), "dc3".to_string(), BitsPerSecond::gbps(100));
+    l.set_declared_latency(1500);
+    infra.add_site_link(l).unwrap();
+    let t = Topology::from_infrastructure(&infra);
+    assert_eq!(t.hops(&dc1, &dc3), Some(2));
+    assert_eq!(t.latency_us(&dc2, &dc3), Some(1500));
+    assert_eq!(t.bottleneck(&dc2, &dc3), Some(BitsPerSecond::gbps(100)));
+}
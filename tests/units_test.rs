@@ -0,0 +1,142 @@
+use chrono::NaiveDate;
+use libtheia::error::TheiaError;
+use libtheia::models::data_centre::{Compute, DataCentre, Disk, DiskType, InterConnect, Storage, GPU};
+use libtheia::models::resource::{Allocation, ClaimError};
+use libtheia::models::units::{BitsPerSecond, Bytes, Hertz, Iops, UnitError};
+
+#[test]
+fn test_parse_units() {
+    assert_eq!("512GiB".parse::<Bytes>(), Ok(Bytes::gib(512)));
+    assert_eq!("1.5 TB".parse::<Bytes>(), Ok(Bytes::gb(1500)));
+    assert_eq!("4096".parse::<Bytes>(), Ok(Bytes::kib(4)));
+    assert_eq!("25Gbps".parse::<BitsPerSecond>(), Ok(BitsPerSecond::gbps(25)));
+    assert_eq!("1GB/s".parse::<BitsPerSecond>(), Ok(BitsPerSecond::gbps(8)));
+    assert_eq!("2.45GHz".parse::<Hertz>(), Ok(Hertz::mhz(2450)));
+    assert_eq!("1.2M".parse::<Iops>(), Ok(Iops(1_200_000)));
+
+    assert_eq!("".parse::<Bytes>(), Err(UnitError::Empty));
+    assert_eq!("12XB".parse::<Bytes>(), Err(UnitError::UnknownUnit("XB".to_string())));
+    assert_eq!("1.2.3GB".parse::<Bytes>(), Err(UnitError::InvalidNumber("1.2.3".to_string())));
+    assert_eq!(".5GB".parse::<Bytes>(), Err(UnitError::InvalidNumber(".5".to_string())));
+    assert_eq!("0.5B".parse::<Bytes>(), Err(UnitError::Inexact("0.5B".to_string())));
+    assert_eq!("20EB".parse::<Bytes>(), Err(UnitError::UnknownUnit("EB".to_string())));
+    assert_eq!("20000PB".parse::<Bytes>(), Err(UnitError::Overflow("20000PB".to_string())));
+}
+
+#[test]
+fn test_format_units() {
+    assert_eq!(Bytes::gib(512).to_string(), "512GiB");
+    assert_eq!(Bytes::gb(500).to_string(), "500GB");
+    assert_eq!(Bytes::mib(1536).to_string(), "1.5GiB");
+    assert_eq!(Bytes(0).to_string(), "0B");
+    assert_eq!(Bytes(1001).to_string(), "1.001kB");
+    assert_eq!(Bytes(1_000_001).to_string(), "1000.001kB");
+    assert_eq!(BitsPerSecond::mbps(2500).to_string(), "2.5Gbps");
+    assert_eq!(Hertz(2_456_700_000).to_string(), "2456.7MHz");
+    assert_eq!(Iops(50_000).to_string(), "50kIOPS");
+
+    for s in ["512GiB", "1.5TB", "3.25GHz", "100Gbps", "7IOPS", "123456.789kB"] {
+        let round_trip = match s.chars().last() {
+            Some('B') => s.parse::<Bytes>().unwrap().to_string(),
+            Some('z') => s.parse::<Hertz>().unwrap().to_string(),
+            Some('s') => s.parse::<BitsPerSecond>().unwrap().to_string(),
+            _ => s.parse::<Iops>().unwrap().to_string(),
+        };
+        assert_eq!(round_trip, s);
+    }
+}
+
+#[test]
+fn test_serde_units() {
+    let c = Compute::new("n1".to_string(), 8, "2.45GHz".parse().unwrap(), Bytes::gib(512));
+    let json = serde_json::to_value(&c).unwrap();
+    assert_eq!(json["core_clock"], "2.45GHz");
+    assert_eq!(json["ram"], "512GiB");
+    assert_eq!(serde_json::from_value::<Compute>(json).unwrap(), c);
+
+    assert_eq!(serde_json::from_str::<Bytes>("1024").unwrap(), Bytes::kib(1));
+    assert!(serde_json::from_str::<Bytes>("\"12 parsecs\"").is_err());
+}
+
+#[test]
+fn test_legacy_fields() {
+    let compute: Compute = serde_json::from_str(
+        r#"{"name": "n1", "cores": 8, "core_ghz": 3, "ram_gb": 64, "disks": [], "links": [], "gpus": []}"#,
+    )
+    .unwrap();
+    assert_eq!(compute, Compute::new("n1".to_string(), 8, Hertz::ghz(3), Bytes::gib(64)));
+
+    let disk: Disk = serde_json::from_str(
+        r#"{"name": "d0", "disk_type": "NVMe", "capacity_tb": 2, "write_speed_mb": 500, "read_speed_mb": null, "iops": 90000}"#,
+    )
+    .unwrap();
+    let expected = Disk::new(
+        "d0".to_string(),
+        DiskType::NVMe,
+        Bytes::tb(2),
+        Some(BitsPerSecond::mb_per_second(500)),
+        None,
+        Some(Iops(90_000)),
+    );
+    assert_eq!(disk, expected);
+
+    let storage: Storage = serde_json::from_str(r#"{"name": "s1", "disks": null, "capacity_gb": 100, "links": []}"#).unwrap();
+    assert_eq!(storage.capacity, Bytes::gb(100));
+
+    let link: InterConnect = serde_json::from_str(r#"{"name": "ib0", "speed_gb": 200, "low_latency": true}"#).unwrap();
+    assert_eq!(link.speed, BitsPerSecond::gbps(200));
+
+    let link: InterConnect = serde_json::from_str(r#"{"name": "ib0", "speed": "400Gbps", "low_latency": true}"#).unwrap();
+    assert_eq!(link.speed, BitsPerSecond::gbps(400));
+}
+
+#[test]
+fn test_bare_numbers_under_current_names_are_base_units() {
+    let compute: Compute = serde_json::from_str(
+        r#"{"name": "n1", "cores": 8, "core_clock": 3, "ram": 64, "disks": [], "links": [], "gpus": []}"#,
+    )
+    .unwrap();
+    assert_eq!((compute.core_clock, compute.ram), (Hertz(3), Bytes(64)));
+
+    let disk: Disk = serde_json::from_str(r#"{"name": "d0", "disk_type": "NVMe", "capacity": 2, "write_speed": 500}"#).unwrap();
+    assert_eq!((disk.capacity, disk.write_speed), (Bytes(2), Some(BitsPerSecond(500))));
+
+    let gpu: GPU = serde_json::from_str(r#"{"name": "g0", "clock": 2, "ram": 128, "bus_type": "PCIe"}"#).unwrap();
+    assert_eq!((gpu.clock, gpu.ram), (Hertz(2), Bytes(128)));
+
+    let link: InterConnect = serde_json::from_str(r#"{"name": "ib0", "speed": 200, "low_latency": true}"#).unwrap();
+    assert_eq!(link.speed, BitsPerSecond(200));
+
+    // a value under both names is ambiguous, no name at all is missing
+    assert!(serde_json::from_str::<InterConnect>(r#"{"name": "ib0", "speed": 200, "speed_gb": 200, "low_latency": true}"#).is_err());
+    assert!(serde_json::from_str::<Storage>(r#"{"name": "s1", "disks": null, "links": []}"#).is_err());
+}
+
+#[test]
+fn test_arithmetic_does_not_overflow() {
+    assert_eq!(Bytes(u64::MAX) + Bytes(1), Bytes(u64::MAX));
+    assert_eq!(Bytes(1) - Bytes(2), Bytes(0));
+    assert_eq!([Bytes(u64::MAX), Bytes::gib(1)].iter().sum::<Bytes>(), Bytes(u64::MAX));
+    assert_eq!(Bytes::tib(u64::MAX), Bytes(u64::MAX));
+    assert_eq!(Bytes(u64::MAX).checked_add(Bytes(1)), None);
+
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_storage(Storage::new("s1".to_string(), None, Bytes::tb(100))).unwrap();
+    dc.add_interconnect(InterConnect::new("wan".to_string(), BitsPerSecond::gbps(100), false)).unwrap();
+    let claim = |name: &str, size: Bytes, bandwidth: BitsPerSecond| {
+        let mut a = Allocation::new(name.to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), None);
+        a.add_storage("s1", size);
+        a.add_interconnect("wan", bandwidth);
+        a
+    };
+    dc.add_claim(claim("a", Bytes::tb(1), BitsPerSecond::gbps(1))).unwrap();
+    assert_eq!(
+        dc.add_claim(claim("b", Bytes(u64::MAX), BitsPerSecond(0))),
+        Err(TheiaError::Claim(ClaimError::InsufficientCapacity("s1".to_string())))
+    );
+    assert_eq!(
+        dc.add_claim(claim("c", Bytes(0), BitsPerSecond(u64::MAX))),
+        Err(TheiaError::Claim(ClaimError::InsufficientCapacity("wan".to_string())))
+    );
+    assert_eq!(dc.capacity().storage.claimed, Bytes::tb(1));
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use chrono::NaiveDate;
+use common::node;
+use libtheia::crdt::{CmRDT, CvRDT};
+use libtheia::models::data_centre::{Compute, DataCentre, Disk, DiskType, InterConnect, Storage};
+use libtheia::models::hierarchy::Location;
+use libtheia::models::infrastructure::LogicalInfrastructure;
+use libtheia::models::resource::Allocation;
+use libtheia::models::units::{BitsPerSecond, Bytes, Hertz};
+use libtheia::validate::{Finding, Rule, Severity, Validator};
+
+fn disk(name: &str, capacity: Bytes) -> Disk {
+    Disk::new(name.to_string(), DiskType::SolidState, capacity, None, None, None)
+}
+
+fn infra(dcs: Vec<DataCentre>) -> LogicalInfrastructure {
+    let mut infra = LogicalInfrastructure::new(1);
+    for dc in dcs {
+        infra.add_data_centre(dc).unwrap();
+    }
+    infra
+}
+
+fn report(infra: &LogicalInfrastructure) -> Vec<String> {
+    Validator::new().validate(infra).iter().map(|f| f.to_string()).collect()
+}
+
+#[test]
+fn test_valid_infrastructure() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    let mut n1 = node("n1", 32);
+    n1.add_disk(disk("d0", Bytes::tb(4)));
+    dc.add_compute(n1).unwrap();
+    dc.add_storage(Storage::new("s1".to_string(), Some(vec![disk("d0", Bytes::tb(4))]), Bytes::tb(4))).unwrap();
+    let infra = infra(vec![dc]);
+    assert!(Validator::new().validate(&infra).is_empty());
+    assert!(Validator::new().is_valid(&infra));
+}
+
+#[test]
+fn test_duplicates() {
+    let mut a = DataCentre::new("dc1".to_string(), 1).with_actor(1);
+    let mut b = a.clone().with_actor(2);
+    a.add_compute(node("n1", 32)).unwrap();
+    b.add_compute(Compute::new("n1".to_string(), 64, Hertz::ghz(2), Bytes::gib(512))).unwrap();
+    a.compute.merge(b.compute);
+    let mut n2 = node("n2", 32);
+    n2.add_disk(disk("d0", Bytes::tb(1)));
+    n2.add_disk(disk("d0", Bytes::tb(1)));
+    a.add_compute(n2).unwrap();
+
+    assert_eq!(
+        report(&infra(vec![a, DataCentre::new("dc2".to_string(), 1), DataCentre::new("dc2".to_string(), 1)])),
+        vec![
+            "error [duplicates] dc2: data centre is defined more than once",
+            "error [duplicates] dc1/n1: concurrently defined more than once",
+            "error [duplicates] dc1/n2: disk d0 is defined more than once",
+        ]
+    );
+}
+
+#[test]
+fn test_impossible_values_and_unit_mismatches() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    let mut n1 = Compute::new("n1".to_string(), -4, Hertz(2), Bytes(0));
+    n1.add_disk(disk("d0", Bytes(12)));
+    dc.add_compute(n1).unwrap();
+    dc.add_storage(Storage::new("s1".to_string(), Some(vec![disk("d0", Bytes::tb(4))]), Bytes::tb(8))).unwrap();
+    let mut wan = InterConnect::new("wan".to_string(), BitsPerSecond(10), false);
+    wan.set_latency(-1);
+    dc.add_interconnect(wan).unwrap();
+
+    let infra = infra(vec![dc]);
+    assert_eq!(
+        report(&infra),
+        vec![
+            "error [impossible-values] dc1/n1: -4 cores",
+            "error [impossible-values] dc1/n1: no ram",
+            "error [impossible-values] dc1/s1: declares 8TB but its disks deliver 4TB",
+            "error [impossible-values] dc1/wan: link wan has a latency of -1us",
+            "warning [unit-mismatches] dc1/n1: core clock of 2Hz is below 1MHz",
+            "warning [unit-mismatches] dc1/n1: disk d0 capacity of 12B is below 1GB",
+            "warning [unit-mismatches] dc1/wan: link wan speed of 10bps is below 1Mbps",
+        ]
+    );
+    assert!(!Validator::new().is_valid(&infra));
+}
+
+#[test]
+fn test_dangling_references() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    let mut n1 = node("n1", 32);
+    n1.set_location(Location::rack("hall1", "a", "01"));
+    let mut eth0 = InterConnect::new("eth0".to_string(), BitsPerSecond::gbps(100), false);
+    eth0.set_peer("spine".to_string());
+    n1.add_link(eth0);
+    dc.add_compute(n1).unwrap();
+    let mut a = Allocation::new("tenant-a".to_string(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), None);
+    a.add_compute("n9");
+    dc.claims.apply(dc.claims.add(a, 1).unwrap());
+
+    assert_eq!(
+        report(&infra(vec![dc])),
+        vec![
+            "error [dangling-references] dc1/n1: link eth0 peers with unknown spine",
+            "error [dangling-references] dc1/n1: placed at unknown location hall1/a/01",
+            "warning [dangling-references] dc1/tenant-a: claims unknown resource n9",
+        ]
+    );
+}
+
+struct Named;
+
+impl Rule for Named {
+    fn name(&self) -> &str {
+        "naming"
+    }
+
+    fn check(&self, infra: &LogicalInfrastructure) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for dc in infra.data_centres.iter() {
+            for name in dc.compute.names().filter(|n| !n.starts_with("node-")) {
+                findings.push(Finding::new(self.name(), Severity::Info, &dc.name, Some(name), "does not follow node-<n>".to_string()));
+            }
+        }
+        findings
+    }
+}
+
+#[test]
+fn test_pluggable_rules() {
+    let mut dc = DataCentre::new("dc1".to_string(), 1);
+    dc.add_compute(Compute::new("n1".to_string(), 0, Hertz::ghz(2), Bytes::gib(256))).unwrap();
+    let infra = infra(vec![dc]);
+
+    let validator = Validator::new().without_rule("impossible-values").with_rule(Named);
+    assert_eq!(validator.rules(), vec!["duplicates", "dangling-references", "unit-mismatches", "naming"]);
+    assert_eq!(
+        validator.validate(&infra),
+        vec![Finding::new("naming", Severity::Info, "dc1", Some("n1"), "does not follow node-<n>".to_string())]
+    );
+    assert!(validator.is_valid(&infra));
+    assert!(Validator::empty().validate(&infra).is_empty());
+}
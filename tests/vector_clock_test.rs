@@ -1,4 +1,3 @@
-use std::fmt::Debug;
 use libtheia::crdt::{Reset, Version, CvRDT, CmRDT, VectorClock};
 
 #[test]
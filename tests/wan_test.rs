@@ -0,0 +1,126 @@
+use libtheia::crdt::CvRDT;
+use libtheia::inventory::{self, Format, InventoryError};
+use libtheia::models::data_centre::DataCentre;
+use libtheia::models::infrastructure::LogicalInfrastructure;
+use libtheia::models::units::{BitsPerSecond, Bytes};
+use libtheia::models::wan::{Coordinates, SiteLink};
+use libtheia::validate::Validator;
+
+const ZURICH: Coordinates = Coordinates { latitude: 47.3769, longitude: 8.5417 };
+const GENEVA: Coordinates = Coordinates { latitude: 46.2044, longitude: 6.1432 };
+const FRANKFURT: Coordinates = Coordinates { latitude: 50.1109, longitude: 8.6821 };
+
+fn link(a: &str, b: &str, gbps: u64, us: Option<i32>) -> SiteLink {
+    let mut l = SiteLink::new(a.to_string(), b.to_string(), BitsPerSecond::gbps(gbps));
+    if let Some(us) = us {
+        l.set_declared_latency(us);
+    }
+    l
+}
+
+fn infra() -> LogicalInfrastructure {
+    let mut infra = LogicalInfrastructure::new(1);
+    for (name, at) in [("zrh", ZURICH), ("gva", GENEVA), ("fra", FRANKFURT)] {
+        let mut dc = DataCentre::new(name.to_string(), 1);
+        dc.set_coordinates(at).unwrap();
+        infra.add_data_centre(dc).unwrap();
+    }
+    infra.add_data_centre(DataCentre::new("sin".to_string(), 1)).unwrap();
+    infra
+}
+
+#[test]
+fn test_coordinates() {
+    assert_eq!(ZURICH.distance_km(&GENEVA).round(), 224.0);
+    assert_eq!(GENEVA.distance_km(&ZURICH), ZURICH.distance_km(&GENEVA));
+    assert_eq!(ZURICH.distance_km(&ZURICH), 0.0);
+    assert_eq!(ZURICH.latency_us(&GENEVA), 1_122);
+    assert_eq!(SiteLink::key("zrh", "gva"), SiteLink::key("gva", "zrh"));
+}
+
+#[test]
+fn test_latency_queries() {
+    let mut infra = infra();
+    infra.add_site_link(link("zrh", "gva", 100, Some(2_000))).unwrap();
+    // no latency given, estimated from the coordinates
+    infra.add_site_link(link("fra", "zrh", 400, None)).unwrap();
+    // neither latency nor coordinates, not usable for latency
+    infra.add_site_link(link("fra", "sin", 10, None)).unwrap();
+
+    assert_eq!(infra.latency_us("zrh", "gva"), Some(2_000));
+    assert_eq!(infra.latency_us("gva", "fra"), Some(2_000 + ZURICH.latency_us(&FRANKFURT)));
+    assert_eq!(infra.latency_us("zrh", "sin"), None);
+    let fra = ZURICH.latency_us(&FRANKFURT);
+    assert_eq!(infra.within_latency("zrh", 5_000), vec![("fra".to_string(), fra), ("gva".to_string(), 2_000)]);
+    assert_eq!(infra.within_latency("zrh", 1_000), vec![]);
+
+    // a measurement wins over the declaration
+    infra.record_latency("gva", "zrh", 900).unwrap();
+    assert_eq!(infra.within_latency("zrh", 1_000), vec![("gva".to_string(), 900)]);
+    assert_eq!(infra.latency_matrix()[&("gva".to_string(), "zrh".to_string())], 900);
+    assert!(infra.record_latency("zrh", "sin", 1).is_err());
+}
+
+#[test]
+fn test_bandwidth_and_egress() {
+    let mut infra = infra();
+    let mut l = link("zrh", "fra", 400, Some(2_000));
+    l.set_egress_cost(0.02);
+    infra.add_site_link(l).unwrap();
+
+    assert_eq!(infra.site_bandwidth("fra", "zrh"), Some(BitsPerSecond::gbps(400)));
+    assert_eq!(infra.site_bandwidth("zrh", "gva"), None);
+    assert_eq!(infra.egress_cost("zrh", "fra", Bytes::tb(5)), Some(100.0));
+    assert_eq!(infra.egress_cost("gva", "fra", Bytes::tb(5)), None);
+    infra.remove_site_link("fra", "zrh").unwrap();
+    assert!(infra.get_site_link("zrh", "fra").is_none());
+}
+
+#[test]
+fn test_site_links_replicate() {
+    let mut a = infra().with_actor(1);
+    let mut b = a.clone().with_actor(2);
+    a.add_site_link(link("zrh", "gva", 100, Some(2_000))).unwrap();
+    b.add_site_link(link("zrh", "fra", 400, Some(4_000))).unwrap();
+    a.site_links.merge(b.site_links.clone());
+    b.site_links.merge(a.site_links.clone());
+    b.record_latency("zrh", "gva", 1_800).unwrap();
+    a.site_links.merge(b.site_links.clone());
+
+    for infra in [&a, &b] {
+        assert_eq!(infra.site_links.len(), 2);
+        assert_eq!(infra.latency_us("gva", "fra"), Some(5_800));
+    }
+}
+
+#[test]
+fn test_inventory_and_validation() {
+    let yaml = r#"
+data_centres:
+  - name: zrh
+    coordinates: {latitude: 47.3769, longitude: 8.5417}
+  - name: gva
+    coordinates: {latitude: 46.2044, longitude: 6.1432}
+site_links:
+  - {a: zrh, b: gva, bandwidth: 100Gbps, egress_cost: 0.01}
+"#;
+    let infra = inventory::parse(yaml, Format::Yaml, 1).unwrap();
+    assert_eq!(infra.get_data_centre("zrh").unwrap().coordinates(), Some(ZURICH));
+    assert_eq!(infra.latency_us("zrh", "gva"), Some(ZURICH.latency_us(&GENEVA)));
+
+    let unknown = yaml.replace("b: gva", "b: fra");
+    assert_eq!(
+        inventory::parse(&unknown, Format::Yaml, 1).err(),
+        Some(InventoryError::UnknownPeer { name: "fra<->zrh".to_string(), peer: "fra".to_string() })
+    );
+    let twice = yaml.to_string() + "  - {a: gva, b: zrh, bandwidth: 10Gbps}\n";
+    assert_eq!(
+        inventory::parse(&twice, Format::Yaml, 1).err(),
+        Some(InventoryError::Duplicate { name: "gva<->zrh".to_string() })
+    );
+
+    let mut infra = infra;
+    infra.remove_data_centre("gva").unwrap();
+    let findings: Vec<String> = Validator::new().validate(&infra).iter().map(|f| f.to_string()).collect();
+    assert_eq!(findings, vec!["error [dangling-references] zrh/gva<->zrh: links to unknown data centre gva"]);
+}